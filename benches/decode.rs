@@ -0,0 +1,111 @@
+//! Decoding throughput: `cargo bench --bench decode`. Decodes torrent-like
+//! documents of growing size, where with single-pass parsing the MiB/s
+//! should stay about level, and a stream of small KRPC messages, where
+//! allocation dominates, and a list of short strings.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use bencode_decoder::{cow::CowBencode, decode};
+
+/// A multi-file info dictionary with `files` entries, and some pieces.
+fn document(files: usize) -> Vec<u8> {
+    let mut out = b"d4:infod5:filesl".to_vec();
+    for i in 0..files {
+        let name = format!("file-{i}.bin");
+        out.extend_from_slice(
+            format!(
+                "d6:lengthi{}e4:pathl3:dir{}:{name}ee",
+                i * 1000 + 7,
+                name.len()
+            )
+            .as_bytes(),
+        );
+    }
+    let pieces = files * 20;
+    out.extend_from_slice(
+        format!("e4:name4:test12:piece lengthi262144e6:pieces{pieces}:").as_bytes(),
+    );
+    out.extend((0..pieces).map(|i| i as u8));
+    out.extend_from_slice(b"ee");
+    out
+}
+
+/// A `get_peers` response with eight compact peers, like most DHT traffic.
+fn krpc(i: usize) -> Vec<u8> {
+    let mut out = format!("d1:rd2:id20:{:020}5:token8:{:08}6:valuesl", i, i % 1000).into_bytes();
+    for peer in 0..8u8 {
+        out.extend_from_slice(b"6:");
+        out.extend_from_slice(&[10, 0, peer, i as u8, 0x1a, 0xe1]);
+    }
+    out.extend_from_slice(format!("ee1:t2:{:02}1:y1:re", i % 100).as_bytes());
+    out
+}
+
+fn main() {
+    let messages: Vec<_> = (0..100_000).map(krpc).collect();
+    let bytes: usize = messages.iter().map(Vec::len).sum();
+    let runs = 20;
+    let report = |name: &str, elapsed: Duration| {
+        println!(
+            "{name}: {} KRPC messages, {:.2?} per run, {:.0} MiB/s",
+            messages.len(),
+            elapsed / runs,
+            (bytes * runs as usize) as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64()
+        )
+    };
+    let start = Instant::now();
+    for _ in 0..runs {
+        for message in &messages {
+            black_box(decode(black_box(message)).unwrap());
+        }
+    }
+    report("decode", start.elapsed());
+    // The same without allocating for each string.
+    let start = Instant::now();
+    for _ in 0..runs {
+        for message in &messages {
+            black_box(CowBencode::decode(black_box(message)).unwrap());
+        }
+    }
+    report("borrowed", start.elapsed());
+
+    for files in [1_000, 10_000, 100_000] {
+        let input = document(files);
+        let runs = 20_000_000 / input.len() + 1;
+
+        let start = Instant::now();
+        for _ in 0..runs {
+            black_box(decode(black_box(&input)).unwrap());
+        }
+        let elapsed = start.elapsed();
+
+        println!(
+            "decode: {files} files, {} KiB, {:.2?} per run, {:.0} MiB/s",
+            input.len() / 1024,
+            elapsed / runs as u32,
+            (input.len() * runs) as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64()
+        );
+    }
+
+    // Many short strings: each length prefix should cost only its own
+    // bytes, so 4× the strings should take about 4× the time.
+    for strings in [100_000, 400_000] {
+        let mut input = b"l".to_vec();
+        for i in 0..strings {
+            input.extend_from_slice(format!("{}:{i}", i.to_string().len()).as_bytes());
+        }
+        input.push(b'e');
+
+        let start = Instant::now();
+        for _ in 0..10 {
+            black_box(decode(black_box(&input)).unwrap());
+        }
+        println!(
+            "decode: {strings} short strings, {:.2?} per run",
+            start.elapsed() / 10
+        );
+    }
+}
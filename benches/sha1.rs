@@ -0,0 +1,31 @@
+//! Piece hashing throughput: `cargo bench --bench sha1`. Hashes 1 GiB in
+//! 256 KiB pieces by default; set `BENCH_GIB` for larger inputs.
+
+use std::{env, hint::black_box, time::Instant};
+
+use bencode_decoder::sha1::{is_accelerated, sha1};
+
+fn main() {
+    let gib: u64 = env::var("BENCH_GIB")
+        .ok()
+        .and_then(|gib| gib.parse().ok())
+        .unwrap_or(1);
+    let piece: Vec<u8> = (0..256 * 1024u32).map(|i| (i * 31 + i / 7) as u8).collect();
+    let pieces = gib * 1024 * 1024 * 1024 / piece.len() as u64;
+
+    let start = Instant::now();
+    for _ in 0..pieces {
+        black_box(sha1(black_box(&piece)));
+    }
+    let elapsed = start.elapsed();
+
+    let bytes = pieces * piece.len() as u64;
+    println!(
+        "sha1 ({}): {gib} GiB in {elapsed:.2?}, {:.0} MiB/s",
+        match is_accelerated() {
+            true => "SHA extensions",
+            false => "portable",
+        },
+        bytes as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64()
+    );
+}
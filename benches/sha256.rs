@@ -0,0 +1,32 @@
+//! Merkle hashing throughput: `cargo bench --bench sha256`. Hashes 1 GiB in
+//! the 16 KiB blocks of v2 torrents by default; set `BENCH_GIB` for larger
+//! inputs.
+
+use std::{env, hint::black_box, time::Instant};
+
+use bencode_decoder::{sha1::is_accelerated, sha256::sha256};
+
+fn main() {
+    let gib: u64 = env::var("BENCH_GIB")
+        .ok()
+        .and_then(|gib| gib.parse().ok())
+        .unwrap_or(1);
+    let block: Vec<u8> = (0..16 * 1024u32).map(|i| (i * 31 + i / 7) as u8).collect();
+    let blocks = gib * 1024 * 1024 * 1024 / block.len() as u64;
+
+    let start = Instant::now();
+    for _ in 0..blocks {
+        black_box(sha256(black_box(&block)));
+    }
+    let elapsed = start.elapsed();
+
+    let bytes = blocks * block.len() as u64;
+    println!(
+        "sha256 ({}): {gib} GiB in {elapsed:.2?}, {:.0} MiB/s",
+        match is_accelerated() {
+            true => "SHA extensions",
+            false => "portable",
+        },
+        bytes as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64()
+    );
+}
@@ -0,0 +1,129 @@
+//! Derive macros for `bencode_decoder`. Use them through the crate's
+//! `derive` feature rather than depending on this crate directly.
+
+use proc_macro::{Literal, TokenStream};
+
+mod parse;
+
+/// Encodes a struct with named fields as a dictionary.
+///
+/// Field attributes:
+/// - `#[bencode(rename = "piece length")]` uses a different key.
+/// - `#[bencode(skip)]` leaves the field out.
+/// - `#[bencode(optional)]` omits the entry when the value is `None`. This is
+///   implied for fields declared as `Option<T>`.
+/// - `#[bencode(bytes)]` encodes an `AsRef<[u8]>` field as a byte string
+///   instead of a list.
+#[proc_macro_derive(ToBencode, attributes(bencode))]
+pub fn derive_to_bencode(input: TokenStream) -> TokenStream {
+    match parse::parse_struct(input, "ToBencode") {
+        Ok(item) => to_bencode(&item).parse().unwrap(),
+        Err(message) => compile_error(&message),
+    }
+}
+
+fn to_bencode(item: &parse::Struct) -> String {
+    let mut body = String::new();
+
+    for field in item.fields.iter().filter(|field| !field.skip) {
+        let key = Literal::byte_string(field.key.as_bytes());
+        let encode = |value: &str| {
+            match field.bytes {
+            true => format!(
+                "::bencode_decoder::Bencode::String(::core::convert::AsRef::<[u8]>::as_ref({value}).to_vec())"
+            ),
+            false => format!("::bencode_decoder::ToBencode::to_bencode({value})"),
+        }
+        };
+
+        if field.optional {
+            body.push_str(&format!(
+                "if let ::core::option::Option::Some(value) = &self.{} {{ dict.insert({key}.to_vec(), {}); }}\n",
+                field.ident,
+                encode("value")
+            ));
+        } else {
+            body.push_str(&format!(
+                "dict.insert({key}.to_vec(), {});\n",
+                encode(&format!("&self.{}", field.ident))
+            ));
+        }
+    }
+
+    format!(
+        "impl ::bencode_decoder::ToBencode for {name} {{
+            fn to_bencode(&self) -> ::bencode_decoder::Bencode {{
+                #[allow(unused_mut)]
+                let mut dict = ::bencode_decoder::__private::BTreeMap::new();
+                {body}
+                ::bencode_decoder::Bencode::Dictionary(dict)
+            }}
+        }}",
+        name = item.name
+    )
+}
+
+/// Decodes a struct with named fields from a dictionary.
+///
+/// Field attributes:
+/// - `#[bencode(rename = "piece length")]` reads a different key.
+/// - `#[bencode(skip)]` doesn't read the field and uses `Default::default()`.
+/// - `#[bencode(optional)]` decodes a missing key as `None`. This is implied
+///   for fields declared as `Option<T>`.
+/// - `#[bencode(default)]` or `#[bencode(default = "function")]` fills in a
+///   missing key from `Default::default()` or the given function.
+/// - `#[bencode(bytes)]` reads a byte string into `Vec<u8>` or `[u8; N]`.
+#[proc_macro_derive(FromBencode, attributes(bencode))]
+pub fn derive_from_bencode(input: TokenStream) -> TokenStream {
+    match parse::parse_struct(input, "FromBencode") {
+        Ok(item) => from_bencode(&item).parse().unwrap(),
+        Err(message) => compile_error(&message),
+    }
+}
+
+fn from_bencode(item: &parse::Struct) -> String {
+    let mut fields = String::new();
+
+    for field in &item.fields {
+        let key = format!("{:?}", field.key);
+        let read = match field.bytes {
+            true => "optional_bytes",
+            false => "optional_field",
+        };
+        let value = match (field.skip, field.optional, &field.default) {
+            (true, _, _) => "::core::default::Default::default()".to_string(),
+            (false, true, _) => format!("::bencode_decoder::__private::{read}(dict, {key})?"),
+            (false, false, None) => format!(
+                "::bencode_decoder::__private::{read}(dict, {key})?.ok_or_else(|| ::bencode_decoder::Error::missing_key({key}))?"
+            ),
+            (false, false, Some(parse::Default::Trait)) => format!(
+                "::bencode_decoder::__private::{read}(dict, {key})?.unwrap_or_default()"
+            ),
+            (false, false, Some(parse::Default::Function(function))) => format!(
+                "::bencode_decoder::__private::{read}(dict, {key})?.unwrap_or_else({function})"
+            ),
+        };
+        fields.push_str(&format!("{}: {value},\n", field.ident));
+    }
+
+    format!(
+        "impl ::bencode_decoder::FromBencode for {name} {{
+            fn from_bencode(
+                value: &::bencode_decoder::Bencode,
+            ) -> ::core::result::Result<Self, ::bencode_decoder::Error> {{
+                #[allow(unused_variables)]
+                let dict = ::bencode_decoder::__private::dictionary(value)?;
+                ::core::result::Result::Ok(Self {{
+                    {fields}
+                }})
+            }}
+        }}",
+        name = item.name
+    )
+}
+
+fn compile_error(message: &str) -> TokenStream {
+    format!("::core::compile_error!({message:?});")
+        .parse()
+        .unwrap()
+}
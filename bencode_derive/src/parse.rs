@@ -0,0 +1,228 @@
+//! Just enough of a Rust parser to read a struct with named fields and its
+//! `#[bencode(...)]` attributes.
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+pub struct Struct {
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+pub struct Field {
+    /// The identifier as written, e.g. `r#type`.
+    pub ident: String,
+    /// Dictionary key the field maps to.
+    pub key: String,
+    pub skip: bool,
+    pub optional: bool,
+    pub bytes: bool,
+    pub default: Option<Default>,
+}
+
+/// Where a missing field's value comes from when decoding.
+#[derive(Clone)]
+pub enum Default {
+    Trait,
+    Function(String),
+}
+
+#[derive(Default)]
+struct Attributes {
+    rename: Option<String>,
+    skip: bool,
+    optional: bool,
+    bytes: bool,
+    default: Option<Default>,
+}
+
+pub fn parse_struct(input: TokenStream, derive: &str) -> Result<Struct, String> {
+    let unsupported = || format!("{derive} can only be derived for structs with named fields");
+    let mut tokens = input.into_iter();
+
+    loop {
+        match tokens.next() {
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "struct" => break,
+            Some(TokenTree::Ident(ident)) if ["enum", "union"].contains(&&*ident.to_string()) => {
+                return Err(unsupported())
+            }
+            // Outer attributes and visibility
+            Some(_) => continue,
+            None => return Err(unsupported()),
+        }
+    }
+
+    let Some(TokenTree::Ident(name)) = tokens.next() else {
+        return Err(unsupported());
+    };
+
+    match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => Ok(Struct {
+            name: name.to_string(),
+            fields: parse_fields(group.stream())?,
+        }),
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            Err(format!("{derive} does not support generic structs"))
+        }
+        _ => Err(unsupported()),
+    }
+}
+
+fn parse_fields(stream: TokenStream) -> Result<Vec<Field>, String> {
+    let mut fields = Vec::new();
+    let mut tokens = stream.into_iter().peekable();
+
+    loop {
+        let mut attributes = Attributes::default();
+
+        while matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '#') {
+            tokens.next();
+            let Some(TokenTree::Group(group)) = tokens.next() else {
+                return Err("malformed attribute".to_string());
+            };
+            parse_attribute(group.stream(), &mut attributes)?;
+        }
+
+        let ident = loop {
+            match tokens.next() {
+                None => return Ok(fields),
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "pub" => {
+                    // `pub(crate)` and friends
+                    if matches!(tokens.peek(), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis)
+                    {
+                        tokens.next();
+                    }
+                }
+                Some(TokenTree::Ident(ident)) => break ident.to_string(),
+                Some(other) => return Err(format!("unexpected `{other}` in struct body")),
+            }
+        };
+
+        match tokens.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ':' => {}
+            _ => return Err(format!("expected `:` after field `{ident}`")),
+        }
+
+        // The type runs up to the next comma outside of angle brackets
+        let mut ty = Vec::new();
+        let mut depth = 0usize;
+        for token in tokens.by_ref() {
+            if let TokenTree::Punct(punct) = &token {
+                match punct.as_char() {
+                    '<' => depth += 1,
+                    '>' => depth = depth.saturating_sub(1),
+                    ',' if depth == 0 => break,
+                    _ => {}
+                }
+            }
+            ty.push(token);
+        }
+
+        let is_option = matches!(
+            ty.iter().position(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == '<')),
+            Some(i) if i > 0 && ty[i - 1].to_string() == "Option"
+        );
+        let key = attributes
+            .rename
+            .unwrap_or_else(|| ident.trim_start_matches("r#").to_string());
+
+        fields.push(Field {
+            ident,
+            key,
+            skip: attributes.skip,
+            optional: attributes.optional || is_option,
+            bytes: attributes.bytes,
+            default: attributes.default,
+        });
+    }
+}
+
+fn parse_attribute(stream: TokenStream, attributes: &mut Attributes) -> Result<(), String> {
+    let mut tokens = stream.into_iter();
+
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "bencode" => {}
+        // Doc comments and other derives' attributes
+        _ => return Ok(()),
+    }
+
+    let Some(TokenTree::Group(group)) = tokens.next() else {
+        return Err("expected #[bencode(...)]".to_string());
+    };
+
+    let mut options = group.stream().into_iter().peekable();
+    while let Some(option) = options.next() {
+        let TokenTree::Ident(option) = option else {
+            return Err(format!("unexpected `{option}` in #[bencode(...)]"));
+        };
+
+        match option.to_string().as_str() {
+            "skip" => attributes.skip = true,
+            "optional" => attributes.optional = true,
+            "bytes" => attributes.bytes = true,
+            "rename" => attributes.rename = Some(string_value(&mut options, "rename")?),
+            "default" => {
+                attributes.default = match options.peek() {
+                    Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => {
+                        Some(Default::Function(string_value(&mut options, "default")?))
+                    }
+                    _ => Some(Default::Trait),
+                }
+            }
+            other => return Err(format!("unknown bencode attribute `{other}`")),
+        }
+
+        match options.next() {
+            None => break,
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => {}
+            Some(other) => return Err(format!("unexpected `{other}` in #[bencode(...)]")),
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the `= "..."` following an option name.
+fn string_value(
+    options: &mut impl Iterator<Item = TokenTree>,
+    option: &str,
+) -> Result<String, String> {
+    match options.next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => {}
+        _ => return Err(format!("expected `{option} = \"...\"`")),
+    }
+    match options.next() {
+        Some(TokenTree::Literal(literal)) => unquote(&literal.to_string()),
+        _ => Err(format!("expected a string literal after `{option} =`")),
+    }
+}
+
+/// Reads the value of a plain or raw string literal.
+fn unquote(literal: &str) -> Result<String, String> {
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return Ok(raw[hashes + 1..raw.len() - hashes - 1].to_string());
+    }
+
+    let inner = literal
+        .strip_prefix('"')
+        .and_then(|l| l.strip_suffix('"'))
+        .ok_or_else(|| format!("expected a string literal, found {literal}"))?;
+
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('0') => value.push('\0'),
+            Some(c @ ('\\' | '"' | '\'')) => value.push(c),
+            _ => return Err(format!("unsupported escape in {literal}")),
+        }
+    }
+    Ok(value)
+}
@@ -0,0 +1,43 @@
+import assert from 'node:assert/strict'
+import { createRequire } from 'node:module'
+import { test } from 'node:test'
+
+const { decode, encode } = createRequire(import.meta.url)('../index.js')
+
+const bencoded = (value) => encode(value).toString('latin1')
+
+test('safe integer numbers encode exactly', () => {
+  assert.equal(bencoded(42), 'i42e')
+  assert.equal(bencoded(-0), 'i0e')
+  assert.equal(bencoded(Number.MAX_SAFE_INTEGER), 'i9007199254740991e')
+  assert.equal(bencoded(Number.MIN_SAFE_INTEGER), 'i-9007199254740991e')
+})
+
+test('other numbers are refused', () => {
+  for (const number of [1.5, NaN, Infinity, -Infinity, 2 ** 53, -(2 ** 53), 1e300]) {
+    assert.throws(() => encode(number), /safe integer/, String(number))
+  }
+})
+
+test('BigInts encode across the 64-bit range', () => {
+  assert.equal(bencoded(5n), 'i5e')
+  assert.equal(bencoded(2n ** 63n - 1n), 'i9223372036854775807e')
+  assert.equal(bencoded(-(2n ** 63n)), 'i-9223372036854775808e')
+  assert.throws(() => encode(2n ** 63n), /64-bit/)
+  assert.throws(() => encode(-(2n ** 63n) - 1n), /64-bit/)
+})
+
+test('large integers decode as BigInts', () => {
+  assert.equal(decode(Buffer.from('i9007199254740991e')), 9007199254740991)
+  assert.equal(decode(Buffer.from('i-9007199254740991e')), -9007199254740991)
+  assert.equal(decode(Buffer.from('i9007199254740993e')), 9007199254740993n)
+  assert.equal(decode(Buffer.from('i-9223372036854775808e')), -(2n ** 63n))
+  const value = decode(Buffer.from('d4:sizei9223372036854775807ee'))
+  assert.equal(bencoded(value), 'd4:sizei9223372036854775807ee')
+})
+
+test('non-UTF-8 keys are refused', () => {
+  const data = Buffer.concat([Buffer.from('d2:'), Buffer.from([0xff, 0x00]), Buffer.from('i1ee')])
+  assert.throws(() => decode(data), /key "\\xff\\x00" isn't UTF-8/)
+  assert.deepEqual(Object.keys(decode(Buffer.from('d3:keyi1ee'))), ['key'])
+})
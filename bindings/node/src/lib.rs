@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use bencode::{torrent, Bencode};
+use napi::{
+    bindgen_prelude::Buffer, Env, Error, JsBigInt, JsBuffer, JsNumber, JsObject, JsString,
+    JsUnknown, Result, ValueType,
+};
+use napi_derive::napi;
+
+/// Largest integer a JS number holds exactly, `Number.MAX_SAFE_INTEGER`.
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// The integer a JS number stands for. Fractions, NaN, infinities and
+/// numbers beyond the safe range, which may already have been rounded, are
+/// refused rather than truncated.
+fn integer_from_number(number: f64) -> Result<i64> {
+    match number.fract() == 0.0 && number.abs() <= MAX_SAFE_INTEGER as f64 {
+        true => Ok(number as i64),
+        false => Err(Error::from_reason(format!(
+            "cannot bencode {number}, which isn't a safe integer; pass a BigInt instead"
+        ))),
+    }
+}
+
+/// A dictionary key as an object property name. Keys that aren't UTF-8,
+/// such as raw infohashes, are refused: replacing their bytes would corrupt
+/// them and could make different keys collide.
+fn property_name(key: &[u8]) -> Result<&str> {
+    std::str::from_utf8(key).map_err(|_| {
+        Error::from_reason(format!(
+            "dictionary key \"{}\" isn't UTF-8, so it can't be an object property",
+            key.escape_ascii()
+        ))
+    })
+}
+
+fn to_js(env: &Env, value: &Bencode) -> Result<JsUnknown> {
+    Ok(match value {
+        Bencode::String(s) => env
+            .create_buffer_with_data(s.clone())?
+            .into_raw()
+            .into_unknown(),
+        // Numbers as long as they're exact, BigInts beyond that
+        Bencode::Integer(i) if i.unsigned_abs() <= MAX_SAFE_INTEGER as u64 => {
+            env.create_int64(*i)?.into_unknown()
+        }
+        Bencode::Integer(i) => env.create_bigint_from_i64(*i)?.into_unknown()?,
+        Bencode::List(l) => {
+            let mut array = env.create_array_with_length(l.len())?;
+            for (i, bencode) in l.iter().enumerate() {
+                array.set_element(i as u32, to_js(env, bencode)?)?;
+            }
+            array.into_unknown()
+        }
+        Bencode::Dictionary(d) => {
+            let mut object = env.create_object()?;
+            for (key, value) in d {
+                object.set_named_property(property_name(key)?, to_js(env, value)?)?;
+            }
+            object.into_unknown()
+        }
+    })
+}
+
+fn from_js(value: JsUnknown) -> Result<Bencode> {
+    match value.get_type()? {
+        ValueType::Number => {
+            let number = unsafe { value.cast::<JsNumber>() }.get_double()?;
+            integer_from_number(number).map(Bencode::Integer)
+        }
+        ValueType::BigInt => match unsafe { value.cast::<JsBigInt>() }.get_i64()? {
+            (integer, true) => Ok(Bencode::Integer(integer)),
+            (_, false) => Err(Error::from_reason(
+                "cannot bencode a BigInt outside the 64-bit signed range",
+            )),
+        },
+        ValueType::String => {
+            let string = unsafe { value.cast::<JsString>() }.into_utf8()?;
+            Ok(Bencode::String(string.as_slice().to_vec()))
+        }
+        ValueType::Object if value.is_buffer()? => {
+            let buffer = unsafe { value.cast::<JsBuffer>() }.into_value()?;
+            Ok(Bencode::String(buffer.to_vec()))
+        }
+        ValueType::Object if value.is_array()? => {
+            let array = unsafe { value.cast::<JsObject>() };
+            (0..array.get_array_length()?)
+                .map(|i| from_js(array.get_element::<JsUnknown>(i)?))
+                .collect::<Result<_>>()
+                .map(Bencode::List)
+        }
+        ValueType::Object => {
+            let object = unsafe { value.cast::<JsObject>() };
+            let names = object.get_property_names()?;
+            let mut d = BTreeMap::new();
+            for i in 0..names.get_array_length()? {
+                let key = names.get_element::<JsString>(i)?.into_utf8()?;
+                let value = object.get_named_property::<JsUnknown>(key.as_str()?)?;
+                d.insert(key.as_slice().to_vec(), from_js(value)?);
+            }
+            Ok(Bencode::Dictionary(d))
+        }
+        other => Err(Error::from_reason(format!("cannot bencode a {other}"))),
+    }
+}
+
+fn reason(error: bencode::Error) -> Error {
+    Error::from_reason(error.to_string())
+}
+
+/// Decodes a bencoded buffer into Buffers, numbers, arrays and objects.
+/// Integers beyond `Number.MAX_SAFE_INTEGER` become BigInts, and keys that
+/// aren't UTF-8 are an error.
+#[napi(ts_return_type = "unknown")]
+pub fn decode(env: Env, data: Buffer) -> Result<JsUnknown> {
+    to_js(&env, &bencode::decode(&data).map_err(reason)?)
+}
+
+/// Encodes strings, Buffers, integers, arrays and objects as bencode.
+/// Integers are safe-integer numbers or BigInts.
+#[napi]
+pub fn encode(#[napi(ts_arg_type = "unknown")] value: JsUnknown) -> Result<Buffer> {
+    Ok(bencode::encode(&from_js(value)?).into())
+}
+
+/// Hex-encoded infohash of a `.torrent` file.
+#[napi]
+pub fn infohash(torrent: Buffer) -> Result<String> {
+    let infohash = torrent::infohash(&torrent).map_err(reason)?;
+    Ok(infohash.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+#[napi]
+pub fn magnet(torrent: Buffer) -> Result<String> {
+    torrent::magnet_link(&torrent).map_err(reason)
+}
+
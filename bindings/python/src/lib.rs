@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use bencode::{torrent, Bencode};
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyBytes, PyDict, PyList, PyString, PyTuple},
+};
+
+fn to_python(py: Python<'_>, value: &Bencode) -> PyResult<PyObject> {
+    Ok(match value {
+        Bencode::String(s) => PyBytes::new_bound(py, s).into_py(py),
+        Bencode::Integer(i) => i.into_py(py),
+        Bencode::List(l) => {
+            let list = PyList::empty_bound(py);
+            for bencode in l {
+                list.append(to_python(py, bencode)?)?;
+            }
+            list.into_py(py)
+        }
+        Bencode::Dictionary(d) => {
+            let dict = PyDict::new_bound(py);
+            for (key, value) in d {
+                dict.set_item(PyBytes::new_bound(py, key), to_python(py, value)?)?;
+            }
+            dict.into_py(py)
+        }
+    })
+}
+
+fn to_bytes(object: &Bound<'_, PyAny>) -> PyResult<Option<Vec<u8>>> {
+    if let Ok(bytes) = object.downcast::<PyBytes>() {
+        return Ok(Some(bytes.as_bytes().to_vec()));
+    }
+    if let Ok(string) = object.downcast::<PyString>() {
+        return Ok(Some(string.to_str()?.as_bytes().to_vec()));
+    }
+    Ok(None)
+}
+
+fn from_python(object: &Bound<'_, PyAny>) -> PyResult<Bencode> {
+    if let Some(bytes) = to_bytes(object)? {
+        return Ok(Bencode::String(bytes));
+    }
+    if let Ok(i) = object.extract::<i64>() {
+        return Ok(Bencode::Integer(i));
+    }
+    if object.is_instance_of::<PyList>() || object.is_instance_of::<PyTuple>() {
+        return object
+            .iter()?
+            .map(|item| from_python(&item?))
+            .collect::<PyResult<_>>()
+            .map(Bencode::List);
+    }
+    if let Ok(dict) = object.downcast::<PyDict>() {
+        let mut d = BTreeMap::new();
+        for (key, value) in dict {
+            let key = to_bytes(&key)?
+                .ok_or_else(|| PyTypeError::new_err("dictionary keys must be bytes or str"))?;
+            d.insert(key, from_python(&value)?);
+        }
+        return Ok(Bencode::Dictionary(d));
+    }
+
+    Err(PyTypeError::new_err(format!(
+        "cannot bencode object of type {}",
+        object.get_type().name()?
+    )))
+}
+
+fn value_error(error: bencode::Error) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// Decodes a bencoded document into bytes, int, list and dict objects.
+#[pyfunction]
+fn decode(py: Python<'_>, data: &[u8]) -> PyResult<PyObject> {
+    to_python(py, &bencode::decode(data).map_err(value_error)?)
+}
+
+/// Encodes bytes/str, int, list/tuple and dict objects as bencode.
+#[pyfunction]
+fn encode<'py>(py: Python<'py>, object: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyBytes>> {
+    Ok(PyBytes::new_bound(
+        py,
+        &bencode::encode(&from_python(object)?),
+    ))
+}
+
+/// A `.torrent` file, decoded once, with helpers for its metainfo.
+#[pyclass(frozen)]
+struct Torrent {
+    raw: Vec<u8>,
+    value: Bencode,
+}
+
+#[pymethods]
+impl Torrent {
+    #[new]
+    fn new(data: &[u8]) -> PyResult<Self> {
+        Ok(Self {
+            raw: data.to_vec(),
+            value: bencode::decode(data).map_err(value_error)?,
+        })
+    }
+
+    /// The whole document, as `decode` returns it.
+    #[getter]
+    fn value(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_python(py, &self.value)
+    }
+
+    /// Hex-encoded SHA-1 of the raw info dictionary.
+    #[getter]
+    fn infohash(&self) -> PyResult<String> {
+        let infohash = torrent::infohash(&self.raw).map_err(value_error)?;
+        Ok(infohash.iter().map(|b| format!("{b:02x}")).collect())
+    }
+
+    #[getter]
+    fn magnet(&self) -> PyResult<String> {
+        torrent::magnet_link(&self.raw).map_err(value_error)
+    }
+
+    /// `(path, length)` pairs, the path a list of bytes components starting
+    /// with the torrent's name, exactly as stored.
+    #[getter]
+    fn files<'py>(&self, py: Python<'py>) -> PyResult<Vec<(Vec<Bound<'py, PyBytes>>, i64)>> {
+        Ok(torrent::files(&self.value)
+            .map_err(value_error)?
+            .into_iter()
+            .map(|file| {
+                let path = file
+                    .path
+                    .iter()
+                    .map(|c| PyBytes::new_bound(py, c))
+                    .collect();
+                (path, file.length)
+            })
+            .collect())
+    }
+
+    /// The SHA-1 hash of each piece, as 20-byte bytes objects.
+    #[getter]
+    fn pieces<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyBytes>>> {
+        Ok(torrent::pieces(&self.value)
+            .map_err(value_error)?
+            .iter()
+            .map(|hash| PyBytes::new_bound(py, hash))
+            .collect())
+    }
+}
+
+#[pymodule]
+fn bencode_decoder(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(decode, m)?)?;
+    m.add_function(wrap_pyfunction!(encode, m)?)?;
+    m.add_class::<Torrent>()?;
+    Ok(())
+}
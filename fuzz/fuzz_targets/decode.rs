@@ -0,0 +1,12 @@
+//! Decoding arbitrary bytes returns a value or an error, never panics,
+//! and an error never points past the end of the input.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Err(error) = bencode_decoder::decode(data) {
+        assert!(!error.offset().is_some_and(|offset| offset > data.len()));
+    }
+});
@@ -0,0 +1,18 @@
+//! Whatever decodes re-encodes to something that decodes to the same value,
+//! and encoding that again changes nothing: decode, then encode, is a fixed
+//! point after one step.
+
+#![no_main]
+
+use bencode_decoder::{decode, encode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = decode(data) else {
+        return;
+    };
+    let encoded = encode(&value);
+    let again = decode(&encoded).expect("an encoded value decodes");
+    assert_eq!(again, value);
+    assert_eq!(encode(&again), encoded);
+});
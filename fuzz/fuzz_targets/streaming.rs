@@ -0,0 +1,27 @@
+//! The sans-IO parser agrees with decoding the whole buffer at once, however
+//! the stream is split. The first byte picks the chunk size and the rest is
+//! the stream, fed in as a socket would deliver it.
+
+#![no_main]
+
+use bencode_decoder::{decode_iter, Bencode, FeedParser};
+use libfuzzer_sys::fuzz_target;
+
+fn feed(stream: &[u8], chunk: usize) -> Option<Vec<Bencode>> {
+    let mut parser = FeedParser::new();
+    let mut values = Vec::new();
+    for data in stream.chunks(chunk) {
+        values.extend(parser.feed(data).ok()?.values);
+    }
+    parser.finish().ok()?;
+    Some(values)
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&chunk, stream)) = data.split_first() else {
+        return;
+    };
+    let chunk = usize::from(chunk).max(1);
+    let whole = decode_iter(stream).collect::<Result<Vec<_>, _>>().ok();
+    assert_eq!(feed(stream, chunk), whole);
+});
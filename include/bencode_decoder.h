@@ -0,0 +1,59 @@
+/* C API for bencode_decoder, built with the `ffi` feature. Keep in sync with
+ * src/ffi.rs. */
+
+#ifndef BENCODE_DECODER_H
+#define BENCODE_DECODER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Bencode Bencode;
+
+typedef enum BencodeStatus {
+    BENCODE_OK = 0,
+    BENCODE_NULL_POINTER = 1,
+    BENCODE_UNEXPECTED_EOF = 2,
+    BENCODE_INVALID_SYNTAX = 3,
+    BENCODE_TRAILING_DATA = 4,
+    BENCODE_INVALID_PATH = 5,
+    BENCODE_NOT_FOUND = 6,
+    BENCODE_TYPE_MISMATCH = 7,
+} BencodeStatus;
+
+typedef enum BencodeType {
+    BENCODE_STRING = 0,
+    BENCODE_INTEGER = 1,
+    BENCODE_LIST = 2,
+    BENCODE_DICTIONARY = 3,
+} BencodeType;
+
+/* Decodes a complete document. On success *out owns the value and must be
+ * released with bencode_free. Nesting deeper than 256 levels is
+ * BENCODE_INVALID_SYNTAX. */
+BencodeStatus bencode_decode(const uint8_t *data, size_t len, Bencode **out);
+
+/* Looks up a path such as ".info.files[0].length". *out borrows from value. */
+BencodeStatus bencode_get_path(const Bencode *value, const char *path, const Bencode **out);
+
+BencodeType bencode_type(const Bencode *value);
+
+BencodeStatus bencode_get_int(const Bencode *value, int64_t *out);
+
+/* The returned bytes are not NUL-terminated and borrow from value. */
+BencodeStatus bencode_get_bytes(const Bencode *value, const uint8_t **data, size_t *len);
+
+/* Takes any int, so that out-of-range values from C are safe; those are
+ * described as an unknown status. */
+const char *bencode_status_message(int status);
+
+void bencode_free(Bencode *value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BENCODE_DECODER_H */
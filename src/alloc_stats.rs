@@ -0,0 +1,165 @@
+//! Allocation counting for tests and benchmarks. Install
+//! [`CountingAllocator`] as the global allocator of the test or bench
+//! binary, then wrap the code to measure in [`measure`]:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator;
+//!
+//! let (value, stats) = measure(|| bencode_decoder::decode(input));
+//! assert!(stats.allocations <= 40);
+//! ```
+//!
+//! Counts are kept per thread, so tests running in parallel don't see each
+//! other's allocations.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+/// What a [`measure`]d closure allocated.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Stats {
+    /// Allocations and reallocations made.
+    pub allocations: usize,
+    /// Bytes requested across all of them.
+    pub bytes: usize,
+    /// Most bytes live at once, above what was live when measuring started.
+    pub peak: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Counters {
+    allocations: usize,
+    bytes: usize,
+    live: isize,
+    peak: isize,
+}
+
+thread_local! {
+    static COUNTERS: Cell<Counters> = const {
+        Cell::new(Counters {
+            allocations: 0,
+            bytes: 0,
+            live: 0,
+            peak: 0,
+        })
+    };
+}
+
+/// Forwards to the system allocator, counting on the calling thread.
+pub struct CountingAllocator;
+
+fn record(allocated: usize, freed: usize) {
+    // Fails only while the thread is being torn down, when nobody's looking
+    let _ = COUNTERS.try_with(|counters| {
+        let mut c = counters.get();
+        if allocated > 0 {
+            c.allocations += 1;
+            c.bytes += allocated;
+        }
+        c.live += allocated as isize - freed as isize;
+        c.peak = c.peak.max(c.live);
+        counters.set(c);
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size(), 0);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(layout.size(), 0);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record(0, layout.size());
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size, layout.size());
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Runs `f`, returning its result and what it allocated on this thread.
+/// Reads zero unless [`CountingAllocator`] is the global allocator.
+pub fn measure<R>(f: impl FnOnce() -> R) -> (R, Stats) {
+    let start = COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        c.peak = c.live;
+        counters.set(c);
+        c
+    });
+    let result = f();
+    let end = COUNTERS.with(Cell::get);
+    (
+        result,
+        Stats {
+            allocations: end.allocations - start.allocations,
+            bytes: end.bytes - start.bytes,
+            peak: (end.peak - start.live) as usize,
+        },
+    )
+}
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cow::CowBencode, decode, encode};
+
+    /// A `get_peers` response with eight compact peers.
+    const KRPC: &[u8] = b"d1:rd2:id20:abcdefghij01234567895:token8:aoeusnth\
+        6:valuesl6:axje.u6:idhtnm6:axje.v6:idhtnn6:axje.w6:idhtno6:axje.x6:idhtnpe\
+        e1:t2:aa1:y1:re";
+
+    #[test]
+    fn counts() {
+        let (_, stats) = measure(|| Vec::<u8>::with_capacity(100));
+        assert_eq!(
+            stats,
+            Stats {
+                allocations: 1,
+                bytes: 100,
+                peak: 100
+            }
+        );
+        let (vec, stats) = measure(|| {
+            let mut vec = Vec::<u8>::with_capacity(10);
+            vec.extend_from_slice(&[0; 10]);
+            vec.reserve_exact(20);
+            vec
+        });
+        assert_eq!((stats.allocations, stats.bytes, stats.peak), (2, 40, 30));
+        drop(vec);
+    }
+
+    // Budgets for the hot paths, so a change that starts allocating more
+    // shows up here.
+
+    #[test]
+    fn decode_budget() {
+        let (value, stats) = measure(|| decode(KRPC).unwrap());
+        // One per string and key, one per list, and a node or two per
+        // dictionary.
+        assert!(stats.allocations <= 22, "{stats:?}");
+
+        let (_, stats) = measure(|| CowBencode::decode(KRPC).unwrap());
+        assert!(stats.allocations <= 4, "borrowed: {stats:?}");
+
+        let (encoded, stats) = measure(|| encode(&value));
+        assert_eq!(encoded, KRPC);
+        // Growing the output by doubling, from 8 bytes, and nothing else.
+        assert!(stats.allocations <= 6, "encode: {stats:?}");
+        assert!(stats.peak <= 2 * KRPC.len(), "{stats:?}");
+    }
+}
@@ -0,0 +1,216 @@
+//! The `announce-list` tracker tiers of BEP 12.
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    convert::derive::{dictionary, optional_field},
+    Bencode, Error, FromBencode, ToBencode,
+};
+
+/// Trackers a client tries in order, moving to the next tier only once every
+/// tracker in this one has failed.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Tier {
+    pub trackers: Vec<String>,
+}
+
+/// Tiers in priority order. Empty tiers are never kept.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct AnnounceList {
+    pub tiers: Vec<Tier>,
+}
+
+impl AnnounceList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the tiers of a decoded torrent. Without `announce-list`, the
+    /// `announce` tracker makes up a single tier.
+    pub fn from_torrent(torrent: &Bencode) -> Result<Self, Error> {
+        let d = dictionary(torrent)?;
+        if let Some(list) = optional_field::<AnnounceList>(d, "announce-list")? {
+            return Ok(list);
+        }
+
+        let mut list = Self::new();
+        if let Some(announce) = optional_field::<String>(d, "announce")? {
+            list.add_tier([announce]);
+        }
+        Ok(list)
+    }
+
+    /// Writes the tiers back as `announce-list`, removing it if there are
+    /// none. `announce` is kept for clients without BEP 12 support, and set
+    /// to the first tracker when missing or no longer listed.
+    pub fn apply_to(&self, torrent: &mut Bencode) {
+        let Bencode::Dictionary(d) = torrent else {
+            return;
+        };
+
+        let Some(first) = self.flatten().first().copied() else {
+            d.remove(&b"announce-list"[..]);
+            return;
+        };
+        d.insert(b"announce-list".to_vec(), self.to_bencode());
+
+        let listed = match d.get(&b"announce"[..]) {
+            Some(Bencode::String(announce)) => self.contains(&String::from_utf8_lossy(announce)),
+            _ => false,
+        };
+        if !listed {
+            d.insert(b"announce".to_vec(), first.into());
+        }
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.flatten().contains(&url)
+    }
+
+    /// Every tracker in priority order.
+    pub fn flatten(&self) -> Vec<&str> {
+        self.tiers
+            .iter()
+            .flat_map(|tier| tier.trackers.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// Appends a tier of lowest priority, unless it's empty.
+    pub fn add_tier<S: Into<String>>(&mut self, trackers: impl IntoIterator<Item = S>) {
+        let trackers: Vec<String> = trackers.into_iter().map(Into::into).collect();
+        if !trackers.is_empty() {
+            self.tiers.push(Tier { trackers });
+        }
+    }
+
+    /// Adds a tracker to the end of tier `tier`, or in a new last tier if
+    /// there's no such tier.
+    pub fn add(&mut self, tier: usize, url: impl Into<String>) {
+        match self.tiers.get_mut(tier) {
+            Some(tier) => tier.trackers.push(url.into()),
+            None => self.add_tier([url]),
+        }
+    }
+
+    /// Removes every occurrence of `url`, returning whether there was one.
+    pub fn remove(&mut self, url: &str) -> bool {
+        let before = self.flatten().len();
+        for tier in &mut self.tiers {
+            tier.trackers.retain(|tracker| tracker != url);
+        }
+        self.tiers.retain(|tier| !tier.trackers.is_empty());
+        self.flatten().len() != before
+    }
+
+    /// Moves `url` to the front of its tier, as BEP 12 has clients do after a
+    /// successful announce. Returns whether it was found.
+    pub fn promote(&mut self, url: &str) -> bool {
+        for tier in &mut self.tiers {
+            if let Some(index) = tier.trackers.iter().position(|tracker| tracker == url) {
+                let tracker = tier.trackers.remove(index);
+                tier.trackers.insert(0, tracker);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Keeps only the first, highest-priority occurrence of each tracker.
+    pub fn dedupe(&mut self) {
+        let mut seen: Vec<String> = Vec::new();
+        for tier in &mut self.tiers {
+            tier.trackers.retain(|tracker| {
+                let first = !seen.contains(tracker);
+                if first {
+                    seen.push(tracker.clone());
+                }
+                first
+            });
+        }
+        self.tiers.retain(|tier| !tier.trackers.is_empty());
+    }
+}
+
+impl ToBencode for AnnounceList {
+    fn to_bencode(&self) -> Bencode {
+        let tiers = self.tiers.iter().map(|tier| tier.trackers.to_bencode());
+        Bencode::List(tiers.collect())
+    }
+}
+
+impl FromBencode for AnnounceList {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        let mut list = Self::new();
+        for trackers in Vec::<Vec<String>>::from_bencode(value)? {
+            list.add_tier(trackers);
+        }
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode, encode};
+
+    fn list(tiers: &[&[&str]]) -> AnnounceList {
+        let mut list = AnnounceList::new();
+        for tier in tiers {
+            list.add_tier(tier.iter().copied());
+        }
+        list
+    }
+
+    #[test]
+    fn read_tiers() {
+        let torrent = decode(b"d8:announce1:a13:announce-listll1:b1:celel1:deee").unwrap();
+        assert_eq!(
+            AnnounceList::from_torrent(&torrent),
+            Ok(list(&[&["b", "c"], &["d"]]))
+        );
+
+        let torrent = decode(b"d8:announce1:ae").unwrap();
+        assert_eq!(AnnounceList::from_torrent(&torrent), Ok(list(&[&["a"]])));
+        assert_eq!(
+            AnnounceList::from_torrent(&decode(b"de").unwrap()),
+            Ok(AnnounceList::new())
+        );
+    }
+
+    #[test]
+    fn manipulate() {
+        let mut trackers = list(&[&["a", "b"], &["c", "a"]]);
+
+        assert!(trackers.promote("b"));
+        assert!(!trackers.promote("x"));
+        assert_eq!(trackers, list(&[&["b", "a"], &["c", "a"]]));
+
+        trackers.dedupe();
+        assert_eq!(trackers.flatten(), ["b", "a", "c"]);
+
+        trackers.add(1, "d");
+        trackers.add(7, "e");
+        assert_eq!(trackers, list(&[&["b", "a"], &["c", "d"], &["e"]]));
+
+        assert!(trackers.remove("e"));
+        assert!(!trackers.remove("e"));
+        assert_eq!(trackers.tiers.len(), 2);
+    }
+
+    #[test]
+    fn write_back() {
+        let mut torrent = decode(b"d8:announce1:x4:infodee").unwrap();
+
+        list(&[&["a", "b"], &["c"]]).apply_to(&mut torrent);
+        assert_eq!(
+            encode(&torrent),
+            b"d8:announce1:a13:announce-listll1:a1:bel1:cee4:infodee"
+        );
+
+        list(&[&["c", "a"]]).apply_to(&mut torrent);
+        assert_eq!(torrent.get_path("announce"), Ok(Some(&"a".into())));
+
+        AnnounceList::new().apply_to(&mut torrent);
+        assert_eq!(encode(&torrent), b"d8:announce1:a4:infodee");
+    }
+}
@@ -0,0 +1,280 @@
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::fmt::Write;
+
+use crate::path::{self, Segment};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Bencode {
+    String(Vec<u8>),
+    Integer(i64),
+    List(Vec<Bencode>),
+    Dictionary(BTreeMap<Vec<u8>, Bencode>),
+}
+
+impl Bencode {
+    /// Looks up a nested value by a path such as `.info.files[0].length`.
+    pub fn get_path(&self, path: &str) -> Result<Option<&Bencode>, crate::Error> {
+        let mut value = self;
+
+        for segment in path::parse(path)? {
+            let next = match (segment, value) {
+                (Segment::Key(key), Bencode::Dictionary(d)) => d.get(key.as_bytes()),
+                (Segment::Index(i), Bencode::List(l)) => l.get(i),
+                _ => None,
+            };
+
+            match next {
+                Some(next) => value = next,
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(value))
+    }
+
+    /// Like [`get_path`](Self::get_path), for changing the value in place.
+    pub fn get_path_mut(&mut self, path: &str) -> Result<Option<&mut Bencode>, crate::Error> {
+        Ok(get_segments_mut(self, &path::parse(path)?))
+    }
+
+    /// Deletes the value at `path` and returns it, or `None` if there is
+    /// nothing there. Removing a list element shifts the later ones down, as
+    /// [`Vec::remove`] does. The root can't be removed.
+    pub fn remove_path(&mut self, path: &str) -> Result<Option<Bencode>, crate::Error> {
+        let mut segments = path::parse(path)?;
+        let last = segments
+            .pop()
+            .ok_or_else(|| crate::Error::InvalidPath(path.into()))?;
+
+        Ok(match (last, get_segments_mut(self, &segments)) {
+            (Segment::Key(key), Some(Bencode::Dictionary(d))) => d.remove(key.as_bytes()),
+            (Segment::Index(i), Some(Bencode::List(l))) if i < l.len() => Some(l.remove(i)),
+            _ => None,
+        })
+    }
+
+    /// Stores `value` at `path`, replacing and returning what was there. The
+    /// parent must already exist; a list index must be in range.
+    pub fn set_path(
+        &mut self,
+        path: &str,
+        value: Bencode,
+    ) -> Result<Option<Bencode>, crate::Error> {
+        self.set(path, value, false)
+    }
+
+    /// Like [`set_path`](Self::set_path), but creates missing dictionaries
+    /// along the way, e.g. `info` and `x` for `.info.x.y` on an empty
+    /// dictionary. Missing lists can't be created.
+    pub fn set_path_create(
+        &mut self,
+        path: &str,
+        value: Bencode,
+    ) -> Result<Option<Bencode>, crate::Error> {
+        self.set(path, value, true)
+    }
+
+    fn set(
+        &mut self,
+        path: &str,
+        value: Bencode,
+        create: bool,
+    ) -> Result<Option<Bencode>, crate::Error> {
+        let segments = path::parse(path)?;
+        let not_found = || crate::Error::NotFound(path.into());
+        let Some((last, parents)) = segments.split_last() else {
+            return Ok(Some(core::mem::replace(self, value)));
+        };
+
+        let mut parent = self;
+        for (depth, segment) in parents.iter().enumerate() {
+            let at = || segments_path(&segments[..depth]);
+            parent = match (segment, parent) {
+                (Segment::Key(key), Bencode::Dictionary(d)) => match create {
+                    true => d
+                        .entry(key.as_bytes().to_vec())
+                        .or_insert_with(|| Bencode::Dictionary(BTreeMap::new())),
+                    false => d.get_mut(key.as_bytes()).ok_or_else(not_found)?,
+                },
+                (Segment::Index(i), Bencode::List(l)) => l.get_mut(*i).ok_or_else(not_found)?,
+                (segment, _) => return Err(expected_container(segment, at())),
+            };
+        }
+
+        match (last, parent) {
+            (Segment::Key(key), Bencode::Dictionary(d)) => {
+                Ok(d.insert(key.as_bytes().to_vec(), value))
+            }
+            (Segment::Index(i), Bencode::List(l)) => {
+                let slot = l.get_mut(*i).ok_or_else(not_found)?;
+                Ok(Some(core::mem::replace(slot, value)))
+            }
+            (segment, _) => Err(expected_container(segment, segments_path(parents))),
+        }
+    }
+}
+
+/// The error for a path step that needs a dictionary or a list at `path`.
+fn expected_container(segment: &Segment, path: String) -> crate::Error {
+    let expected = match segment {
+        Segment::Key(_) => "dictionary",
+        Segment::Index(_) => "list",
+    };
+    crate::Error::InvalidType { path, expected }
+}
+
+/// Formats segments like error paths, e.g. `info.files[0]`.
+fn segments_path(segments: &[Segment]) -> String {
+    let mut path = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Key(key) if path.is_empty() => path.push_str(key),
+            Segment::Key(key) => {
+                let _ = write!(path, ".{key}");
+            }
+            Segment::Index(i) => {
+                let _ = write!(path, "[{i}]");
+            }
+        }
+    }
+    path
+}
+
+fn get_segments_mut<'a>(
+    mut value: &'a mut Bencode,
+    segments: &[Segment],
+) -> Option<&'a mut Bencode> {
+    for segment in segments {
+        value = match (segment, value) {
+            (Segment::Key(key), Bencode::Dictionary(d)) => d.get_mut(key.as_bytes())?,
+            (Segment::Index(i), Bencode::List(l)) => l.get_mut(*i)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+impl From<i64> for Bencode {
+    fn from(i: i64) -> Self {
+        Bencode::Integer(i)
+    }
+}
+
+impl From<&str> for Bencode {
+    fn from(s: &str) -> Self {
+        Bencode::String(s.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for Bencode {
+    fn from(s: String) -> Self {
+        Bencode::String(s.into_bytes())
+    }
+}
+
+impl From<&[u8]> for Bencode {
+    fn from(s: &[u8]) -> Self {
+        Bencode::String(s.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for Bencode {
+    fn from(s: Vec<u8>) -> Self {
+        Bencode::String(s)
+    }
+}
+
+impl From<Vec<Bencode>> for Bencode {
+    fn from(l: Vec<Bencode>) -> Self {
+        Bencode::List(l)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn get_path() {
+        let value = decode(b"d4:infod5:filesld6:lengthi7eeee4:name3:fooe").unwrap();
+
+        assert_eq!(
+            value.get_path(".info.files[0].length"),
+            Ok(Some(&Bencode::Integer(7)))
+        );
+        assert_eq!(
+            value.get_path("name"),
+            Ok(Some(&Bencode::String(b"foo".to_vec())))
+        );
+        assert_eq!(value.get_path(".info.files[1]"), Ok(None));
+        assert_eq!(value.get_path(".name[0]"), Ok(None));
+    }
+
+    #[test]
+    fn remove_path() {
+        let mut value = decode(b"d4:infod5:filesl1:a1:b1:cee4:name3:fooe").unwrap();
+
+        assert_eq!(
+            value.remove_path(".info.files[0]"),
+            Ok(Some(Bencode::from("a")))
+        );
+        assert_eq!(
+            value.get_path(".info.files[0]"),
+            Ok(Some(&Bencode::from("b")))
+        );
+        assert_eq!(value.remove_path(".info.files[2]"), Ok(None));
+        assert_eq!(value.remove_path(".name"), Ok(Some(Bencode::from("foo"))));
+        assert_eq!(value.remove_path(".name"), Ok(None));
+        assert_eq!(value.remove_path(".info.files[0].x"), Ok(None));
+        assert_eq!(
+            value.remove_path("."),
+            Err(crate::Error::InvalidPath(".".into()))
+        );
+
+        *value.get_path_mut(".info.files[1]").unwrap().unwrap() = Bencode::Integer(1);
+        assert_eq!(crate::encode(&value), b"d4:infod5:filesl1:bi1eeee");
+    }
+
+    #[test]
+    fn set_path() {
+        use crate::Error;
+
+        let mut value = decode(b"d4:infod5:filesli1eeee").unwrap();
+
+        assert_eq!(
+            value.set_path(".info.files[0]", 2.into()),
+            Ok(Some(Bencode::Integer(1)))
+        );
+        assert_eq!(value.set_path(".info.name", "x".into()), Ok(None));
+        assert_eq!(
+            value.set_path(".info.files[1]", 3.into()),
+            Err(Error::NotFound(".info.files[1]".into()))
+        );
+        assert_eq!(
+            value.set_path(".meta.source", "y".into()),
+            Err(Error::NotFound(".meta.source".into()))
+        );
+        assert_eq!(value.set_path_create(".meta.source", "y".into()), Ok(None));
+        assert_eq!(
+            value.set_path_create(".info.name.x", 1.into()),
+            Err(Error::invalid_type("dictionary")
+                .at_key("name")
+                .at_key("info"))
+        );
+        assert_eq!(
+            value.set_path_create(".info.files[0][0]", 1.into()),
+            Err(Error::invalid_type("list")
+                .at_index(0)
+                .at_key("files")
+                .at_key("info"))
+        );
+        assert_eq!(
+            crate::encode(&value),
+            b"d4:infod5:filesli2ee4:name1:xe4:metad6:source1:yee"
+        );
+
+        assert_eq!(value.set_path(".", 0.into()).unwrap().map(|_| ()), Some(()));
+        assert_eq!(value, Bencode::Integer(0));
+    }
+}
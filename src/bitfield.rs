@@ -0,0 +1,176 @@
+//! Piece bitfields, as sent in peer `bitfield` messages and stored in client
+//! resume data.
+
+use alloc::vec::Vec;
+
+use crate::Error;
+
+/// A fixed number of bits, most significant bit of the first byte first, as
+/// in the peer wire protocol (BEP 3).
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Bitfield {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bitfield {
+    /// `len` cleared bits.
+    pub fn new(len: usize) -> Self {
+        Self {
+            bytes: alloc::vec![0; len.div_ceil(8)],
+            len,
+        }
+    }
+
+    /// `len` set bits.
+    pub fn full(len: usize) -> Self {
+        (0..len).map(|_| true).collect()
+    }
+
+    /// Reads the payload of a `bitfield` message for a torrent with `len`
+    /// pieces. It must be exactly long enough, with the spare bits at the end
+    /// cleared.
+    pub fn from_bytes(bytes: &[u8], len: usize) -> Result<Self, Error> {
+        let error = Error::invalid_type("bitfield matching the piece count");
+        if bytes.len() != len.div_ceil(8) {
+            return Err(error);
+        }
+        let spare = bytes.len() * 8 - len;
+        if spare > 0 && bytes[bytes.len() - 1] & ((1 << spare) - 1) != 0 {
+            return Err(error);
+        }
+
+        Ok(Self {
+            bytes: bytes.to_vec(),
+            len,
+        })
+    }
+
+    /// Reads libtorrent's one-byte-per-piece form, where a piece is present
+    /// if its byte has the low bit set.
+    pub fn from_piece_bytes(bytes: &[u8]) -> Self {
+        bytes.iter().map(|byte| byte & 1 == 1).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The packed bits, ready to send in a `bitfield` message.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Whether bit `index` is set; out-of-range bits are never set.
+    pub fn get(&self, index: usize) -> bool {
+        index < self.len && self.bytes[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+
+    /// Panics if `index` is out of range.
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(
+            index < self.len,
+            "bit {index} out of range for {} bits",
+            self.len
+        );
+        let mask = 0x80 >> (index % 8);
+        match value {
+            true => self.bytes[index / 8] |= mask,
+            false => self.bytes[index / 8] &= !mask,
+        }
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.bytes
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+
+    /// Indices of the set bits, in increasing order.
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(|&index| self.get(index))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.count_ones() == self.len
+    }
+
+    /// Percentage of a torrent's `piece_count` pieces that are set. Bits past
+    /// `piece_count` are ignored and missing ones count as not downloaded. A
+    /// torrent with no pieces is complete.
+    pub fn completion(&self, piece_count: usize) -> f64 {
+        if piece_count == 0 {
+            return 100.0;
+        }
+        let have = self.ones().take_while(|&index| index < piece_count).count();
+        have as f64 * 100.0 / piece_count as f64
+    }
+}
+
+impl FromIterator<bool> for Bitfield {
+    fn from_iter<I: IntoIterator<Item = bool>>(bits: I) -> Self {
+        let mut bitfield = Self::default();
+        for bit in bits {
+            if bitfield.len % 8 == 0 {
+                bitfield.bytes.push(0);
+            }
+            bitfield.len += 1;
+            bitfield.set(bitfield.len - 1, bit);
+        }
+        bitfield
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn peer_message_payload() {
+        let bitfield = Bitfield::from_bytes(&[0b1010_0000, 0b1000_0000], 9).unwrap();
+
+        assert_eq!(bitfield.len(), 9);
+        assert!(bitfield.get(0) && !bitfield.get(1) && bitfield.get(8));
+        assert!(!bitfield.get(9));
+        assert_eq!(bitfield.ones().collect::<Vec<_>>(), [0, 2, 8]);
+        assert_eq!(bitfield.count_ones(), 3);
+
+        assert!(Bitfield::from_bytes(&[0xff], 9).is_err());
+        assert!(Bitfield::from_bytes(&[0xff, 0xc0], 9).is_err());
+        assert!(Bitfield::from_bytes(&[0xff, 0x80], 9)
+            .unwrap()
+            .is_complete());
+    }
+
+    #[test]
+    fn build_and_set() {
+        let mut bitfield = Bitfield::new(10);
+        bitfield.set(3, true);
+        bitfield.set(9, true);
+        assert_eq!(bitfield.as_bytes(), [0b0001_0000, 0b0100_0000]);
+        bitfield.set(3, false);
+        assert_eq!(bitfield.count_ones(), 1);
+
+        assert_eq!(
+            Bitfield::from_piece_bytes(&[1, 0, 3]),
+            [true, false, true].into_iter().collect()
+        );
+        assert_eq!(Bitfield::full(3).as_bytes(), [0b1110_0000]);
+    }
+
+    #[test]
+    fn completion() {
+        let bitfield: Bitfield = vec![true, false, true, true].into_iter().collect();
+
+        assert_eq!(bitfield.completion(4), 75.0);
+        assert_eq!(bitfield.completion(8), 37.5);
+        assert_eq!(bitfield.completion(2), 50.0);
+        assert_eq!(Bitfield::new(0).completion(0), 100.0);
+    }
+}
@@ -0,0 +1,155 @@
+//! The bloom filters of DHT scrapes (BEP 33). A `get_peers` response with
+//! `scrape` set carries `BFsd` and `BFpe`, 256-byte filters of the seeds'
+//! and downloaders' IP addresses, which the requester merges and uses to
+//! estimate the swarm's size.
+
+use core::net::IpAddr;
+
+use crate::{sha1::sha1, Bencode, Error};
+
+/// The number of bits in a filter.
+const BITS: usize = 2048;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct BloomFilter([u8; BITS / 8]);
+
+impl Default for BloomFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BloomFilter {
+    pub fn new() -> Self {
+        Self([0; BITS / 8])
+    }
+
+    /// Reads a filter as sent in `BFsd` or `BFpe`, which must be 256 bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| Error::invalid_type("256-byte bloom filter"))
+    }
+
+    /// Reads the filter at `path` in a decoded message, e.g. `r.BFsd`.
+    pub fn from_message(message: &Bencode, path: &str) -> Result<Option<Self>, Error> {
+        match message.get_path(path)? {
+            None => Ok(None),
+            Some(Bencode::String(bytes)) => Self::from_bytes(bytes).map(Some),
+            Some(_) => Err(Error::invalid_type("string")),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Adds an address, in its compact form as BEP 33 hashes it.
+    pub fn insert(&mut self, ip: IpAddr) {
+        for index in indices(ip) {
+            self.0[index / 8] |= 1 << (index % 8);
+        }
+    }
+
+    /// Whether `ip` may have been added. False positives are expected;
+    /// false negatives can't happen.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        indices(ip)
+            .iter()
+            .all(|&index| self.0[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    /// Merges in the addresses of `other`, as when combining the filters
+    /// from several nodes.
+    pub fn union(&mut self, other: &BloomFilter) {
+        for (byte, other) in self.0.iter_mut().zip(other.0) {
+            *byte |= other;
+        }
+    }
+
+    /// An estimate of how many distinct addresses were added.
+    #[cfg(feature = "std")]
+    pub fn estimate(&self) -> f64 {
+        let m = BITS as f64;
+        let zeros = self.0.iter().map(|byte| byte.count_zeros()).sum::<u32>();
+        // A full filter only says the swarm is large; cap it to stay finite.
+        let zeros = f64::from(zeros.max(1));
+        (zeros / m).ln() / (2.0 * (1.0 - 1.0 / m).ln())
+    }
+}
+
+impl From<BloomFilter> for Bencode {
+    fn from(filter: BloomFilter) -> Self {
+        Bencode::String(filter.0.to_vec())
+    }
+}
+
+/// The two bits an address sets: the first two and next two bytes of its
+/// SHA-1, little-endian.
+fn indices(ip: IpAddr) -> [usize; 2] {
+    let hash = match ip {
+        IpAddr::V4(ip) => sha1(&ip.octets()),
+        IpAddr::V6(ip) => sha1(&ip.octets()),
+    };
+    [
+        usize::from(u16::from_le_bytes([hash[0], hash[1]])) % BITS,
+        usize::from(u16::from_le_bytes([hash[2], hash[3]])) % BITS,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::net::{Ipv4Addr, Ipv6Addr};
+
+    /// The example from BEP 33: 256 IPv4 and 1000 IPv6 addresses.
+    fn example() -> BloomFilter {
+        let mut filter = BloomFilter::new();
+        for i in 0..=255 {
+            filter.insert(Ipv4Addr::new(192, 0, 2, i).into());
+        }
+        for i in 0..=0x3e7 {
+            filter.insert(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, i).into());
+        }
+        filter
+    }
+
+    #[test]
+    fn membership() {
+        let filter = example();
+        assert!(filter.contains(Ipv4Addr::new(192, 0, 2, 7).into()));
+        assert!(filter.contains(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).into()));
+
+        let mut merged = BloomFilter::new();
+        assert!(!merged.contains(Ipv4Addr::new(192, 0, 2, 7).into()));
+        merged.union(&filter);
+        assert_eq!(merged, filter);
+
+        let bytes = Bencode::from(filter.clone());
+        let message = crate::builder::DictBuilder::new()
+            .insert(
+                "r",
+                crate::builder::DictBuilder::new().insert("BFsd", bytes),
+            )
+            .build();
+        assert_eq!(
+            BloomFilter::from_message(&message, "r.BFsd"),
+            Ok(Some(filter))
+        );
+        assert_eq!(BloomFilter::from_message(&message, "r.BFpe"), Ok(None));
+        assert!(BloomFilter::from_bytes(&[0; 255]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn estimate() {
+        // BEP 33 gives 1224.93 for its example.
+        assert!(
+            (example().estimate() - 1224.93).abs() < 0.01,
+            "{}",
+            example().estimate()
+        );
+        assert_eq!(BloomFilter::new().estimate(), 0.0);
+    }
+}
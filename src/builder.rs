@@ -0,0 +1,92 @@
+//! Chainable construction of lists and dictionaries.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use crate::Bencode;
+
+#[derive(Default)]
+pub struct ListBuilder {
+    list: Vec<Bencode>,
+}
+
+impl ListBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(mut self, value: impl Into<Bencode>) -> Self {
+        self.list.push(value.into());
+        self
+    }
+
+    pub fn build(self) -> Bencode {
+        Bencode::List(self.list)
+    }
+}
+
+/// Builds a dictionary. Keys are kept sorted, so the result is canonical; a
+/// repeated key replaces the earlier value.
+#[derive(Default)]
+pub struct DictBuilder {
+    dict: BTreeMap<Vec<u8>, Bencode>,
+}
+
+impl DictBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(mut self, key: impl Into<Vec<u8>>, value: impl Into<Bencode>) -> Self {
+        self.dict.insert(key.into(), value.into());
+        self
+    }
+
+    /// Inserts the entry only when a value is given, for optional fields.
+    pub fn insert_some(self, key: impl Into<Vec<u8>>, value: Option<impl Into<Bencode>>) -> Self {
+        match value {
+            Some(value) => self.insert(key, value),
+            None => self,
+        }
+    }
+
+    pub fn build(self) -> Bencode {
+        Bencode::Dictionary(self.dict)
+    }
+}
+
+impl From<ListBuilder> for Bencode {
+    fn from(builder: ListBuilder) -> Self {
+        builder.build()
+    }
+}
+
+impl From<DictBuilder> for Bencode {
+    fn from(builder: DictBuilder) -> Self {
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode;
+
+    #[test]
+    fn build_nested() {
+        let value = DictBuilder::new()
+            .insert("name", "file.txt")
+            .insert("length", 42)
+            .insert_some("comment", None::<&str>)
+            .insert(
+                "announce-list",
+                ListBuilder::new().push(ListBuilder::new().push("udp://a")),
+            )
+            .insert("pieces", &[0xff, 0x00][..])
+            .build();
+
+        assert_eq!(
+            encode(&value),
+            b"d13:announce-listll7:udp://aee6:lengthi42e4:name8:file.txt6:pieces2:\xff\x00e"
+        );
+    }
+}
@@ -0,0 +1,36 @@
+//! Cancelling long decodes and hashing runs from another thread, such as a
+//! GUI's cancel button or a server dropping a request that timed out.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::Error;
+
+/// Shared flag that operations taking one poll as they go. Clones share the
+/// flag, so one is kept to cancel with and another handed to the work.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every operation holding a clone of this token to stop. There's
+    /// no undoing it.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fails with [`Error::Cancelled`] once cancelled.
+    pub fn check(&self) -> Result<(), Error> {
+        match self.is_cancelled() {
+            true => Err(Error::Cancelled),
+            false => Ok(()),
+        }
+    }
+}
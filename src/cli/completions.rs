@@ -0,0 +1,273 @@
+//! Completion scripts generated from the command table.
+
+use std::fmt::Write;
+
+use super::{Command, COMMANDS};
+
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+const BIN: &str = "bencode_decoder";
+
+pub fn generate(shell: &str) -> String {
+    match shell {
+        "bash" => bash(),
+        "zsh" => zsh(),
+        "fish" => fish(),
+        "powershell" => powershell(),
+        _ => unreachable!("shell is validated against SHELLS"),
+    }
+}
+
+/// Flags and fixed argument values offered after a command.
+fn words(command: &Command) -> Vec<String> {
+    let mut words: Vec<String> = command
+        .flags
+        .iter()
+        .flat_map(|flag| {
+            let long = format!("--{}", flag.long);
+            flag.short
+                .map(|s| format!("-{s}"))
+                .into_iter()
+                .chain([long])
+        })
+        .collect();
+    for arg in command.args {
+        words.extend(arg.choices.iter().map(|choice| choice.to_string()));
+    }
+    words
+}
+
+fn names() -> String {
+    COMMANDS
+        .iter()
+        .map(|command| command.name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bash() -> String {
+    let mut script = String::new();
+    let function = format!("_{BIN}");
+
+    writeln!(script, "{function}() {{").unwrap();
+    writeln!(script, r#"    local cur="${{COMP_WORDS[COMP_CWORD]}}""#).unwrap();
+    writeln!(script, r#"    if [ "$COMP_CWORD" -eq 1 ]; then"#).unwrap();
+    writeln!(
+        script,
+        r#"        COMPREPLY=($(compgen -W "{}" -- "$cur"))"#,
+        names()
+    )
+    .unwrap();
+    writeln!(script, "        return").unwrap();
+    writeln!(script, "    fi").unwrap();
+    writeln!(script, r#"    case "${{COMP_WORDS[1]}}" in"#).unwrap();
+    for command in COMMANDS {
+        writeln!(
+            script,
+            r#"        {}) COMPREPLY=($(compgen -W "{}" -- "$cur")) ;;"#,
+            command.name,
+            words(command).join(" ")
+        )
+        .unwrap();
+    }
+    writeln!(script, "    esac").unwrap();
+    writeln!(script, "}}").unwrap();
+    writeln!(script, "complete -o default -F {function} {BIN}").unwrap();
+
+    script
+}
+
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', r"'\''")
+        .replace('[', r"\[")
+        .replace(']', r"\]")
+        .replace(':', r"\:")
+}
+
+fn zsh() -> String {
+    let mut script = String::new();
+
+    writeln!(script, "#compdef {BIN}\n").unwrap();
+    writeln!(script, "_{BIN}() {{").unwrap();
+    writeln!(script, "    local -a commands").unwrap();
+    writeln!(script, "    commands=(").unwrap();
+    for command in COMMANDS {
+        writeln!(
+            script,
+            "        '{}:{}'",
+            command.name,
+            zsh_escape(command.about)
+        )
+        .unwrap();
+    }
+    writeln!(script, "    )").unwrap();
+    writeln!(script, "    if (( CURRENT == 2 )); then").unwrap();
+    writeln!(script, "        _describe 'command' commands").unwrap();
+    writeln!(script, "        return").unwrap();
+    writeln!(script, "    fi").unwrap();
+    writeln!(script, "    shift words").unwrap();
+    writeln!(script, "    (( CURRENT-- ))").unwrap();
+    writeln!(script, "    case $words[1] in").unwrap();
+    for command in COMMANDS {
+        writeln!(script, "        {})", command.name).unwrap();
+        write!(script, "            _arguments").unwrap();
+        for flag in command.flags {
+            let about = zsh_escape(flag.about);
+            let value = flag.value.map_or(String::new(), |v| format!(":{v}:_files"));
+            match flag.short {
+                Some(s) => write!(
+                    script,
+                    " '(-{s} --{long})'{{-{s},--{long}}}'[{about}]{value}'",
+                    long = flag.long
+                ),
+                None => write!(script, " '--{}[{about}]{value}'", flag.long),
+            }
+            .unwrap();
+        }
+        for (i, arg) in command.args.iter().enumerate() {
+            let action = if arg.choices.is_empty() {
+                "_files".to_string()
+            } else {
+                format!("({})", arg.choices.join(" "))
+            };
+            match arg.variadic {
+                true => write!(script, " '*:{}:{action}'", arg.name),
+                false => write!(script, " '{}:{}:{action}'", i + 1, arg.name),
+            }
+            .unwrap();
+        }
+        writeln!(script, "\n            ;;").unwrap();
+    }
+    writeln!(script, "    esac").unwrap();
+    writeln!(script, "}}\n").unwrap();
+    writeln!(script, r#"_{BIN} "$@""#).unwrap();
+
+    script
+}
+
+fn fish() -> String {
+    let mut script = String::new();
+
+    for command in COMMANDS {
+        writeln!(
+            script,
+            "complete -c {BIN} -f -n __fish_use_subcommand -a {} -d '{}'",
+            command.name,
+            command.about.replace('\'', r"\'")
+        )
+        .unwrap();
+    }
+    for command in COMMANDS {
+        let condition = format!("'__fish_seen_subcommand_from {}'", command.name);
+        for flag in command.flags {
+            write!(script, "complete -c {BIN} -n {condition} -l {}", flag.long).unwrap();
+            if let Some(s) = flag.short {
+                write!(script, " -s {s}").unwrap();
+            }
+            if flag.value.is_some() {
+                write!(script, " -r").unwrap();
+            }
+            writeln!(script, " -d '{}'", flag.about.replace('\'', r"\'")).unwrap();
+        }
+        for arg in command.args.iter().filter(|arg| !arg.choices.is_empty()) {
+            writeln!(
+                script,
+                "complete -c {BIN} -f -n {condition} -a '{}'",
+                arg.choices.join(" ")
+            )
+            .unwrap();
+        }
+    }
+
+    script
+}
+
+fn powershell() -> String {
+    let mut script = String::new();
+
+    writeln!(
+        script,
+        "Register-ArgumentCompleter -Native -CommandName {BIN} -ScriptBlock {{"
+    )
+    .unwrap();
+    writeln!(
+        script,
+        "    param($wordToComplete, $commandAst, $cursorPosition)"
+    )
+    .unwrap();
+    writeln!(
+        script,
+        "    $words = @($commandAst.CommandElements | ForEach-Object {{ $_.ToString() }})"
+    )
+    .unwrap();
+    writeln!(
+        script,
+        "    if ($words.Count -le 1 -or ($words.Count -eq 2 -and $wordToComplete)) {{"
+    )
+    .unwrap();
+    let names: Vec<String> = COMMANDS
+        .iter()
+        .map(|command| format!("'{}'", command.name))
+        .collect();
+    writeln!(script, "        $candidates = @({})", names.join(", ")).unwrap();
+    writeln!(script, "    }} else {{").unwrap();
+    writeln!(script, "        $candidates = switch ($words[1]) {{").unwrap();
+    for command in COMMANDS {
+        let words: Vec<String> = words(command)
+            .iter()
+            .map(|word| format!("'{word}'"))
+            .collect();
+        writeln!(
+            script,
+            "            '{}' {{ @({}) }}",
+            command.name,
+            words.join(", ")
+        )
+        .unwrap();
+    }
+    writeln!(script, "            default {{ @() }}").unwrap();
+    writeln!(script, "        }}").unwrap();
+    writeln!(script, "    }}").unwrap();
+    writeln!(
+        script,
+        r#"    $candidates | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{"#
+    )
+    .unwrap();
+    writeln!(
+        script,
+        "        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)"
+    )
+    .unwrap();
+    writeln!(script, "    }}").unwrap();
+    writeln!(script, "}}").unwrap();
+
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_cover_every_command() {
+        for shell in SHELLS {
+            let script = generate(shell);
+            for command in COMMANDS {
+                assert!(script.contains(command.name), "{shell}: {}", command.name);
+            }
+            assert!(script.contains("help"), "{shell}");
+        }
+    }
+
+    #[test]
+    fn bash_script() {
+        let script = bash();
+
+        assert!(script.contains(
+            r#"compgen -W "decode info report infohash hash dump graph stats files pieces query validate schema inspect-session dedupe verify metadata-fetch edit normalize merge conformance generate generate-torrent minimize mutate selftest completions" -- "$cur""#
+        ));
+        assert!(script.contains(
+            r#"completions) COMPREPLY=($(compgen -W "-h --help bash zsh fish powershell" -- "$cur")) ;;"#
+        ));
+    }
+}
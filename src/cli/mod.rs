@@ -0,0 +1,902 @@
+//! Command table and argument parsing for the binary. Every subcommand and
+//! flag is declared in [`COMMANDS`], which also drives `--help` output and
+//! shell completions.
+
+use std::collections::HashMap;
+
+pub mod completions;
+
+pub struct Command {
+    pub name: &'static str,
+    pub about: &'static str,
+    pub args: &'static [Arg],
+    pub flags: &'static [Flag],
+}
+
+pub struct Arg {
+    pub name: &'static str,
+    /// Fixed set of accepted values, offered by completions.
+    pub choices: &'static [&'static str],
+    /// Accepts any number of values, including none.
+    pub variadic: bool,
+}
+
+pub struct Flag {
+    pub long: &'static str,
+    pub short: Option<char>,
+    pub about: &'static str,
+    /// Name of the flag's value, for flags that take one.
+    pub value: Option<&'static str>,
+}
+
+const HELP: Flag = Flag {
+    long: "help",
+    short: Some('h'),
+    about: "Print help",
+    value: None,
+};
+
+/// Repeatable: `-v` for progress, `-vv` for phase timings, `-vvv` for
+/// everything.
+const VERBOSE: Flag = Flag {
+    long: "verbose",
+    short: Some('v'),
+    about: "Log more to stderr; repeat for more detail",
+    value: None,
+};
+
+const QUIET: Flag = Flag {
+    long: "quiet",
+    short: Some('q'),
+    about: "Log only errors",
+    value: None,
+};
+
+const ERRORS: Flag = Flag {
+    long: "errors",
+    short: None,
+    about: "Error format on stderr: text or json",
+    value: Some("format"),
+};
+
+/// For commands that rewrite files in place.
+const DRY_RUN: Flag = Flag {
+    long: "dry-run",
+    short: Some('n'),
+    about: "Print what would change without writing anything",
+    value: None,
+};
+
+/// Overrides `output` from the config file.
+const OUTPUT_FORMAT: Flag = Flag {
+    long: "output",
+    short: None,
+    about: "Output format: text or json",
+    value: Some("format"),
+};
+
+/// For commands whose output may be binary, which shells can mangle.
+const OUTPUT: Flag = Flag {
+    long: "output-file",
+    short: Some('o'),
+    about: "Write the result to a file, replacing it atomically",
+    value: Some("path"),
+};
+
+/// For commands reading files that may be gzip- or zstd-compressed.
+const NO_DECOMPRESS: Flag = Flag {
+    long: "no-decompress",
+    short: None,
+    about: "Read the file as is, even if it looks compressed",
+    value: None,
+};
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "decode",
+        about: "Decode a bencoded value and print it",
+        args: &[Arg {
+            name: "value",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            OUTPUT_FORMAT,
+            Flag {
+                long: "ascii",
+                short: None,
+                about: "Escape non-ASCII characters in text output",
+                value: None,
+            },
+            OUTPUT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "info",
+        about: "Summarize a .torrent file",
+        args: &[Arg {
+            name: "file",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            NO_DECOMPRESS,
+            Flag {
+                long: "format",
+                short: Some('f'),
+                about: "Print one line from a template like \"{name}\\t{infohash}\\t{size}\"",
+                value: Some("template"),
+            },
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "report",
+        about: "Write a summary of a torrent to share, as a web page or Markdown",
+        args: &[Arg {
+            name: "torrent",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            Flag {
+                long: "output",
+                short: None,
+                about: "Report format: html (default) or markdown",
+                value: Some("format"),
+            },
+            NO_DECOMPRESS,
+            OUTPUT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "infohash",
+        about: "Print a torrent's infohash",
+        args: &[Arg {
+            name: "torrent",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            Flag {
+                long: "format",
+                short: Some('f'),
+                about: "Encoding: hex (default), base32 or raw bytes",
+                value: Some("encoding"),
+            },
+            NO_DECOMPRESS,
+            OUTPUT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "hash",
+        about: "Hash the encoded bytes of a value as they appear in the file",
+        args: &[Arg {
+            name: "file",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            Flag {
+                long: "path",
+                short: Some('p'),
+                about: "Value to hash, e.g. .info.files; the whole file by default",
+                value: Some("path"),
+            },
+            Flag {
+                long: "algo",
+                short: Some('a'),
+                about: "Hash function: sha256 (default) or sha1",
+                value: Some("name"),
+            },
+            NO_DECOMPRESS,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "dump",
+        about: "Print the raw encoded bytes, escaping what isn't printable",
+        args: &[Arg {
+            name: "file",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            Flag {
+                long: "highlight",
+                short: None,
+                about: "Color length prefixes, strings, integers and markers",
+                value: None,
+            },
+            NO_DECOMPRESS,
+            OUTPUT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "graph",
+        about: "Print the structure of a file as a Graphviz graph",
+        args: &[Arg {
+            name: "file",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[NO_DECOMPRESS, OUTPUT, VERBOSE, QUIET, ERRORS, HELP],
+    },
+    Command {
+        name: "stats",
+        about: "Count the values in a file and what its bytes are spent on",
+        args: &[Arg {
+            name: "file",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            Flag {
+                long: "size-breakdown",
+                short: None,
+                about: "List the paths taking at least 1% of the file, largest first",
+                value: None,
+            },
+            NO_DECOMPRESS,
+            OUTPUT_FORMAT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "files",
+        about: "List the files in a torrent",
+        args: &[Arg {
+            name: "torrent",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            Flag {
+                long: "hashes",
+                short: None,
+                about: "Include each file's v2 pieces root",
+                value: None,
+            },
+            NO_DECOMPRESS,
+            OUTPUT_FORMAT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "pieces",
+        about: "List a torrent's piece hashes",
+        args: &[Arg {
+            name: "torrent",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[NO_DECOMPRESS, OUTPUT_FORMAT, VERBOSE, QUIET, ERRORS, HELP],
+    },
+    Command {
+        name: "query",
+        about: "Print the values queries select, e.g. .info.files[*].path",
+        args: &[
+            Arg {
+                name: "file",
+                choices: &[],
+                variadic: false,
+            },
+            Arg {
+                name: "queries",
+                choices: &[],
+                variadic: true,
+            },
+        ],
+        flags: &[NO_DECOMPRESS, OUTPUT_FORMAT, VERBOSE, QUIET, ERRORS, HELP],
+    },
+    Command {
+        name: "validate",
+        about: "Check that a file is valid bencode, optionally against a schema",
+        args: &[Arg {
+            name: "file",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            NO_DECOMPRESS,
+            Flag {
+                long: "schema",
+                short: Some('s'),
+                about: "Built-in schema to check against: torrent, tracker-response or krpc",
+                value: Some("name"),
+            },
+            Flag {
+                long: "key",
+                short: None,
+                about: "Check the torrent's signatures against a PEM public key or certificate",
+                value: Some("pem"),
+            },
+            Flag {
+                long: "deep",
+                short: None,
+                about: "Also check that v2 piece layers hash up to their files' pieces roots",
+                value: None,
+            },
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "schema",
+        about: "Infer the structure shared by a set of bencoded files",
+        args: &[Arg {
+            name: "files",
+            choices: &[],
+            variadic: true,
+        }],
+        flags: &[NO_DECOMPRESS, VERBOSE, QUIET, ERRORS, HELP],
+    },
+    Command {
+        name: "inspect-session",
+        about: "Report on each torrent in a client's session directory",
+        args: &[Arg {
+            name: "dir",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            Flag {
+                long: "client",
+                short: Some('c'),
+                about: "Session format: qbittorrent (BT_backup, the default) or rtorrent",
+                value: Some("name"),
+            },
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "dedupe",
+        about: "Find duplicate torrents in a directory tree",
+        args: &[Arg {
+            name: "dir",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[NO_DECOMPRESS, OUTPUT_FORMAT, VERBOSE, QUIET, ERRORS, HELP],
+    },
+    Command {
+        name: "verify",
+        about: "Check downloaded data against a torrent's piece hashes",
+        args: &[
+            Arg {
+                name: "torrent",
+                choices: &[],
+                variadic: false,
+            },
+            Arg {
+                name: "dir",
+                choices: &[],
+                variadic: false,
+            },
+        ],
+        flags: &[NO_DECOMPRESS, VERBOSE, QUIET, ERRORS, HELP],
+    },
+    Command {
+        name: "metadata-fetch",
+        about: "Download a torrent's info dictionary from peers, e.g. for a magnet link",
+        args: &[
+            Arg {
+                name: "infohash",
+                choices: &[],
+                variadic: false,
+            },
+            Arg {
+                name: "peers",
+                choices: &[],
+                variadic: true,
+            },
+        ],
+        flags: &[
+            Flag {
+                long: "timeout",
+                short: None,
+                about: "Seconds to wait on each peer before trying the next (default 10)",
+                value: Some("secs"),
+            },
+            OUTPUT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "edit",
+        about: "Change values in a file in place, keeping all other bytes",
+        args: &[Arg {
+            name: "file",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            Flag {
+                long: "set",
+                short: None,
+                about: "Replace the value at a path with a bencoded value, e.g. .announce=3:url",
+                value: Some("path=value"),
+            },
+            Flag {
+                long: "delete",
+                short: None,
+                about: "Remove the value at a path, e.g. .announce-list",
+                value: Some("path"),
+            },
+            Flag {
+                long: "sort",
+                short: None,
+                about: "Sort the list at a path, e.g. .url-list",
+                value: Some("path"),
+            },
+            Flag {
+                long: "sort-by",
+                short: None,
+                about: "Sort --sort lists by a key inside each element, e.g. length",
+                value: Some("key"),
+            },
+            Flag {
+                long: "dedupe",
+                short: None,
+                about: "Remove repeated elements from the list at a path",
+                value: Some("path"),
+            },
+            Flag {
+                long: "sign",
+                short: None,
+                about: "Sign the result with a PEM RSA private key (BEP 35)",
+                value: Some("key"),
+            },
+            Flag {
+                long: "signer",
+                short: None,
+                about: "Name to sign as, e.g. tracker.example",
+                value: Some("name"),
+            },
+            Flag {
+                long: "interactive",
+                short: Some('i'),
+                about: "Edit the whole document as JSON in $VISUAL or $EDITOR",
+                value: None,
+            },
+            DRY_RUN,
+            OUTPUT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "normalize",
+        about: "Rewrite files in canonical form, with sorted keys",
+        args: &[Arg {
+            name: "files",
+            choices: &[],
+            variadic: true,
+        }],
+        flags: &[DRY_RUN, OUTPUT, VERBOSE, QUIET, ERRORS, HELP],
+    },
+    Command {
+        name: "merge",
+        about: "Deep-merge files into the first one, later files winning",
+        args: &[Arg {
+            name: "files",
+            choices: &[],
+            variadic: true,
+        }],
+        flags: &[
+            Flag {
+                long: "keep",
+                short: None,
+                about: "Never overwrite values already present",
+                value: None,
+            },
+            Flag {
+                long: "combine-lists",
+                short: None,
+                about: "Append lists instead of replacing them, skipping repeats",
+                value: None,
+            },
+            NO_DECOMPRESS,
+            OUTPUT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "conformance",
+        about: "Check a decoder against the BEP 3 accept and reject cases",
+        args: &[],
+        flags: &[
+            Flag {
+                long: "command",
+                short: Some('c'),
+                about: "Decoder to check, run with each case's file and accepting by exiting 0; \
+                        this crate's decoder by default",
+                value: Some("command"),
+            },
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "generate",
+        about: "Write a random document, for fuzzing corpora and load tests",
+        args: &[],
+        flags: &[
+            Flag {
+                long: "seed",
+                short: Some('s'),
+                about: "Seed to generate from, so the output can be made again; logged with -v",
+                value: Some("n"),
+            },
+            Flag {
+                long: "depth",
+                short: None,
+                about: "Deepest nesting of lists and dictionaries (default 4)",
+                value: Some("n"),
+            },
+            Flag {
+                long: "length",
+                short: None,
+                about: "Most elements per list or dictionary (default 8)",
+                value: Some("n"),
+            },
+            Flag {
+                long: "string-length",
+                short: None,
+                about: "Longest string in bytes (default 32)",
+                value: Some("n"),
+            },
+            Flag {
+                long: "weights",
+                short: None,
+                about: "Relative chances of strings, integers, lists and dictionaries (default 4,3,2,2)",
+                value: Some("s,i,l,d"),
+            },
+            Flag {
+                long: "near-valid",
+                short: None,
+                about: "Break the document with a few small edits, as a fuzzer would",
+                value: None,
+            },
+            OUTPUT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "generate-torrent",
+        about: "Write fake metainfo of any size, for benchmarks and testing at scale",
+        args: &[],
+        flags: &[
+            Flag {
+                long: "files",
+                short: None,
+                about: "Files in the torrent (default 1, a single-file torrent)",
+                value: Some("n"),
+            },
+            Flag {
+                long: "size",
+                short: None,
+                about: "Total size, e.g. 10GB or 512MiB (default 1GB)",
+                value: Some("size"),
+            },
+            Flag {
+                long: "seed",
+                short: Some('s'),
+                about: "Seed to generate from, so the output can be made again; logged with -v",
+                value: Some("n"),
+            },
+            OUTPUT,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "minimize",
+        about: "Shrink an input while a command keeps failing on it",
+        args: &[
+            Arg {
+                name: "file",
+                choices: &[],
+                variadic: false,
+            },
+            Arg {
+                name: "command",
+                choices: &[],
+                variadic: false,
+            },
+        ],
+        flags: &[NO_DECOMPRESS, OUTPUT, VERBOSE, QUIET, ERRORS, HELP],
+    },
+    Command {
+        name: "mutate",
+        about: "Decode randomly broken copies of a document, reporting panics and anomalies",
+        args: &[Arg {
+            name: "file",
+            choices: &[],
+            variadic: false,
+        }],
+        flags: &[
+            Flag {
+                long: "count",
+                short: Some('n'),
+                about: "Copies to try (default 10000)",
+                value: Some("n"),
+            },
+            Flag {
+                long: "seed",
+                short: Some('s'),
+                about: "Seed for the edits, so a run can be repeated; logged with -v",
+                value: Some("n"),
+            },
+            NO_DECOMPRESS,
+            VERBOSE,
+            QUIET,
+            ERRORS,
+            HELP,
+        ],
+    },
+    Command {
+        name: "selftest",
+        about: "Check this build against known answers for decoding, encoding and hashing",
+        args: &[],
+        flags: &[VERBOSE, QUIET, ERRORS, HELP],
+    },
+    Command {
+        name: "completions",
+        about: "Print a shell completion script",
+        args: &[Arg {
+            name: "shell",
+            choices: completions::SHELLS,
+            variadic: false,
+        }],
+        flags: &[HELP],
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+#[derive(Default, Debug)]
+pub struct Matches {
+    pub args: Vec<String>,
+    flags: HashMap<&'static str, Vec<String>>,
+}
+
+impl Matches {
+    pub fn flag(&self, long: &str) -> bool {
+        self.flags.contains_key(long)
+    }
+
+    /// How many times a flag was given, e.g. 2 for `-vv`.
+    pub fn count(&self, long: &str) -> usize {
+        self.flags.get(long).map_or(0, Vec::len)
+    }
+
+    /// Every value given for a repeatable flag, in order.
+    pub fn values(&self, long: &str) -> &[String] {
+        self.flags.get(long).map_or(&[], Vec::as_slice)
+    }
+
+    /// The last value given for a flag that takes one.
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.flags.get(long)?.last().map(String::as_str)
+    }
+}
+
+pub fn parse(command: &Command, args: &[String]) -> Result<Matches, String> {
+    let mut matches = Matches::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            let flag = command.flags.iter().find(|flag| flag.long == name);
+            (flag.ok_or(format!("unknown flag --{name}"))?, value)
+        } else if arg.len() == 2 && arg.starts_with('-') {
+            let short = arg.chars().nth(1);
+            let flag = command.flags.iter().find(|flag| flag.short == short);
+            (flag.ok_or(format!("unknown flag {arg}"))?, None)
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|s| s.len() > 1) {
+            // A cluster like `-vv`, of flags without values.
+            for short in shorts.chars() {
+                let flag = command
+                    .flags
+                    .iter()
+                    .find(|flag| flag.short == Some(short) && flag.value.is_none())
+                    .ok_or(format!("unknown flag -{short} in {arg}"))?;
+                matches
+                    .flags
+                    .entry(flag.long)
+                    .or_default()
+                    .push(String::new());
+            }
+            continue;
+        } else {
+            matches.args.push(arg.clone());
+            continue;
+        };
+
+        let value = match (flag.value, inline_value) {
+            (Some(_), Some(value)) => value,
+            (Some(name), None) => args
+                .next()
+                .cloned()
+                .ok_or(format!("--{} requires a <{name}> value", flag.long))?,
+            (None, Some(_)) => return Err(format!("--{} does not take a value", flag.long)),
+            (None, None) => String::new(),
+        };
+        matches.flags.entry(flag.long).or_default().push(value);
+    }
+
+    if matches.flag("help") {
+        return Ok(matches);
+    }
+
+    let variadic = command.args.last().is_some_and(|arg| arg.variadic);
+    let required = command.args.len() - variadic as usize;
+    if matches.args.len() < required || (!variadic && matches.args.len() > required) {
+        return Err(format!("usage: {}", usage(command)));
+    }
+
+    for (arg, value) in command.args.iter().zip(&matches.args) {
+        if !arg.choices.is_empty() && !arg.choices.contains(&value.as_str()) {
+            return Err(format!(
+                "invalid <{}> {value:?}, expected one of: {}",
+                arg.name,
+                arg.choices.join(", ")
+            ));
+        }
+    }
+
+    Ok(matches)
+}
+
+fn usage(command: &Command) -> String {
+    let mut usage = format!("bencode_decoder {}", command.name);
+    for arg in command.args {
+        let dots = if arg.variadic { "..." } else { "" };
+        usage.push_str(&format!(" <{}>{dots}", arg.name));
+    }
+    if command.flags.len() > 1 {
+        usage.push_str(" [options]");
+    }
+    usage
+}
+
+pub fn help(command: &Command) -> String {
+    let mut help = format!(
+        "{}\n\nUsage: {}\n\nOptions:\n",
+        command.about,
+        usage(command)
+    );
+    for flag in command.flags {
+        let short = flag.short.map_or("    ".to_string(), |s| format!("-{s}, "));
+        let value = flag.value.map_or(String::new(), |v| format!(" <{v}>"));
+        help.push_str(&format!(
+            "  {short}--{}{value}\n      {}\n",
+            flag.long, flag.about
+        ));
+    }
+    help
+}
+
+pub fn overview() -> String {
+    let mut overview = "Usage: bencode_decoder <command> [args]\n\nCommands:\n".to_string();
+    let width = COMMANDS.iter().map(|c| c.name.len()).max().unwrap_or(0) + 2;
+    for command in COMMANDS {
+        overview.push_str(&format!("  {:<width$}{}\n", command.name, command.about));
+    }
+    overview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn overview_aligns_descriptions() {
+        let overview = overview();
+        let lines: Vec<_> = overview
+            .lines()
+            .filter(|line| line.starts_with("  "))
+            .collect();
+        assert_eq!(lines.len(), COMMANDS.len());
+        let column = |line: &str| line.find(|c: char| c.is_uppercase()).unwrap();
+        for (line, command) in lines.iter().zip(COMMANDS) {
+            assert!(line[2 + command.name.len()..].starts_with("  "), "{line}");
+            assert_eq!(column(line), column(lines[0]), "{line}");
+        }
+    }
+
+    #[test]
+    fn parse_args() {
+        let decode = find("decode").unwrap();
+
+        let matches = parse(decode, &args(&["i1e"])).unwrap();
+        assert_eq!(matches.args, ["i1e"]);
+        assert!(!matches.flag("help"));
+
+        assert!(parse(decode, &args(&["-h"])).unwrap().flag("help"));
+        assert!(parse(decode, &args(&[])).is_err());
+        assert!(parse(decode, &args(&["i1e", "i2e"])).is_err());
+        assert!(parse(decode, &args(&["--nope", "i1e"])).is_err());
+
+        let validate = find("validate").unwrap();
+        let matches = parse(validate, &args(&["a.torrent", "--schema", "torrent"])).unwrap();
+        assert_eq!(matches.value("schema"), Some("torrent"));
+        let matches = parse(validate, &args(&["-s", "krpc", "--schema=torrent", "x"])).unwrap();
+        assert_eq!(matches.value("schema"), Some("torrent"));
+        assert_eq!(
+            parse(validate, &args(&["x"])).unwrap().value("schema"),
+            None
+        );
+        assert!(parse(validate, &args(&["x", "--schema"])).is_err());
+
+        let info = find("info").unwrap();
+        let matches = parse(info, &args(&["-vv", "x", "--verbose"])).unwrap();
+        assert_eq!(matches.count("verbose"), 3);
+        assert_eq!(matches.count("quiet"), 0);
+        assert!(parse(info, &args(&["-vq", "x"])).unwrap().flag("quiet"));
+        assert!(parse(info, &args(&["-vx", "x"])).is_err());
+
+        let completions = find("completions").unwrap();
+        assert!(parse(completions, &args(&["zsh"])).is_ok());
+        assert!(parse(completions, &args(&["tcsh"])).is_err());
+    }
+}
@@ -0,0 +1,359 @@
+//! CLI defaults from `~/.config/bencode_decoder/config.toml` and the
+//! environment. Flags given on the command line take precedence over
+//! `BENCODE_OUTPUT`, `BENCODE_COLOR`, `BENCODE_MAX_DEPTH` and
+//! `BENCODE_MAX_TOTAL_BYTES`, which take precedence over the file.
+//!
+//! Only a subset of TOML is read: one `key = value` per line with string,
+//! integer or boolean values, and `#` comments.
+//!
+//! ```toml
+//! output = "json"   # text or json
+//! color = "never"   # auto, always or never
+//! max_depth = 64    # 256 if not set
+//! max_total_bytes = 1_000_000_000  # unlimited if not set
+//! ```
+
+use std::{env, fs, io::IsTerminal, path::PathBuf};
+
+use bencode_decoder::DecodeOptions;
+
+use crate::{cli::Matches, log};
+
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum Output {
+    #[default]
+    Text,
+    Json,
+}
+
+impl Output {
+    pub const NAMES: &[&str] = &["text", "json"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Output::Text),
+            "json" => Some(Output::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum Color {
+    /// Color when writing to a terminal, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl Color {
+    pub const NAMES: &[&str] = &["auto", "always", "never"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Color::Auto),
+            "always" => Some(Color::Always),
+            "never" => Some(Color::Never),
+            _ => None,
+        }
+    }
+
+    pub fn enabled_for_stderr(self) -> bool {
+        self.enabled_for(std::io::stderr().is_terminal())
+    }
+
+    pub fn enabled_for_stdout(self) -> bool {
+        self.enabled_for(std::io::stdout().is_terminal())
+    }
+
+    fn enabled_for(self, terminal: bool) -> bool {
+        match self {
+            Color::Auto => env::var_os("NO_COLOR").is_none() && terminal,
+            Color::Always => true,
+            Color::Never => false,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Default)]
+pub struct Config {
+    pub output: Output,
+    pub color: Color,
+    pub max_depth: Option<usize>,
+    pub max_total_bytes: Option<usize>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let base = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("bencode_decoder").join("config.toml"))
+    }
+
+    /// Reads the config file, or the defaults if there isn't one, then
+    /// applies the environment on top.
+    pub fn load() -> Result<Self, String> {
+        let mut config = Self::load_file()?;
+        config.apply_env(|name| env::var(name).ok())?;
+        Ok(config)
+    }
+
+    fn load_file() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(toml) => Self::parse(&toml).map_err(|error| format!("{}: {error}", path.display())),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(format!("{}: {error}", path.display())),
+        }
+    }
+
+    /// Overrides settings from `BENCODE_*` variables, looked up with `var`.
+    /// Empty variables are ignored.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        let var = |name| var(name).filter(|value| !value.is_empty());
+
+        if let Some(name) = var("BENCODE_OUTPUT") {
+            self.output = Output::parse(&name)
+                .ok_or_else(|| expected_one_of("BENCODE_OUTPUT", Output::NAMES))?;
+        }
+        if let Some(name) = var("BENCODE_COLOR") {
+            self.color = Color::parse(&name)
+                .ok_or_else(|| expected_one_of("BENCODE_COLOR", Color::NAMES))?;
+        }
+        let limit = |name: &'static str| {
+            var(name)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| format!("{name} must be a non-negative integer"))
+                })
+                .transpose()
+        };
+        if let Some(depth) = limit("BENCODE_MAX_DEPTH")? {
+            self.max_depth = Some(depth);
+        }
+        if let Some(bytes) = limit("BENCODE_MAX_TOTAL_BYTES")? {
+            self.max_total_bytes = Some(bytes);
+        }
+        Ok(())
+    }
+
+    pub fn parse(toml: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+
+        for (number, line) in toml.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let at = |message: String| format!("line {}: {message}", number + 1);
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| at("expected `key = value`".into()))?;
+            let (key, value) = (key.trim(), parse_value(value.trim()).map_err(at)?);
+
+            match (key, value) {
+                ("output", Value::String(name)) => {
+                    config.output = Output::parse(&name)
+                        .ok_or_else(|| at(expected_one_of("output", Output::NAMES)))?;
+                }
+                ("color", Value::String(name)) => {
+                    config.color = Color::parse(&name)
+                        .ok_or_else(|| at(expected_one_of("color", Color::NAMES)))?;
+                }
+                ("max_depth", Value::Integer(depth)) => {
+                    let depth = usize::try_from(depth)
+                        .map_err(|_| at("max_depth must not be negative".into()))?;
+                    config.max_depth = Some(depth);
+                }
+                ("max_total_bytes", Value::Integer(bytes)) => {
+                    let bytes = usize::try_from(bytes)
+                        .map_err(|_| at("max_total_bytes must not be negative".into()))?;
+                    config.max_total_bytes = Some(bytes);
+                }
+                ("output" | "color", _) => return Err(at(format!("{key} must be a string"))),
+                ("max_depth" | "max_total_bytes", _) => {
+                    return Err(at(format!("{key} must be an integer")))
+                }
+                _ => log::warning!("ignoring unknown config key `{key}`"),
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// `--output` if given, otherwise the configured default.
+    pub fn output(&self, matches: &Matches) -> Result<Output, String> {
+        match matches.value("output") {
+            Some(name) => {
+                Output::parse(name).ok_or_else(|| expected_one_of("--output", Output::NAMES))
+            }
+            None => Ok(self.output),
+        }
+    }
+
+    pub fn decode_options(&self) -> DecodeOptions {
+        let defaults = DecodeOptions::default();
+        DecodeOptions {
+            max_depth: self.max_depth.or(defaults.max_depth),
+            max_total_bytes: self.max_total_bytes.or(defaults.max_total_bytes),
+            ..defaults
+        }
+    }
+}
+
+fn expected_one_of(setting: &str, names: &[&str]) -> String {
+    format!("{setting} must be one of: {}", names.join(", "))
+}
+
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean,
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(value: &str) -> Result<Value, String> {
+    if let Some(quoted) = value.strip_prefix('"') {
+        let quoted = quoted.strip_suffix('"').ok_or("unterminated string")?;
+        let mut string = String::new();
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            string.push(match (c, c == '\\') {
+                (_, false) => c,
+                (_, true) => match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    _ => return Err("unsupported escape in string".into()),
+                },
+            });
+        }
+        return Ok(Value::String(string));
+    }
+
+    match value {
+        "true" | "false" => Ok(Value::Boolean),
+        _ => value
+            .replace('_', "")
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| format!("unsupported value `{value}`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_settings() {
+        let config = Config::parse(
+            "# defaults\noutput = \"json\" # for scripts\n\ncolor=\"never\"\nmax_depth = 1_000\n\
+             max_total_bytes = 1_000_000\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                output: Output::Json,
+                color: Color::Never,
+                max_depth: Some(1000),
+                max_total_bytes: Some(1_000_000),
+            }
+        );
+        assert_eq!(config.decode_options().max_total_bytes, Some(1_000_000));
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |toml| Config::parse(toml).unwrap_err();
+
+        assert_eq!(
+            error("output = \"yaml\""),
+            "line 1: output must be one of: text, json"
+        );
+        assert_eq!(error("\ncolor = 1"), "line 2: color must be a string");
+        assert_eq!(
+            error("max_depth = -1"),
+            "line 1: max_depth must not be negative"
+        );
+        assert_eq!(
+            error("max_depth = true"),
+            "line 1: max_depth must be an integer"
+        );
+        assert_eq!(
+            error("max_total_bytes = -5"),
+            "line 1: max_total_bytes must not be negative"
+        );
+        assert_eq!(
+            error("max_total_bytes = \"1G\""),
+            "line 1: max_total_bytes must be an integer"
+        );
+        assert_eq!(error("output"), "line 1: expected `key = value`");
+        assert_eq!(error("output = \"json"), "line 1: unterminated string");
+    }
+
+    #[test]
+    fn environment_overrides_file() {
+        let mut config =
+            Config::parse("output = \"json\"\ncolor = \"always\"\nmax_total_bytes = 10\n").unwrap();
+        config
+            .apply_env(|name| match name {
+                "BENCODE_OUTPUT" => Some("text".into()),
+                "BENCODE_COLOR" => Some("".into()),
+                "BENCODE_MAX_DEPTH" => Some("32".into()),
+                "BENCODE_MAX_TOTAL_BYTES" => Some("4096".into()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                output: Output::Text,
+                color: Color::Always,
+                max_depth: Some(32),
+                max_total_bytes: Some(4096),
+            }
+        );
+        assert_eq!(
+            Config::default().apply_env(|name| (name == "BENCODE_MAX_DEPTH").then(|| "-1".into())),
+            Err("BENCODE_MAX_DEPTH must be a non-negative integer".into())
+        );
+        assert_eq!(
+            Config::default()
+                .apply_env(|name| (name == "BENCODE_MAX_TOTAL_BYTES").then(|| "1G".into())),
+            Err("BENCODE_MAX_TOTAL_BYTES must be a non-negative integer".into())
+        );
+    }
+
+    #[test]
+    fn color_needs_a_terminal_when_auto() {
+        assert!(!Color::Auto.enabled_for(false));
+        assert!(Color::Always.enabled_for(false));
+        assert!(!Color::Never.enabled_for(true));
+    }
+}
@@ -0,0 +1,107 @@
+//! What BEP 3 requires of a decoder, as a table of inputs and whether a
+//! conforming one accepts them. This crate's decoder is checked against it
+//! in the tests; other implementations through the `conformance` command.
+//! Key order is part of the spec, so the decoder runs with
+//! [`DecodeOptions::sorted_keys`](crate::DecodeOptions::sorted_keys) on.
+
+/// One input and what BEP 3 says about it.
+#[derive(Debug, Clone, Copy)]
+pub struct Case {
+    pub name: &'static str,
+    pub input: &'static [u8],
+    pub verdict: Verdict,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Verdict {
+    Accept,
+    Reject,
+    /// The spec allows it, but a decoder rejecting it is common and
+    /// reasonable, as with integers beyond 64 bits, which BEP 3 doesn't
+    /// limit.
+    Either,
+}
+
+impl Verdict {
+    /// Whether accepting, or rejecting, the input conforms.
+    pub fn allows(self, accepted: bool) -> bool {
+        match self {
+            Verdict::Accept => accepted,
+            Verdict::Reject => !accepted,
+            Verdict::Either => true,
+        }
+    }
+}
+
+macro_rules! cases {
+    ($($verdict:ident $name:literal $input:literal,)*) => {
+        &[$(Case { name: $name, input: $input, verdict: Verdict::$verdict },)*]
+    };
+}
+
+pub const CASES: &[Case] = cases![
+    Accept "zero" b"i0e",
+    Accept "negative integer" b"i-3e",
+    Accept "smallest 64-bit integer" b"i-9223372036854775808e",
+    Accept "largest 64-bit integer" b"i9223372036854775807e",
+    Either "integer beyond 64 bits" b"i9223372036854775808e",
+    Reject "negative zero" b"i-0e",
+    Reject "leading zero" b"i03e",
+    Reject "negative leading zero" b"i-03e",
+    Reject "zero with leading zero" b"i00e",
+    Reject "empty integer" b"ie",
+    Reject "minus without digits" b"i-e",
+    Reject "plus sign" b"i+1e",
+    Reject "space in integer" b"i 1e",
+    Reject "fractional integer" b"i1.5e",
+    Reject "unterminated integer" b"i12",
+    Accept "empty string" b"0:",
+    Accept "string" b"4:spam",
+    Accept "binary string" b"3:\x00\xff\n",
+    Reject "string shorter than its length" b"4:spa",
+    Reject "negative string length" b"-1:a",
+    Reject "string length with leading zero" b"02:ab",
+    Reject "string without colon" b"4spam",
+    Accept "empty list" b"le",
+    Accept "list" b"l4:spami42ee",
+    Accept "nested lists" b"llleee",
+    Reject "unterminated list" b"l4:spam",
+    Accept "empty dictionary" b"de",
+    Accept "dictionary" b"d3:bar4:spam3:fooi42ee",
+    Accept "empty key" b"d0:i1ee",
+    Accept "empty name" b"d4:infod4:name0:ee",
+    Reject "unsorted keys" b"d3:fooi1e3:bari2ee",
+    Reject "duplicate keys" b"d3:fooi1e3:fooi2ee",
+    Reject "keys in case-insensitive order" b"d1:ai1e1:Bi2ee",
+    Reject "integer key" b"di1e3:fooe",
+    Reject "list key" b"dle3:fooe",
+    Reject "key without value" b"d3:fooe",
+    Reject "unterminated dictionary" b"d3:fooi1e",
+    Reject "empty input" b"",
+    Reject "end marker alone" b"e",
+    Reject "unknown type" b"x",
+    Reject "leading whitespace" b" i1e",
+    Reject "trailing data" b"i1ei2e",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_with, DecodeOptions};
+
+    #[test]
+    fn decoder_conforms() {
+        let strict = DecodeOptions {
+            sorted_keys: true,
+            ..Default::default()
+        };
+        for case in CASES {
+            let result = decode_with(case.input, &strict);
+            assert!(
+                case.verdict.allows(result.is_ok()),
+                "{}: {result:?}",
+                case.name
+            );
+        }
+    }
+}
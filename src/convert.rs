@@ -0,0 +1,355 @@
+//! Conversion between Rust values and bencode.
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use crate::{Bencode, Error};
+
+/// Types that can be encoded as a bencode value. With the `derive` feature,
+/// `#[derive(ToBencode)]` implements this for structs, mapping fields to
+/// dictionary entries.
+///
+/// `Vec<u8>` encodes as a list of integers like any other `Vec`; mark byte
+/// string fields with `#[bencode(bytes)]` or wrap them in a `Bencode`.
+/// `u64` and `usize` values above `i64::MAX` panic.
+pub trait ToBencode {
+    fn to_bencode(&self) -> Bencode;
+}
+
+impl ToBencode for Bencode {
+    fn to_bencode(&self) -> Bencode {
+        self.clone()
+    }
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(impl ToBencode for $t {
+            fn to_bencode(&self) -> Bencode {
+                Bencode::Integer(i64::from(*self))
+            }
+        })*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, u8, u16, u32);
+
+/// Bencode integers here are `i64`, so these panic above `i64::MAX` rather
+/// than encode a different number. Sizes and lengths never get that large.
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {
+        $(impl ToBencode for $t {
+            fn to_bencode(&self) -> Bencode {
+                match i64::try_from(*self) {
+                    Ok(i) => Bencode::Integer(i),
+                    Err(_) => panic!("{self} doesn't fit a bencode integer"),
+                }
+            }
+        })*
+    };
+}
+
+impl_unsigned!(u64, usize);
+
+impl ToBencode for str {
+    fn to_bencode(&self) -> Bencode {
+        Bencode::String(self.as_bytes().to_vec())
+    }
+}
+
+impl ToBencode for String {
+    fn to_bencode(&self) -> Bencode {
+        self.as_str().to_bencode()
+    }
+}
+
+impl<T: ToBencode> ToBencode for [T] {
+    fn to_bencode(&self) -> Bencode {
+        Bencode::List(self.iter().map(ToBencode::to_bencode).collect())
+    }
+}
+
+impl<T: ToBencode> ToBencode for Vec<T> {
+    fn to_bencode(&self) -> Bencode {
+        self.as_slice().to_bencode()
+    }
+}
+
+impl<K: AsRef<[u8]>, V: ToBencode> ToBencode for BTreeMap<K, V> {
+    fn to_bencode(&self) -> Bencode {
+        Bencode::Dictionary(
+            self.iter()
+                .map(|(key, value)| (key.as_ref().to_vec(), value.to_bencode()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: ToBencode + ?Sized> ToBencode for &T {
+    fn to_bencode(&self) -> Bencode {
+        (**self).to_bencode()
+    }
+}
+
+/// Types that can be read from a bencode value. Errors report the path of
+/// the offending value, e.g. ``missing key `length` at path `info.files[3]` ``.
+/// With the `derive` feature, `#[derive(FromBencode)]` implements this for
+/// structs.
+pub trait FromBencode: Sized {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error>;
+}
+
+impl FromBencode for Bencode {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        Ok(value.clone())
+    }
+}
+
+macro_rules! impl_from_integer {
+    ($($t:ty),*) => {
+        $(impl FromBencode for $t {
+            fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+                match value {
+                    Bencode::Integer(i) => <$t>::try_from(*i)
+                        .map_err(|_| Error::invalid_type(concat!("integer fitting ", stringify!($t)))),
+                    _ => Err(Error::invalid_type("integer")),
+                }
+            }
+        })*
+    };
+}
+
+impl_from_integer!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+impl FromBencode for String {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        match value {
+            Bencode::String(s) => {
+                String::from_utf8(s.clone()).map_err(|_| Error::invalid_type("UTF-8 string"))
+            }
+            _ => Err(Error::invalid_type("string")),
+        }
+    }
+}
+
+impl<T: FromBencode> FromBencode for Vec<T> {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        match value {
+            Bencode::List(l) => l
+                .iter()
+                .enumerate()
+                .map(|(i, value)| T::from_bencode(value).map_err(|error| error.at_index(i)))
+                .collect(),
+            _ => Err(Error::invalid_type("list")),
+        }
+    }
+}
+
+impl<V: FromBencode> FromBencode for BTreeMap<String, V> {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        let Bencode::Dictionary(d) = value else {
+            return Err(Error::invalid_type("dictionary"));
+        };
+
+        d.iter()
+            .map(|(key, value)| {
+                let key = String::from_utf8_lossy(key);
+                let value = V::from_bencode(value).map_err(|error| error.at_key(&key))?;
+                Ok((key.into_owned(), value))
+            })
+            .collect()
+    }
+}
+
+/// Support code for the derive macros.
+#[doc(hidden)]
+pub mod derive {
+    use super::*;
+
+    pub fn dictionary(value: &Bencode) -> Result<&BTreeMap<Vec<u8>, Bencode>, Error> {
+        match value {
+            Bencode::Dictionary(d) => Ok(d),
+            _ => Err(Error::invalid_type("dictionary")),
+        }
+    }
+
+    pub fn optional_field<T: FromBencode>(
+        d: &BTreeMap<Vec<u8>, Bencode>,
+        key: &str,
+    ) -> Result<Option<T>, Error> {
+        d.get(key.as_bytes())
+            .map(|value| T::from_bencode(value).map_err(|error| error.at_key(key)))
+            .transpose()
+    }
+
+    pub fn field<T: FromBencode>(d: &BTreeMap<Vec<u8>, Bencode>, key: &str) -> Result<T, Error> {
+        optional_field(d, key)?.ok_or_else(|| Error::missing_key(key))
+    }
+
+    /// Reads a byte string into `Vec<u8>`, or a fixed-size array when the
+    /// length matches.
+    pub fn optional_bytes<T: TryFrom<Vec<u8>>>(
+        d: &BTreeMap<Vec<u8>, Bencode>,
+        key: &str,
+    ) -> Result<Option<T>, Error> {
+        match d.get(key.as_bytes()) {
+            None => Ok(None),
+            Some(Bencode::String(s)) => T::try_from(s.clone())
+                .map(Some)
+                .map_err(|_| Error::invalid_type("byte string of the expected length").at_key(key)),
+            Some(_) => Err(Error::invalid_type("byte string").at_key(key)),
+        }
+    }
+
+    pub fn bytes<T: TryFrom<Vec<u8>>>(
+        d: &BTreeMap<Vec<u8>, Bencode>,
+        key: &str,
+    ) -> Result<T, Error> {
+        optional_bytes(d, key)?.ok_or_else(|| Error::missing_key(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode;
+
+    #[test]
+    fn encode_rust_values() {
+        let mut map = BTreeMap::new();
+        map.insert("b", vec![1u8, 2]);
+        map.insert("a", vec![]);
+
+        assert_eq!(encode(&map.to_bencode()), b"d1:ale1:bli1ei2eee");
+        assert_eq!(encode(&"spam".to_bencode()), b"4:spam");
+        assert_eq!(encode(&(-3i32).to_bencode()), b"i-3e");
+        assert_eq!(
+            encode(&(i64::MAX as u64).to_bencode()),
+            b"i9223372036854775807e"
+        );
+        assert_eq!(encode(&vec![7usize].to_bencode()), b"li7ee");
+    }
+
+    #[test]
+    #[should_panic(expected = "9223372036854775808 doesn't fit a bencode integer")]
+    fn unsigned_overflow() {
+        (i64::MAX as u64 + 1).to_bencode();
+    }
+
+    #[test]
+    fn decode_rust_values() {
+        let value = crate::decode(b"l1:ai1ee").unwrap();
+        assert_eq!(
+            Vec::<String>::from_bencode(&value),
+            Err(Error::invalid_type("string").at_index(1))
+        );
+
+        let value = crate::decode(b"d1:ali300eee").unwrap();
+        let error = BTreeMap::<String, Vec<u8>>::from_bencode(&value).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected integer fitting u8 at path `a[0]`"
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_to_bencode() {
+        use crate::ToBencode;
+
+        #[derive(ToBencode)]
+        struct File {
+            length: i64,
+            #[bencode(rename = "path")]
+            components: Vec<String>,
+            #[bencode(bytes)]
+            md5sum: Vec<u8>,
+            comment: Option<String>,
+            #[bencode(skip)]
+            #[allow(dead_code)]
+            cached: u32,
+            r#type: Option<&'static str>,
+        }
+
+        let file = File {
+            length: 10,
+            components: vec!["dir".into(), "a.txt".into()],
+            md5sum: vec![0xde, 0xad],
+            comment: None,
+            cached: 7,
+            r#type: Some("file"),
+        };
+
+        assert_eq!(
+            encode(&file.to_bencode()),
+            b"d6:lengthi10e6:md5sum2:\xde\xad4:pathl3:dir5:a.txte4:type4:filee"
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_from_bencode() {
+        use crate::FromBencode;
+
+        #[derive(FromBencode, PartialEq, Debug)]
+        struct File {
+            length: u64,
+            path: Vec<String>,
+            #[bencode(bytes)]
+            md5sum: Option<[u8; 2]>,
+        }
+
+        #[derive(FromBencode, PartialEq, Debug)]
+        struct Info {
+            #[bencode(rename = "piece length")]
+            piece_length: u32,
+            files: Vec<File>,
+            #[bencode(default)]
+            private: u8,
+            #[bencode(default = "untitled")]
+            name: String,
+            #[bencode(bytes)]
+            pieces: Vec<u8>,
+            #[bencode(skip)]
+            cached: Option<u32>,
+        }
+
+        fn untitled() -> String {
+            "untitled".into()
+        }
+
+        let value = crate::decode(
+            b"d5:filesld6:lengthi3e6:md5sum2:\xab\xcd4:pathl1:aeee12:piece lengthi16e6:pieces1:xe",
+        )
+        .unwrap();
+        assert_eq!(
+            Info::from_bencode(&value),
+            Ok(Info {
+                piece_length: 16,
+                files: vec![File {
+                    length: 3,
+                    path: vec!["a".into()],
+                    md5sum: Some([0xab, 0xcd]),
+                }],
+                private: 0,
+                name: "untitled".into(),
+                pieces: b"x".to_vec(),
+                cached: None,
+            })
+        );
+
+        let value = crate::decode(
+            b"d5:filesld6:lengthi3e4:pathleed4:pathleee12:piece lengthi16e6:pieces0:e",
+        )
+        .unwrap();
+        assert_eq!(
+            Info::from_bencode(&value).unwrap_err().to_string(),
+            "missing key `length` at path `files[1]`"
+        );
+
+        let value = crate::decode(b"d5:filesle12:piece lengthi16e6:piecesi0ee").unwrap();
+        assert_eq!(
+            Info::from_bencode(&value).unwrap_err().to_string(),
+            "expected byte string at path `pieces`"
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! A value type whose strings borrow from the input they were decoded from
+//! until they're changed, bridging [`crate::span::Node`], which only
+//! borrows, and [`Bencode`], which only owns.
+
+use alloc::{borrow::Cow, collections::BTreeMap, vec::Vec};
+
+use crate::{
+    decode::{decode_tree, Tree},
+    Bencode, DecodeOptions, Error,
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum CowBencode<'a> {
+    String(Cow<'a, [u8]>),
+    Integer(i64),
+    List(Vec<CowBencode<'a>>),
+    Dictionary(BTreeMap<Cow<'a, [u8]>, CowBencode<'a>>),
+}
+
+impl<'a> CowBencode<'a> {
+    /// Decodes a complete document without copying any strings.
+    pub fn decode(encoded_value: &'a [u8]) -> Result<Self, Error> {
+        Self::decode_with(encoded_value, &DecodeOptions::default())
+    }
+
+    /// Like [`CowBencode::decode`], enforcing the limits in `options`.
+    pub fn decode_with(encoded_value: &'a [u8], options: &DecodeOptions) -> Result<Self, Error> {
+        decode_tree(encoded_value, options)
+    }
+
+    /// Value of dictionary key `key`.
+    pub fn get(&self, key: &[u8]) -> Option<&CowBencode<'a>> {
+        match self {
+            CowBencode::Dictionary(dict) => dict.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut CowBencode<'a>> {
+        match self {
+            CowBencode::Dictionary(dict) => dict.get_mut(key),
+            _ => None,
+        }
+    }
+
+    /// Copies whatever still borrows, so the value outlives the input.
+    pub fn into_owned(self) -> CowBencode<'static> {
+        match self {
+            CowBencode::String(s) => CowBencode::String(Cow::Owned(s.into_owned())),
+            CowBencode::Integer(i) => CowBencode::Integer(i),
+            CowBencode::List(l) => {
+                CowBencode::List(l.into_iter().map(CowBencode::into_owned).collect())
+            }
+            CowBencode::Dictionary(d) => CowBencode::Dictionary(
+                d.into_iter()
+                    .map(|(key, value)| (Cow::Owned(key.into_owned()), value.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Whether no string in the value has been copied or replaced.
+    pub fn is_borrowed(&self) -> bool {
+        match self {
+            CowBencode::String(s) => matches!(s, Cow::Borrowed(_)),
+            CowBencode::Integer(_) => true,
+            CowBencode::List(l) => l.iter().all(CowBencode::is_borrowed),
+            CowBencode::Dictionary(d) => d
+                .iter()
+                .all(|(key, value)| matches!(key, Cow::Borrowed(_)) && value.is_borrowed()),
+        }
+    }
+}
+
+impl<'a> Tree<'a> for CowBencode<'a> {
+    type Key = Cow<'a, [u8]>;
+
+    fn key(bytes: &'a [u8]) -> Self::Key {
+        Cow::Borrowed(bytes)
+    }
+
+    fn owned_key(bytes: Vec<u8>) -> Self::Key {
+        Cow::Owned(bytes)
+    }
+
+    fn string(bytes: &'a [u8]) -> Self {
+        CowBencode::String(Cow::Borrowed(bytes))
+    }
+
+    fn integer(number: i64) -> Self {
+        CowBencode::Integer(number)
+    }
+
+    fn list(list: Vec<Self>) -> Self {
+        CowBencode::List(list)
+    }
+
+    fn dictionary(dict: BTreeMap<Self::Key, Self>) -> Self {
+        CowBencode::Dictionary(dict)
+    }
+}
+
+impl From<CowBencode<'_>> for Bencode {
+    fn from(value: CowBencode<'_>) -> Self {
+        match value {
+            CowBencode::String(s) => Bencode::String(s.into_owned()),
+            CowBencode::Integer(i) => Bencode::Integer(i),
+            CowBencode::List(l) => Bencode::List(l.into_iter().map(Bencode::from).collect()),
+            CowBencode::Dictionary(d) => Bencode::Dictionary(
+                d.into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<Bencode> for CowBencode<'static> {
+    fn from(value: Bencode) -> Self {
+        match value {
+            Bencode::String(s) => CowBencode::String(Cow::Owned(s)),
+            Bencode::Integer(i) => CowBencode::Integer(i),
+            Bencode::List(l) => CowBencode::List(l.into_iter().map(CowBencode::from).collect()),
+            Bencode::Dictionary(d) => CowBencode::Dictionary(
+                d.into_iter()
+                    .map(|(key, value)| (Cow::Owned(key), value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode, encode};
+
+    #[test]
+    fn borrows_until_changed() {
+        let input = b"d4:infod4:name3:abc6:lengthi5ee4:tagsl1:xee".to_vec();
+        let mut value = CowBencode::decode(&input).unwrap();
+        assert!(value.is_borrowed());
+        let Some(CowBencode::String(name)) = value.get(b"info").and_then(|info| info.get(b"name"))
+        else {
+            panic!("name should be a string");
+        };
+        assert!(core::ptr::eq(name.as_ptr(), input[16..].as_ptr()));
+
+        let Some(CowBencode::Dictionary(info)) = value.get_mut(b"info") else {
+            panic!("info should be a dictionary");
+        };
+        if let Some(CowBencode::String(name)) = info.get_mut(&b"name"[..]) {
+            name.to_mut().extend_from_slice(b"def");
+        }
+        assert!(!value.is_borrowed());
+
+        let owned = value.clone().into_owned();
+        drop(input);
+        assert_eq!(
+            encode(&Bencode::from(owned)),
+            b"d4:infod6:lengthi5e4:name6:abcdefe4:tagsl1:xee"
+        );
+    }
+
+    #[test]
+    fn matches_owned_decoding() {
+        let input = b"d1:ai-1e1:bl0:d1:c1:deee";
+        let value = CowBencode::decode(input).unwrap();
+        assert_eq!(Bencode::from(value.clone()), decode(input).unwrap());
+        assert_eq!(CowBencode::from(decode(input).unwrap()), value);
+
+        assert_eq!(
+            CowBencode::decode(b"di1e1:ae"),
+            Err(Error::NonStringKey { offset: 1 })
+        );
+        assert_eq!(
+            CowBencode::decode_with(
+                b"lle",
+                &DecodeOptions {
+                    max_depth: Some(1),
+                    ..Default::default()
+                }
+            ),
+            Err(Error::DepthLimitExceeded)
+        );
+    }
+}
@@ -0,0 +1,762 @@
+use alloc::{collections::BTreeMap, string::ToString, vec::Vec};
+
+use crate::{
+    cancel::CancelToken,
+    cow::CowBencode,
+    lexer::{scan_integer, scan_string},
+    Bencode, Error,
+};
+
+/// Nesting allowed by default: far deeper than real documents go, yet
+/// shallow enough that decoding a value, and dropping it, fits in the stack
+/// of a spawned thread even in a debug build.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Limits and leniency for [`decode_with`]. The default limits nesting to
+/// [`DEFAULT_MAX_DEPTH`], has no other limits and accepts only what the
+/// specification allows.
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    /// Deepest nesting of lists and dictionaries allowed; a flat list has
+    /// depth 1. Bounds the recursion on hostile input; `None` lifts the
+    /// limit, for input that's trusted.
+    pub max_depth: Option<usize>,
+    /// Accepts integer dictionary keys, which some broken producers write,
+    /// as the string of their digits: `di1e1:ae` decodes as `d1:11:ae`.
+    pub integer_keys: bool,
+    /// Rejects dictionaries whose keys aren't in strictly increasing byte
+    /// order, as canonical documents such as info dictionaries must be, so
+    /// that re-encoding gives back the same bytes.
+    pub sorted_keys: bool,
+    /// Most memory the decoded value may take: the bytes of its strings and
+    /// keys, plus the size of a value or key for each one. A few bytes of
+    /// input, such as the `le` of an empty list, can take many times that
+    /// once decoded.
+    pub max_total_bytes: Option<usize>,
+    /// Stops decoding with [`Error::Cancelled`] once cancelled. It's
+    /// checked every few thousand values.
+    pub cancel: Option<CancelToken>,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            integer_keys: false,
+            sorted_keys: false,
+            max_total_bytes: None,
+            cancel: None,
+        }
+    }
+}
+
+impl DecodeOptions {
+    fn max_depth(&self) -> usize {
+        self.max_depth.unwrap_or(usize::MAX)
+    }
+}
+
+/// Decodes a single value from the start of `encoded_value`, returning it
+/// along with the unconsumed remainder of the input.
+pub fn decode_bencoded_value(encoded_value: &[u8]) -> Result<(Bencode, &[u8]), Error> {
+    Parser::new().decode_prefix(encoded_value)
+}
+
+/// Decoding set up once for many inputs, such as the small messages a
+/// tracker or DHT node receives, rather than passing options to every call.
+#[derive(Debug, Clone, Default)]
+pub struct Parser {
+    options: DecodeOptions,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: DecodeOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn options(&self) -> &DecodeOptions {
+        &self.options
+    }
+
+    /// Like [`decode_with`], with this parser's options.
+    pub fn decode(&self, encoded_value: &[u8]) -> Result<Bencode, Error> {
+        decode_tree(encoded_value, &self.options)
+    }
+
+    /// Like [`decode_bencoded_value`], with this parser's options.
+    pub fn decode_prefix<'a>(&self, encoded_value: &'a [u8]) -> Result<(Bencode, &'a [u8]), Error> {
+        let mut decoder = Decoder::new(encoded_value, &self.options);
+        let value = decoder.value(self.options.max_depth())?;
+        Ok((value, decoder.rest()))
+    }
+
+    /// Like [`Parser::decode`], borrowing strings from the input.
+    pub fn decode_borrowed<'a>(&self, encoded_value: &'a [u8]) -> Result<CowBencode<'a>, Error> {
+        decode_tree(encoded_value, &self.options)
+    }
+}
+
+/// A value type the decoder can build: [`Bencode`], or
+/// [`crate::cow::CowBencode`] borrowing its strings from the input.
+pub(crate) trait Tree<'a>: Sized {
+    type Key: Ord + AsRef<[u8]>;
+
+    fn key(bytes: &'a [u8]) -> Self::Key;
+    /// A key that isn't in the input as such.
+    fn owned_key(bytes: Vec<u8>) -> Self::Key;
+    fn string(bytes: &'a [u8]) -> Self;
+    fn integer(number: i64) -> Self;
+    fn list(list: Vec<Self>) -> Self;
+    fn dictionary(dict: BTreeMap<Self::Key, Self>) -> Self;
+}
+
+impl<'a> Tree<'a> for Bencode {
+    type Key = Vec<u8>;
+
+    fn key(bytes: &'a [u8]) -> Self::Key {
+        bytes.to_vec()
+    }
+
+    fn owned_key(bytes: Vec<u8>) -> Self::Key {
+        bytes
+    }
+
+    fn string(bytes: &'a [u8]) -> Self {
+        Bencode::String(bytes.to_vec())
+    }
+
+    fn integer(number: i64) -> Self {
+        Bencode::Integer(number)
+    }
+
+    fn list(list: Vec<Self>) -> Self {
+        Bencode::List(list)
+    }
+
+    fn dictionary(dict: BTreeMap<Self::Key, Self>) -> Self {
+        Bencode::Dictionary(dict)
+    }
+}
+
+/// How many values are decoded between looks at the cancel token, which
+/// costs an atomic load.
+const CANCEL_CHECK_EVERY: u32 = 4096;
+
+/// A cursor over the input. Each byte is looked at once: markers are
+/// stepped over, and strings and integers are handed to the lexer's
+/// scanners, which stop at the end of their token.
+struct Decoder<'a> {
+    input: &'a [u8],
+    pos: usize,
+    integer_keys: bool,
+    sorted_keys: bool,
+    /// What's left of [`DecodeOptions::max_total_bytes`].
+    budget: usize,
+    cancel: Option<CancelToken>,
+    /// Values until the cancel token is next checked.
+    until_check: u32,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(input: &'a [u8], options: &DecodeOptions) -> Self {
+        Self {
+            input,
+            pos: 0,
+            integer_keys: options.integer_keys,
+            sorted_keys: options.sorted_keys,
+            budget: options.max_total_bytes.unwrap_or(usize::MAX),
+            cancel: options.cancel.clone(),
+            until_check: 0,
+        }
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.input[self.pos..]
+    }
+
+    /// Moves past a token scanned from [`Self::rest`], given what's left
+    /// after it.
+    fn advance_to(&mut self, rest: &[u8]) {
+        self.pos = self.input.len() - rest.len();
+    }
+
+    /// Takes `bytes` from the size budget.
+    fn charge(&mut self, bytes: usize) -> Result<(), Error> {
+        self.budget = self
+            .budget
+            .checked_sub(bytes)
+            .ok_or(Error::SizeLimitExceeded)?;
+        Ok(())
+    }
+
+    /// `depth` is how many more levels of nesting may be opened.
+    fn value<T: Tree<'a>>(&mut self, depth: usize) -> Result<T, Error> {
+        if self.pos < self.input.len() {
+            self.charge(size_of::<T>())?;
+        }
+        if let Some(cancel) = &self.cancel {
+            if self.until_check == 0 {
+                cancel.check()?;
+                self.until_check = CANCEL_CHECK_EVERY;
+            }
+            self.until_check -= 1;
+        }
+        match self.input.get(self.pos) {
+            None => Err(Error::needs(1)),
+            Some(b'0'..=b'9') => {
+                let string = self.string()?;
+                self.charge(string.len())?;
+                Ok(T::string(string))
+            }
+            Some(b'i') => self.integer().map(T::integer),
+            Some(b'l' | b'd') if depth == 0 => Err(Error::DepthLimitExceeded),
+            Some(b'l') => {
+                self.pos += 1;
+                let mut list = Vec::new();
+                while !self.end()? {
+                    list.push(
+                        self.value(depth - 1)
+                            .map_err(|error| error.needing_more(1))?,
+                    );
+                }
+                Ok(T::list(list))
+            }
+            Some(b'd') => {
+                self.pos += 1;
+                let mut dict = BTreeMap::<T::Key, T>::new();
+                let mut last_offset = 0;
+                while !self.end()? {
+                    // Still to come are at least a value and the e
+                    let close = |error: Error| error.needing_more(2);
+                    let start = self.pos;
+                    let key = match self.input[start] {
+                        b'0'..=b'9' => T::key(self.string().map_err(close)?),
+                        b'i' if self.integer_keys => {
+                            T::owned_key(self.integer().map_err(close)?.to_string().into_bytes())
+                        }
+                        _ => {
+                            // Still decode the key, so errors inside it win
+                            self.value::<T>(depth - 1).map_err(close)?;
+                            return Err(Error::NonStringKey { offset: start });
+                        }
+                    };
+                    self.charge(size_of::<T::Key>() + key.as_ref().len())?;
+                    if self.sorted_keys {
+                        // Keys so far are in order, so the last one is the
+                        // greatest
+                        if let Some((last, _)) = dict.last_key_value() {
+                            if key <= *last {
+                                return Err(Error::UnsortedKeys {
+                                    previous: last.as_ref().to_vec(),
+                                    previous_offset: last_offset,
+                                    key: key.as_ref().to_vec(),
+                                    offset: start,
+                                });
+                            }
+                        }
+                        last_offset = start;
+                    }
+                    let value = self
+                        .value(depth - 1)
+                        .map_err(|error| error.needing_more(1))?;
+                    dict.insert(key, value);
+                }
+                Ok(T::dictionary(dict))
+            }
+            Some(&b) => Err(Error::UnexpectedByte(b)),
+        }
+    }
+
+    fn integer(&mut self) -> Result<i64, Error> {
+        let (number, rest) =
+            scan_integer(self.rest()).map_err(|error| error.offset_by(self.pos))?;
+        self.advance_to(rest);
+        Ok(number)
+    }
+
+    fn string(&mut self) -> Result<&'a [u8], Error> {
+        let (string, rest) = scan_string(self.rest()).map_err(|error| error.offset_by(self.pos))?;
+        self.advance_to(rest);
+        Ok(string)
+    }
+
+    /// Steps over the `e` closing a list or dictionary, if it's next.
+    fn end(&mut self) -> Result<bool, Error> {
+        match self.input.get(self.pos) {
+            None => Err(Error::needs(1)),
+            Some(b'e') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+        }
+    }
+}
+
+/// Decodes a complete document, rejecting any bytes left after the value.
+pub fn decode(encoded_value: &[u8]) -> Result<Bencode, Error> {
+    decode_with(encoded_value, &DecodeOptions::default())
+}
+
+/// Like [`decode`], enforcing the limits in `options`.
+pub fn decode_with(encoded_value: &[u8], options: &DecodeOptions) -> Result<Bencode, Error> {
+    decode_tree(encoded_value, options)
+}
+
+/// Decodes every value in an input of back-to-back documents, as found in
+/// logs and captured DHT traffic. An empty input holds no values.
+pub fn decode_all(encoded_value: &[u8]) -> Result<Vec<Bencode>, Error> {
+    decode_iter(encoded_value).collect()
+}
+
+/// Like [`decode_all`], one value at a time.
+pub fn decode_iter(encoded_value: &[u8]) -> Values<'_> {
+    Values {
+        input: encoded_value,
+        pos: 0,
+    }
+}
+
+/// Iterator over back-to-back values, from [`decode_iter`]. Errors carry
+/// offsets into the whole input, and end the iteration.
+#[derive(Debug, Clone)]
+pub struct Values<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Values<'_> {
+    /// Where the next value starts.
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+}
+
+impl Iterator for Values<'_> {
+    type Item = Result<Bencode, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.input[self.pos..];
+        if rest.is_empty() {
+            return None;
+        }
+        match decode_bencoded_value(rest) {
+            Ok((value, after)) => {
+                self.pos = self.input.len() - after.len();
+                Some(Ok(value))
+            }
+            Err(error) => {
+                let error = error.offset_by(self.pos);
+                self.pos = self.input.len();
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+pub(crate) fn decode_tree<'a, T: Tree<'a>>(
+    encoded_value: &'a [u8],
+    options: &DecodeOptions,
+) -> Result<T, Error> {
+    let mut decoder = Decoder::new(encoded_value, options);
+    let value = decoder.value(options.max_depth())?;
+    match decoder.rest() {
+        [] => Ok(value),
+        _ => Err(Error::TrailingData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IntegerError;
+    use alloc::string::String;
+
+    fn string(s: &str) -> Bencode {
+        Bencode::String(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn decode_bencode_sting() {
+        assert_eq!(
+            decode_bencoded_value(b"3:Hey"),
+            Ok((string("Hey"), &b""[..]))
+        );
+        assert_eq!(
+            decode_bencoded_value(b"4:Test"),
+            Ok((string("Test"), &b""[..]))
+        )
+    }
+
+    #[test]
+    fn decode_bencode_integer() {
+        assert_eq!(
+            decode_bencoded_value(b"i30e"),
+            Ok((Bencode::Integer(30), &b""[..]))
+        );
+        assert_eq!(
+            decode_bencoded_value(b"i-42e"),
+            Ok((Bencode::Integer(-42), &b""[..]))
+        );
+    }
+
+    #[test]
+    fn decode_bencode_list() {
+        assert_eq!(
+            decode_bencoded_value(b"l4:spam4:eggse"),
+            Ok((
+                Bencode::List(vec![string("spam"), string("eggs")]),
+                &b""[..]
+            ))
+        );
+        assert_eq!(
+            decode_bencoded_value(b"l5:helloi52ee"),
+            Ok((
+                Bencode::List(vec![string("hello"), Bencode::Integer(52)]),
+                &b""[..]
+            ))
+        )
+    }
+
+    #[test]
+    fn decode_bencode_dictionary() {
+        let mut test = BTreeMap::new();
+        test.insert(b"foo".to_vec(), string("bar"));
+        test.insert(b"hello".to_vec(), Bencode::Integer(52));
+
+        assert_eq!(
+            decode_bencoded_value(b"d3:foo3:bar5:helloi52ee"),
+            Ok((Bencode::Dictionary(test), &b""[..]))
+        )
+    }
+
+    #[test]
+    fn decode_bencode_errors() {
+        assert_eq!(decode(b""), Err(Error::needs(1)));
+        assert_eq!(decode(b"5:abc"), Err(Error::needs(2)));
+        assert_eq!(decode(b"12"), Err(Error::needs(13)));
+        assert_eq!(
+            decode(b"1x2"),
+            Err(Error::InvalidLengthPrefix { offset: 1 })
+        );
+        assert_eq!(
+            decode(b"l1:a03:abce"),
+            Err(Error::InvalidLengthPrefix { offset: 4 })
+        );
+        assert_eq!(
+            decode(b"i03e"),
+            Err(Error::InvalidInteger {
+                offset: 1,
+                reason: IntegerError::LeadingZero
+            })
+        );
+        assert_eq!(
+            decode(b"li1ed1:ai-0eee"),
+            Err(Error::InvalidInteger {
+                offset: 10,
+                reason: IntegerError::NegativeZero
+            })
+        );
+        assert_eq!(
+            decode(b"d1:ai1eli1eei2ee"),
+            Err(Error::NonStringKey { offset: 7 })
+        );
+        assert_eq!(decode(b"x"), Err(Error::UnexpectedByte(b'x')));
+        assert_eq!(decode(b"i1ei2e"), Err(Error::TrailingData));
+        assert_eq!(decode(b"le"), Ok(Bencode::List(vec![])));
+    }
+
+    #[test]
+    fn reused_parser() {
+        let parser = Parser::with_options(DecodeOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        });
+        for _ in 0..2 {
+            assert_eq!(
+                parser.decode(b"li1ee"),
+                Ok(Bencode::List(vec![Bencode::Integer(1)]))
+            );
+            assert_eq!(parser.decode(b"llee"), Err(Error::DepthLimitExceeded));
+        }
+        assert_eq!(
+            parser.decode_prefix(b"lei1e"),
+            Ok((Bencode::List(vec![]), &b"i1e"[..]))
+        );
+        assert_eq!(
+            parser.decode_prefix(b"llee"),
+            Err(Error::DepthLimitExceeded)
+        );
+        assert_eq!(
+            parser.decode_borrowed(b"1:a"),
+            Ok(CowBencode::String(b"a"[..].into()))
+        );
+    }
+
+    #[test]
+    fn integer_keys() {
+        let options = DecodeOptions {
+            integer_keys: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            decode_with(b"di-1e1:ai2e1:be", &options),
+            decode(b"d2:-11:a1:21:be")
+        );
+        assert_eq!(
+            CowBencode::decode_with(b"di7ei0ee", &options).map(Bencode::from),
+            decode(b"d1:7i0ee")
+        );
+        assert_eq!(decode_with(b"di1ei2e", &options), Err(Error::needs(1)));
+        assert_eq!(
+            decode_with(b"dlei2ee", &options),
+            Err(Error::NonStringKey { offset: 1 })
+        );
+    }
+
+    #[test]
+    fn size_limit() {
+        let limit = |bytes| DecodeOptions {
+            max_total_bytes: Some(bytes),
+            ..Default::default()
+        };
+        let value = size_of::<Bencode>();
+        let key = size_of::<Vec<u8>>();
+
+        assert!(decode_with(b"3:abc", &limit(value + 3)).is_ok());
+        assert_eq!(
+            decode_with(b"3:abc", &limit(value + 2)),
+            Err(Error::SizeLimitExceeded)
+        );
+        // The dictionary, its key, the list and the integer in it
+        let dict = b"d1:ali1eee";
+        assert!(decode_with(dict, &limit(3 * value + key + 1)).is_ok());
+        assert_eq!(
+            decode_with(dict, &limit(3 * value + key)),
+            Err(Error::SizeLimitExceeded)
+        );
+
+        // Thousands of empty lists, from a few kilobytes
+        let mut bomb = b"l".to_vec();
+        bomb.extend(b"le".repeat(5000));
+        bomb.push(b'e');
+        assert_eq!(
+            decode_with(&bomb, &limit(64 * 1024)),
+            Err(Error::SizeLimitExceeded)
+        );
+        assert!(CowBencode::decode_with(&bomb, &limit(5001 * size_of::<CowBencode>())).is_ok());
+    }
+
+    #[test]
+    fn cancel() {
+        let cancel = CancelToken::new();
+        let options = DecodeOptions {
+            cancel: Some(cancel.clone()),
+            ..Default::default()
+        };
+        let input = [&b"l"[..], &b"i1e".repeat(10_000), b"e"].concat();
+        assert!(decode_with(&input, &options).is_ok());
+        cancel.cancel();
+        assert_eq!(decode_with(&input, &options), Err(Error::Cancelled));
+        assert_eq!(
+            CowBencode::decode_with(b"i1e", &options),
+            Err(Error::Cancelled)
+        );
+    }
+
+    #[test]
+    fn sorted_keys() {
+        let options = DecodeOptions {
+            sorted_keys: true,
+            ..Default::default()
+        };
+        assert!(decode_with(b"d1:ai1e1:bd0:i0e1:xi0eee", &options).is_ok());
+        assert_eq!(
+            decode_with(b"d1:ai1e2:abi2e1:bd1:ci0e1:ai0eee", &options),
+            Err(Error::UnsortedKeys {
+                previous: b"c".to_vec(),
+                previous_offset: 18,
+                key: b"a".to_vec(),
+                offset: 24,
+            })
+        );
+        let error = decode_with(b"d1:ai1e1:ai2ee", &options).unwrap_err();
+        assert_eq!(error.offset(), Some(7));
+        assert_eq!(
+            error.to_string(),
+            "dictionary key `a` at byte 7 is not after `a` at byte 1"
+        );
+        // Without the option the last duplicate wins
+        assert_eq!(decode(b"d1:ai1e1:ai2ee"), Ok(decode(b"d1:ai2ee").unwrap()));
+
+        let options = DecodeOptions {
+            integer_keys: true,
+            ..options
+        };
+        assert!(decode_with(b"di1ei0ei2ei0ee", &options).is_ok());
+        assert!(decode_with(b"di2ei0ei10ei0ee", &options).is_err());
+        assert!(CowBencode::decode_with(b"d1:b0:1:a0:e", &options).is_err());
+    }
+
+    #[test]
+    fn back_to_back() {
+        assert_eq!(
+            decode_all(b"i1e3:abcd1:ali2eee"),
+            Ok(vec![
+                Bencode::Integer(1),
+                Bencode::String(b"abc".to_vec()),
+                decode(b"d1:ali2eee").unwrap(),
+            ])
+        );
+        assert_eq!(decode_all(b""), Ok(vec![]));
+        assert_eq!(decode_all(b"i1ei2"), Err(Error::needs(1)));
+
+        let mut values = decode_iter(b"i1edi1e1:aei3e");
+        assert_eq!(values.next(), Some(Ok(Bencode::Integer(1))));
+        assert_eq!(values.offset(), 3);
+        assert_eq!(values.next(), Some(Err(Error::NonStringKey { offset: 4 })));
+        assert_eq!(values.next(), None);
+
+        let error = decode_all(b"i1ei01e").unwrap_err();
+        assert_eq!(error.offset(), Some(4));
+    }
+
+    #[test]
+    fn needed_bytes() {
+        // The rest of the string, then the e of the inner list, the
+        // dictionary and the outer list.
+        assert_eq!(decode(b"ld1:al5:ab"), Err(Error::needs(6)));
+        // A value for the key and the e.
+        assert_eq!(decode(b"d3:key"), Err(Error::needs(2)));
+        assert_eq!(decode(b"d3:k"), Err(Error::needs(4)));
+        assert_eq!(decode(b"li1"), Err(Error::needs(2)));
+        assert_eq!(
+            decode(b"l").unwrap_err().to_string(),
+            "unexpected end of input, needing at least 1 more byte"
+        );
+    }
+
+    #[test]
+    fn depth_limit() {
+        let options = DecodeOptions {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+
+        assert!(decode_with(b"ldee", &options).is_ok());
+        assert!(decode_with(b"d1:ali1eee", &options).is_ok());
+        assert_eq!(
+            decode_with(b"llleee", &options),
+            Err(Error::DepthLimitExceeded)
+        );
+        assert_eq!(
+            decode_with(b"d1:ald1:bi1eeee", &options),
+            Err(Error::DepthLimitExceeded)
+        );
+        assert_eq!(
+            decode_with(
+                b"i1e",
+                &DecodeOptions {
+                    max_depth: Some(0),
+                    ..Default::default()
+                }
+            ),
+            Ok(Bencode::Integer(1))
+        );
+
+        let nested = |depth: usize| [vec![b'l'; depth], vec![b'e'; depth]].concat();
+        assert!(decode(&nested(DEFAULT_MAX_DEPTH)).is_ok());
+        assert_eq!(
+            decode(&nested(DEFAULT_MAX_DEPTH + 1)),
+            Err(Error::DepthLimitExceeded)
+        );
+        // Deep enough to overflow the stack without the default limit
+        assert_eq!(decode(&nested(200_000)), Err(Error::DepthLimitExceeded));
+        let unlimited = DecodeOptions {
+            max_depth: None,
+            ..Default::default()
+        };
+        assert!(decode_with(&nested(DEFAULT_MAX_DEPTH + 1), &unlimited).is_ok());
+    }
+
+    /// Differential fuzzing against the crate's other parsers, which were
+    /// written separately: the spanned tree, the pull parser and borrowed
+    /// decoding must accept exactly what this decoder accepts, and agree on
+    /// the value and the error. Encoding what's accepted must give back the
+    /// input exactly when it's canonical.
+    #[test]
+    fn agrees_with_other_parsers() {
+        use crate::{cow::CowBencode, pull::PullParser, span::parse_spanned};
+
+        let seeds: [&[u8]; 5] = [
+            b"d4:infod6:lengthi77e4:name5:a.txt12:piece lengthi16384e6:pieces20:aaaaabbbbbcccccddddde8:url-listl0:ee",
+            b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe",
+            b"li-9223372036854775808ei0el1:xdeee",
+            b"d1:bi1e1:ai2e1:bi3ee",
+            b"lllleeee",
+        ];
+        // xorshift64, so failures reproduce.
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        const BYTES: &[u8] = b"0123456789:-idle";
+
+        for _ in 0..20_000 {
+            let mut input = seeds[next(seeds.len())].to_vec();
+            for _ in 0..1 + next(3) {
+                let at = next(input.len() + 1);
+                match next(4) {
+                    0 if at < input.len() => input[at] = BYTES[next(BYTES.len())],
+                    1 => input.insert(at, BYTES[next(BYTES.len())]),
+                    2 if at < input.len() => drop(input.remove(at)),
+                    _ => input.truncate(at),
+                }
+            }
+
+            let decoded = decode(&input);
+            let context = String::from_utf8_lossy(&input);
+            assert_eq!(
+                parse_spanned(&input).map(|node| node.to_bencode()),
+                decoded,
+                "spanned: {context}"
+            );
+            assert_eq!(
+                CowBencode::decode(&input).map(Bencode::from),
+                decoded,
+                "borrowed: {context}"
+            );
+
+            let mut parser = PullParser::new(&input);
+            let pulled = loop {
+                match parser.next_event() {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => break Ok(()),
+                    Err(error) => break Err(error),
+                }
+            };
+            assert_eq!(pulled.is_ok(), decoded.is_ok(), "pull: {context}");
+
+            if let Ok(value) = &decoded {
+                let encoded = crate::encode(value);
+                assert_eq!(decode(&encoded).as_ref(), Ok(value), "encode: {context}");
+                let sorted = DecodeOptions {
+                    sorted_keys: true,
+                    ..Default::default()
+                };
+                assert_eq!(
+                    encoded == input,
+                    decode_with(&input, &sorted).is_ok(),
+                    "canonical: {context}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Finding duplicate torrents in a directory tree, for `dedupe`.
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bencode_decoder::{builder::DictBuilder, decode_with, torrent, Bencode, DecodeOptions};
+
+pub struct Torrent {
+    pub path: PathBuf,
+    pub infohash: [u8; 20],
+    pub trackers: Vec<String>,
+    /// Piece length and hashes, equal for torrents of the same content even
+    /// when other `info` keys (like `private` or `source`) differ.
+    content: (i64, Vec<u8>),
+}
+
+impl Torrent {
+    pub fn parse(
+        path: &Path,
+        input: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        let value = decode_with(input, options)?;
+
+        let piece_length = match value.get_path("info.piece length")? {
+            Some(Bencode::Integer(length)) => *length,
+            _ => 0,
+        };
+        let pieces = match value.get_path("info.pieces")? {
+            Some(Bencode::String(pieces)) => pieces.clone(),
+            _ => Vec::new(),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            infohash: torrent::infohash(input)?,
+            trackers: torrent::trackers(&value)
+                .into_iter()
+                .map(|tracker| String::from_utf8_lossy(tracker).into_owned())
+                .collect(),
+            content: (piece_length, pieces),
+        })
+    }
+
+    pub fn infohash_hex(&self) -> String {
+        self.infohash.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+/// Why the torrents in a group are duplicates.
+#[derive(PartialEq, Debug)]
+pub enum Match {
+    /// Same infohash; trackers and other top-level keys may differ.
+    Infohash,
+    /// Different infohashes over the same pieces.
+    Content,
+}
+
+impl Match {
+    fn name(&self) -> &'static str {
+        match self {
+            Match::Infohash => "infohash",
+            Match::Content => "content",
+        }
+    }
+}
+
+pub struct Group<'a> {
+    pub by: Match,
+    pub torrents: Vec<&'a Torrent>,
+}
+
+/// Every `.torrent` file under `dir`, in path order.
+pub fn find_torrents(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|extension| extension == "torrent")
+            {
+                found.push(path);
+            }
+        }
+    }
+
+    found.sort();
+    Ok(found)
+}
+
+/// Groups torrents sharing an infohash, then groups of distinct infohashes
+/// sharing their content. Torrents without duplicates are left out.
+pub fn group(torrents: &[Torrent]) -> Vec<Group<'_>> {
+    let mut by_infohash: BTreeMap<[u8; 20], Vec<&Torrent>> = BTreeMap::new();
+    for torrent in torrents {
+        by_infohash
+            .entry(torrent.infohash)
+            .or_default()
+            .push(torrent);
+    }
+
+    let mut by_content: BTreeMap<&(i64, Vec<u8>), Vec<&Torrent>> = BTreeMap::new();
+    for same in by_infohash.values() {
+        let first = same[0];
+        if !first.content.1.is_empty() {
+            by_content.entry(&first.content).or_default().push(first);
+        }
+    }
+
+    let infohash_groups = by_infohash.into_values().map(|torrents| Group {
+        by: Match::Infohash,
+        torrents,
+    });
+    let content_groups = by_content.into_values().map(|torrents| Group {
+        by: Match::Content,
+        torrents,
+    });
+
+    infohash_groups
+        .chain(content_groups)
+        .filter(|group| group.torrents.len() > 1)
+        .collect()
+}
+
+pub fn table(groups: &[Group]) -> String {
+    let mut rows = vec![["group", "match", "infohash", "path", "trackers"].map(String::from)];
+    for (i, group) in groups.iter().enumerate() {
+        for torrent in &group.torrents {
+            rows.push([
+                (i + 1).to_string(),
+                group.by.name().into(),
+                torrent.infohash_hex(),
+                torrent.path.display().to_string(),
+                match torrent.trackers.is_empty() {
+                    true => "-".into(),
+                    false => torrent.trackers.join(", "),
+                },
+            ]);
+        }
+    }
+
+    let mut widths = [0; 5];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in &rows {
+        let line: Vec<_> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        table.push_str(line.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+pub fn json(groups: &[Group]) -> String {
+    let groups = groups
+        .iter()
+        .map(|group| {
+            let torrents = group.torrents.iter().map(|torrent| {
+                let trackers = torrent
+                    .trackers
+                    .iter()
+                    .map(|tracker| tracker.as_str().into());
+                DictBuilder::new()
+                    .insert("infohash", torrent.infohash_hex())
+                    .insert("path", torrent.path.display().to_string())
+                    .insert("trackers", trackers.collect::<Vec<Bencode>>())
+                    .build()
+            });
+            DictBuilder::new()
+                .insert("match", group.by.name())
+                .insert("torrents", torrents.collect::<Vec<_>>())
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+    bencode_decoder::json::to_json(&Bencode::List(groups))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torrent(path: &str, infohash: u8, pieces: &[u8]) -> Torrent {
+        Torrent {
+            path: path.into(),
+            infohash: [infohash; 20],
+            trackers: vec![format!("udp://{path}")],
+            content: (16384, pieces.to_vec()),
+        }
+    }
+
+    #[test]
+    fn groups() {
+        let torrents = [
+            torrent("a", 1, b"x"),
+            torrent("b", 1, b"x"),
+            torrent("c", 2, b"x"),
+            torrent("d", 3, b"y"),
+        ];
+        let groups = group(&torrents);
+
+        let summary: Vec<_> = groups
+            .iter()
+            .map(|group| {
+                let paths: Vec<_> = group.torrents.iter().map(|t| t.path.clone()).collect();
+                (group.by.name(), paths)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("infohash", vec![PathBuf::from("a"), "b".into()]),
+                ("content", vec![PathBuf::from("a"), "c".into()]),
+            ]
+        );
+
+        assert_eq!(
+            json(&groups[1..]),
+            format!(
+                r#"[{{"match":"content","torrents":[{{"infohash":"{}","path":"a","trackers":["udp://a"]}},{{"infohash":"{}","path":"c","trackers":["udp://c"]}}]}}]"#,
+                "01".repeat(20),
+                "02".repeat(20)
+            )
+        );
+        assert!(table(&groups).starts_with("group  match     infohash"));
+    }
+}
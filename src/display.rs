@@ -0,0 +1,321 @@
+//! Human-readable rendering of values, as `{}` gives with the defaults and
+//! [`Bencode::display`] with [`FormatOptions`] chosen by the application.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Display, Write};
+
+use crate::Bencode;
+
+/// How [`Bencode::display`] renders a value. The default is what `{}`
+/// prints: whole strings with special characters escaped, keys in byte
+/// order, nesting of any depth.
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    /// Longest string shown, in characters; longer ones are cut and
+    /// followed by `...`.
+    pub max_string_len: Option<usize>,
+    pub escape: Escape,
+    pub key_order: KeyOrder,
+    /// Deepest nesting shown; lists and dictionaries below it are shown as
+    /// `[...]` and `{...}`. A flat list has depth 1.
+    pub max_depth: Option<usize>,
+}
+
+/// What in a string is written as an escape sequence.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Escape {
+    /// Nothing; invalid UTF-8 shows as U+FFFD. Strings holding quotes or
+    /// newlines make the output ambiguous.
+    None,
+    /// Quotes, backslashes and control characters, as Rust writes them, and
+    /// bytes that aren't UTF-8 as `\xNN`, so every string reads back
+    /// unambiguously.
+    #[default]
+    Special,
+    /// Like `Special`, and every non-ASCII character as `\u{...}`.
+    Ascii,
+}
+
+/// The order dictionary entries are shown in.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum KeyOrder {
+    /// Byte order, as they're encoded.
+    #[default]
+    Encoded,
+    /// Ignoring ASCII case, so `Name` and `name` sit together.
+    CaseInsensitive,
+}
+
+impl Bencode {
+    /// Renders the value as `options` say, for use with `{}`.
+    pub fn display<'a>(&'a self, options: &'a FormatOptions) -> Formatted<'a> {
+        Formatted {
+            value: self,
+            options,
+        }
+    }
+}
+
+impl Bencode {
+    /// An indented outline of the value, one line per node with its type,
+    /// the number of elements or bytes, and for lists and dictionaries the
+    /// encoded size, such as `"pieces": bytes[20480]`. Keys are escaped as
+    /// with [`Escape::Special`], and strings that are text are previewed.
+    pub fn to_debug_tree(&self) -> String {
+        let mut out = String::new();
+        debug_tree(self, None, 0, &mut out);
+        out
+    }
+}
+
+fn debug_tree(value: &Bencode, label: Option<&dyn Display>, depth: usize, out: &mut String) {
+    let _ = write!(out, "{:indent$}", "", indent = depth * 2);
+    if let Some(label) = label {
+        let _ = write!(out, "{label}: ");
+    }
+    let _ = match value {
+        Bencode::String(s) => writeln!(out, "bytes[{}]{}", s.len(), Preview(s)),
+        Bencode::Integer(i) => writeln!(out, "int {i}"),
+        Bencode::List(l) => writeln!(out, "list[{}] {} bytes", l.len(), value.encoded_len()),
+        Bencode::Dictionary(d) => writeln!(out, "dict[{}] {} bytes", d.len(), value.encoded_len()),
+    };
+
+    match value {
+        Bencode::List(l) => {
+            for (index, item) in l.iter().enumerate() {
+                debug_tree(item, Some(&format_args!("[{index}]")), depth + 1, out);
+            }
+        }
+        Bencode::Dictionary(d) => {
+            for (key, item) in d {
+                debug_tree(item, Some(&Key(key)), depth + 1, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A space and the start of a string in quotes if it's text, for debug
+/// trees; nothing for binary strings.
+pub(crate) struct Preview<'a>(pub &'a [u8]);
+
+impl Display for Preview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match core::str::from_utf8(self.0) {
+            Ok(text) if !text.is_empty() && !text.chars().any(char::is_control) => {
+                let options = FormatOptions {
+                    max_string_len: Some(40),
+                    ..Default::default()
+                };
+                f.write_char(' ')?;
+                write_string(f, self.0, &options)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A dictionary key in quotes, escaped so that binary keys and keys holding
+/// quotes or newlines read unambiguously, for debug trees.
+pub(crate) struct Key<'a>(pub &'a [u8]);
+
+impl Display for Key<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_string(f, self.0, &FormatOptions::default())
+    }
+}
+
+/// A value with its [`FormatOptions`], from [`Bencode::display`].
+pub struct Formatted<'a> {
+    value: &'a Bencode,
+    options: &'a FormatOptions,
+}
+
+impl Display for Formatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let depth = self.options.max_depth.unwrap_or(usize::MAX);
+        write_value(f, self.value, self.options, depth)
+    }
+}
+
+impl Display for Bencode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(&FormatOptions::default()).fmt(f)
+    }
+}
+
+/// `depth` is how many more levels of nesting may be shown.
+fn write_value(
+    f: &mut fmt::Formatter<'_>,
+    value: &Bencode,
+    options: &FormatOptions,
+    depth: usize,
+) -> fmt::Result {
+    match value {
+        Bencode::String(s) => write_string(f, s, options),
+        Bencode::Integer(i) => write!(f, "{i}"),
+        Bencode::List(l) if l.is_empty() => f.write_str("[]"),
+        Bencode::List(_) if depth == 0 => f.write_str("[...]"),
+        Bencode::List(l) => {
+            f.write_char('[')?;
+            for (i, item) in l.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write_value(f, item, options, depth - 1)?;
+            }
+            f.write_char(']')
+        }
+        Bencode::Dictionary(d) if d.is_empty() => f.write_str("{}"),
+        Bencode::Dictionary(_) if depth == 0 => f.write_str("{...}"),
+        Bencode::Dictionary(d) => {
+            let mut entries: Vec<_> = d.iter().collect();
+            if options.key_order == KeyOrder::CaseInsensitive {
+                // Stable, so keys differing only in case stay in byte order
+                entries.sort_by_key(|(key, _)| key.to_ascii_lowercase());
+            }
+
+            f.write_char('{')?;
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write_string(f, key, options)?;
+                f.write_str(": ")?;
+                write_value(f, value, options, depth - 1)?;
+            }
+            f.write_char('}')
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &[u8], options: &FormatOptions) -> fmt::Result {
+    let mut left = options.max_string_len.unwrap_or(usize::MAX);
+    f.write_char('"')?;
+    for chunk in s.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if left == 0 {
+                return f.write_str("\"...");
+            }
+            left -= 1;
+            write_char(f, c, options.escape)?;
+        }
+        if !chunk.invalid().is_empty() {
+            if left == 0 {
+                return f.write_str("\"...");
+            }
+            left -= 1;
+            match options.escape {
+                Escape::None => f.write_char(char::REPLACEMENT_CHARACTER)?,
+                Escape::Special | Escape::Ascii => {
+                    for byte in chunk.invalid() {
+                        write!(f, "\\x{byte:02x}")?;
+                    }
+                }
+            }
+        }
+    }
+    f.write_char('"')
+}
+
+fn write_char(f: &mut fmt::Formatter<'_>, c: char, escape: Escape) -> fmt::Result {
+    match (c, escape) {
+        (c, Escape::None) => f.write_char(c),
+        ('"', _) => f.write_str("\\\""),
+        ('\\', _) => f.write_str("\\\\"),
+        ('\n', _) => f.write_str("\\n"),
+        ('\r', _) => f.write_str("\\r"),
+        ('\t', _) => f.write_str("\\t"),
+        (c, _) if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32),
+        (c, Escape::Ascii) if !c.is_ascii() => write!(f, "\\u{{{:x}}}", c as u32),
+        (c, _) => f.write_char(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn default_escapes() {
+        let value = decode(b"d1:al1:xi-1ee1:bd1:c0:e1:d2:\xffye").unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{"a": ["x", -1], "b": {"c": ""}, "d": "\xffy"}"#
+        );
+        assert_eq!(
+            Bencode::from("say \"hi\"\nbye").to_string(),
+            r#""say \"hi\"\nbye""#
+        );
+        assert_eq!(decode(b"lledee").unwrap().to_string(), "[[], {}]");
+    }
+
+    #[test]
+    fn options() {
+        let value = decode(b"d4:Zeta1:\n5:alphal3:abcl1:xee4:betad1:ai1eee").unwrap();
+        let options = FormatOptions {
+            max_string_len: Some(2),
+            escape: Escape::Special,
+            key_order: KeyOrder::CaseInsensitive,
+            max_depth: Some(2),
+        };
+        assert_eq!(
+            value.display(&options).to_string(),
+            r#"{"al"...: ["ab"..., [...]], "be"...: {"a": 1}, "Ze"...: "\n"}"#
+        );
+
+        let depth = |max_depth| FormatOptions {
+            max_depth: Some(max_depth),
+            ..Default::default()
+        };
+        assert_eq!(value.display(&depth(0)).to_string(), "{...}");
+        assert_eq!(decode(b"le").unwrap().display(&depth(0)).to_string(), "[]");
+    }
+
+    #[test]
+    fn debug_tree() {
+        let value = decode(
+            b"d8:announce14:http://tracker4:infod5:filesld6:lengthi-1e4:pathl1:aeee\
+              4:name4:x\ny\n6:pieces3:\x00\x01\x02ee",
+        )
+        .unwrap();
+        assert_eq!(
+            value.to_debug_tree(),
+            "\
+dict[2] 96 bytes
+  \"announce\": bytes[14] \"http://tracker\"
+  \"info\": dict[3] 61 bytes
+    \"files\": list[1] 27 bytes
+      [0]: dict[2] 25 bytes
+        \"length\": int -1
+        \"path\": list[1] 5 bytes
+          [0]: bytes[1] \"a\"
+    \"name\": bytes[4]
+    \"pieces\": bytes[3]
+"
+        );
+
+        let value = decode(b"d3:a\nbi1e5:x: \"\xffi2ee").unwrap();
+        assert_eq!(
+            value.to_debug_tree(),
+            "dict[2] 20 bytes\n  \"a\\nb\": int 1\n  \"x: \\\"\\xff\": int 2\n"
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        let value = Bencode::from(&b"a\"b\\c\t\x01\xc3\xa9\xff"[..]);
+        let with = |escape| {
+            value
+                .display(&FormatOptions {
+                    escape,
+                    ..Default::default()
+                })
+                .to_string()
+        };
+        assert_eq!(with(Escape::Special), r#""a\"b\\c\t\u{1}é\xff""#);
+        assert_eq!(with(Escape::Ascii), r#""a\"b\\c\t\u{1}\u{e9}\xff""#);
+        assert_eq!(with(Escape::None), "\"a\"b\\c\t\u{1}é\u{fffd}\"");
+    }
+}
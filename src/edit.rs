@@ -0,0 +1,167 @@
+//! In-place editing of encoded documents. Replacements are spliced into the
+//! original bytes, so everything outside the edited values — including
+//! non-canonical encodings an infohash depends on — is kept as it was.
+
+use alloc::vec::Vec;
+
+use crate::{
+    encode,
+    path::{self, Segment},
+    span::{parse_spanned, Node, NodeKind, Span},
+    Bencode, Error,
+};
+
+pub struct Editor<'a> {
+    source: &'a [u8],
+    root: Node<'a>,
+    edits: Vec<(Span, Vec<u8>)>,
+}
+
+impl<'a> Editor<'a> {
+    pub fn new(source: &'a [u8]) -> Result<Self, Error> {
+        Ok(Self {
+            source,
+            root: parse_spanned(source)?,
+            edits: Vec::new(),
+        })
+    }
+
+    pub fn root(&self) -> &Node<'a> {
+        &self.root
+    }
+
+    /// Replaces the encoded bytes in `span`, which should cover a whole value
+    /// as reported by [`Node::span`].
+    pub fn replace(&mut self, span: Span, value: &Bencode) -> Result<(), Error> {
+        self.splice(span, encode(value))
+    }
+
+    fn splice(&mut self, span: Span, bytes: Vec<u8>) -> Result<(), Error> {
+        if self
+            .edits
+            .iter()
+            .any(|(edit, _)| edit.start < span.end && span.start < edit.end)
+        {
+            return Err(Error::OverlappingEdit);
+        }
+
+        self.edits.push((span, bytes));
+        Ok(())
+    }
+
+    /// Replaces the value at `path`, e.g. `.announce`.
+    pub fn replace_path(&mut self, path: &str, value: &Bencode) -> Result<(), Error> {
+        let span = self
+            .root
+            .get_path(path)?
+            .ok_or_else(|| Error::NotFound(path.into()))?
+            .span();
+        self.replace(span, value)
+    }
+
+    /// Deletes the value at `path`: a list element, or every entry of a
+    /// dictionary key, so that no earlier duplicate shows through.
+    pub fn remove_path(&mut self, path: &str) -> Result<(), Error> {
+        let not_found = || Error::NotFound(path.into());
+        let mut segments = path::parse(path)?;
+        let last = segments
+            .pop()
+            .ok_or_else(|| Error::InvalidPath(path.into()))?;
+        let parent = self.root.get_segments(&segments).ok_or_else(not_found)?;
+
+        let spans: Vec<Span> = match (last, parent.kind()) {
+            (Segment::Key(key), NodeKind::Dictionary(entries)) => entries
+                .iter()
+                .filter(|entry| entry.key == key.as_bytes())
+                .map(|entry| entry.key_span.start..entry.value.span().end)
+                .collect(),
+            (Segment::Index(i), NodeKind::List(l)) => {
+                l.get(i).map(Node::span).into_iter().collect()
+            }
+            _ => Vec::new(),
+        };
+        if spans.is_empty() {
+            return Err(not_found());
+        }
+        for span in spans {
+            self.splice(span, Vec::new())?;
+        }
+        Ok(())
+    }
+
+    /// Produces the edited document.
+    pub fn apply(mut self) -> Vec<u8> {
+        self.edits.sort_by_key(|(span, _)| span.start);
+
+        let mut output = Vec::with_capacity(self.source.len());
+        let mut offset = 0;
+        for (span, replacement) in &self.edits {
+            output.extend_from_slice(&self.source[offset..span.start]);
+            output.extend_from_slice(replacement);
+            offset = span.end;
+        }
+        output.extend_from_slice(&self.source[offset..]);
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_keeps_other_bytes() {
+        // The info dictionary has unsorted keys, which re-encoding would fix
+        // and thereby change the infohash
+        let source = b"d8:announce5:old:14:infod4:name1:x6:lengthi1ee7:comment2:hie";
+        let mut editor = Editor::new(source).unwrap();
+
+        editor
+            .replace_path(".announce", &Bencode::String(b"udp://new".to_vec()))
+            .unwrap();
+        editor
+            .replace_path(".comment", &Bencode::Integer(5))
+            .unwrap();
+
+        assert_eq!(
+            editor.apply(),
+            b"d8:announce9:udp://new4:infod4:name1:x6:lengthi1ee7:commenti5ee"
+        );
+    }
+
+    #[test]
+    fn remove_entries() {
+        let mut editor = Editor::new(b"d1:ai1e1:bli1ei2ee1:ai3e1:ci0ee").unwrap();
+
+        editor.remove_path(".a").unwrap();
+        editor.remove_path(".b[0]").unwrap();
+        assert_eq!(
+            editor.remove_path(".b[5]"),
+            Err(Error::NotFound(".b[5]".into()))
+        );
+        assert_eq!(
+            editor.remove_path(".x.y"),
+            Err(Error::NotFound(".x.y".into()))
+        );
+        assert_eq!(editor.remove_path("."), Err(Error::InvalidPath(".".into())));
+        assert_eq!(editor.remove_path(".b"), Err(Error::OverlappingEdit));
+        assert_eq!(editor.apply(), b"d1:bli2ee1:ci0ee");
+    }
+
+    #[test]
+    fn edit_errors() {
+        let mut editor = Editor::new(b"d1:ali1ei2eee").unwrap();
+
+        assert_eq!(
+            editor.replace_path(".b", &Bencode::Integer(0)),
+            Err(Error::NotFound(".b".into()))
+        );
+        editor.replace_path(".a[1]", &Bencode::Integer(3)).unwrap();
+        assert_eq!(
+            editor.replace_path(".a", &Bencode::List(vec![])),
+            Err(Error::OverlappingEdit)
+        );
+        assert_eq!(editor.apply(), b"d1:ali1ei3eee");
+    }
+}
@@ -0,0 +1,139 @@
+use alloc::vec::Vec;
+
+use crate::Bencode;
+
+/// Encodes a value in canonical form, the one way of writing it: keys in
+/// byte order, each once, because dictionaries are stored sorted and
+/// unique, and integers and lengths without leading zeros or `-0`. So equal
+/// values always encode the same, and `encode(&decode(x)?)` gives back `x`
+/// exactly when `x` is canonical, i.e. when decoding it with
+/// [`crate::DecodeOptions::sorted_keys`] succeeds. A document with a
+/// repeated key decodes keeping the last value for it.
+pub fn encode(value: &Bencode) -> Vec<u8> {
+    let mut encoded_value = Vec::new();
+    encode_into(value, &mut encoded_value);
+    encoded_value
+}
+
+impl Bencode {
+    /// The length of [`encode`]'s output for the value, without encoding it.
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            Bencode::String(s) => string_len(s),
+            Bencode::Integer(i) => {
+                2 + usize::from(*i < 0) + decimal(i.unsigned_abs(), &mut [0; 20]).len()
+            }
+            Bencode::List(l) => 2 + l.iter().map(Bencode::encoded_len).sum::<usize>(),
+            Bencode::Dictionary(d) => {
+                2 + d
+                    .iter()
+                    .map(|(key, value)| string_len(key) + value.encoded_len())
+                    .sum::<usize>()
+            }
+        }
+    }
+}
+
+fn string_len(s: &[u8]) -> usize {
+    decimal(s.len() as u64, &mut [0; 20]).len() + 1 + s.len()
+}
+
+fn encode_into(value: &Bencode, out: &mut Vec<u8>) {
+    match value {
+        Bencode::String(s) => encode_string(s, out),
+        Bencode::Integer(i) => {
+            out.push(b'i');
+            if *i < 0 {
+                out.push(b'-');
+            }
+            push_decimal(i.unsigned_abs(), out);
+            out.push(b'e');
+        }
+        Bencode::List(l) => {
+            out.push(b'l');
+            for bencode in l {
+                encode_into(bencode, out);
+            }
+            out.push(b'e');
+        }
+        Bencode::Dictionary(d) => {
+            out.push(b'd');
+            for (key, value) in d {
+                encode_string(key, out);
+                encode_into(value, out);
+            }
+            out.push(b'e');
+        }
+    }
+}
+
+fn encode_string(s: &[u8], out: &mut Vec<u8>) {
+    push_decimal(s.len() as u64, out);
+    out.push(b':');
+    out.extend_from_slice(s);
+}
+
+/// Writes `n` in decimal without going through a formatted `String`.
+fn push_decimal(n: u64, out: &mut Vec<u8>) {
+    out.extend_from_slice(decimal(n, &mut [0; 20]));
+}
+
+/// `n` in decimal, in the end of `digits`.
+pub(crate) fn decimal(mut n: u64, digits: &mut [u8; 20]) -> &[u8] {
+    let mut start = digits.len();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    &digits[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode, decode_with, DecodeOptions};
+
+    #[test]
+    fn encode_roundtrip() {
+        for encoded_value in [
+            &b"3:Hey"[..],
+            b"i-42e",
+            b"l4:spami52ee",
+            b"d3:bar4:spam3:fooi42e5:hellolee",
+            b"d1:ade1:blee",
+            b"li0ei-9223372036854775808ei9223372036854775807ee",
+        ] {
+            let value = decode(encoded_value).unwrap();
+            assert_eq!(encode(&value), encoded_value);
+            assert_eq!(value.encoded_len(), encoded_value.len());
+        }
+    }
+
+    #[test]
+    fn canonical_form() {
+        // Unsorted and repeated keys are what decoding accepts that isn't
+        // canonical; everything else non-minimal is an error.
+        for (input, canonical) in [
+            (&b"d1:bi1e1:ai2ee"[..], &b"d1:ai2e1:bi1ee"[..]),
+            (b"d1:ai1e1:ai2ee", b"d1:ai2ee"),
+            (b"ld1:bi0e1:a0:ee", b"ld1:a0:1:bi0eee"),
+        ] {
+            let value = decode(input).unwrap();
+            assert_eq!(encode(&value), canonical);
+            assert_eq!(encode(&decode(canonical).unwrap()), canonical);
+            let sorted = DecodeOptions {
+                sorted_keys: true,
+                ..Default::default()
+            };
+            assert!(decode_with(input, &sorted).is_err());
+            assert!(decode_with(canonical, &sorted).is_ok());
+        }
+        for input in [&b"i-0e"[..], b"i03e", b"03:abc"] {
+            assert!(decode(input).is_err());
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Writing a document piece by piece, for output too large to build as a
+//! [`Bencode`] tree or hold in memory.
+
+use std::io::{self, Write};
+
+use crate::{encode::decimal, Bencode};
+
+enum Frame {
+    List,
+    Dict {
+        /// The previous key, which the next must sort after.
+        last_key: Option<Vec<u8>>,
+        key_next: bool,
+    },
+}
+
+/// Emits one value to `W` as it's described: open lists and dictionaries
+/// with [`Encoder::begin_list`] and [`Encoder::begin_dict`], close them with
+/// [`Encoder::end`]. Dictionary keys must be given in sorted order, as the
+/// specification requires; anything out of place fails with
+/// [`io::ErrorKind::InvalidInput`] and writes nothing.
+///
+/// Every call writes straight through, so give it a buffered writer.
+pub struct Encoder<W: Write> {
+    out: W,
+    stack: Vec<Frame>,
+    done: bool,
+}
+
+fn misuse(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            stack: Vec::new(),
+            done: false,
+        }
+    }
+
+    pub fn begin_list(&mut self) -> io::Result<()> {
+        self.start_value()?;
+        self.stack.push(Frame::List);
+        self.out.write_all(b"l")
+    }
+
+    pub fn begin_dict(&mut self) -> io::Result<()> {
+        self.start_value()?;
+        self.stack.push(Frame::Dict {
+            last_key: None,
+            key_next: true,
+        });
+        self.out.write_all(b"d")
+    }
+
+    /// The next key of the innermost dictionary, to be followed by its value.
+    pub fn key(&mut self, key: impl AsRef<[u8]>) -> io::Result<()> {
+        let key = key.as_ref();
+        let Some(Frame::Dict { last_key, key_next }) = self.stack.last_mut() else {
+            return Err(misuse("key outside a dictionary"));
+        };
+        if !*key_next {
+            return Err(misuse("key where a value was expected"));
+        }
+        if last_key.as_deref().is_some_and(|last| last >= key) {
+            return Err(misuse("dictionary keys must be unique and sorted"));
+        }
+        *last_key = Some(key.to_vec());
+        *key_next = false;
+        write_string(&mut self.out, key)
+    }
+
+    pub fn int(&mut self, number: i64) -> io::Result<()> {
+        self.start_value()?;
+        self.value_done();
+        let sign: &[u8] = if number < 0 { b"-" } else { b"" };
+        self.out.write_all(b"i")?;
+        self.out.write_all(sign)?;
+        self.out
+            .write_all(decimal(number.unsigned_abs(), &mut [0; 20]))?;
+        self.out.write_all(b"e")
+    }
+
+    pub fn bytes(&mut self, bytes: impl AsRef<[u8]>) -> io::Result<()> {
+        self.start_value()?;
+        self.value_done();
+        write_string(&mut self.out, bytes.as_ref())
+    }
+
+    /// Writes a whole decoded value in place, e.g. an `info` dictionary
+    /// copied from another torrent.
+    pub fn value(&mut self, value: &Bencode) -> io::Result<()> {
+        self.start_value()?;
+        self.value_done();
+        self.out.write_all(&crate::encode(value))
+    }
+
+    /// Closes the innermost list or dictionary.
+    pub fn end(&mut self) -> io::Result<()> {
+        match self.stack.last() {
+            None => return Err(misuse("end with nothing open")),
+            Some(Frame::Dict {
+                key_next: false, ..
+            }) => return Err(misuse("key without a value")),
+            Some(_) => {}
+        }
+        self.stack.pop();
+        self.value_done();
+        self.out.write_all(b"e")
+    }
+
+    /// Checks the value is complete, flushes, and hands back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.done {
+            return Err(misuse("value left incomplete"));
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn start_value(&self) -> io::Result<()> {
+        match self.stack.last() {
+            None if self.done => Err(misuse("only one value may be written")),
+            Some(Frame::Dict { key_next: true, .. }) => {
+                Err(misuse("value where a key was expected"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Marks the current position filled, once a value has been written.
+    fn value_done(&mut self) {
+        match self.stack.last_mut() {
+            None => self.done = true,
+            Some(Frame::Dict { key_next, .. }) => *key_next = true,
+            Some(Frame::List) => {}
+        }
+    }
+}
+
+fn write_string(out: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    out.write_all(decimal(bytes.len() as u64, &mut [0; 20]))?;
+    out.write_all(b":")?;
+    out.write_all(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn writes_documents() {
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.begin_dict().unwrap();
+        encoder.key("a").unwrap();
+        encoder.int(-12).unwrap();
+        encoder.key("b").unwrap();
+        encoder.begin_list().unwrap();
+        encoder.bytes("spam").unwrap();
+        encoder.begin_dict().unwrap();
+        encoder.end().unwrap();
+        encoder.value(&decode(b"li0ee").unwrap()).unwrap();
+        encoder.end().unwrap();
+        encoder.end().unwrap();
+        assert_eq!(encoder.finish().unwrap(), b"d1:ai-12e1:bl4:spamdeli0eeee");
+    }
+
+    #[test]
+    fn rejects_misuse() {
+        let error = |steps: &dyn Fn(&mut Encoder<Vec<u8>>) -> io::Result<()>| {
+            let mut encoder = Encoder::new(Vec::new());
+            let error = steps(&mut encoder).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            error.to_string()
+        };
+
+        assert_eq!(
+            error(&|e| {
+                e.begin_dict()?;
+                e.key("b")?;
+                e.int(1)?;
+                e.key("a")
+            }),
+            "dictionary keys must be unique and sorted"
+        );
+        assert_eq!(
+            error(&|e| {
+                e.begin_dict()?;
+                e.key("a")?;
+                e.int(1)?;
+                e.key("a")
+            }),
+            "dictionary keys must be unique and sorted"
+        );
+        assert_eq!(
+            error(&|e| {
+                e.begin_dict()?;
+                e.int(1)
+            }),
+            "value where a key was expected"
+        );
+        assert_eq!(
+            error(&|e| {
+                e.begin_dict()?;
+                e.key("a")?;
+                e.end()
+            }),
+            "key without a value"
+        );
+        assert_eq!(error(&|e| e.key("a")), "key outside a dictionary");
+        assert_eq!(error(&|e| e.end()), "end with nothing open");
+        assert_eq!(
+            error(&|e| {
+                e.int(1)?;
+                e.int(2)
+            }),
+            "only one value may be written"
+        );
+
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.begin_list().unwrap();
+        assert_eq!(
+            encoder.finish().unwrap_err().to_string(),
+            "value left incomplete"
+        );
+    }
+}
@@ -0,0 +1,301 @@
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Display;
+
+/// What is wrong with an integer in [`Error::InvalidInteger`].
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum IntegerError {
+    /// `ie` or `i-e`.
+    NoDigits,
+    /// `i03e`; only `i0e` may start with a zero.
+    LeadingZero,
+    /// `i-0e`.
+    NegativeZero,
+    /// Anything but a digit, such as the `+` of `i+5e`, a second `-` or
+    /// whitespace.
+    UnexpectedByte(u8),
+    /// Doesn't fit in an `i64`.
+    Overflow,
+}
+
+impl Display for IntegerError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            IntegerError::NoDigits => f.write_str("no digits"),
+            IntegerError::LeadingZero => f.write_str("leading zero"),
+            IntegerError::NegativeZero => f.write_str("negative zero"),
+            IntegerError::UnexpectedByte(b) => write!(f, "unexpected byte {:?}", *b as char),
+            IntegerError::Overflow => f.write_str("out of range for a 64-bit integer"),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Error {
+    /// The input ended inside a value. `needed` is how many more bytes it
+    /// takes at least to complete it, when the parser can tell: the rest
+    /// of a string, the closing `e` of each open list and dictionary.
+    UnexpectedEof {
+        needed: Option<usize>,
+    },
+    /// A string length that isn't plain decimal digits followed by a colon,
+    /// starts with a zero, or doesn't fit in memory. `offset` is of the
+    /// offending byte, or of the first digit.
+    InvalidLengthPrefix {
+        offset: usize,
+    },
+    /// An `i...e` integer that breaks the syntax; `offset` is of the
+    /// offending byte, from the start of the input.
+    InvalidInteger {
+        offset: usize,
+        reason: IntegerError,
+    },
+    /// A dictionary key that isn't a string; `offset` is of the key.
+    NonStringKey {
+        offset: usize,
+    },
+    /// A dictionary key that doesn't sort after the one before it, under
+    /// [`crate::DecodeOptions::sorted_keys`]. Both offsets are of the keys.
+    UnsortedKeys {
+        previous: Vec<u8>,
+        previous_offset: usize,
+        key: Vec<u8>,
+        offset: usize,
+    },
+    UnexpectedByte(u8),
+    TrailingData,
+    InvalidPath(String),
+    /// Nothing exists at a path that was required to exist.
+    NotFound(String),
+    /// `path` locates the dictionary the key is missing from, empty for the
+    /// root.
+    MissingKey {
+        key: String,
+        path: String,
+    },
+    InvalidType {
+        path: String,
+        expected: &'static str,
+    },
+    OverlappingEdit,
+    /// Lists and dictionaries were nested deeper than allowed.
+    DepthLimitExceeded,
+    /// The decoded value would take more memory than allowed.
+    SizeLimitExceeded,
+    /// Stopped early through a [`crate::CancelToken`].
+    Cancelled,
+    /// JSON that can't be converted to bencode; `offset` is in bytes.
+    InvalidJson {
+        offset: usize,
+        reason: &'static str,
+    },
+    /// A key or certificate that can't be read or used.
+    InvalidKey(&'static str),
+}
+
+impl Error {
+    pub fn missing_key(key: &str) -> Self {
+        Error::MissingKey {
+            key: key.into(),
+            path: String::new(),
+        }
+    }
+
+    pub fn invalid_type(expected: &'static str) -> Self {
+        Error::InvalidType {
+            path: String::new(),
+            expected,
+        }
+    }
+
+    /// Records that the error happened inside the value of dictionary key
+    /// `key`, for errors that carry a path.
+    pub fn at_key(self, key: &str) -> Self {
+        self.prefixed(key)
+    }
+
+    /// Records that the error happened inside list element `index`.
+    pub fn at_index(self, index: usize) -> Self {
+        self.prefixed(&format!("[{index}]"))
+    }
+
+    /// A stable snake_case name for the kind of error, for tools that react
+    /// to specific failures.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::UnexpectedEof { .. } => "unexpected_eof",
+            Error::InvalidLengthPrefix { .. } => "invalid_length_prefix",
+            Error::InvalidInteger { .. } => "invalid_integer",
+            Error::NonStringKey { .. } => "non_string_key",
+            Error::UnsortedKeys { .. } => "unsorted_keys",
+            Error::UnexpectedByte(_) => "unexpected_byte",
+            Error::TrailingData => "trailing_data",
+            Error::InvalidPath(_) => "invalid_path",
+            Error::NotFound(_) => "not_found",
+            Error::MissingKey { .. } => "missing_key",
+            Error::InvalidType { .. } => "invalid_type",
+            Error::OverlappingEdit => "overlapping_edit",
+            Error::DepthLimitExceeded => "depth_limit_exceeded",
+            Error::SizeLimitExceeded => "size_limit_exceeded",
+            Error::Cancelled => "cancelled",
+            Error::InvalidJson { .. } => "invalid_json",
+            Error::InvalidKey(_) => "invalid_key",
+        }
+    }
+
+    /// The path within the document that the error refers to, if any.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::InvalidPath(path)
+            | Error::NotFound(path)
+            | Error::MissingKey { path, .. }
+            | Error::InvalidType { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Byte offset in the input that the error refers to, for errors that
+    /// carry one.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Error::InvalidLengthPrefix { offset }
+            | Error::NonStringKey { offset }
+            | Error::UnsortedKeys { offset, .. }
+            | Error::InvalidInteger { offset, .. }
+            | Error::InvalidJson { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// The input ended and at least `needed` more bytes are required.
+    pub(crate) fn needs(needed: usize) -> Self {
+        Error::UnexpectedEof {
+            needed: Some(needed),
+        }
+    }
+
+    /// Adds `extra` to the bytes an [`Error::UnexpectedEof`] needs, as each
+    /// enclosing list or dictionary passes it on.
+    pub(crate) fn needing_more(mut self, extra: usize) -> Self {
+        if let Error::UnexpectedEof {
+            needed: Some(needed),
+        } = &mut self
+        {
+            *needed = needed.saturating_add(extra);
+        }
+        self
+    }
+
+    /// Moves the offset of an error found in a slice of the input starting
+    /// at `start` to be from the start of the whole input.
+    pub(crate) fn offset_by(mut self, start: usize) -> Self {
+        if let Error::InvalidLengthPrefix { offset }
+        | Error::InvalidInteger { offset, .. }
+        | Error::NonStringKey { offset } = &mut self
+        {
+            *offset += start;
+        }
+        if let Error::UnsortedKeys {
+            previous_offset,
+            offset,
+            ..
+        } = &mut self
+        {
+            *previous_offset += start;
+            *offset += start;
+        }
+        self
+    }
+
+    fn prefixed(mut self, segment: &str) -> Self {
+        if let Error::MissingKey { path, .. } | Error::InvalidType { path, .. } = &mut self {
+            *path = match (path.is_empty(), path.starts_with('[')) {
+                (true, _) => segment.into(),
+                (false, true) => format!("{segment}{path}"),
+                (false, false) => format!("{segment}.{path}"),
+            };
+        }
+        self
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::UnexpectedEof { needed: None } => f.write_str("unexpected end of input"),
+            Error::UnexpectedEof {
+                needed: Some(needed),
+            } => write!(
+                f,
+                "unexpected end of input, needing at least {needed} more byte{}",
+                if *needed == 1 { "" } else { "s" }
+            ),
+            Error::InvalidLengthPrefix { offset } => {
+                write!(f, "invalid string length prefix at byte {offset}")
+            }
+            Error::InvalidInteger { offset, reason } => {
+                write!(f, "invalid integer at byte {offset}: {reason}")
+            }
+            Error::NonStringKey { offset } => {
+                write!(f, "dictionary key at byte {offset} is not a string")
+            }
+            Error::UnsortedKeys {
+                previous,
+                previous_offset,
+                key,
+                offset,
+            } => write!(
+                f,
+                "dictionary key `{}` at byte {offset} is not after `{}` at byte {previous_offset}",
+                key.escape_ascii(),
+                previous.escape_ascii()
+            ),
+            Error::UnexpectedByte(b) => write!(f, "unexpected byte {:?}", *b as char),
+            Error::TrailingData => f.write_str("trailing data after value"),
+            Error::InvalidPath(path) => write!(f, "invalid path: {path}"),
+            Error::NotFound(path) => write!(f, "nothing at path `{path}`"),
+            Error::MissingKey { key, path } if path.is_empty() => {
+                write!(f, "missing key `{key}`")
+            }
+            Error::MissingKey { key, path } => write!(f, "missing key `{key}` at path `{path}`"),
+            Error::InvalidType { path, expected } if path.is_empty() => {
+                write!(f, "expected {expected}")
+            }
+            Error::InvalidType { path, expected } => {
+                write!(f, "expected {expected} at path `{path}`")
+            }
+            Error::OverlappingEdit => f.write_str("edit overlaps an earlier edit"),
+            Error::DepthLimitExceeded => f.write_str("nesting exceeds the depth limit"),
+            Error::SizeLimitExceeded => f.write_str("decoded value exceeds the size limit"),
+            Error::Cancelled => f.write_str("cancelled"),
+            Error::InvalidJson { offset, reason } => {
+                write!(f, "invalid JSON at byte {offset}: {reason}")
+            }
+            Error::InvalidKey(reason) => write!(f, "invalid key: {reason}"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_paths() {
+        let error = Error::missing_key("length")
+            .at_index(3)
+            .at_key("files")
+            .at_key("info");
+        assert_eq!(
+            error.to_string(),
+            "missing key `length` at path `info.files[3]`"
+        );
+
+        let error = Error::invalid_type("integer").at_key("a").at_index(0);
+        assert_eq!(error.to_string(), "expected integer at path `[0].a`");
+        assert_eq!(error.code(), "invalid_type");
+        assert_eq!(error.path(), Some("[0].a"));
+        assert_eq!(Error::TrailingData.path(), None);
+    }
+}
@@ -0,0 +1,118 @@
+//! How command failures are reported: as text, or with `--errors json` as one
+//! JSON object per failure so that scripts can react to specific errors.
+//!
+//! ```json
+//! {"code":"unexpected_eof","file":"a.torrent","message":"unexpected end of input","offset":154}
+//! ```
+//!
+//! `code` is always present: the library's [`Error::code`], `io` for
+//! filesystem errors, `usage` for bad arguments, `config` for a bad config
+//! file or environment, or `error` for anything else.
+//! `file`, `offset` (in bytes) and `path` (within the document) appear when
+//! known.
+
+use std::{error, fmt, io};
+
+use bencode_decoder::{builder::DictBuilder, json, pull::PullParser, Error};
+
+/// A decoding error along with where it happened.
+#[derive(Debug)]
+pub struct DecodeFailure {
+    pub error: Error,
+    pub file: Option<String>,
+    pub offset: Option<usize>,
+}
+
+impl DecodeFailure {
+    /// Locates `error` by re-reading `input` up to the first syntax error.
+    pub fn new(error: Error, file: Option<&str>, input: &[u8]) -> Self {
+        let mut parser = PullParser::new(input);
+        let offset = loop {
+            match parser.next_event() {
+                Ok(Some(_)) => continue,
+                Ok(None) => break None,
+                Err(_) => break Some(parser.offset()),
+            }
+        };
+        Self {
+            error,
+            file: file.map(Into::into),
+            offset,
+        }
+    }
+}
+
+impl fmt::Display for DecodeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl error::Error for DecodeFailure {}
+
+pub fn to_json(code: &str, error: &(dyn error::Error + 'static)) -> String {
+    let mut fields = DictBuilder::new().insert("message", error.to_string());
+    let mut code = code;
+
+    if let Some(failure) = error.downcast_ref::<DecodeFailure>() {
+        code = failure.error.code();
+        fields = fields
+            .insert_some("path", failure.error.path())
+            .insert_some("file", failure.file.as_deref())
+            .insert_some("offset", failure.offset.map(|offset| offset as i64));
+    } else if let Some(error) = error.downcast_ref::<Error>() {
+        code = error.code();
+        fields = fields.insert_some("path", error.path());
+    } else if error.is::<io::Error>() {
+        code = "io";
+    }
+
+    json::to_json(&fields.insert("code", code).build())
+}
+
+/// A failure that has only a message, such as a usage error.
+#[derive(Debug)]
+pub struct Message(pub String);
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl error::Error for Message {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_reports() {
+        let failure = DecodeFailure::new(
+            Error::UnexpectedEof { needed: Some(2) },
+            Some("a.torrent"),
+            b"d1:ali1e",
+        );
+        assert_eq!(
+            to_json("error", &failure),
+            r#"{"code":"unexpected_eof","file":"a.torrent","message":"unexpected end of input, needing at least 2 more bytes","offset":8}"#
+        );
+
+        let trailing = DecodeFailure::new(Error::TrailingData, None, b"i1ei2e");
+        assert_eq!(trailing.offset, Some(3));
+
+        assert_eq!(
+            to_json("error", &Error::missing_key("info")),
+            r#"{"code":"missing_key","message":"missing key `info`","path":""}"#
+        );
+        let io = io::Error::new(io::ErrorKind::NotFound, "no such file");
+        assert_eq!(
+            to_json("error", &io),
+            r#"{"code":"io","message":"no such file"}"#
+        );
+        assert_eq!(
+            to_json("usage", &Message("unknown flag --x".into())),
+            r#"{"code":"usage","message":"unknown flag --x"}"#
+        );
+    }
+}
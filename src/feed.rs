@@ -0,0 +1,495 @@
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+use crate::{
+    lexer::{scan_token, TokenKind},
+    Bencode, DecodeOptions, Error, Parser,
+};
+
+/// Values completed by a call to [`FeedParser::feed`].
+#[derive(PartialEq, Debug)]
+pub struct Feed {
+    pub values: Vec<Bencode>,
+    /// Whether a partially received value is buffered, waiting for more bytes.
+    pub needs_more_data: bool,
+    /// How many more bytes it takes at least to complete that value, when
+    /// the parser can tell.
+    pub needed: Option<usize>,
+}
+
+/// Why [`FeedParser::feed`] failed, with the values the same call completed
+/// before the error.
+#[derive(PartialEq, Debug)]
+pub struct FeedError {
+    pub values: Vec<Bencode>,
+    pub error: Error,
+}
+
+impl Display for FeedError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl core::error::Error for FeedError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Limits for [`FeedParser::with_options`]. The default caps nesting at
+/// [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH), as [`DecodeOptions`]
+/// does, and leaves the buffer unbounded.
+#[derive(Debug, Clone, Default)]
+pub struct FeedOptions {
+    /// Applied to each value in the stream.
+    pub decode: DecodeOptions,
+    /// Most bytes of an incomplete value kept waiting for the rest, so a peer
+    /// can't make the buffer grow without bound. Going past it fails with
+    /// [`Error::SizeLimitExceeded`].
+    pub max_buffer: Option<usize>,
+}
+
+/// Sans-IO parser for a stream of back-to-back values. Bytes are pushed in as
+/// they arrive and every value they complete is handed back.
+#[derive(Default)]
+pub struct FeedParser {
+    parser: Parser,
+    max_buffer: Option<usize>,
+    buffer: Vec<u8>,
+    /// How far the incomplete value at the start of the buffer has been
+    /// scanned. A value is decoded only once it's all there, so one arriving
+    /// in small chunks costs time linear in its size, not quadratic.
+    scan: Scan,
+    /// What the incomplete value needs at least, when known.
+    needed: Option<usize>,
+}
+
+enum Frame {
+    List,
+    Dict { key_next: bool },
+}
+
+/// The structure of a value so far: the containers open at `offset`, where
+/// scanning picks up when more bytes arrive.
+#[derive(Default)]
+struct Scan {
+    offset: usize,
+    stack: Vec<Frame>,
+}
+
+enum Scanned {
+    /// The value ends at the scan offset.
+    Complete,
+    Incomplete {
+        needed: Option<usize>,
+    },
+    /// Something the decoder will reject, as it can explain exactly why.
+    Invalid,
+}
+
+impl Scan {
+    /// Continues scanning `pending`, which starts with the value, token by
+    /// token as [`PullParser`](crate::pull::PullParser) does. Only the
+    /// nesting is checked: the decoder validates the complete value.
+    fn resume(&mut self, pending: &[u8], max_depth: usize) -> Scanned {
+        loop {
+            let rest = &pending[self.offset..];
+            let expecting_key = matches!(self.stack.last(), Some(Frame::Dict { key_next: true }));
+            if rest.is_empty()
+                && matches!(
+                    self.stack.last(),
+                    Some(Frame::List | Frame::Dict { key_next: true })
+                )
+            {
+                return Scanned::Incomplete {
+                    needed: Some(self.stack.len()),
+                };
+            }
+            let token = match scan_token(rest) {
+                Ok((token, after)) => {
+                    self.offset = pending.len() - after.len();
+                    token
+                }
+                // Each open container still needs its e, and a key its value
+                Err(Error::UnexpectedEof { needed }) => {
+                    let pending = self.stack.len() + usize::from(expecting_key);
+                    return Scanned::Incomplete {
+                        needed: needed.map(|needed| needed.saturating_add(pending)),
+                    };
+                }
+                Err(_) => return Scanned::Invalid,
+            };
+
+            match token {
+                TokenKind::End => match self.stack.pop() {
+                    Some(Frame::List | Frame::Dict { key_next: true }) => {}
+                    _ => return Scanned::Invalid,
+                },
+                TokenKind::String(_) if expecting_key => {
+                    if let Some(Frame::Dict { key_next }) = self.stack.last_mut() {
+                        *key_next = false;
+                    }
+                    continue;
+                }
+                _ if expecting_key => return Scanned::Invalid,
+                TokenKind::ListStart | TokenKind::DictStart => {
+                    self.stack.push(match token {
+                        TokenKind::ListStart => Frame::List,
+                        _ => Frame::Dict { key_next: true },
+                    });
+                    match self.stack.len() > max_depth {
+                        true => return Scanned::Invalid,
+                        false => continue,
+                    }
+                }
+                TokenKind::Integer(_) | TokenKind::String(_) => {}
+            }
+
+            // A value just ended
+            match self.stack.last_mut() {
+                Some(Frame::Dict { key_next }) => *key_next = true,
+                Some(Frame::List) => {}
+                None => return Scanned::Complete,
+            }
+        }
+    }
+}
+
+impl FeedParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: FeedOptions) -> Self {
+        Self {
+            parser: Parser::with_options(options.decode),
+            max_buffer: options.max_buffer,
+            ..Self::default()
+        }
+    }
+
+    /// Appends `data` and decodes as many complete values as possible. After
+    /// an error the buffered input is left untouched and the parser should be
+    /// discarded.
+    pub fn feed(&mut self, data: &[u8]) -> Result<Feed, FeedError> {
+        self.buffer.extend_from_slice(data);
+
+        let mut values = Vec::new();
+        let max_depth = self.parser.options().max_depth.unwrap_or(usize::MAX);
+        let mut consumed = 0;
+        self.needed = None;
+        while consumed < self.buffer.len() {
+            let pending = &self.buffer[consumed..];
+            if let Scanned::Incomplete { needed } = self.scan.resume(pending, max_depth) {
+                self.needed = needed;
+                break;
+            }
+            self.scan = Scan::default();
+            match self.parser.decode_prefix(pending) {
+                Ok((value, rest)) => {
+                    values.push(value);
+                    consumed = self.buffer.len() - rest.len();
+                }
+                Err(Error::UnexpectedEof { needed }) => {
+                    self.needed = needed;
+                    break;
+                }
+                Err(error) => return Err(FeedError { values, error }),
+            }
+        }
+        self.buffer.drain(..consumed);
+
+        if self.max_buffer.is_some_and(|max| self.buffer.len() > max) {
+            return Err(FeedError {
+                values,
+                error: Error::SizeLimitExceeded,
+            });
+        }
+        Ok(Feed {
+            values,
+            needs_more_data: !self.buffer.is_empty(),
+            needed: self.needed,
+        })
+    }
+
+    /// Signals the end of the stream, failing if a value was left incomplete.
+    pub fn finish(self) -> Result<(), Error> {
+        if self.buffer.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::UnexpectedEof {
+                needed: self.needed,
+            })
+        }
+    }
+
+    /// Feeds everything `reader` yields and returns the values in it, for
+    /// sockets and pipes. Reads interrupted by a signal are retried; decode
+    /// errors, and a stream ending inside a value, come back as
+    /// `InvalidData` wrapping the [`Error`].
+    #[cfg(feature = "std")]
+    pub fn read_to_end(mut self, mut reader: impl std::io::Read) -> std::io::Result<Vec<Bencode>> {
+        use std::io::{self, ErrorKind};
+
+        let invalid = |error: Error| io::Error::new(ErrorKind::InvalidData, error);
+        let mut values = Vec::new();
+        let mut chunk = [0; 8192];
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            let feed = self
+                .feed(&chunk[..read])
+                .map_err(|error| invalid(error.error))?;
+            values.extend(feed.values);
+        }
+        self.finish().map_err(invalid)?;
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_in_chunks() {
+        let mut parser = FeedParser::new();
+
+        assert_eq!(
+            parser.feed(b"i1el1").unwrap(),
+            Feed {
+                values: vec![Bencode::Integer(1)],
+                needs_more_data: true,
+                needed: Some(3),
+            }
+        );
+        assert_eq!(
+            parser.feed(b"1:hello").unwrap(),
+            Feed {
+                values: vec![],
+                needs_more_data: true,
+                needed: Some(7),
+            }
+        );
+        assert_eq!(
+            parser.feed(b" worlde3:abc").unwrap(),
+            Feed {
+                values: vec![
+                    Bencode::List(vec![Bencode::String(b"hello world".to_vec())]),
+                    Bencode::String(b"abc".to_vec()),
+                ],
+                needs_more_data: false,
+                needed: None,
+            }
+        );
+        assert_eq!(parser.finish(), Ok(()));
+    }
+
+    /// A reader that misbehaves as sockets may: reads of a byte or a few at
+    /// a time, interruptions between them, and an end of stream or an error
+    /// at `cut`, which can fall in the middle of a token.
+    #[cfg(feature = "std")]
+    struct Faulty<'a> {
+        data: &'a [u8],
+        pos: usize,
+        cut: usize,
+        error: Option<std::io::ErrorKind>,
+        rng: crate::generate::Rng,
+    }
+
+    #[cfg(feature = "std")]
+    impl<'a> Faulty<'a> {
+        fn new(data: &'a [u8], seed: u64) -> Self {
+            Self {
+                data,
+                pos: 0,
+                cut: data.len(),
+                error: None,
+                rng: crate::generate::Rng::new(seed),
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Read for Faulty<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.rng.below(3) == 0 {
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+            if self.pos == self.cut {
+                return match self.error {
+                    Some(kind) => Err(kind.into()),
+                    None => Ok(0),
+                };
+            }
+            let len = (1 + self.rng.below(4))
+                .min(buf.len())
+                .min(self.cut - self.pos);
+            buf[..len].copy_from_slice(&self.data[self.pos..self.pos + len]);
+            self.pos += len;
+            Ok(len)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    const STREAM: &[u8] = b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe\
+                            i-42el5:helloi0ee0:";
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn faulty_reads() {
+        let expected = crate::decode_all(STREAM).unwrap();
+        for seed in 0..50 {
+            let read = FeedParser::new().read_to_end(Faulty::new(STREAM, seed));
+            assert_eq!(read.unwrap(), expected, "seed {seed}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn faulty_ends() {
+        // Each cut lands between values or inside one, as a dropped
+        // connection would
+        for cut in 0..STREAM.len() {
+            let mut reader = Faulty::new(STREAM, cut as u64);
+            reader.cut = cut;
+            let read = FeedParser::new().read_to_end(reader);
+
+            let complete = crate::decode_iter(&STREAM[..cut]).collect::<Result<Vec<_>, _>>();
+            match (read, complete) {
+                (Ok(values), Ok(expected)) => assert_eq!(values, expected, "cut at {cut}"),
+                (Err(error), Err(expected)) => {
+                    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+                    let error = error.into_inner().unwrap().downcast::<Error>().unwrap();
+                    assert!(
+                        matches!(*error, Error::UnexpectedEof { .. }),
+                        "cut at {cut}"
+                    );
+                    assert!(matches!(expected, Error::UnexpectedEof { .. }));
+                }
+                (read, complete) => panic!("cut at {cut}: {read:?} but {complete:?}"),
+            }
+
+            let mut reader = Faulty::new(STREAM, cut as u64);
+            reader.cut = cut;
+            reader.error = Some(std::io::ErrorKind::ConnectionReset);
+            let error = FeedParser::new().read_to_end(reader).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::ConnectionReset);
+        }
+    }
+
+    #[test]
+    fn feed_errors() {
+        let mut parser = FeedParser::new();
+        assert_eq!(
+            parser.feed(b"i1ei2ex"),
+            Err(FeedError {
+                values: vec![Bencode::Integer(1), Bencode::Integer(2)],
+                error: Error::UnexpectedByte(b'x'),
+            })
+        );
+
+        let mut parser = FeedParser::new();
+        parser.feed(b"d3:foo").unwrap();
+        assert_eq!(parser.finish(), Err(Error::needs(2)));
+    }
+
+    #[test]
+    fn waits_for_needed_bytes() {
+        let mut parser = FeedParser::new();
+        assert_eq!(parser.feed(b"i1e10:ab").unwrap().needed, Some(8));
+        // Not decoded again until the string can be complete
+        for (chunk, needed) in [(&b"cde"[..], Some(5)), (b"fgh", Some(2))] {
+            let feed = parser.feed(chunk).unwrap();
+            assert_eq!((feed.values.len(), feed.needed), (0, needed));
+        }
+        let feed = parser.feed(b"ijl").unwrap();
+        assert_eq!(feed.values, [Bencode::String(b"abcdefghij".to_vec())]);
+        assert_eq!(feed.needed, Some(1));
+        assert_eq!(parser.feed(b"e").unwrap().values, [Bencode::List(vec![])]);
+        assert_eq!(parser.finish(), Ok(()));
+    }
+
+    #[test]
+    fn resumes_scanning() {
+        // Each push is scanned once, not decoded again with all before it
+        let mut parser = FeedParser::new();
+        parser.feed(b"ld1:a").unwrap();
+        for _ in 0..1000 {
+            let feed = parser.feed(b"i1e1:a").unwrap();
+            assert_eq!((feed.values.len(), feed.needed), (0, Some(3)));
+            assert_eq!(parser.scan.offset, parser.buffer.len());
+        }
+        let feed = parser.feed(b"i1eee").unwrap();
+        let dict = crate::builder::DictBuilder::new().insert("a", 1i64).build();
+        assert_eq!(feed.values, [Bencode::List(vec![dict])]);
+        assert_eq!(parser.finish(), Ok(()));
+
+        // Errors in a partial value come as soon as they arrive, as the
+        // decoder reports them
+        for (chunks, error) in [
+            ([&b"li1e"[..], b"x"], Error::UnexpectedByte(b'x')),
+            ([b"d", b"i1e"], Error::NonStringKey { offset: 1 }),
+            ([b"d1:a", b"e"], Error::UnexpectedByte(b'e')),
+        ] {
+            let mut parser = FeedParser::new();
+            parser.feed(chunks[0]).unwrap();
+            assert_eq!(parser.feed(chunks[1]).unwrap_err().error, error);
+            assert_eq!(crate::decode(&chunks.concat()), Err(error));
+        }
+    }
+
+    #[test]
+    fn buffer_limit() {
+        let mut parser = FeedParser::with_options(FeedOptions {
+            max_buffer: Some(8),
+            ..Default::default()
+        });
+        assert_eq!(parser.feed(b"i1e5:abc").unwrap().needed, Some(2));
+        assert_eq!(
+            parser.feed(b"dei2e30:0123456789"),
+            Err(FeedError {
+                values: vec![Bencode::String(b"abcde".to_vec()), Bencode::Integer(2)],
+                error: Error::SizeLimitExceeded,
+            })
+        );
+
+        let mut parser = FeedParser::with_options(FeedOptions {
+            decode: DecodeOptions {
+                max_depth: Some(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(
+            parser.feed(b"lleelllee").unwrap_err().error,
+            Error::DepthLimitExceeded
+        );
+        // Without waiting for the rest of the value
+        let mut parser = FeedParser::with_options(FeedOptions {
+            decode: DecodeOptions {
+                max_depth: Some(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        parser.feed(b"ll").unwrap();
+        assert_eq!(
+            parser.feed(b"l").unwrap_err().error,
+            Error::DepthLimitExceeded
+        );
+
+        let deep = [b'l'; crate::DEFAULT_MAX_DEPTH + 1];
+        let mut parser = FeedParser::new();
+        parser.feed(&deep[1..]).unwrap();
+        assert_eq!(
+            parser.feed(b"l").unwrap_err().error,
+            Error::DepthLimitExceeded
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! C API over the decoder. The matching declarations live in
+//! `include/bencode_decoder.h`.
+
+use std::{
+    ffi::{c_char, c_int, CStr},
+    ptr, slice,
+};
+
+use crate::{decode, Bencode, Error};
+
+#[repr(C)]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BencodeStatus {
+    Ok = 0,
+    NullPointer = 1,
+    UnexpectedEof = 2,
+    InvalidSyntax = 3,
+    TrailingData = 4,
+    InvalidPath = 5,
+    NotFound = 6,
+    TypeMismatch = 7,
+}
+
+#[repr(C)]
+#[derive(PartialEq, Debug)]
+pub enum BencodeType {
+    String = 0,
+    Integer = 1,
+    List = 2,
+    Dictionary = 3,
+}
+
+impl From<Error> for BencodeStatus {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::UnexpectedEof { .. } => BencodeStatus::UnexpectedEof,
+            Error::TrailingData => BencodeStatus::TrailingData,
+            Error::InvalidPath(_) => BencodeStatus::InvalidPath,
+            _ => BencodeStatus::InvalidSyntax,
+        }
+    }
+}
+
+/// Decodes `len` bytes at `data` into a newly allocated value stored in
+/// `*out`, which must later be released with `bencode_free`. Nesting deeper
+/// than [`crate::DEFAULT_MAX_DEPTH`] is [`BencodeStatus::InvalidSyntax`].
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bencode_decode(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut Bencode,
+) -> BencodeStatus {
+    if data.is_null() || out.is_null() {
+        return BencodeStatus::NullPointer;
+    }
+
+    match decode(slice::from_raw_parts(data, len)) {
+        Ok(value) => {
+            *out = Box::into_raw(Box::new(value));
+            BencodeStatus::Ok
+        }
+        Err(error) => {
+            *out = ptr::null_mut();
+            error.into()
+        }
+    }
+}
+
+/// Looks up `path` (e.g. `.info.piece length`) inside `value`. The pointer
+/// stored in `*out` borrows from `value` and is valid until it is freed.
+///
+/// # Safety
+///
+/// `value` must come from `bencode_decode`, `path` must be a NUL-terminated
+/// string and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bencode_get_path(
+    value: *const Bencode,
+    path: *const c_char,
+    out: *mut *const Bencode,
+) -> BencodeStatus {
+    if value.is_null() || path.is_null() || out.is_null() {
+        return BencodeStatus::NullPointer;
+    }
+
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        return BencodeStatus::InvalidPath;
+    };
+
+    *out = ptr::null();
+    match (*value).get_path(path) {
+        Ok(Some(found)) => {
+            *out = found;
+            BencodeStatus::Ok
+        }
+        Ok(None) => BencodeStatus::NotFound,
+        Err(error) => error.into(),
+    }
+}
+
+/// # Safety
+///
+/// `value` must be a non-null pointer obtained from this library.
+#[no_mangle]
+pub unsafe extern "C" fn bencode_type(value: *const Bencode) -> BencodeType {
+    match *value {
+        Bencode::String(_) => BencodeType::String,
+        Bencode::Integer(_) => BencodeType::Integer,
+        Bencode::List(_) => BencodeType::List,
+        Bencode::Dictionary(_) => BencodeType::Dictionary,
+    }
+}
+
+/// # Safety
+///
+/// `value` must be obtained from this library and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bencode_get_int(value: *const Bencode, out: *mut i64) -> BencodeStatus {
+    if value.is_null() || out.is_null() {
+        return BencodeStatus::NullPointer;
+    }
+
+    match *value {
+        Bencode::Integer(i) => {
+            *out = i;
+            BencodeStatus::Ok
+        }
+        _ => BencodeStatus::TypeMismatch,
+    }
+}
+
+/// Exposes the bytes of a string value. The data is not NUL-terminated and
+/// stays valid until the owning value is freed.
+///
+/// # Safety
+///
+/// `value` must be obtained from this library and `data`/`len` must be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn bencode_get_bytes(
+    value: *const Bencode,
+    data: *mut *const u8,
+    len: *mut usize,
+) -> BencodeStatus {
+    if value.is_null() || data.is_null() || len.is_null() {
+        return BencodeStatus::NullPointer;
+    }
+
+    match &*value {
+        Bencode::String(s) => {
+            *data = s.as_ptr();
+            *len = s.len();
+            BencodeStatus::Ok
+        }
+        _ => BencodeStatus::TypeMismatch,
+    }
+}
+
+const MESSAGES: &[(BencodeStatus, &[u8])] = &[
+    (BencodeStatus::Ok, b"ok\0"),
+    (BencodeStatus::NullPointer, b"null pointer argument\0"),
+    (BencodeStatus::UnexpectedEof, b"unexpected end of input\0"),
+    (BencodeStatus::InvalidSyntax, b"invalid bencode\0"),
+    (BencodeStatus::TrailingData, b"trailing data after value\0"),
+    (BencodeStatus::InvalidPath, b"invalid path\0"),
+    (BencodeStatus::NotFound, b"path not found\0"),
+    (BencodeStatus::TypeMismatch, b"value has a different type\0"),
+];
+
+/// Returns a static, NUL-terminated description of `status`. It takes an
+/// `int` rather than the enum, since C can pass any value, and describes
+/// values it doesn't know as an unknown status.
+#[no_mangle]
+pub extern "C" fn bencode_status_message(status: c_int) -> *const c_char {
+    let message = MESSAGES
+        .iter()
+        .find(|&&(known, _)| known as c_int == status)
+        .map_or(&b"unknown status\0"[..], |&(_, message)| message);
+    message.as_ptr().cast()
+}
+
+/// Releases a value returned by `bencode_decode`. Passing NULL is a no-op.
+///
+/// # Safety
+///
+/// `value` must be NULL or a pointer from `bencode_decode` that has not
+/// already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bencode_free(value: *mut Bencode) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_and_get_path() {
+        let data = b"d4:infod6:lengthi42e4:name3:fooee";
+        let mut value = ptr::null_mut();
+        let mut found = ptr::null();
+        let mut number = 0;
+        let (mut bytes, mut len) = (ptr::null(), 0);
+
+        unsafe {
+            assert_eq!(
+                bencode_decode(data.as_ptr(), data.len(), &mut value),
+                BencodeStatus::Ok
+            );
+
+            let path = c".info.length";
+            assert_eq!(
+                bencode_get_path(value, path.as_ptr(), &mut found),
+                BencodeStatus::Ok
+            );
+            assert_eq!(bencode_type(found), BencodeType::Integer);
+            assert_eq!(bencode_get_int(found, &mut number), BencodeStatus::Ok);
+            assert_eq!(number, 42);
+
+            let path = c".info.name";
+            bencode_get_path(value, path.as_ptr(), &mut found);
+            assert_eq!(
+                bencode_get_bytes(found, &mut bytes, &mut len),
+                BencodeStatus::Ok
+            );
+            assert_eq!(slice::from_raw_parts(bytes, len), b"foo");
+
+            let path = c".info.missing";
+            assert_eq!(
+                bencode_get_path(value, path.as_ptr(), &mut found),
+                BencodeStatus::NotFound
+            );
+
+            bencode_free(value);
+        }
+    }
+
+    #[test]
+    fn decode_error() {
+        let data = b"i12";
+        let mut value = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(
+                bencode_decode(data.as_ptr(), data.len(), &mut value),
+                BencodeStatus::UnexpectedEof
+            );
+        }
+        assert!(value.is_null());
+
+        // Rejected at the default depth limit rather than overflowing the stack
+        let deep = [vec![b'l'; 200_000], vec![b'e'; 200_000]].concat();
+        unsafe {
+            assert_eq!(
+                bencode_decode(deep.as_ptr(), deep.len(), &mut value),
+                BencodeStatus::InvalidSyntax
+            );
+        }
+        assert!(value.is_null());
+    }
+
+    #[test]
+    fn status_messages() {
+        let message = |status| unsafe { CStr::from_ptr(bencode_status_message(status)) };
+        assert_eq!(message(BencodeStatus::Ok as c_int), c"ok");
+        assert_eq!(
+            message(BencodeStatus::TypeMismatch as c_int),
+            c"value has a different type"
+        );
+        assert_eq!(message(8), c"unknown status");
+        assert_eq!(message(-1), c"unknown status");
+    }
+
+    #[test]
+    fn header_declares_exports() {
+        let header = include_str!("../include/bencode_decoder.h");
+
+        for name in [
+            "bencode_decode",
+            "bencode_get_path",
+            "bencode_type",
+            "bencode_get_int",
+            "bencode_get_bytes",
+            "bencode_status_message",
+            "bencode_free",
+        ] {
+            assert!(header.contains(&format!("{name}(")), "{name} missing");
+        }
+    }
+}
@@ -0,0 +1,378 @@
+//! Random documents for fuzzing corpora and load tests. [`generate`] builds
+//! valid values within [`GenerateOptions`], [`mutate`] makes the small
+//! edits that turn an encoding into a near miss, and [`torrent`] makes
+//! metainfo files of any size. Everything is driven by a seeded [`Rng`], so
+//! a corpus can be rebuilt from its seed.
+
+use alloc::{collections::BTreeMap, format, vec::Vec};
+
+use crate::{
+    builder::{DictBuilder, ListBuilder},
+    Bencode,
+};
+
+/// What [`generate`] may build.
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// Deepest nesting of lists and dictionaries; 0 gives only strings and
+    /// integers.
+    pub max_depth: usize,
+    /// Most elements in a list or entries in a dictionary.
+    pub max_len: usize,
+    /// Longest string, in bytes. Keys are kept to 16.
+    pub max_string_len: usize,
+    pub weights: Weights,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_len: 8,
+            max_string_len: 32,
+            weights: Weights::default(),
+        }
+    }
+}
+
+/// Relative chances of each type being picked for a value. Lists and
+/// dictionaries aren't picked at the depth limit; if nothing else has a
+/// weight, strings are.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Weights {
+    pub string: u32,
+    pub integer: u32,
+    pub list: u32,
+    pub dictionary: u32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            string: 4,
+            integer: 3,
+            list: 2,
+            dictionary: 2,
+        }
+    }
+}
+
+/// A xorshift64* generator: fast and reproducible, and not for anything
+/// that needs to be unpredictable.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Mixed so nearby seeds start far apart; xorshift stalls at zero
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number below `bound`, which must not be zero.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// A random value as `options` allow.
+pub fn generate(rng: &mut Rng, options: &GenerateOptions) -> Bencode {
+    value(rng, options, options.max_depth)
+}
+
+fn value(rng: &mut Rng, options: &GenerateOptions, depth: usize) -> Bencode {
+    let w = options.weights;
+    let (list, dictionary) = match depth {
+        0 => (0, 0),
+        _ => (w.list, w.dictionary),
+    };
+    let total = w.string + w.integer + list + dictionary;
+    if total == 0 {
+        return Bencode::String(string(rng, options.max_string_len));
+    }
+
+    let mut pick = rng.below(total as usize) as u32;
+    for (weight, kind) in [(w.string, 0), (w.integer, 1), (list, 2), (dictionary, 3)] {
+        if pick >= weight {
+            pick -= weight;
+            continue;
+        }
+        return match kind {
+            0 => Bencode::String(string(rng, options.max_string_len)),
+            1 => Bencode::Integer(integer(rng)),
+            2 => {
+                let len = rng.below(options.max_len + 1);
+                Bencode::List((0..len).map(|_| value(rng, options, depth - 1)).collect())
+            }
+            _ => {
+                let len = rng.below(options.max_len + 1);
+                let mut dict = BTreeMap::new();
+                for _ in 0..len {
+                    let key_len = 1 + rng.below(16);
+                    let key = text(rng, key_len);
+                    dict.insert(key, value(rng, options, depth - 1));
+                }
+                Bencode::Dictionary(dict)
+            }
+        };
+    }
+    unreachable!("pick is below the total weight")
+}
+
+/// Half text, half arbitrary bytes, as metainfo files mix names and hashes.
+fn string(rng: &mut Rng, max_len: usize) -> Vec<u8> {
+    let len = rng.below(max_len + 1);
+    match rng.below(2) {
+        0 => text(rng, len),
+        _ => (0..len).map(|_| rng.next_u64() as u8).collect(),
+    }
+}
+
+fn text(rng: &mut Rng, len: usize) -> Vec<u8> {
+    const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789 ._-";
+    (0..len).map(|_| CHARS[rng.below(CHARS.len())]).collect()
+}
+
+/// Mostly small numbers, as lengths and flags are, with the extremes and
+/// the full range now and then.
+fn integer(rng: &mut Rng) -> i64 {
+    match rng.below(8) {
+        0 => [0, -1, i64::MIN, i64::MAX][rng.below(4)],
+        1 => rng.next_u64() as i64,
+        _ => rng.below(2001) as i64 - 1000,
+    }
+}
+
+/// `encoded` with one to three edits of the kind that break bencode:
+/// replacing, inserting or removing one of `0123456789:-idle`, flipping a
+/// bit, or cutting the rest off. The result is usually invalid, but not
+/// always.
+pub fn mutate(rng: &mut Rng, encoded: &[u8]) -> Vec<u8> {
+    const BYTES: &[u8] = b"0123456789:-idle";
+    let mut input = encoded.to_vec();
+    for _ in 0..1 + rng.below(3) {
+        let at = rng.below(input.len() + 1);
+        match rng.below(5) {
+            0 if at < input.len() => input[at] = BYTES[rng.below(BYTES.len())],
+            1 => input.insert(at, BYTES[rng.below(BYTES.len())]),
+            2 if at < input.len() => drop(input.remove(at)),
+            3 if at < input.len() => input[at] ^= 1 << rng.below(8),
+            _ => input.truncate(at),
+        }
+    }
+    input
+}
+
+/// What [`torrent`] describes.
+#[derive(Debug, Clone)]
+pub struct TorrentOptions {
+    /// Files in the torrent; 1 makes a single-file torrent.
+    pub files: usize,
+    /// Their total length in bytes.
+    pub total_size: u64,
+}
+
+/// Fake but well-formed metainfo: files of skewed sizes under a few levels
+/// of directories, a piece length as torrent makers choose it and random
+/// piece hashes, which no content matches. For benchmarking and testing
+/// tools at scale without hashing real data.
+pub fn torrent(rng: &mut Rng, options: &TorrentOptions) -> Bencode {
+    const DIRS: &[&str] = &[
+        "data", "extras", "docs", "season 1", "raw", "images", "2024",
+    ];
+    const EXTENSIONS: &[&str] = &["bin", "mkv", "flac", "txt", "jpg", "iso", "zip"];
+
+    let files = options.files.max(1);
+    // Squared, so a few files hold most of the data as in real torrents
+    let weights: Vec<u128> = (0..files)
+        .map(|_| (rng.below(1000) as u128 + 1).pow(2))
+        .collect();
+    let sum: u128 = weights.iter().sum();
+    let total = u128::from(options.total_size);
+    let mut lengths: Vec<i64> = weights.iter().map(|w| (total * w / sum) as i64).collect();
+    lengths[0] += (total - lengths.iter().map(|&l| l as u128).sum::<u128>()) as i64;
+
+    // The smallest power of two from 16 KiB to 16 MiB giving at most 2000
+    // pieces
+    let mut piece_length: u64 = 16 * 1024;
+    while piece_length < 16 * 1024 * 1024 && options.total_size / piece_length >= 2000 {
+        piece_length *= 2;
+    }
+    let pieces = options.total_size.div_ceil(piece_length) as usize;
+    let hashes: Vec<u8> = (0..pieces * 20).map(|_| rng.next_u64() as u8).collect();
+
+    let name = format!("synthetic-{:08x}", rng.next_u64() as u32);
+    let mut info = DictBuilder::new()
+        .insert("name", name.as_str())
+        .insert("piece length", piece_length as i64)
+        .insert("pieces", hashes);
+    if files == 1 {
+        info = info.insert("length", lengths[0]);
+    } else {
+        let list = lengths
+            .iter()
+            .enumerate()
+            .fold(ListBuilder::new(), |list, (i, length)| {
+                let mut path = ListBuilder::new();
+                for _ in 0..rng.below(3) {
+                    path = path.push(DIRS[rng.below(DIRS.len())]);
+                }
+                let extension = EXTENSIONS[rng.below(EXTENSIONS.len())];
+                path = path.push(format!("file-{i:05}.{extension}").as_str());
+                list.push(
+                    DictBuilder::new()
+                        .insert("length", *length)
+                        .insert("path", path),
+                )
+            });
+        info = info.insert("files", list);
+    }
+
+    DictBuilder::new()
+        .insert("announce", "http://tracker.example.org:6969/announce")
+        .insert(
+            "announce-list",
+            ListBuilder::new()
+                .push(ListBuilder::new().push("http://tracker.example.org:6969/announce"))
+                .push(ListBuilder::new().push("udp://tracker.example.net:1337/announce")),
+        )
+        .insert("created by", "bencode_decoder generate-torrent")
+        // Some time in 2020-2024
+        .insert(
+            "creation date",
+            1_577_836_800 + rng.below(5 * 365 * 86400) as i64,
+        )
+        .insert("info", info)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode, encode};
+
+    fn depth(value: &Bencode) -> usize {
+        match value {
+            Bencode::List(l) => 1 + l.iter().map(depth).max().unwrap_or(0),
+            Bencode::Dictionary(d) => 1 + d.values().map(depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn within_options() {
+        let options = GenerateOptions {
+            max_depth: 3,
+            max_len: 4,
+            max_string_len: 10,
+            ..Default::default()
+        };
+        let mut rng = Rng::new(7);
+        let mut deepest = 0;
+        for _ in 0..500 {
+            let value = generate(&mut rng, &options);
+            assert_eq!(decode(&encode(&value)), Ok(value.clone()));
+            assert!(depth(&value) <= 3);
+            deepest = deepest.max(depth(&value));
+        }
+        assert_eq!(deepest, 3);
+
+        let a = generate(&mut Rng::new(1), &GenerateOptions::default());
+        assert_eq!(generate(&mut Rng::new(1), &GenerateOptions::default()), a);
+        assert_ne!(generate(&mut Rng::new(2), &GenerateOptions::default()), a);
+    }
+
+    #[test]
+    fn weights() {
+        let only = |weights| GenerateOptions {
+            weights,
+            ..Default::default()
+        };
+        let integers = only(Weights {
+            string: 0,
+            integer: 1,
+            list: 0,
+            dictionary: 0,
+        });
+        let mut rng = Rng::new(3);
+        for _ in 0..50 {
+            assert!(matches!(generate(&mut rng, &integers), Bencode::Integer(_)));
+        }
+
+        let mut lists = only(Weights {
+            string: 0,
+            integer: 0,
+            list: 1,
+            dictionary: 0,
+        });
+        lists.max_depth = 2;
+        for _ in 0..50 {
+            let value = generate(&mut rng, &lists);
+            assert!(matches!(value, Bencode::List(_)));
+            assert!(depth(&value) <= 2);
+        }
+    }
+
+    #[test]
+    fn torrents() {
+        let options = TorrentOptions {
+            files: 300,
+            total_size: 10_000_000_000,
+        };
+        let value = torrent(&mut Rng::new(5), &options);
+        assert_eq!(torrent(&mut Rng::new(5), &options), value);
+        let get = |value: &Bencode, path| value.get_path(path).unwrap().cloned();
+        let Some(Bencode::List(files)) = get(&value, "info.files") else {
+            panic!("multi-file torrents list their files");
+        };
+        assert_eq!(files.len(), 300);
+        let total: i64 = files
+            .iter()
+            .map(|file| match get(file, "length") {
+                Some(Bencode::Integer(length)) => length,
+                _ => panic!("files have lengths"),
+            })
+            .sum();
+        assert_eq!(total, 10_000_000_000);
+        assert_eq!(
+            get(&value, "info.piece length"),
+            Some(Bencode::Integer(8 << 20))
+        );
+        let Some(Bencode::String(pieces)) = get(&value, "info.pieces") else {
+            panic!("torrents have pieces");
+        };
+        assert_eq!(pieces.len(), 1193 * 20);
+
+        let single = torrent(
+            &mut Rng::new(5),
+            &TorrentOptions {
+                files: 1,
+                total_size: 100,
+            },
+        );
+        assert_eq!(get(&single, "info.length"), Some(Bencode::Integer(100)));
+        assert_eq!(
+            get(&single, "info.piece length"),
+            Some(Bencode::Integer(16384))
+        );
+    }
+
+    #[test]
+    fn mutations() {
+        let mut rng = Rng::new(11);
+        let encoded = encode(&generate(&mut rng, &GenerateOptions::default()));
+        let invalid = (0..200)
+            .filter(|_| decode(&mutate(&mut rng, &encoded)).is_err())
+            .count();
+        assert!(invalid > 100, "{invalid}");
+    }
+}
@@ -0,0 +1,90 @@
+//! Golden tests over the sample files in `fixtures/`: each one is decoded
+//! and rendered as pretty JSON, which must match the `.json` snapshot
+//! checked in next to it, so a parser change that alters what real inputs
+//! decode to shows up as a diff. Run with `UPDATE_GOLDEN=1` to rewrite the
+//! snapshots after an intended change, and review them like code.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{decode, encode, json::to_json_pretty, resume, schema, torrent};
+
+fn dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+/// Every sample, leaving out the snapshots and the readme.
+fn fixtures() -> Vec<PathBuf> {
+    let mut files: Vec<_> = fs::read_dir(dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext != "json" && ext != "md")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn snapshots() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut stale = Vec::new();
+    for file in fixtures() {
+        let input = fs::read(&file).unwrap();
+        let value = decode(&input).unwrap_or_else(|error| panic!("{}: {error}", file.display()));
+        // Fixtures are what clients write, which is canonical
+        assert_eq!(encode(&value), input, "{}", file.display());
+
+        let json = to_json_pretty(&value) + "\n";
+        let snapshot = file.with_extension(format!(
+            "{}.json",
+            file.extension().unwrap().to_string_lossy()
+        ));
+        match fs::read_to_string(&snapshot) {
+            Ok(expected) if expected == json => {}
+            _ if update => fs::write(&snapshot, json).unwrap(),
+            Ok(_) => stale.push(format!("{} differs", snapshot.display())),
+            Err(_) => stale.push(format!("{} is missing", snapshot.display())),
+        }
+    }
+    assert!(
+        stale.is_empty(),
+        "{}; rerun with UPDATE_GOLDEN=1 if the change is intended",
+        stale.join(", ")
+    );
+}
+
+#[test]
+fn typed_views() {
+    let read = |name: &str| fs::read(dir().join(name)).unwrap();
+
+    for name in [
+        "single-file.torrent",
+        "multi-file.torrent",
+        "v2.torrent",
+        "hybrid.torrent",
+    ] {
+        let input = read(name);
+        let value = decode(&input).unwrap();
+        assert!(!torrent::files(&value).unwrap().is_empty(), "{name}");
+        let violations = schema::validate_schema(&value, &schema::TORRENT);
+        assert!(violations.is_empty(), "{name}: {violations:?}");
+        torrent::infohash(&input).unwrap();
+    }
+    let multi = decode(&read("multi-file.torrent")).unwrap();
+    assert_eq!(torrent::files(&multi).unwrap().len(), 4);
+    assert_eq!(torrent::pieces(&multi).unwrap().len(), 6);
+
+    let qbt = resume::qbittorrent::parse(&read("qbittorrent.fastresume")).unwrap();
+    assert_eq!(qbt.category.as_deref(), Some("music"));
+    assert_eq!(qbt.tags, ["flac", "2023"]);
+    let state = resume::rtorrent::parse_state(&read("rtorrent.rtorrent")).unwrap();
+    assert_eq!(state.directory, "/downloads/Album (2023) [FLAC]");
+    assert!(state.complete);
+    let transmission = resume::transmission::parse(&read("transmission.resume")).unwrap();
+    assert_eq!(transmission.name.as_deref(), Some("Album (2023) [FLAC]"));
+}
@@ -0,0 +1,137 @@
+//! The structure of a value as a Graphviz graph, for `graph`: one node per
+//! value, labeled with its key or index, type and size, and an edge from
+//! each list or dictionary to what it holds.
+
+use std::fmt::Write;
+
+use bencode_decoder::Bencode;
+
+/// Longest text string shown in a label, in characters.
+const PREVIEW: usize = 30;
+
+/// Renders `value` as a DOT `digraph`, for `dot -Tsvg` and friends.
+pub fn to_dot(value: &Bencode) -> String {
+    let mut out = String::from("digraph bencode {\n  node [shape=box, fontname=\"monospace\"];\n");
+    let mut next = 0;
+    node(value, None, &mut next, &mut out);
+    out.push_str("}\n");
+    out
+}
+
+/// Writes the node for `value` and everything below it, returning its id.
+fn node(value: &Bencode, key: Option<&str>, next: &mut usize, out: &mut String) -> usize {
+    let id = *next;
+    *next += 1;
+
+    let mut label = key.map(|key| format!("{key}\n")).unwrap_or_default();
+    match value {
+        Bencode::String(s) => {
+            let _ = write!(label, "bytes[{}]", s.len());
+            if let Some(text) = preview(s) {
+                let _ = write!(label, " \"{text}\"");
+            }
+        }
+        Bencode::Integer(i) => {
+            let _ = write!(label, "int {i}");
+        }
+        Bencode::List(l) => {
+            let _ = write!(label, "list[{}]\n{} bytes", l.len(), value.encoded_len());
+        }
+        Bencode::Dictionary(d) => {
+            let _ = write!(label, "dict[{}]\n{} bytes", d.len(), value.encoded_len());
+        }
+    }
+    let _ = writeln!(out, "  n{id} [label=\"{}\"];", escape(&label));
+
+    let children: Vec<(String, &Bencode)> = match value {
+        Bencode::List(l) => l
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (format!("[{index}]"), item))
+            .collect(),
+        Bencode::Dictionary(d) => d
+            .iter()
+            .map(|(key, item)| (String::from_utf8_lossy(key).into_owned(), item))
+            .collect(),
+        _ => Vec::new(),
+    };
+    for (key, child) in children {
+        let child = node(child, Some(&key), next, out);
+        let _ = writeln!(out, "  n{id} -> n{child};");
+    }
+    id
+}
+
+/// The start of `s` if it's text, cut to [`PREVIEW`] characters, with
+/// quotes and backslashes escaped so it reads back unambiguously.
+fn preview(s: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(s).ok()?;
+    if text.is_empty() || text.chars().any(char::is_control) {
+        return None;
+    }
+    let mut preview = String::new();
+    for c in text.chars().take(PREVIEW) {
+        if matches!(c, '"' | '\\') {
+            preview.push('\\');
+        }
+        preview.push(c);
+    }
+    if text.chars().nth(PREVIEW).is_some() {
+        preview.push_str("...");
+    }
+    Some(preview)
+}
+
+/// Makes `label` a DOT string: quotes and backslashes escaped, and newlines
+/// as `\n`, which centers the line.
+fn escape(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bencode_decoder::decode;
+
+    #[test]
+    fn dot() {
+        let value =
+            decode(b"d8:announce3:url4:infod4:name3:a\"b6:pieces2:\x00\x01e4:tagsli1eee").unwrap();
+        assert_eq!(
+            to_dot(&value),
+            r#"digraph bencode {
+  node [shape=box, fontname="monospace"];
+  n0 [label="dict[3]\n59 bytes"];
+  n1 [label="announce\nbytes[3] \"url\""];
+  n0 -> n1;
+  n2 [label="info\ndict[2]\n25 bytes"];
+  n3 [label="name\nbytes[3] \"a\\\"b\""];
+  n2 -> n3;
+  n4 [label="pieces\nbytes[2]"];
+  n2 -> n4;
+  n0 -> n2;
+  n5 [label="tags\nlist[1]\n5 bytes"];
+  n6 [label="[0]\nint 1"];
+  n5 -> n6;
+  n0 -> n5;
+}
+"#
+        );
+        assert_eq!(
+            preview(&[b'x'; 40]).unwrap(),
+            format!("{}...", "x".repeat(30))
+        );
+    }
+}
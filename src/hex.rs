@@ -0,0 +1,25 @@
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+pub fn encode(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        let _ = write!(hex, "{b:02x}");
+    }
+    hex
+}
+
+/// Reads hex digits of either case, or `None` if `hex` isn't an even number
+/// of them.
+#[cfg_attr(not(any(feature = "json", feature = "torrent")), allow(dead_code))]
+pub fn decode(hex: &[u8]) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    hex.chunks(2)
+        .map(|pair| {
+            let digits = core::str::from_utf8(pair).ok()?;
+            u8::from_str_radix(digits, 16).ok()
+        })
+        .collect()
+}
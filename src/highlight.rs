@@ -0,0 +1,81 @@
+//! The raw encoded bytes of a file as text, for `dump`: printable ASCII as
+//! is and anything else as `\xNN`, optionally colored by what each byte is
+//! part of.
+
+use std::fmt::Write;
+
+use bencode_decoder::{
+    lexer::{Lexer, TokenKind},
+    Error,
+};
+
+const PREFIX: &str = "\x1b[2m";
+const PAYLOAD: &str = "\x1b[32m";
+const INTEGER: &str = "\x1b[33m";
+const MARKER: &str = "\x1b[1;35m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders `input` token by token. Nesting isn't checked, so a file that's
+/// structurally broken still shows up to the first unreadable token.
+pub fn dump(input: &[u8], color: bool) -> Result<String, Error> {
+    let mut out = String::new();
+    let mut part = |style: &str, bytes: &[u8]| {
+        if color {
+            out.push_str(style);
+        }
+        escape(bytes, &mut out);
+        if color {
+            out.push_str(RESET);
+        }
+    };
+
+    for token in Lexer::new(input) {
+        let token = token?;
+        let raw = &input[token.span.clone()];
+        match token.kind {
+            TokenKind::String(payload) => {
+                let (prefix, _) = raw.split_at(raw.len() - payload.len());
+                part(PREFIX, prefix);
+                part(PAYLOAD, payload);
+            }
+            TokenKind::Integer(_) => part(INTEGER, raw),
+            TokenKind::ListStart | TokenKind::DictStart | TokenKind::End => part(MARKER, raw),
+        }
+    }
+    Ok(out)
+}
+
+fn escape(bytes: &[u8], out: &mut String) {
+    for &byte in bytes {
+        match byte {
+            b'\\' => out.push_str(r"\\"),
+            b' '..=b'~' => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\x{byte:02x}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain() {
+        assert_eq!(
+            dump(b"d3:a\\b4:\x00\x01\xffzli-3eee", false).unwrap(),
+            r"d3:a\\b4:\x00\x01\xffzli-3eee"
+        );
+        assert_eq!(dump(b"l1:ai01e", false).unwrap_err().offset(), Some(5));
+    }
+
+    #[test]
+    fn colored() {
+        assert_eq!(
+            dump(b"l2:abi1ee", true).unwrap(),
+            "\x1b[1;35ml\x1b[0m\x1b[2m2:\x1b[0m\x1b[32mab\x1b[0m\x1b[33mi1e\x1b[0m\
+             \x1b[1;35me\x1b[0m"
+        );
+    }
+}
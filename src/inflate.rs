@@ -0,0 +1,412 @@
+//! A DEFLATE (RFC 1951) decoder for reading gzip (RFC 1952) input, after
+//! Mark Adler's `puff.c`.
+
+use std::io;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("gzip: {message}"))
+}
+
+struct Bits<'a> {
+    input: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl Bits<'_> {
+    fn take(&mut self, n: u32) -> io::Result<u32> {
+        while self.count < n {
+            let byte = *self
+                .input
+                .get(self.position)
+                .ok_or_else(|| invalid("unexpected end of data"))?;
+            self.position += 1;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let bits = self.buffer & ((1 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(bits)
+    }
+
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+/// Canonical Huffman code: how many codes of each length, then the symbols
+/// in code order.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(invalid("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut Bits) -> io::Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.take(1)? as i32;
+            let count = count as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+fn too_large(limit: usize) -> io::Error {
+    invalid(&format!("decompressed data is over the {limit}-byte limit"))
+}
+
+fn codes(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    limit: usize,
+    lengths: &Huffman,
+    distances: &Huffman,
+) -> io::Result<()> {
+    loop {
+        let symbol = lengths.decode(bits)? as usize;
+        if symbol != 256 && out.len() >= limit {
+            return Err(too_large(limit));
+        }
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let symbol = symbol - 257;
+                if symbol >= 29 {
+                    return Err(invalid("invalid length code"));
+                }
+                let length =
+                    LENGTH_BASE[symbol] as usize + bits.take(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+                let symbol = distances.decode(bits)? as usize;
+                if symbol >= 30 {
+                    return Err(invalid("invalid distance code"));
+                }
+                let distance = DISTANCE_BASE[symbol] as usize
+                    + bits.take(DISTANCE_EXTRA[symbol] as u32)? as usize;
+                if distance > out.len() {
+                    return Err(invalid("distance too far back"));
+                }
+                if out.len() + length > limit {
+                    return Err(too_large(limit));
+                }
+
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+fn fixed_codes() -> io::Result<(Huffman, Huffman)> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(bits: &mut Bits) -> io::Result<(Huffman, Huffman)> {
+    const ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+
+    let literals = bits.take(5)? as usize + 257;
+    let distances = bits.take(5)? as usize + 1;
+    let code_lengths = bits.take(4)? as usize + 4;
+    if literals > 286 || distances > 30 {
+        return Err(invalid("bad code counts"));
+    }
+
+    let mut lengths = [0u8; 19];
+    for &index in &ORDER[..code_lengths] {
+        lengths[index] = bits.take(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&lengths)?;
+
+    let mut lengths = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        let (value, repeat) = match code_lengths.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| invalid("repeat with no length"))?;
+                (previous, 3 + bits.take(2)?)
+            }
+            17 => (0, 3 + bits.take(3)?),
+            _ => (0, 11 + bits.take(7)?),
+        };
+        if lengths.len() + repeat as usize > literals + distances {
+            return Err(invalid("too many code lengths"));
+        }
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths[256] == 0 {
+        return Err(invalid("no end-of-block code"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literals])?,
+        Huffman::new(&lengths[literals..])?,
+    ))
+}
+
+/// Decodes a raw DEFLATE stream, returning the data and the bytes consumed.
+/// Output past `limit` bytes is an error.
+fn inflate(input: &[u8], limit: usize) -> io::Result<(Vec<u8>, usize)> {
+    let mut bits = Bits {
+        input,
+        position: 0,
+        buffer: 0,
+        count: 0,
+    };
+    let mut out = Vec::new();
+
+    loop {
+        let last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => {
+                bits.align();
+                let header = input
+                    .get(bits.position..bits.position + 4)
+                    .ok_or_else(|| invalid("unexpected end of data"))?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                if length != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err(invalid("stored block length mismatch"));
+                }
+                let start = bits.position + 4;
+                let block = input
+                    .get(start..start + length as usize)
+                    .ok_or_else(|| invalid("unexpected end of data"))?;
+                if out.len() + block.len() > limit {
+                    return Err(too_large(limit));
+                }
+                out.extend_from_slice(block);
+                bits.position = start + length as usize;
+            }
+            1 => {
+                let (lengths, distances) = fixed_codes()?;
+                codes(&mut bits, &mut out, limit, &lengths, &distances)?;
+            }
+            2 => {
+                let (lengths, distances) = dynamic_codes(&mut bits)?;
+                codes(&mut bits, &mut out, limit, &lengths, &distances)?;
+            }
+            _ => return Err(invalid("invalid block type")),
+        }
+        if last {
+            return Ok((out, bits.position));
+        }
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn skip(rest: &[u8], n: usize) -> io::Result<&[u8]> {
+    rest.get(n..).ok_or_else(|| invalid("truncated header"))
+}
+
+pub const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+
+/// Decompresses a gzip file, checking its CRC and length. Concatenated
+/// members are decoded in turn. More than `limit` bytes of output is an
+/// error, against files built to expand enormously.
+pub fn gunzip(mut input: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    const FHCRC: u8 = 2;
+    const FEXTRA: u8 = 4;
+    const FNAME: u8 = 8;
+    const FCOMMENT: u8 = 16;
+
+    let mut out = Vec::new();
+
+    while !input.is_empty() {
+        if input.len() < 18 || !input.starts_with(GZIP_MAGIC) || input[2] != 8 {
+            return Err(invalid("not a deflate-compressed gzip member"));
+        }
+        let flags = input[3];
+        let mut rest = &input[10..];
+
+        if flags & FEXTRA != 0 {
+            let length = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+            rest = skip(rest, 2 + length)?;
+        }
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                let end = rest
+                    .iter()
+                    .position(|&b| b == 0)
+                    .ok_or_else(|| invalid("truncated header"))?;
+                rest = skip(rest, end + 1)?;
+            }
+        }
+        if flags & FHCRC != 0 {
+            rest = skip(rest, 2)?;
+        }
+
+        let (data, used) = inflate(rest, limit - out.len())?;
+        let trailer = rest
+            .get(used..used + 8)
+            .ok_or_else(|| invalid("missing trailer"))?;
+        if crc32(&data) != u32::from_le_bytes(trailer[..4].try_into().unwrap()) {
+            return Err(invalid("CRC mismatch"));
+        }
+        if data.len() as u32 != u32::from_le_bytes(trailer[4..].try_into().unwrap()) {
+            return Err(invalid("length mismatch"));
+        }
+
+        out.extend(data);
+        input = &rest[used + 8..];
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gunzip_all(input: &[u8]) -> io::Result<Vec<u8>> {
+        gunzip(input, usize::MAX)
+    }
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn block_types() {
+        let fixed = unhex("1f8b08000000000002034b31b12a2e48cccd34314a4d050023cfe0a20c000000");
+        let stored =
+            unhex("1f8b0800000000000403010c00f3ff64343a7370616d693432656523cfe0a20c000000");
+        assert_eq!(gunzip_all(&fixed).unwrap(), b"d4:spami42ee");
+        assert_eq!(gunzip_all(&stored).unwrap(), b"d4:spami42ee");
+
+        let dynamic = unhex(concat!(
+            "1f8b08000000000002035dce3b0ec2401044c12bd9d3ddebcf6d9058244b86fb870488605e5859dd4f9f9f",
+            "c77be67c5df75c66e3da599dea74673a47e7d6b9771e7f8e5f638157b860c186030f78837718bfc2aff02b",
+            "fc0abfc2aff02bfc0abfc2aff0137ec24ff8093fe127fc849ff0137ec2cff8193fe367fc8c9ff1337ec6cf",
+            "f819bfe017fc825ff00b7ec12ff805bfe09763ce2f58a456ceb8030000",
+        ));
+        let expected: Vec<u8> = (0..60)
+            .flat_map(|i| format!("d4:name{}:file{i}e", i.to_string().len() + 4).into_bytes())
+            .collect();
+        assert_eq!(
+            gunzip_all(&dynamic).unwrap(),
+            [&b"l"[..], &expected, b"e"].concat()
+        );
+
+        let mut both = fixed.clone();
+        both.extend(&stored);
+        assert_eq!(gunzip_all(&both).unwrap(), b"d4:spami42eed4:spami42ee");
+    }
+
+    #[test]
+    fn corrupt_input() {
+        let mut fixed = unhex("1f8b08000000000002034b31b12a2e48cccd34314a4d050023cfe0a20c000000");
+        let last = fixed.len() - 4;
+        fixed[last] ^= 1;
+        assert!(gunzip_all(&fixed)
+            .unwrap_err()
+            .to_string()
+            .contains("length mismatch"));
+
+        assert!(gunzip_all(&fixed[..20]).is_err());
+        assert!(gunzip_all(b"\x1f\x8b\x09").is_err());
+    }
+
+    #[test]
+    fn limit() {
+        // 100 KB of zeros in 132 bytes
+        let zeros = unhex(concat!(
+            "1f8b08000000000002ffedc13101000000c2a0f54f6d0d0fa00000000000000000000000000000",
+            "000000000000000000000000000000000000000000000000000000000000000000000000000000",
+            "000000000000000000000000000000000000000000000000000000000000000000000000000000",
+            "000000008057037d9511d4a0860100",
+        ));
+        assert_eq!(gunzip(&zeros, 100_000).unwrap().len(), 100_000);
+        let error = gunzip(&zeros, 99_999).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("99999-byte limit"), "{error}");
+
+        let stored =
+            unhex("1f8b0800000000000403010c00f3ff64343a7370616d693432656523cfe0a20c000000");
+        assert!(gunzip(&stored, 11).is_err());
+        assert!(gunzip(&[stored.clone(), stored].concat(), 23).is_err());
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+}
@@ -0,0 +1,30 @@
+//! Reading input files, decompressing them unless `--no-decompress` is given.
+
+use std::{fs, io, path::Path};
+
+use crate::{cli::Matches, inflate, zstd};
+
+/// Most bytes a compressed input may expand to, far beyond any real torrent
+/// or session file, so that a small file built to expand enormously fails
+/// instead of exhausting memory.
+pub const MAX_DECOMPRESSED: usize = 256 * 1024 * 1024;
+
+pub fn read(matches: &Matches, path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let input = fs::read(path)?;
+    match matches.flag("no-decompress") {
+        true => Ok(input),
+        false => decompress(input),
+    }
+}
+
+/// Decompresses gzip and zstd input, recognized by their magic bytes.
+/// Anything else is returned as is.
+pub fn decompress(input: Vec<u8>) -> io::Result<Vec<u8>> {
+    if input.starts_with(inflate::GZIP_MAGIC) {
+        inflate::gunzip(&input, MAX_DECOMPRESSED)
+    } else if input.starts_with(zstd::MAGIC) {
+        zstd::decompress(&input, MAX_DECOMPRESSED)
+    } else {
+        Ok(input)
+    }
+}
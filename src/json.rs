@@ -0,0 +1,409 @@
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::fmt::Write;
+
+use crate::{hex, Bencode, Error};
+
+/// Renders a value as compact JSON. Strings that aren't valid UTF-8 become
+/// `{"hex": "..."}` objects, and such dictionary keys are written as hex.
+pub fn to_json(value: &Bencode) -> String {
+    let mut json = String::new();
+    let _ = write_json(value, &mut json, None);
+    json
+}
+
+/// Like [`to_json`], indented by two spaces per level, for reading and
+/// editing by hand. [`from_json`] reads it back.
+pub fn to_json_pretty(value: &Bencode) -> String {
+    let mut json = String::new();
+    let _ = write_json(value, &mut json, Some(0));
+    json
+}
+
+/// Like [`to_json_pretty`], written to `writer` as it's produced rather
+/// than built up in memory first. Wrap files and sockets in a
+/// [`std::io::BufWriter`].
+#[cfg(feature = "std")]
+pub fn to_writer_pretty(value: &Bencode, writer: impl std::io::Write) -> std::io::Result<()> {
+    /// Carries the writer's error out through [`core::fmt::Error`], which
+    /// has no room for it.
+    struct Adapter<W> {
+        writer: W,
+        error: Option<std::io::Error>,
+    }
+
+    impl<W: std::io::Write> Write for Adapter<W> {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            self.writer.write_all(s.as_bytes()).map_err(|error| {
+                self.error = Some(error);
+                core::fmt::Error
+            })
+        }
+    }
+
+    let mut adapter = Adapter {
+        writer,
+        error: None,
+    };
+    match write_json(value, &mut adapter, Some(0)) {
+        Ok(()) => adapter.writer.flush(),
+        Err(_) => Err(adapter
+            .error
+            .unwrap_or_else(|| std::io::Error::other("formatting failed"))),
+    }
+}
+
+/// Starts a new line at `level` when pretty-printing.
+fn newline(out: &mut impl Write, level: Option<usize>) -> core::fmt::Result {
+    if let Some(level) = level {
+        out.write_char('\n')?;
+        for _ in 0..level {
+            out.write_str("  ")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json(value: &Bencode, out: &mut impl Write, level: Option<usize>) -> core::fmt::Result {
+    let inner = level.map(|level| level + 1);
+    match value {
+        Bencode::String(s) => match core::str::from_utf8(s) {
+            Ok(s) => write_string(s, out),
+            Err(_) => {
+                out.write_str(r#"{"hex": ""#)?;
+                write_hex(s, out)?;
+                out.write_str("\"}")
+            }
+        },
+        Bencode::Integer(i) => write!(out, "{i}"),
+        Bencode::List(l) => {
+            out.write_char('[')?;
+            for (i, bencode) in l.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                newline(out, inner)?;
+                write_json(bencode, out, inner)?;
+            }
+            if !l.is_empty() {
+                newline(out, level)?;
+            }
+            out.write_char(']')
+        }
+        Bencode::Dictionary(d) => {
+            out.write_char('{')?;
+            for (i, (key, value)) in d.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                newline(out, inner)?;
+                match core::str::from_utf8(key) {
+                    Ok(key) => write_string(key, out)?,
+                    Err(_) => {
+                        out.write_char('"')?;
+                        write_hex(key, out)?;
+                        out.write_char('"')?;
+                    }
+                }
+                out.write_char(':')?;
+                if level.is_some() {
+                    out.write_char(' ')?;
+                }
+                write_json(value, out, inner)?;
+            }
+            if !d.is_empty() {
+                newline(out, level)?;
+            }
+            out.write_char('}')
+        }
+    }
+}
+
+/// Writes `bytes` as lowercase hex, as [`hex::encode`] spells them.
+fn write_hex(bytes: &[u8], out: &mut impl Write) -> core::fmt::Result {
+    bytes.iter().try_for_each(|byte| write!(out, "{byte:02x}"))
+}
+
+/// Parses JSON as produced by [`to_json`]: objects become dictionaries,
+/// strings become byte strings and `{"hex": "..."}` objects become the bytes
+/// they spell. Numbers must be integers that fit an `i64`; `true`, `false`,
+/// `null` and fractions have no bencode equivalent and are rejected.
+///
+/// Binary dictionary keys are written as plain hex by [`to_json`], so they
+/// don't survive a round trip; compare the re-encoded result if that matters.
+pub fn from_json(json: &str) -> Result<Bencode, Error> {
+    let mut parser = JsonParser { json, offset: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.offset == json.len() {
+        true => Ok(value),
+        false => Err(parser.error("trailing characters after value")),
+    }
+}
+
+struct JsonParser<'a> {
+    json: &'a str,
+    offset: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn error(&self, reason: &'static str) -> Error {
+        Error::InvalidJson {
+            offset: self.offset,
+            reason,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.json[self.offset..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    /// Consumes `c` after any whitespace, if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        let found = self.rest().starts_with(c);
+        if found {
+            self.offset += c.len_utf8();
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char, reason: &'static str) -> Result<(), Error> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.error(reason)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Bencode, Error> {
+        self.skip_whitespace();
+        match self.rest().chars().next() {
+            Some('"') => Ok(Bencode::String(self.string()?.into_bytes())),
+            Some('[') => {
+                self.offset += 1;
+                let mut list = Vec::new();
+                if !self.eat(']') {
+                    loop {
+                        list.push(self.value()?);
+                        if self.eat(']') {
+                            break;
+                        }
+                        self.expect(',', "expected `,` or `]`")?;
+                    }
+                }
+                Ok(Bencode::List(list))
+            }
+            Some('{') => {
+                self.offset += 1;
+                let mut dict = BTreeMap::new();
+                if !self.eat('}') {
+                    loop {
+                        self.skip_whitespace();
+                        if !self.rest().starts_with('"') {
+                            return Err(self.error("expected a string key"));
+                        }
+                        let key = self.string()?;
+                        self.expect(':', "expected `:`")?;
+                        dict.insert(key.into_bytes(), self.value()?);
+                        if self.eat('}') {
+                            break;
+                        }
+                        self.expect(',', "expected `,` or `}`")?;
+                    }
+                }
+                Ok(unhex(dict))
+            }
+            Some('-' | '0'..='9') => self.integer(),
+            Some(_) => Err(self.error("expected a string, integer, list or object")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn integer(&mut self) -> Result<Bencode, Error> {
+        let rest = self.rest();
+        let digits = rest.strip_prefix('-').unwrap_or(rest);
+        let len = rest.len() - digits.len()
+            + digits
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(digits.len());
+        if rest[len..].starts_with(['.', 'e', 'E']) {
+            return Err(self.error("only integers are supported"));
+        }
+        let number = rest[..len]
+            .parse()
+            .map_err(|_| self.error("invalid or out of range integer"))?;
+        self.offset += len;
+        Ok(Bencode::Integer(number))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.offset += 1;
+        let mut string = String::new();
+
+        loop {
+            let mut chars = self.rest().chars();
+            let c = chars
+                .next()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.offset += c.len_utf8();
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escape = chars
+                        .next()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.offset += 1;
+                    string.push(match escape {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    });
+                }
+                c if (c as u32) < 0x20 => return Err(self.error("control character in string")),
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// The character of a `\uXXXX` escape, which may be the first half of a
+    /// surrogate pair.
+    fn unicode_escape(&mut self) -> Result<char, Error> {
+        let first = self.code_unit()?;
+        let units = match first {
+            0xd800..=0xdbff => {
+                if !self.rest().starts_with("\\u") {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                self.offset += 2;
+                alloc::vec![first, self.code_unit()?]
+            }
+            _ => alloc::vec![first],
+        };
+        char::decode_utf16(units)
+            .next()
+            .and_then(Result::ok)
+            .ok_or_else(|| self.error("unpaired surrogate"))
+    }
+
+    fn code_unit(&mut self) -> Result<u16, Error> {
+        let unit = self
+            .rest()
+            .get(..4)
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+        self.offset += 4;
+        Ok(unit)
+    }
+}
+
+/// Turns a `{"hex": "..."}` object back into the bytes it stands for.
+fn unhex(dict: BTreeMap<Vec<u8>, Bencode>) -> Bencode {
+    if let [(key, Bencode::String(digits))] = dict.iter().collect::<Vec<_>>()[..] {
+        if key == b"hex" {
+            if let Some(bytes) = hex::decode(digits) {
+                return Bencode::String(bytes);
+            }
+        }
+    }
+    Bencode::Dictionary(dict)
+}
+
+fn write_string(s: &str, out: &mut impl Write) -> core::fmt::Result {
+    out.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str(r#"\""#)?,
+            '\\' => out.write_str(r"\\")?,
+            '\n' => out.write_str(r"\n")?,
+            '\r' => out.write_str(r"\r")?,
+            '\t' => out.write_str(r"\t")?,
+            c if (c as u32) < 0x20 => write!(out, r"\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn bencode_to_json() {
+        let value = decode(b"d3:bari-7e3:fool5:a\"b\nc2:\xff\x00ee").unwrap();
+
+        assert_eq!(
+            to_json(&value),
+            r#"{"bar":-7,"foo":["a\"b\nc",{"hex": "ff00"}]}"#
+        );
+    }
+
+    #[test]
+    fn pretty_round_trip() {
+        let value =
+            decode(b"d3:bari-7e3:fool5:a\"b\nc2:\xff\x00le0:ded3:hexi1eee3:numdee").unwrap();
+        let pretty = to_json_pretty(&value);
+
+        assert_eq!(
+            pretty,
+            "{\n  \"bar\": -7,\n  \"foo\": [\n    \"a\\\"b\\nc\",\n    {\"hex\": \"ff00\"},\n    [],\n    \"\",\n    {},\n    {\n      \"hex\": 1\n    }\n  ],\n  \"num\": {}\n}"
+        );
+        assert_eq!(from_json(&pretty), Ok(value));
+        assert_eq!(
+            from_json(r#" {"a": "\u00e9\ud83d\ude00", "b": [1, -2]} "#).unwrap(),
+            decode("d1:a6:é😀1:bli1ei-2eee".as_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn streamed_pretty() {
+        let value = decode(b"d3:bari-7e3:fool1:a2:\xff\x00dee2:\x01\xffi1ee").unwrap();
+        let mut out = Vec::new();
+        to_writer_pretty(&value, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), to_json_pretty(&value));
+
+        // Writing stops at the first error, which is passed on
+        let mut full = [0u8; 16];
+        let error = to_writer_pretty(&value, &mut full[..]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::WriteZero);
+        assert_eq!(&full, b"{\n  \"01ff\": 1,\n ");
+    }
+
+    #[test]
+    fn json_errors() {
+        let error = |json| match from_json(json) {
+            Err(Error::InvalidJson { offset, reason }) => (offset, reason),
+            other => panic!("{other:?}"),
+        };
+
+        assert_eq!(
+            error("[1,]"),
+            (3, "expected a string, integer, list or object")
+        );
+        assert_eq!(error("1.5"), (0, "only integers are supported"));
+        assert_eq!(
+            error("true"),
+            (0, "expected a string, integer, list or object")
+        );
+        assert_eq!(
+            error("99999999999999999999"),
+            (0, "invalid or out of range integer")
+        );
+        assert_eq!(error("{1: 2}"), (1, "expected a string key"));
+        assert_eq!(error(r#""\ud800""#), (7, "unpaired surrogate"));
+        assert_eq!(error("[] x"), (3, "trailing characters after value"));
+    }
+}
@@ -0,0 +1,300 @@
+//! Low-level tokenizer. It scans length prefixes, integers and structural
+//! markers with their positions, but doesn't check how they nest; see
+//! [`crate::pull`] for that.
+
+use crate::{span::Span, Error, IntegerError};
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum TokenKind<'a> {
+    /// `l`
+    ListStart,
+    /// `d`
+    DictStart,
+    /// `e`, closing a list or dictionary.
+    End,
+    Integer(i64),
+    /// The payload of a length-prefixed string.
+    String(&'a [u8]),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Token<'a> {
+    pub kind: TokenKind<'a>,
+    /// Source range of the whole token, including any prefix or markers.
+    pub span: Span,
+}
+
+/// Reads the `<len>:` prefix at the start of `input`, returning the declared
+/// length and the input following the colon. Only the digits and the colon
+/// are looked at. A length running past the end of the input is left for
+/// the caller, as it may just be truncated.
+pub fn scan_length_prefix(input: &[u8]) -> Result<(usize, &[u8]), Error> {
+    let invalid = |offset| Error::InvalidLengthPrefix { offset };
+    let mut len = 0usize;
+    let mut pos = 0;
+    while let Some(&b) = input.get(pos).filter(|b| b.is_ascii_digit()) {
+        // Only 0: may start with a zero
+        if pos == 1 && input[0] == b'0' {
+            return Err(invalid(0));
+        }
+        len = len
+            .checked_mul(10)
+            .and_then(|len| len.checked_add(usize::from(b - b'0')))
+            .ok_or(invalid(0))?;
+        pos += 1;
+    }
+
+    match input.get(pos) {
+        // A bare run of digits may just be a prefix cut short, needing at
+        // least the colon and the payload
+        None => Err(Error::needs(len.saturating_add(1))),
+        Some(b':') if pos > 0 => Ok((len, &input[pos + 1..])),
+        Some(_) => Err(invalid(pos)),
+    }
+}
+
+/// Splits a length-prefixed string off the front of `input`, returning its
+/// payload and the remaining input.
+pub fn scan_string(input: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+    let (len, rest) = scan_length_prefix(input)?;
+    if rest.len() < len {
+        return Err(Error::needs(len - rest.len()));
+    }
+
+    Ok(rest.split_at(len))
+}
+
+/// Splits an `i<number>e` integer off the front of `input`, reading the
+/// digits as it goes rather than searching for the `e` first. Offsets in
+/// errors are from the start of `input`.
+pub fn scan_integer(input: &[u8]) -> Result<(i64, &[u8]), Error> {
+    match input.first() {
+        Some(b'i') => {}
+        Some(&b) => return Err(Error::UnexpectedByte(b)),
+        // The i, a digit and the e
+        None => return Err(Error::needs(3)),
+    }
+    let invalid = |offset, reason| Error::InvalidInteger { offset, reason };
+    let mut pos = 1;
+    let negative = input.get(pos) == Some(&b'-');
+    if negative {
+        pos += 1;
+    }
+
+    let digits = pos;
+    // Accumulated negatively, since i64::MIN has no positive counterpart
+    let mut number = 0i64;
+    while let Some(&b) = input.get(pos).filter(|b| b.is_ascii_digit()) {
+        if pos == digits + 1 && input[digits] == b'0' {
+            return Err(invalid(digits, IntegerError::LeadingZero));
+        }
+        number = number
+            .checked_mul(10)
+            .and_then(|number| number.checked_sub(i64::from(b - b'0')))
+            .ok_or(invalid(digits, IntegerError::Overflow))?;
+        pos += 1;
+    }
+
+    match input.get(pos) {
+        // A digit if there's none yet, and the e
+        None => return Err(Error::needs(if pos == digits { 2 } else { 1 })),
+        Some(b'e') if pos == digits => return Err(invalid(pos, IntegerError::NoDigits)),
+        Some(b'e') => {}
+        Some(&b) => return Err(invalid(pos, IntegerError::UnexpectedByte(b))),
+    }
+    if negative && number == 0 {
+        return Err(invalid(digits, IntegerError::NegativeZero));
+    }
+    let number = match negative {
+        true => number,
+        false => number
+            .checked_neg()
+            .ok_or(invalid(digits, IntegerError::Overflow))?,
+    };
+    Ok((number, &input[pos + 1..]))
+}
+
+/// Splits the token at the start of `input` off the rest.
+pub fn scan_token(input: &[u8]) -> Result<(TokenKind<'_>, &[u8]), Error> {
+    match input.first() {
+        None => Err(Error::needs(1)),
+        Some(b'0'..=b'9') => scan_string(input).map(|(s, rest)| (TokenKind::String(s), rest)),
+        Some(b'i') => scan_integer(input).map(|(i, rest)| (TokenKind::Integer(i), rest)),
+        Some(b'l') => Ok((TokenKind::ListStart, &input[1..])),
+        Some(b'd') => Ok((TokenKind::DictStart, &input[1..])),
+        Some(b'e') => Ok((TokenKind::End, &input[1..])),
+        Some(&b) => Err(Error::UnexpectedByte(b)),
+    }
+}
+
+/// Iterates over the tokens of an input, tracking their offsets.
+pub struct Lexer<'a> {
+    input: &'a [u8],
+    offset: usize,
+    failed: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            offset: 0,
+            failed: false,
+        }
+    }
+
+    /// Byte offset of the next token.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the next token, or `None` at the end of the input. After an
+    /// error the offset stays at the offending token.
+    pub fn next_token(&mut self) -> Result<Option<Token<'a>>, Error> {
+        if self.offset == self.input.len() || self.failed {
+            return Ok(None);
+        }
+
+        let start = self.offset;
+        let (kind, rest) = scan_token(&self.input[start..]).map_err(|error| {
+            self.failed = true;
+            error.offset_by(start)
+        })?;
+        self.offset = self.input.len() - rest.len();
+
+        Ok(Some(Token {
+            kind,
+            span: start..self.offset,
+        }))
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        let tokens: Result<Vec<_>, _> = Lexer::new(b"d3:fooli-3eee").collect();
+
+        assert_eq!(
+            tokens.unwrap(),
+            [
+                Token {
+                    kind: TokenKind::DictStart,
+                    span: 0..1
+                },
+                Token {
+                    kind: TokenKind::String(b"foo"),
+                    span: 1..6
+                },
+                Token {
+                    kind: TokenKind::ListStart,
+                    span: 6..7
+                },
+                Token {
+                    kind: TokenKind::Integer(-3),
+                    span: 7..11
+                },
+                Token {
+                    kind: TokenKind::End,
+                    span: 11..12
+                },
+                Token {
+                    kind: TokenKind::End,
+                    span: 12..13
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unbalanced_input_still_tokenizes() {
+        let kinds: Vec<_> = Lexer::new(b"eei1e")
+            .map(|token| token.unwrap().kind)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            [TokenKind::End, TokenKind::End, TokenKind::Integer(1)]
+        );
+    }
+
+    #[test]
+    fn scanners() {
+        assert_eq!(scan_length_prefix(b"12:abc"), Ok((12, &b"abc"[..])));
+        assert_eq!(scan_length_prefix(b"12"), Err(Error::needs(13)));
+        assert_eq!(scan_length_prefix(b"0:"), Ok((0, &b""[..])));
+        for (input, offset) in [
+            (&b"1x2:a"[..], 1),
+            (b":a", 0),
+            (b"03:abc", 0),
+            (b"00:", 0),
+            (b"99999999999999999999999:a", 0),
+        ] {
+            assert_eq!(
+                scan_length_prefix(input),
+                Err(Error::InvalidLengthPrefix { offset }),
+                "{input:?}"
+            );
+        }
+        assert_eq!(scan_string(b"5:abc"), Err(Error::needs(2)));
+        assert_eq!(scan_string(b"3:abcde"), Ok((&b"abc"[..], &b"de"[..])));
+        assert_eq!(scan_integer(b"i-7ex"), Ok((-7, &b"x"[..])));
+        assert_eq!(
+            scan_integer(b"i-9223372036854775808e"),
+            Ok((i64::MIN, &b""[..]))
+        );
+        assert_eq!(
+            scan_integer(b"i9223372036854775807e"),
+            Ok((i64::MAX, &b""[..]))
+        );
+        assert_eq!(scan_integer(b"i12"), Err(Error::needs(1)));
+        assert_eq!(scan_integer(b"i-"), Err(Error::needs(2)));
+        assert_eq!(scan_integer(b""), Err(Error::needs(3)));
+        assert_eq!(scan_integer(b"x42e"), Err(Error::UnexpectedByte(b'x')));
+        assert_eq!(scan_integer(b"55e"), Err(Error::UnexpectedByte(b'5')));
+
+        let invalid = |input: &[u8]| match scan_integer(input) {
+            Err(Error::InvalidInteger { offset, reason }) => (offset, reason),
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(invalid(b"ie"), (1, IntegerError::NoDigits));
+        assert_eq!(invalid(b"i-e"), (2, IntegerError::NoDigits));
+        assert_eq!(invalid(b"i+5e"), (1, IntegerError::UnexpectedByte(b'+')));
+        assert_eq!(invalid(b"i--1e"), (2, IntegerError::UnexpectedByte(b'-')));
+        assert_eq!(invalid(b"i 5e"), (1, IntegerError::UnexpectedByte(b' ')));
+        assert_eq!(invalid(b"i5 e"), (2, IntegerError::UnexpectedByte(b' ')));
+        assert_eq!(invalid(b"i1x2e"), (2, IntegerError::UnexpectedByte(b'x')));
+        assert_eq!(invalid(b"i03e"), (1, IntegerError::LeadingZero));
+        assert_eq!(invalid(b"i-01e"), (2, IntegerError::LeadingZero));
+        assert_eq!(invalid(b"i00"), (1, IntegerError::LeadingZero));
+        assert_eq!(invalid(b"i-0e"), (2, IntegerError::NegativeZero));
+        assert_eq!(
+            invalid(b"i9223372036854775808e"),
+            (1, IntegerError::Overflow)
+        );
+        assert_eq!(
+            invalid(b"i-99999999999999999999e"),
+            (2, IntegerError::Overflow)
+        );
+        assert_eq!(scan_integer(b"i0e"), Ok((0, &b""[..])));
+
+        let mut lexer = Lexer::new(b"i1ex");
+        assert!(lexer.next_token().is_ok());
+        assert_eq!(lexer.next_token(), Err(Error::UnexpectedByte(b'x')));
+        assert_eq!(lexer.offset(), 3);
+
+        let mut lexer = Lexer::new(b"li1ei+1ee");
+        let error = lexer.find_map(Result::err).unwrap();
+        assert_eq!(error.offset(), Some(5));
+    }
+}
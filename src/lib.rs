@@ -0,0 +1,103 @@
+//! Bencode decoding and encoding.
+//!
+//! Cargo features:
+//! - `std` (default): builds against the standard library. Without it the
+//!   parser only needs `alloc`.
+//! - `json`: JSON rendering of decoded values.
+//! - `torrent`: infohash and magnet link helpers for metainfo files, and
+//!   parsers for client resume data.
+//! - `ffi`: the C API in [`ffi`]. Build the shared library with
+//!   `cargo rustc --release --lib --features ffi --crate-type cdylib`.
+//! - `derive`: `#[derive(ToBencode, FromBencode)]` for structs.
+//! - `net`: networking over `std::net`, such as fetching an info
+//!   dictionary from peers with [`metadata::fetch`]. Enables `std` and
+//!   `torrent`.
+//! - `cli` (default): the `bencode_decoder` binary. Enables `json`,
+//!   `torrent` and `net`.
+//! - `alloc-stats`: an allocator that counts allocations, for tests and
+//!   benchmarks; see [`alloc_stats`].
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+// Lets the derive macros' `::bencode_decoder` paths resolve inside this crate
+extern crate self as bencode_decoder;
+
+#[cfg(feature = "alloc-stats")]
+pub mod alloc_stats;
+#[cfg(feature = "torrent")]
+pub mod announce;
+mod bencode;
+#[cfg(feature = "torrent")]
+pub mod bitfield;
+#[cfg(feature = "torrent")]
+pub mod bloom;
+pub mod builder;
+pub mod cancel;
+pub mod conformance;
+mod convert;
+pub mod cow;
+mod decode;
+pub mod display;
+pub mod edit;
+mod encode;
+#[cfg(feature = "std")]
+mod encoder;
+mod error;
+mod feed;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod generate;
+#[cfg(all(test, feature = "json", feature = "torrent"))]
+mod golden;
+mod hex;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod lexer;
+pub mod list;
+pub mod merge;
+#[cfg(feature = "torrent")]
+pub mod merkle;
+#[cfg(feature = "torrent")]
+pub mod metadata;
+pub mod node_id;
+pub mod path;
+#[cfg(feature = "torrent")]
+pub mod peer;
+pub mod pull;
+pub mod query;
+#[cfg(feature = "torrent")]
+pub mod resume;
+pub mod roundtrip;
+pub mod schema;
+#[cfg(feature = "torrent")]
+pub mod sha1;
+#[cfg(feature = "torrent")]
+pub mod sha256;
+#[cfg(feature = "torrent")]
+pub mod signature;
+pub mod span;
+pub mod timestamp;
+#[cfg(feature = "torrent")]
+pub mod torrent;
+
+pub use bencode::Bencode;
+#[cfg(feature = "derive")]
+pub use bencode_derive::{FromBencode, ToBencode};
+pub use cancel::CancelToken;
+pub use convert::{FromBencode, ToBencode};
+pub use decode::{
+    decode, decode_all, decode_bencoded_value, decode_iter, decode_with, DecodeOptions, Parser,
+    Values, DEFAULT_MAX_DEPTH,
+};
+pub use encode::encode;
+#[cfg(feature = "std")]
+pub use encoder::Encoder;
+pub use error::{Error, IntegerError};
+pub use feed::{Feed, FeedError, FeedOptions, FeedParser};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::convert::derive::*;
+    pub use alloc::collections::BTreeMap;
+}
@@ -0,0 +1,131 @@
+//! Sorting and deduplicating list values, e.g. to clean up tracker lists and
+//! `url-list`s. Combine with [`Bencode::get_path_mut`] to work on a list
+//! inside a document.
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::Bencode;
+
+/// The canonical order of values: integers by value, then strings by their
+/// bytes (the order of dictionary keys), then lists and dictionaries
+/// element by element.
+pub fn compare(a: &Bencode, b: &Bencode) -> Ordering {
+    fn rank(value: &Bencode) -> u8 {
+        match value {
+            Bencode::Integer(_) => 0,
+            Bencode::String(_) => 1,
+            Bencode::List(_) => 2,
+            Bencode::Dictionary(_) => 3,
+        }
+    }
+
+    match (a, b) {
+        (Bencode::Integer(a), Bencode::Integer(b)) => a.cmp(b),
+        (Bencode::String(a), Bencode::String(b)) => a.cmp(b),
+        (Bencode::List(a), Bencode::List(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (Bencode::Dictionary(a), Bencode::Dictionary(b)) => a
+            .iter()
+            .zip(b)
+            .map(|((ka, va), (kb, vb))| ka.cmp(kb).then_with(|| compare(va, vb)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Sorts `list` in [canonical order](compare).
+pub fn sort(list: &mut [Bencode]) {
+    list.sort_by(compare);
+}
+
+/// Sorts `list` by the value at `path` inside each element, e.g. `length`
+/// for the `files` of a torrent. Elements without it go last; the sort is
+/// stable, so ties keep their order.
+pub fn sort_by_key(list: &mut [Bencode], path: &str) -> Result<(), crate::Error> {
+    // Validates the path once, so that lookups below can't fail.
+    crate::path::parse(path)?;
+    list.sort_by(|a, b| {
+        let a = a.get_path(path).ok().flatten();
+        let b = b.get_path(path).ok().flatten();
+        match (a, b) {
+            (Some(a), Some(b)) => compare(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    });
+    Ok(())
+}
+
+/// Removes repeated elements, keeping the first of each, and returns how
+/// many were removed.
+pub fn dedup(list: &mut Vec<Bencode>) -> usize {
+    let before = list.len();
+    let mut seen: Vec<Bencode> = Vec::with_capacity(before);
+    list.retain(|value| match seen.contains(value) {
+        true => false,
+        false => {
+            seen.push(value.clone());
+            true
+        }
+    });
+    before - list.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode, encode};
+
+    #[test]
+    fn sorting() {
+        let mut list = vec![
+            Bencode::from("b"),
+            Bencode::List(vec![]),
+            Bencode::Integer(10),
+            Bencode::from("a"),
+            Bencode::Integer(9),
+        ];
+        sort(&mut list);
+        assert_eq!(encode(&Bencode::List(list)), b"li9ei10e1:a1:blee");
+
+        let Bencode::List(mut files) =
+            decode(b"ld6:lengthi3e1:x1:aed1:x1:bed6:lengthi1e1:x1:cee").unwrap()
+        else {
+            unreachable!()
+        };
+        sort_by_key(&mut files, "length").unwrap();
+        let order: Vec<_> = files.iter().map(|f| f.get_path("x").unwrap()).collect();
+        assert_eq!(
+            order,
+            [
+                Some(&Bencode::from("c")),
+                Some(&Bencode::from("a")),
+                Some(&Bencode::from("b"))
+            ]
+        );
+        assert!(sort_by_key(&mut files, "a..b").is_err());
+    }
+
+    #[test]
+    fn dedupe() {
+        let mut list = vec![
+            Bencode::from("a"),
+            Bencode::from("b"),
+            Bencode::from("a"),
+            Bencode::from("b"),
+            Bencode::from("c"),
+        ];
+        assert_eq!(dedup(&mut list), 2);
+        assert_eq!(
+            list,
+            [Bencode::from("a"), Bencode::from("b"), Bencode::from("c")]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! Leveled diagnostics on stderr, chosen with `-v`, `-vv`, `-vvv` or `-q`.
+//! By default only warnings are shown. At debug level, [`span`]s report how
+//! long each phase took, which is usually enough to find what is slow on a
+//! large collection.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+    time::Instant,
+};
+
+use crate::cli::Matches;
+
+#[derive(PartialEq, PartialOrd, Debug, Clone, Copy)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    const ALL: [Level; 5] = [
+        Level::Error,
+        Level::Warn,
+        Level::Info,
+        Level::Debug,
+        Level::Trace,
+    ];
+
+    /// `--quiet` wins over any number of `-v`.
+    pub fn from_flags(quiet: bool, verbose: usize) -> Self {
+        match quiet {
+            true => Level::Error,
+            false => Self::ALL[(Level::Warn as usize + verbose).min(Level::Trace as usize)],
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warning",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+
+pub fn init(matches: &Matches) {
+    let level = Level::from_flags(matches.flag("quiet"), matches.count("verbose"));
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+pub fn log(level: Level, message: fmt::Arguments) {
+    if enabled(level) {
+        eprintln!("{}: {message}", level.name());
+    }
+}
+
+macro_rules! warning {
+    ($($arg:tt)*) => { $crate::log::log($crate::log::Level::Warn, format_args!($($arg)*)) };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::log::log($crate::log::Level::Info, format_args!($($arg)*)) };
+}
+
+pub(crate) use {info, warning};
+
+/// A timed phase, logged at trace level when entered and at debug level,
+/// with its duration, when dropped.
+pub struct Span {
+    name: String,
+    start: Instant,
+}
+
+pub fn span(name: impl fmt::Display) -> Span {
+    let name = name.to_string();
+    log(Level::Trace, format_args!("{name}: started"));
+    Span {
+        name,
+        start: Instant::now(),
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        log(
+            Level::Debug,
+            format_args!("{}: took {:.2?}", self.name, self.start.elapsed()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_from_flags() {
+        assert_eq!(Level::from_flags(false, 0), Level::Warn);
+        assert_eq!(Level::from_flags(false, 1), Level::Info);
+        assert_eq!(Level::from_flags(false, 2), Level::Debug);
+        assert_eq!(Level::from_flags(false, 9), Level::Trace);
+        assert_eq!(Level::from_flags(true, 2), Level::Error);
+    }
+}
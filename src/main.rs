@@ -1,185 +1,1162 @@
-use std::{
-    collections::HashMap,
-    env,
-    fmt::{Display, Write},
+use std::{env, error::Error, fs, path::Path, process};
+
+use bencode_decoder::{
+    builder::{DictBuilder, ListBuilder},
+    conformance::{Verdict, CASES},
+    decode_with,
+    display::{Escape, FormatOptions},
+    edit::Editor,
+    generate::{self, GenerateOptions, Rng, TorrentOptions, Weights},
+    json, list,
+    merge::Resolution,
+    merkle, metadata,
+    query::Query,
+    schema, sha1, sha256, signature, span, torrent, Bencode, Parser,
 };
+use config::{Config, Output};
+use failure::DecodeFailure;
 
-#[derive(PartialEq, Debug)]
-#[allow(dead_code)]
-enum Bencode {
-    String(String),
-    Integer(i64),
-    List(Vec<Bencode>),
-    Dictionary(HashMap<String, Bencode>),
-}
-
-impl Display for Bencode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Bencode::String(s) => f.write_str(format!(r#""{s}""#).as_str()),
-            Bencode::Integer(i) => f.write_str(format!("{i}").as_str()),
-            Bencode::List(l) => {
-                f.write_char('[')?;
-
-                for (i, bencode) in l.iter().enumerate() {
-                    f.write_str(format!("{bencode}").as_str())?;
-                    if i + 1 < l.len() {
-                        f.write_str(", ")?;
-                    }
+mod cli;
+mod config;
+mod dedupe;
+mod failure;
+mod graph;
+mod highlight;
+mod inflate;
+mod input;
+mod log;
+mod minimize;
+mod mutate;
+mod output;
+mod progress;
+mod report;
+mod rewrite;
+mod selftest;
+mod session;
+mod stats;
+mod template;
+mod verify;
+mod zstd;
+
+type CommandResult = Result<(), Box<dyn Error>>;
+
+fn decode(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let input = matches.args[0].as_bytes();
+    let (decoded_value, _) = Parser::with_options(config.decode_options())
+        .decode_prefix(input)
+        .map_err(|error| DecodeFailure::new(error, None, input))?;
+    let decoded = match config.output(matches)? {
+        Output::Text if matches.flag("ascii") => {
+            let options = FormatOptions {
+                escape: Escape::Ascii,
+                ..Default::default()
+            };
+            decoded_value.display(&options).to_string()
+        }
+        Output::Text => decoded_value.to_string(),
+        Output::Json => json::to_json(&decoded_value),
+    };
+    output::emit(matches, format!("{decoded}\n").as_bytes())?;
+    Ok(())
+}
+
+fn info(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let input = input::read(matches, &matches.args[0])?;
+    let value = parse(&input, &matches.args[0], config)?;
+    let line = |label: &str, value: &dyn std::fmt::Display| println!("{label:<14}{value}");
+    let text = |path| match value.get_path(path) {
+        Ok(Some(Bencode::String(s))) => Some(String::from_utf8_lossy(s).into_owned()),
+        _ => None,
+    };
+
+    let files = torrent::files(&value)?;
+    let infohash: String = {
+        let _span = log::span("hash");
+        torrent::infohash(&input)?
+    }
+    .iter()
+    .map(|b| format!("{b:02x}"))
+    .collect();
+
+    if let Some(format) = matches.value("format") {
+        let template = template::Template::parse(format, INFO_FIELDS)?;
+        let field = |name: &str| match name {
+            "name" => text("info.name"),
+            "infohash" => Some(infohash.clone()),
+            "size" => Some(
+                files
+                    .iter()
+                    .map(|file| file.length)
+                    .sum::<i64>()
+                    .to_string(),
+            ),
+            "files" => Some(files.len().to_string()),
+            "pieces" => match value.get_path("info.pieces") {
+                Ok(Some(Bencode::String(pieces))) => Some((pieces.len() / 20).to_string()),
+                _ => None,
+            },
+            "piece_length" => match value.get_path("info.piece length") {
+                Ok(Some(Bencode::Integer(length))) => Some(length.to_string()),
+                _ => None,
+            },
+            "created" => torrent::creation_date(&value)
+                .ok()?
+                .map(|date| date.to_string()),
+            "created_by" => text("created by"),
+            "comment" => text("comment"),
+            "private" => Some(
+                matches!(
+                    value.get_path("info.private"),
+                    Ok(Some(Bencode::Integer(1)))
+                )
+                .to_string(),
+            ),
+            "trackers" => Some(
+                torrent::trackers(&value)
+                    .iter()
+                    .map(|tracker| String::from_utf8_lossy(tracker))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            "magnet" => torrent::magnet_link(&input).ok(),
+            path => match value.get_path(path) {
+                Ok(Some(Bencode::String(s))) => Some(String::from_utf8_lossy(s).into_owned()),
+                Ok(Some(other)) => Some(other.to_string()),
+                _ => None,
+            },
+        };
+        println!("{}", template.render(field));
+        return Ok(());
+    }
+
+    line("name", &text("info.name").unwrap_or_default());
+    line("infohash", &infohash);
+    match torrent::creation_date(&value) {
+        Ok(Some(date)) => line("created", &date),
+        Ok(None) => {}
+        Err(_) => line("created", &"invalid date"),
+    }
+    if let Some(created_by) = text("created by") {
+        line("created by", &created_by);
+    }
+    if let Some(comment) = text("comment") {
+        line("comment", &comment);
+    }
+    if let Ok(Some(Bencode::Integer(length))) = value.get_path("info.piece length") {
+        line("piece length", length);
+    }
+    if let Ok(Some(Bencode::String(pieces))) = value.get_path("info.pieces") {
+        line("pieces", &(pieces.len() / 20));
+    }
+    line("files", &files.len());
+    line(
+        "total size",
+        &format!(
+            "{} bytes",
+            files.iter().map(|file| file.length).sum::<i64>()
+        ),
+    );
+    if let Ok(Some(Bencode::Integer(1))) = value.get_path("info.private") {
+        line("private", &"yes");
+    }
+    for tracker in torrent::trackers(&value) {
+        line("tracker", &String::from_utf8_lossy(tracker));
+    }
+    Ok(())
+}
+
+fn report(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let input = input::read(matches, &matches.args[0])?;
+    let value = parse(&input, &matches.args[0], config)?;
+    let report = report::Report::new(&input, &value)?;
+    let rendered = match matches.value("output") {
+        None | Some("html") => report.to_html(),
+        Some("markdown") => report.to_markdown(),
+        Some(other) => {
+            return Err(
+                format!("unknown report format {other:?}, expected html or markdown").into(),
+            )
+        }
+    };
+    output::emit(matches, rendered.as_bytes())?;
+    Ok(())
+}
+
+/// Placeholders for `info --format`, besides paths.
+const INFO_FIELDS: &[&str] = &[
+    "name",
+    "infohash",
+    "size",
+    "files",
+    "pieces",
+    "piece_length",
+    "created",
+    "created_by",
+    "comment",
+    "private",
+    "trackers",
+    "magnet",
+];
+
+fn infohash(matches: &cli::Matches) -> CommandResult {
+    let (encoding, newline) = match matches.value("format") {
+        None | Some("hex") => (torrent::HashEncoding::Hex, true),
+        Some("base32") => (torrent::HashEncoding::Base32, true),
+        Some("raw") => (torrent::HashEncoding::Raw, false),
+        Some(other) => {
+            return Err(format!("unknown format {other:?}, expected hex, base32 or raw").into())
+        }
+    };
+    let input = input::read(matches, &matches.args[0])?;
+    let hash = torrent::infohash(&input)
+        .map_err(|error| DecodeFailure::new(error, Some(&matches.args[0]), &input))?;
+
+    let mut encoded = torrent::encode_infohash(&hash, encoding);
+    if newline {
+        encoded.push(b'\n');
+    }
+    output::emit(matches, &encoded)?;
+    Ok(())
+}
+
+/// Hashes the raw bytes of a value, so that a section like `info.files`
+/// can be fingerprinted exactly as stored.
+fn hash(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let algo = matches.value("algo").unwrap_or("sha256");
+    let digest: fn(&[u8]) -> Vec<u8> = match algo {
+        "sha256" => |raw| sha256::sha256(raw).to_vec(),
+        "sha1" => |raw| sha1::sha1(raw).to_vec(),
+        _ => return Err(format!("unknown hash {algo:?}, expected sha256 or sha1").into()),
+    };
+    let file = &matches.args[0];
+    let input = input::read(matches, file)?;
+    let root = span::parse_spanned_within(&input, config.decode_options().max_depth)
+        .map_err(|error| DecodeFailure::new(error, Some(file), &input))?;
+
+    let path = matches.value("path").unwrap_or(".");
+    let raw = root
+        .get_raw(path)?
+        .ok_or_else(|| bencode_decoder::Error::NotFound(path.into()))?;
+    let hex: String = digest(raw).iter().map(|b| format!("{b:02x}")).collect();
+    println!("{hex}");
+    Ok(())
+}
+
+/// Prints the encoded bytes, colored with `--highlight` if the config's
+/// color setting allows it on stdout. Files never get color.
+fn dump(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let input = input::read(matches, &matches.args[0])?;
+    let color = matches.flag("highlight")
+        && matches.value("output-file").is_none()
+        && config.color.enabled_for_stdout();
+    let dumped = highlight::dump(&input, color)
+        .map_err(|error| DecodeFailure::new(error, Some(&matches.args[0]), &input))?;
+    output::emit(matches, format!("{dumped}\n").as_bytes())?;
+    Ok(())
+}
+
+fn graph(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let input = input::read(matches, &matches.args[0])?;
+    let value = parse(&input, &matches.args[0], config)?;
+    output::emit(matches, graph::to_dot(&value).as_bytes())?;
+    Ok(())
+}
+
+/// Prints value counts, or with `--size-breakdown` each large path's share
+/// of the file.
+fn stats(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let file = &matches.args[0];
+    let input = input::read(matches, file)?;
+    let root = span::parse_spanned_within(&input, config.decode_options().max_depth)
+        .map_err(|error| DecodeFailure::new(error, Some(file), &input))?;
+
+    if matches.flag("size-breakdown") {
+        let total = root.span().len();
+        let paths = stats::size_breakdown(&root);
+        match config.output(matches)? {
+            Output::Text => {
+                for (path, bytes) in &paths {
+                    let share = *bytes as f64 * 100.0 / total as f64;
+                    println!("{path}: {share:.1}% ({bytes} bytes)");
                 }
+            }
+            Output::Json => {
+                let list = paths
+                    .iter()
+                    .fold(ListBuilder::new(), |list, (path, bytes)| {
+                        list.push(
+                            DictBuilder::new()
+                                .insert("path", path.as_str())
+                                .insert("bytes", *bytes as i64),
+                        )
+                    });
+                let breakdown = DictBuilder::new()
+                    .insert("total", total as i64)
+                    .insert("paths", list)
+                    .build();
+                println!("{}", json::to_json(&breakdown));
+            }
+        }
+        return Ok(());
+    }
 
-                f.write_char(']')
+    let stats = stats::Stats::new(&root);
+    let counts = [
+        ("strings", stats.strings),
+        ("integers", stats.integers),
+        ("lists", stats.lists),
+        ("dictionaries", stats.dictionaries),
+        ("string_bytes", stats.string_bytes),
+        ("max_depth", stats.max_depth),
+        ("total_bytes", stats.total),
+    ];
+    match config.output(matches)? {
+        Output::Text => {
+            for (label, count) in counts {
+                println!("{label:<14}{count}");
             }
-            Bencode::Dictionary(d) => {
-                f.write_char('{')?;
+        }
+        Output::Json => {
+            let dict = counts
+                .iter()
+                .fold(DictBuilder::new(), |dict, (label, count)| {
+                    dict.insert(*label, *count as i64)
+                });
+            println!("{}", json::to_json(&dict.build()));
+        }
+    }
+    Ok(())
+}
 
-                for (i, (key, value)) in d.iter().enumerate() {
-                    f.write_str(format!(r#""{key}": {value}"#).as_str())?;
-                    if i + 1 < d.len() {
-                        f.write_str(", ")?;
-                    }
+/// Prints each file's size and path, and with `--hashes` its pieces root,
+/// or `-` for files without one (empty files and v1 torrents).
+fn list_files(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let input = input::read(matches, &matches.args[0])?;
+    let value = parse(&input, &matches.args[0], config)?;
+    let hashes = matches.flag("hashes");
+    let root = |file: &torrent::File| {
+        file.pieces_root
+            .map(|root| root.iter().map(|b| format!("{b:02x}")).collect::<String>())
+    };
+
+    let files = torrent::files(&value)?;
+    match config.output(matches)? {
+        Output::Text => {
+            for file in &files {
+                let path: Vec<_> = file
+                    .path
+                    .iter()
+                    .map(|c| String::from_utf8_lossy(c))
+                    .collect();
+                let path = path.join("/");
+                match hashes {
+                    true => println!(
+                        "{:<64}  {:>12}  {path}",
+                        root(file).as_deref().unwrap_or("-"),
+                        file.length
+                    ),
+                    false => println!("{:>12}  {path}", file.length),
                 }
+            }
+        }
+        Output::Json => {
+            let list = files.iter().fold(ListBuilder::new(), |list, file| {
+                let path = file
+                    .path
+                    .iter()
+                    .fold(ListBuilder::new(), |path, component| {
+                        path.push(component.as_slice())
+                    });
+                list.push(
+                    DictBuilder::new()
+                        .insert("path", path)
+                        .insert("length", file.length)
+                        .insert_some("pieces_root", root(file).filter(|_| hashes)),
+                )
+            });
+            println!("{}", json::to_json(&list.build()));
+        }
+    }
+    Ok(())
+}
+
+fn pieces(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let input = input::read(matches, &matches.args[0])?;
+    let value = parse(&input, &matches.args[0], config)?;
+    let hashes = torrent::pieces(&value)?;
+    let hex = |hash: &[u8; 20]| -> String { hash.iter().map(|b| format!("{b:02x}")).collect() };
 
-                f.write_char('}')
+    match config.output(matches)? {
+        Output::Text => {
+            for (index, hash) in hashes.iter().enumerate() {
+                println!("{index} {}", hex(hash));
             }
         }
+        Output::Json => {
+            let list = hashes
+                .iter()
+                .enumerate()
+                .fold(ListBuilder::new(), |list, (index, hash)| {
+                    list.push(
+                        DictBuilder::new()
+                            .insert("index", index as i64)
+                            .insert("hash", hex(hash)),
+                    )
+                });
+            println!("{}", json::to_json(&list.build()));
+        }
     }
+    Ok(())
 }
 
-#[allow(dead_code)]
-fn decode_bencoded_value(encoded_value: &str) -> (Bencode, &str) {
-    // If encoded_value starts with a digit, it's a number
-    match encoded_value.chars().next().unwrap() {
-        '0'..='9' => {
-            if let Some((len, rest)) = encoded_value.split_once(":") {
-                if let Ok(len) = len.parse::<usize>() {
-                    return (Bencode::String(rest[..len].to_string()), &rest[len..]);
+/// With one query, prints what it selects. With several, prints a JSON
+/// object with each query as a key: the value for queries that select at
+/// most one (left out if nothing is there), a list for the others.
+fn query(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let queries = match &matches.args[1..] {
+        [] => return Err("no query given".into()),
+        queries => queries
+            .iter()
+            .map(|query| Query::parse(query))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+    let input = input::read(matches, &matches.args[0])?;
+    let value = parse(&input, &matches.args[0], config)?;
+
+    if let [query] = queries.as_slice() {
+        let selected = query.select(&value)?;
+        match config.output(matches)? {
+            Output::Text => {
+                for found in &selected {
+                    println!("{} = {}", found.path, found.value);
                 }
             }
+            Output::Json => {
+                let list = selected.iter().fold(ListBuilder::new(), |list, found| {
+                    list.push(
+                        DictBuilder::new()
+                            .insert("path", found.path.as_str())
+                            .insert("value", found.value.clone().into_owned()),
+                    )
+                });
+                println!("{}", json::to_json(&list.build()));
+            }
+        }
+        return Ok(());
+    }
+
+    let mut object = DictBuilder::new();
+    for (source, query) in matches.args[1..].iter().zip(&queries) {
+        let mut selected = query.select(&value)?;
+        object = match query.is_single() {
+            true => object.insert_some(
+                source.as_str(),
+                selected.pop().map(|found| found.value.into_owned()),
+            ),
+            false => object.insert(
+                source.as_str(),
+                Bencode::List(
+                    selected
+                        .into_iter()
+                        .map(|found| found.value.into_owned())
+                        .collect(),
+                ),
+            ),
+        };
+    }
+    println!("{}", json::to_json(&object.build()));
+    Ok(())
+}
+
+fn validate(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let input = input::read(matches, &matches.args[0])?;
+    let value = parse(&input, &matches.args[0], config)?;
 
-            panic!("Error decoding Bencode string")
+    let mut violations = Vec::new();
+    if let Some(name) = matches.value("schema") {
+        let schema = schema::builtin(name).ok_or_else(|| {
+            format!(
+                "unknown schema {name:?}, expected one of: {}",
+                schema::BUILTIN_NAMES.join(", ")
+            )
+        })?;
+        violations = schema::validate_schema(&value, schema);
+    }
+    if let Some(key) = matches.value("key") {
+        let key = signature::PublicKey::from_pem(&fs::read_to_string(key)?)?;
+        let signatures = signature::verify(&input, &key)?;
+        if signatures.is_empty() {
+            violations.push(schema::Violation {
+                path: "signatures".into(),
+                message: "torrent is not signed".into(),
+            });
         }
-        'i' => {
-            let (number_string, rest) = encoded_value
-                .strip_prefix('i')
-                .unwrap()
-                .split_once('e')
-                .unwrap();
-            if number_string.chars().next().unwrap() == '0' && number_string.len() > 1 {
-                panic!("All encodings with a leading zero are invalid, other than i0e")
+        for (signer, valid) in signatures {
+            let signer = String::from_utf8_lossy(&signer);
+            match valid {
+                true => log::info!("valid signature by {signer}"),
+                false => violations.push(schema::Violation {
+                    path: format!("signatures.{signer}"),
+                    message: "signature doesn't match the key".into(),
+                }),
             }
+        }
+    }
+    if matches.flag("deep") {
+        let _span = log::span("check piece layers");
+        violations.extend(merkle::check_piece_layers(&value)?);
+    }
 
-            if number_string == "-0" {
-                panic!("i-0e is invalid")
-            }
+    if violations.is_empty() {
+        println!("ok");
+        return Ok(());
+    }
+    for violation in &violations {
+        println!("{violation}");
+    }
+    Err(format!("{} violation(s)", violations.len()).into())
+}
+
+fn infer_schema(matches: &cli::Matches, config: &Config) -> CommandResult {
+    if matches.args.is_empty() {
+        return Err("no files given".into());
+    }
+
+    let read = |path: &str| -> Result<_, Box<dyn Error>> {
+        let input = input::read(matches, path)?;
+        Ok(parse(&input, path, config)?)
+    };
 
-            let number = number_string.parse::<i64>().unwrap();
-            return (Bencode::Integer(number), rest);
+    let mut shape = schema::Shape::new();
+    for path in &matches.args {
+        log::info!("reading {path}");
+        match read(path) {
+            Ok(value) => shape.observe(&value),
+            Err(error) => log::warning!("skipping {path}: {error}"),
         }
-        'l' => {
-            let mut list_string = encoded_value.strip_prefix('l').unwrap();
+    }
+    print!("{shape}");
+    Ok(())
+}
 
-            let mut list = Vec::new();
+fn inspect_session(matches: &cli::Matches) -> CommandResult {
+    let client = matches.value("client").unwrap_or("qbittorrent");
+    let reports = session::inspect(client, matches.args[0].as_ref())?;
+    println!("{}", reports.join("\n").trim_end());
+    Ok(())
+}
 
-            loop {
-                let (decoded_value, rest) = decode_bencoded_value(list_string);
-                list.push(decoded_value);
-                if rest.chars().next().unwrap() == 'e' {
-                    return (Bencode::List(list), rest.strip_prefix('e').unwrap());
-                };
+fn dedupe(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let options = config.decode_options();
+    let mut torrents = Vec::new();
+    let paths = {
+        let _span = log::span("scan");
+        dedupe::find_torrents(matches.args[0].as_ref())?
+    };
+    log::info!("found {} torrent files", paths.len());
+    for path in paths {
+        let _span = log::span(format_args!("parse {}", path.display()));
+        let torrent = input::read(matches, &path)
+            .map_err(Into::into)
+            .and_then(|input| dedupe::Torrent::parse(&path, &input, &options));
+        match torrent {
+            Ok(torrent) => torrents.push(torrent),
+            Err(error) => log::warning!("skipping {}: {error}", path.display()),
+        }
+    }
 
-                list_string = rest
+    let groups = dedupe::group(&torrents);
+    match config.output(matches)? {
+        Output::Json => println!("{}", dedupe::json(&groups)),
+        Output::Text if groups.is_empty() => println!("no duplicates"),
+        Output::Text => print!("{}", dedupe::table(&groups)),
+    }
+    Ok(())
+}
+
+fn edit(matches: &cli::Matches) -> CommandResult {
+    let file = &matches.args[0];
+    let original = fs::read(file)?;
+    let mut editor =
+        Editor::new(&original).map_err(|error| DecodeFailure::new(error, Some(file), &original))?;
+
+    let changes = ["set", "delete", "sort", "dedupe", "sign"]
+        .iter()
+        .any(|flag| !matches.values(flag).is_empty());
+    if matches.flag("interactive") {
+        if changes {
+            return Err(
+                "--set, --delete, --sort, --dedupe and --sign can't be combined with --interactive"
+                    .into(),
+            );
+        }
+        let value = rewrite::edit_as_json(&editor.root().to_bencode())?;
+        let change = rewrite::Change {
+            after: bencode_decoder::encode(&value),
+            details: rewrite::infohash_note(&original, &value)
+                .into_iter()
+                .collect(),
+        };
+        println!(
+            "{}",
+            rewrite::apply(matches, file.as_ref(), &original, change)?
+        );
+        return Ok(());
+    }
+
+    if !changes {
+        return Err(
+            "nothing to change, give --set <path>=<value>, --delete <path>, \
+                    --sort <path>, --dedupe <path> or --interactive"
+                .into(),
+        );
+    }
+    let mut details = Vec::new();
+    for path in matches.values("delete") {
+        editor.remove_path(path)?;
+        details.push(format!("{path}: deleted"));
+    }
+    for set in matches.values("set") {
+        let (path, value) = set
+            .split_once('=')
+            .ok_or_else(|| format!("--set {set:?} is not <path>=<value>"))?;
+        let old = editor
+            .root()
+            .get_raw(path)?
+            .ok_or_else(|| bencode_decoder::Error::NotFound(path.into()))?;
+        editor.replace_path(path, &bencode_decoder::decode(value.as_bytes())?)?;
+        details.push(format!(
+            "{path}: {} -> {}",
+            rewrite::excerpt(old),
+            rewrite::excerpt(value.as_bytes())
+        ));
+    }
+
+    // A list given to both --sort and --dedupe is rewritten once.
+    let mut lists: Vec<&String> = matches.values("sort").iter().collect();
+    for path in matches.values("dedupe") {
+        if !lists.contains(&path) {
+            lists.push(path);
+        }
+    }
+    for path in lists {
+        let Bencode::List(mut list) = editor
+            .root()
+            .get_path(path)?
+            .ok_or_else(|| bencode_decoder::Error::NotFound(path.into()))?
+            .to_bencode()
+        else {
+            return Err(bencode_decoder::Error::InvalidType {
+                path: path.trim_start_matches('.').into(),
+                expected: "list",
             }
+            .into());
+        };
+        if matches.values("sort").contains(path) {
+            match matches.value("sort-by") {
+                Some(key) => list::sort_by_key(&mut list, key)?,
+                None => list::sort(&mut list),
+            }
+            details.push(format!("{path}: sorted"));
+        }
+        if matches.values("dedupe").contains(path) {
+            let removed = list::dedup(&mut list);
+            details.push(format!("{path}: {removed} duplicate(s) removed"));
         }
-        'd' => {
-            let mut dict_string = encoded_value.strip_prefix('d').unwrap();
+        editor.replace_path(path, &Bencode::List(list))?;
+    }
+
+    let mut after = editor.apply();
+    // Signed last, so that the signature covers the other changes.
+    if let Some(key) = matches.value("sign") {
+        let signer = matches
+            .value("signer")
+            .ok_or("--sign needs --signer <name>")?;
+        let key = signature::PrivateKey::from_pem(&fs::read_to_string(key)?)?;
+        let mut value = bencode_decoder::decode(&after)?;
+        signature::sign(&mut value, signer, &key)?;
+        after = bencode_decoder::encode(&value);
+        details.push(format!("signed as {signer}"));
+        details.extend(rewrite::infohash_note(&original, &value));
+    }
+
+    let change = rewrite::Change { after, details };
+    println!(
+        "{}",
+        rewrite::apply(matches, file.as_ref(), &original, change)?
+    );
+    Ok(())
+}
+
+/// Re-encodes each file canonically. Reordering keys inside `info` changes
+/// the infohash, so that is called out.
+fn normalize(matches: &cli::Matches, config: &Config) -> CommandResult {
+    if matches.args.is_empty() {
+        return Err("no files given".into());
+    }
+    if matches.value("output-file").is_some() && matches.args.len() > 1 {
+        return Err("--output-file takes a single file".into());
+    }
+
+    for file in &matches.args {
+        let original = fs::read(file)?;
+        let value = parse(&original, file, config)?;
+        let after = bencode_decoder::encode(&value);
+
+        let details = rewrite::infohash_note(&original, &value)
+            .into_iter()
+            .collect();
+        let change = rewrite::Change { after, details };
+        println!(
+            "{}",
+            rewrite::apply(matches, Path::new(file), &original, change)?
+        );
+    }
+    Ok(())
+}
+
+/// Merges every file into the first and writes the result. Lists with
+/// `--combine-lists` skip elements already present, so that merging tracker
+/// lists doesn't repeat trackers.
+fn merge(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let [first, rest @ ..] = matches.args.as_slice() else {
+        return Err("no files given".into());
+    };
+    if rest.is_empty() {
+        return Err("give at least two files to merge".into());
+    }
+
+    let mut merged = parse(&input::read(matches, first)?, first, config)?;
+    for file in rest {
+        let value = parse(&input::read(matches, file)?, file, config)?;
+        merged.merge(value, |path, existing, incoming| {
+            let resolution = match (existing, incoming) {
+                _ if matches.flag("keep") => Resolution::Keep,
+                (Bencode::List(existing), Bencode::List(incoming))
+                    if matches.flag("combine-lists") =>
+                {
+                    let mut combined = existing.clone();
+                    for value in incoming {
+                        if !combined.contains(value) {
+                            combined.push(value.clone());
+                        }
+                    }
+                    Resolution::Replace(Bencode::List(combined))
+                }
+                _ => Resolution::Overwrite,
+            };
+            let action = match resolution {
+                Resolution::Keep => "kept",
+                Resolution::Overwrite => "overwritten",
+                _ => "combined",
+            };
+            log::info!("{file}: {path} {action}");
+            resolution
+        });
+    }
+    output::emit(matches, &bencode_decoder::encode(&merged))?;
+    Ok(())
+}
+
+/// Runs every case in the conformance table through `--command`, or this
+/// crate's strict decoder, and lists those it gets wrong.
+fn conformance(matches: &cli::Matches) -> CommandResult {
+    let path = env::temp_dir().join(format!("bencode-conformance-{}", process::id()));
+    let accepts = |input: &[u8]| -> Result<bool, Box<dyn Error>> {
+        let Some(command) = matches.value("command") else {
+            let strict = bencode_decoder::DecodeOptions {
+                sorted_keys: true,
+                ..Default::default()
+            };
+            return Ok(decode_with(input, &strict).is_ok());
+        };
+        fs::write(&path, input)?;
+        let status = process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{command} \"$1\""))
+            .arg("sh")
+            .arg(&path)
+            .stdout(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .status()?;
+        Ok(status.success())
+    };
 
-            let mut dict = HashMap::new();
+    let mut failures = 0;
+    let result = CASES.iter().try_for_each(|case| {
+        let accepted = accepts(case.input)?;
+        if case.verdict.allows(accepted) {
+            log::info!("{}: ok", case.name);
+        } else {
+            failures += 1;
+            let expected = match case.verdict {
+                Verdict::Accept => "accept",
+                _ => "reject",
+            };
+            println!(
+                "{}: {} \"{}\", expected to {expected}",
+                case.name,
+                if accepted { "accepted" } else { "rejected" },
+                case.input.escape_ascii()
+            );
+        }
+        Ok::<_, Box<dyn Error>>(())
+    });
+    let _ = fs::remove_file(&path);
+    result?;
 
-            while let (Bencode::String(key), rest) = decode_bencoded_value(dict_string) {
-                let (value, rest) = decode_bencoded_value(rest);
-                dict.insert(key, value);
-                if rest.chars().next().unwrap() == 'e' {
-                    return (Bencode::Dictionary(dict), rest.strip_prefix('e').unwrap());
-                };
+    if failures == 0 {
+        println!("ok");
+        return Ok(());
+    }
+    Err(format!("{failures} of {} cases don't conform", CASES.len()).into())
+}
 
-                dict_string = rest
+/// Runs the built-in checks, printing each one's result.
+fn selftest() -> CommandResult {
+    let results = selftest::run();
+    let mut failed = 0;
+    for (name, result) in &results {
+        match result {
+            Ok(()) => println!("pass  {name}"),
+            Err(error) => {
+                failed += 1;
+                println!("FAIL  {name}: {error}");
             }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{failed} of {} checks failed", results.len()).into()),
+    }
+}
+
+/// Writes fake metainfo with `--files` files adding up to `--size`.
+fn generate_torrent(matches: &cli::Matches) -> CommandResult {
+    let total_size = match matches.value("size") {
+        None => 1_000_000_000,
+        Some(size) => progress::parse_bytes(size)
+            .ok_or_else(|| format!("invalid --size {size:?}, expected e.g. 10GB or 512MiB"))?,
+    };
+    let options = TorrentOptions {
+        files: number(matches, "files")?.unwrap_or(1),
+        total_size,
+    };
+    let value = generate::torrent(&mut Rng::new(seed(matches)?), &options);
+    output::emit(matches, &bencode_decoder::encode(&value))?;
+    Ok(())
+}
+
+/// Breaks the document in `--count` random ways and reports each result the
+/// decoder panics on or makes something impossible of.
+fn mutate(matches: &cli::Matches) -> CommandResult {
+    let file = &matches.args[0];
+    let input = input::read(matches, file)?;
+    bencode_decoder::decode(&input)
+        .map_err(|error| DecodeFailure::new(error, Some(file), &input))?;
+    let count = number(matches, "count")?.unwrap_or(10_000);
+    let mut rng = Rng::new(seed(matches)?);
+
+    // Panics are reported with the input that caused them instead
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let mut anomalies = 0;
+    for _ in 0..count {
+        let mutated = generate::mutate(&mut rng, &input);
+        if let Some(anomaly) = mutate::check(&mutated) {
+            anomalies += 1;
+            println!("{anomaly}: \"{}\"", mutated.escape_ascii());
+        }
+    }
+    std::panic::set_hook(hook);
+
+    if anomalies == 0 {
+        println!("ok, {count} mutations");
+        return Ok(());
+    }
+    Err(format!("{anomalies} anomalies in {count} mutations").into())
+}
+
+/// Writes a random document, or with `--near-valid` one a few edits away
+/// from valid.
+fn generate(matches: &cli::Matches) -> CommandResult {
+    let mut options = GenerateOptions::default();
+    if let Some(depth) = number(matches, "depth")? {
+        options.max_depth = depth;
+    }
+    if let Some(length) = number(matches, "length")? {
+        options.max_len = length;
+    }
+    if let Some(length) = number(matches, "string-length")? {
+        options.max_string_len = length;
+    }
+    if let Some(weights) = matches.value("weights") {
+        let parsed: Vec<u32> = weights
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid --weights {weights:?}, expected four numbers"))?;
+        let [string, integer, list, dictionary] = parsed[..] else {
+            return Err(format!("invalid --weights {weights:?}, expected four numbers").into());
+        };
+        options.weights = Weights {
+            string,
+            integer,
+            list,
+            dictionary,
+        };
+    }
+
+    let mut rng = Rng::new(seed(matches)?);
+    let mut encoded = bencode_decoder::encode(&generate::generate(&mut rng, &options));
+    if matches.flag("near-valid") {
+        encoded = generate::mutate(&mut rng, &encoded);
+    }
+    output::emit(matches, &encoded)?;
+    Ok(())
+}
+
+/// Shrinks the input for as long as the command, run through the shell with
+/// a candidate's path as its last argument, exits with a failure.
+fn minimize(matches: &cli::Matches) -> CommandResult {
+    let input = input::read(matches, &matches.args[0])?;
+    let command = &matches.args[1];
+    let path = env::temp_dir().join(format!("bencode-minimize-{}", process::id()));
+
+    let mut runs = 0;
+    let mut fails = |candidate: &[u8]| {
+        runs += 1;
+        fs::write(&path, candidate)?;
+        let status = process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{command} \"$1\""))
+            .arg("sh")
+            .arg(&path)
+            .stdout(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .status()?;
+        Ok(!status.success())
+    };
+    let minimized = minimize::minimize(&input, &mut fails);
+    let _ = fs::remove_file(&path);
+    let minimized = minimized?;
+
+    log::info!(
+        "{} -> {} bytes in {runs} runs",
+        input.len(),
+        minimized.len()
+    );
+    output::emit(matches, &minimized)?;
+    Ok(())
+}
+
+/// The value of a numeric flag, if it was given.
+fn number<T: std::str::FromStr>(matches: &cli::Matches, flag: &str) -> Result<Option<T>, String> {
+    matches
+        .value(flag)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("invalid --{flag} {value:?}, expected a number"))
+        })
+        .transpose()
+}
+
+/// `--seed`, or one picked from the clock; logged, so a run can be repeated.
+fn seed(matches: &cli::Matches) -> Result<u64, Box<dyn Error>> {
+    let seed = match number(matches, "seed")? {
+        Some(seed) => seed,
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_nanos() as u64,
+    };
+    log::info!("seed {seed}");
+    Ok(seed)
+}
+
+/// Decodes the contents of `file` with the configured limits.
+fn parse(input: &[u8], file: &str, config: &Config) -> Result<Bencode, DecodeFailure> {
+    let _span = log::span(format_args!("decode {} bytes", input.len()));
+    decode_with(input, &config.decode_options())
+        .map_err(|error| DecodeFailure::new(error, Some(file), input))
+}
+
+/// Whether `--errors json` was given, looked up in the raw arguments so that
+/// it also applies to errors in the arguments themselves.
+fn json_errors(args: &[String]) -> bool {
+    let mut json = false;
+    for (i, arg) in args.iter().enumerate() {
+        match arg.strip_prefix("--errors") {
+            Some("") => json = args.get(i + 1).is_some_and(|format| format == "json"),
+            Some(value) if value.starts_with('=') => json = value == "=json",
+            _ => {}
+        }
+    }
+    json
+}
+
+fn verify(matches: &cli::Matches, config: &Config) -> CommandResult {
+    let input = input::read(matches, &matches.args[0])?;
+    let value = parse(&input, &matches.args[0], config)?;
+    let report = verify::verify(&value, matches.args[1].as_ref())?;
+
+    for path in &report.missing {
+        log::warning!("missing {}", path.display());
+    }
+    for piece in &report.bad {
+        println!("piece {piece} failed");
+    }
+    let good = report.pieces - report.bad.len();
+    println!("{good} of {} pieces ok", report.pieces);
+    match report.bad.is_empty() {
+        true => Ok(()),
+        false => Err(format!("{} piece(s) failed", report.bad.len()).into()),
+    }
+}
+
+/// Asks each peer in turn for the info dictionary and writes the first one
+/// that matches the infohash as a torrent holding just `info`. Trackers in
+/// a magnet link aren't carried over.
+fn metadata_fetch(matches: &cli::Matches) -> CommandResult {
+    let infohash = infohash_arg(&matches.args[0])?;
+    let peers = &matches.args[1..];
+    if peers.is_empty() {
+        return Err("expected at least one peer, as host:port".into());
+    }
+    let timeout = std::time::Duration::from_secs(number(matches, "timeout")?.unwrap_or(10));
 
-            return (Bencode::Dictionary(dict), "");
+    let mut rng = Rng::new(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_nanos() as u64,
+    );
+    let mut peer_id = *b"-BD0100-000000000000";
+    for byte in &mut peer_id[8..] {
+        *byte = b'0' + rng.below(10) as u8;
+    }
+
+    for peer in peers {
+        let addrs = match std::net::ToSocketAddrs::to_socket_addrs(peer.as_str()) {
+            Ok(addrs) => addrs,
+            Err(error) => {
+                log::warning!("{peer}: {error}");
+                continue;
+            }
+        };
+        for addr in addrs {
+            match metadata::fetch(addr, &infohash, &peer_id, timeout) {
+                Ok(info) => {
+                    log::info!("{} bytes of metadata from {addr}", info.len());
+                    output::emit(matches, &[&b"d4:info"[..], &info, b"e"].concat())?;
+                    return Ok(());
+                }
+                Err(error) => log::warning!("{addr}: {error}"),
+            }
         }
-        _ => panic!("Unhandled encoded value: {}", encoded_value),
     }
+    Err("no peer sent the metadata".into())
+}
+
+/// An infohash given as 40 hex digits, 32 base32 characters or in a
+/// `magnet:` link.
+fn infohash_arg(arg: &str) -> Result<[u8; 20], String> {
+    let encoded = match arg.split_once("xt=urn:btih:") {
+        Some((_, rest)) => rest.split('&').next().unwrap_or_default(),
+        None => arg,
+    };
+    torrent::decode_infohash(encoded).ok_or_else(|| {
+        format!("invalid infohash {encoded:?}, expected 40 hex digits or 32 base32 characters")
+    })
+}
+
+fn completions(matches: &cli::Matches) -> CommandResult {
+    print!("{}", cli::completions::generate(&matches.args[0]));
+    Ok(())
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let command = &args[1];
+    let Some(command) = args.get(1) else {
+        eprint!("{}", cli::overview());
+        process::exit(2);
+    };
 
-    if command == "decode" {
-        let encoded_value = &args[2];
-        let decoded_value = decode_bencoded_value(encoded_value);
-        println!("{}", decoded_value.0.to_string());
-    } else {
-        println!("unknown command: {}", args[1])
+    if command == "help" || command == "--help" || command == "-h" {
+        print!("{}", cli::overview());
+        return;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    let Some(command) = cli::find(command) else {
+        println!("unknown command: {}", args[1]);
+        process::exit(1);
+    };
 
-    #[test]
-    fn decode_bencode_sting() {
-        assert_eq!(
-            decode_bencoded_value("3:Hey"),
-            (Bencode::String("Hey".to_string()), "")
-        );
-        assert_eq!(
-            decode_bencoded_value("4:Test"),
-            (Bencode::String("Test".to_string()), "")
-        )
-    }
-
-    #[test]
-    fn decode_bencode_integer() {
-        assert_eq!(decode_bencoded_value("i30e"), (Bencode::Integer(30), ""));
-        assert_eq!(decode_bencoded_value("i-42e"), (Bencode::Integer(-42), ""));
-    }
-
-    #[test]
-    fn decode_bencode_list() {
-        assert_eq!(
-            decode_bencoded_value("l4:spam4:eggse"),
-            (
-                Bencode::List(vec![
-                    Bencode::String("spam".to_string()),
-                    Bencode::String("eggs".to_string())
-                ]),
-                ""
-            )
-        );
-        assert_eq!(
-            decode_bencoded_value("l5:helloi52ee"),
-            (
-                Bencode::List(vec![
-                    Bencode::String("hello".to_string()),
-                    Bencode::Integer(52)
-                ]),
-                ""
-            )
-        )
+    // Parsed before loading the config so that `-q` silences its warnings,
+    // but reported after so that the error is colored as configured.
+    let matches =
+        cli::parse(command, &args[2..]).and_then(|matches| match matches.value("errors") {
+            None | Some("text" | "json") => Ok(matches),
+            Some(_) => Err("--errors must be one of: text, json".into()),
+        });
+    if let Ok(matches) = &matches {
+        log::init(matches);
+    }
+
+    let json = json_errors(&args[2..]);
+    let fail = |label: &str, code: &str, error: &(dyn Error + 'static), status| -> ! {
+        match json {
+            true => eprintln!("{}", failure::to_json(code, error)),
+            false => eprintln!("{label}: {error}"),
+        }
+        process::exit(status);
+    };
+
+    let config =
+        Config::load().unwrap_or_else(|error| fail("error", "config", &failure::Message(error), 2));
+    let label = match config.color.enabled_for_stderr() {
+        true => "\x1b[1;31merror\x1b[0m",
+        false => "error",
+    };
+
+    let matches = matches.unwrap_or_else(|error| fail(label, "usage", &failure::Message(error), 2));
+
+    if matches.flag("help") {
+        print!("{}", cli::help(command));
+        return;
     }
 
-    #[test]
-    fn decode_bencode_dictionary() {
-        let mut test = HashMap::new();
-        test.insert("foo".to_string(), Bencode::String("bar".to_string()));
-        test.insert("hello".to_string(), Bencode::Integer(52));
+    let result = match command.name {
+        "decode" => decode(&matches, &config),
+        "info" => info(&matches, &config),
+        "report" => report(&matches, &config),
+        "infohash" => infohash(&matches),
+        "hash" => hash(&matches, &config),
+        "dump" => dump(&matches, &config),
+        "graph" => graph(&matches, &config),
+        "stats" => stats(&matches, &config),
+        "files" => list_files(&matches, &config),
+        "pieces" => pieces(&matches, &config),
+        "query" => query(&matches, &config),
+        "validate" => validate(&matches, &config),
+        "schema" => infer_schema(&matches, &config),
+        "inspect-session" => inspect_session(&matches),
+        "dedupe" => dedupe(&matches, &config),
+        "verify" => verify(&matches, &config),
+        "metadata-fetch" => metadata_fetch(&matches),
+        "edit" => edit(&matches),
+        "normalize" => normalize(&matches, &config),
+        "merge" => merge(&matches, &config),
+        "conformance" => conformance(&matches),
+        "generate" => generate(&matches),
+        "generate-torrent" => generate_torrent(&matches),
+        "minimize" => minimize(&matches),
+        "mutate" => mutate(&matches),
+        "selftest" => selftest(),
+        "completions" => completions(&matches),
+        _ => unreachable!("every command in the table is dispatched"),
+    };
 
-        assert_eq!(
-            decode_bencoded_value("d3:foo3:bar5:helloi52ee"),
-            (Bencode::Dictionary(test), "")
-        )
+    if let Err(error) = result {
+        fail(label, "error", error.as_ref(), 1);
     }
 }
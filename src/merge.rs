@@ -0,0 +1,108 @@
+//! Deep merging of values. Dictionaries on both sides are merged key by key;
+//! every other clash is settled by a strategy that sees the path and both
+//! values.
+
+use alloc::{format, string::String};
+
+use crate::Bencode;
+
+/// How to settle one clash between the value being merged into and the
+/// incoming one.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Resolution {
+    /// Take the incoming value.
+    Overwrite,
+    /// Keep the existing value.
+    Keep,
+    /// Append the incoming list to the existing one; other values are
+    /// overwritten.
+    CombineLists,
+    /// Use this value instead of either.
+    Replace(Bencode),
+}
+
+impl Bencode {
+    /// Merges `other` into `self`. `strategy` is called with the path of
+    /// each clash (like `.info.name`), the existing value and the incoming
+    /// one; keys only in `other` are added as they are.
+    pub fn merge<F>(&mut self, other: Bencode, mut strategy: F)
+    where
+        F: FnMut(&str, &Bencode, &Bencode) -> Resolution,
+    {
+        merge_at(&mut String::new(), self, other, &mut strategy);
+    }
+}
+
+fn merge_at<F>(path: &mut String, target: &mut Bencode, other: Bencode, strategy: &mut F)
+where
+    F: FnMut(&str, &Bencode, &Bencode) -> Resolution,
+{
+    match (&mut *target, other) {
+        (Bencode::Dictionary(existing), Bencode::Dictionary(incoming)) => {
+            for (key, value) in incoming {
+                match existing.get_mut(&key) {
+                    Some(current) => {
+                        let len = path.len();
+                        path.push_str(&format!(".{}", String::from_utf8_lossy(&key)));
+                        merge_at(path, current, value, strategy);
+                        path.truncate(len);
+                    }
+                    None => {
+                        existing.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, other) => {
+            let at = if path.is_empty() { "." } else { path.as_str() };
+            match strategy(at, target, &other) {
+                Resolution::Overwrite => *target = other,
+                Resolution::Keep => {}
+                Resolution::CombineLists => match (&mut *target, other) {
+                    (Bencode::List(existing), Bencode::List(incoming)) => existing.extend(incoming),
+                    (target, other) => *target = other,
+                },
+                Resolution::Replace(value) => *target = value,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode, encode};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn deep_merge() {
+        let mut value = decode(b"d1:ali1ee4:infod4:name1:xe1:si1ee").unwrap();
+        let other = decode(b"d1:ali2ee4:infod6:lengthi5e4:name1:ye1:si2ee").unwrap();
+
+        let mut clashes = Vec::new();
+        value.merge(other, |path, _, _| {
+            clashes.push(String::from(path));
+            match path {
+                ".a" => Resolution::CombineLists,
+                ".info.name" => Resolution::Keep,
+                _ => Resolution::Replace(Bencode::Integer(9)),
+            }
+        });
+
+        assert_eq!(clashes, [".a", ".info.name", ".s"]);
+        assert_eq!(
+            encode(&value),
+            b"d1:ali1ei2ee4:infod6:lengthi5e4:name1:xe1:si9ee"
+        );
+    }
+
+    #[test]
+    fn root_clash() {
+        let mut value = Bencode::Integer(1);
+        value.merge(Bencode::from("x"), |path, _, _| {
+            assert_eq!(path, ".");
+            Resolution::CombineLists
+        });
+        assert_eq!(value, Bencode::from("x"));
+    }
+}
@@ -0,0 +1,340 @@
+//! The SHA-256 merkle trees of v2 torrents (BEP 52). Each file is split
+//! into 16 KiB blocks whose hashes are the leaves of a tree with the file's
+//! `pieces root` at the top; `piece layers` holds, for files longer than a
+//! piece, the layer of that tree with one hash per piece. Proofs let a
+//! downloader check single blocks against the root.
+
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{hex, schema::Violation, sha256::sha256, torrent, Bencode, CancelToken, Error};
+
+/// The size of the blocks at the bottom of the tree.
+pub const BLOCK_SIZE: i64 = 16 * 1024;
+
+/// The root of a tree over `leaves`, padded up to a power of two with `pad`,
+/// the hash of a subtree that lies wholly past the end of the file.
+pub fn root(leaves: &[[u8; 32]], pad: [u8; 32]) -> [u8; 32] {
+    let mut layer = padded(leaves, pad);
+    while layer.len() > 1 {
+        layer = parents(&layer);
+    }
+    layer[0]
+}
+
+/// The hashes of the 16 KiB blocks of `data`, the leaves of a file's tree.
+/// Use a zero leaf as the padding.
+pub fn block_hashes(data: &[u8]) -> Vec<[u8; 32]> {
+    data.chunks(BLOCK_SIZE as usize).map(sha256).collect()
+}
+
+/// Like [`block_hashes`], checking `cancel` between blocks, for files large
+/// enough that hashing them takes a while.
+pub fn block_hashes_cancellable(data: &[u8], cancel: &CancelToken) -> Result<Vec<[u8; 32]>, Error> {
+    data.chunks(BLOCK_SIZE as usize)
+        .map(|block| cancel.check().map(|()| sha256(block)))
+        .collect()
+}
+
+/// The proof that leaf `index` belongs to the tree over `leaves`: its
+/// sibling, then the sibling of each parent up to the root, as BEP 52 hash
+/// messages carry them. `None` if `index` is out of range.
+pub fn proof(leaves: &[[u8; 32]], pad: [u8; 32], index: usize) -> Option<Vec<[u8; 32]>> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut layer = padded(leaves, pad);
+    let mut index = index;
+    let mut uncles = Vec::new();
+    while layer.len() > 1 {
+        uncles.push(layer[index ^ 1]);
+        layer = parents(&layer);
+        index /= 2;
+    }
+    Some(uncles)
+}
+
+/// Checks a [`proof`] that `leaf` is leaf `index` of the tree with `root`.
+pub fn verify_proof(leaf: [u8; 32], index: usize, proof: &[[u8; 32]], root: [u8; 32]) -> bool {
+    if proof.len() < usize::BITS as usize && index >> proof.len() != 0 {
+        return false;
+    }
+    let mut hash = leaf;
+    for (level, uncle) in proof.iter().enumerate() {
+        hash = match (index >> level) & 1 {
+            0 => sha256(&[hash, *uncle].concat()),
+            _ => sha256(&[*uncle, hash].concat()),
+        };
+    }
+    hash == root
+}
+
+fn padded(leaves: &[[u8; 32]], pad: [u8; 32]) -> Vec<[u8; 32]> {
+    let mut layer = leaves.to_vec();
+    layer.resize(leaves.len().max(1).next_power_of_two(), pad);
+    layer
+}
+
+fn parents(layer: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    layer
+        .chunks_exact(2)
+        .map(|pair| sha256(&[pair[0], pair[1]].concat()))
+        .collect()
+}
+
+/// The hash of a piece past the end of a file: a subtree of zero leaves,
+/// one for each block in the piece.
+pub fn pad_piece(piece_length: i64) -> [u8; 32] {
+    let mut hash = [0; 32];
+    let mut blocks = BLOCK_SIZE;
+    while blocks < piece_length {
+        hash = sha256(&[hash, hash].concat());
+        blocks *= 2;
+    }
+    hash
+}
+
+/// Checks that the `piece layers` of a decoded v2 or hybrid torrent hash up
+/// to the `pieces root` of their files, and that every file longer than a
+/// piece has one. Torrents without a `file tree` have nothing to check.
+pub fn check_piece_layers(value: &Bencode) -> Result<Vec<Violation>, Error> {
+    let mut violations = Vec::new();
+    let mut violation = |path: &str, message: String| {
+        violations.push(Violation {
+            path: path.into(),
+            message,
+        })
+    };
+
+    if value.get_path("info.file tree")?.is_none() {
+        return Ok(violations);
+    }
+    let piece_length = match value.get_path("info.piece length")? {
+        Some(&Bencode::Integer(length))
+            if length >= BLOCK_SIZE && (length as u64).is_power_of_two() =>
+        {
+            length
+        }
+        _ => {
+            violation(
+                "info.piece length",
+                "must be a power of two of at least 16 KiB".into(),
+            );
+            return Ok(violations);
+        }
+    };
+    let layers = match value.get_path("piece layers")? {
+        Some(Bencode::Dictionary(layers)) => Some(layers),
+        Some(_) => {
+            violation("piece layers", "must be a dictionary".into());
+            return Ok(violations);
+        }
+        None => None,
+    };
+
+    let pad = pad_piece(piece_length);
+    let files = torrent::files(value)?;
+    for file in &files {
+        let Some(pieces_root) = file.pieces_root else {
+            continue;
+        };
+        if file.length <= piece_length {
+            continue;
+        }
+        let name: Vec<_> = file
+            .path
+            .iter()
+            .map(|c| String::from_utf8_lossy(c))
+            .collect();
+        let name = name.join("/");
+        let path = format!("piece layers.{}", hex::encode(&pieces_root));
+
+        let Some(layer) = layers.and_then(|layers| layers.get(&pieces_root[..])) else {
+            violation("piece layers", format!("no layer for {name}"));
+            continue;
+        };
+        let Bencode::String(layer) = layer else {
+            violation(&path, format!("layer for {name} must be a string"));
+            continue;
+        };
+        let (hashes, rest) = layer.as_chunks::<32>();
+        let expected = (file.length as u64).div_ceil(piece_length as u64);
+        if !rest.is_empty() || hashes.len() as u64 != expected {
+            violation(
+                &path,
+                format!(
+                    "layer for {name} has {} bytes, expected {} hashes of 32 bytes",
+                    layer.len(),
+                    expected
+                ),
+            );
+            continue;
+        }
+        let actual = root(hashes, pad);
+        if actual != pieces_root {
+            violation(
+                &path,
+                format!(
+                    "layer for {name} hashes to {}, not its pieces root",
+                    hex::encode(&actual)
+                ),
+            );
+        }
+    }
+
+    for root in layers.into_iter().flat_map(|layers| layers.keys()) {
+        if !files
+            .iter()
+            .any(|file| file.pieces_root.as_ref().map(|r| &r[..]) == Some(root))
+        {
+            violation(
+                "piece layers",
+                format!("layer for unknown pieces root {}", hex::encode(root)),
+            );
+        }
+    }
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::DictBuilder;
+
+    fn torrent(length: i64, pieces_root: [u8; 32], layer: &[u8]) -> Bencode {
+        DictBuilder::new()
+            .insert(
+                "info",
+                DictBuilder::new()
+                    .insert("name", "a")
+                    .insert("piece length", 2 * BLOCK_SIZE)
+                    .insert(
+                        "file tree",
+                        DictBuilder::new().insert(
+                            "a",
+                            DictBuilder::new().insert(
+                                "",
+                                DictBuilder::new()
+                                    .insert("length", length)
+                                    .insert("pieces root", pieces_root.to_vec()),
+                            ),
+                        ),
+                    ),
+            )
+            .insert(
+                "piece layers",
+                DictBuilder::new().insert(pieces_root.to_vec(), layer),
+            )
+            .build()
+    }
+
+    #[test]
+    fn tree_roots() {
+        let leaf = |byte| [byte; 32];
+        let pair = |a: [u8; 32], b: [u8; 32]| sha256(&[a, b].concat());
+        assert_eq!(root(&[leaf(1)], leaf(0)), leaf(1));
+        assert_eq!(
+            root(&[leaf(1), leaf(2), leaf(3)], leaf(0)),
+            pair(pair(leaf(1), leaf(2)), pair(leaf(3), leaf(0)))
+        );
+        assert_eq!(pad_piece(BLOCK_SIZE), [0; 32]);
+        assert_eq!(
+            pad_piece(4 * BLOCK_SIZE),
+            pair(pair([0; 32], [0; 32]), pair([0; 32], [0; 32]))
+        );
+    }
+
+    #[test]
+    fn proofs() {
+        let data: Vec<u8> = (0..5 * BLOCK_SIZE).map(|i| (i / 1000) as u8).collect();
+        let leaves = block_hashes(&data);
+        assert_eq!(leaves.len(), 5);
+        let cancel = CancelToken::new();
+        assert_eq!(block_hashes_cancellable(&data, &cancel), Ok(leaves.clone()));
+        cancel.cancel();
+        assert_eq!(
+            block_hashes_cancellable(&data, &cancel),
+            Err(Error::Cancelled)
+        );
+        let file_root = root(&leaves, [0; 32]);
+
+        for (index, &leaf) in leaves.iter().enumerate() {
+            let proof = proof(&leaves, [0; 32], index).unwrap();
+            assert_eq!(proof.len(), 3);
+            assert!(verify_proof(leaf, index, &proof, file_root));
+            assert!(!verify_proof(leaf, index ^ 1, &proof, file_root));
+            assert!(!verify_proof(leaf, index + 8, &proof, file_root));
+            assert!(!verify_proof([0; 32], index, &proof, file_root));
+        }
+        assert_eq!(proof(&leaves, [0; 32], 5), None);
+
+        let single = proof(&leaves[..1], [0; 32], 0).unwrap();
+        assert!(single.is_empty());
+        assert!(verify_proof(leaves[0], 0, &single, leaves[0]));
+    }
+
+    #[test]
+    fn piece_layers() {
+        // Three pieces of 32 KiB, the last one short.
+        let hashes = [[1; 32], [2; 32], [3; 32]];
+        let good = root(&hashes, pad_piece(2 * BLOCK_SIZE));
+        let layer = hashes.concat();
+        let length = 5 * BLOCK_SIZE;
+
+        assert_eq!(
+            check_piece_layers(&torrent(length, good, &layer)),
+            Ok(alloc::vec![])
+        );
+
+        let messages = |value: &Bencode| -> Vec<String> {
+            check_piece_layers(value)
+                .unwrap()
+                .iter()
+                .map(|violation| format!("{violation}"))
+                .collect()
+        };
+        let path = format!("piece layers.{}", hex::encode(&[9; 32]));
+        assert_eq!(
+            messages(&torrent(length, [9; 32], &layer)),
+            [format!(
+                "{path}: layer for a hashes to {}, not its pieces root",
+                hex::encode(&good)
+            )]
+        );
+        assert_eq!(
+            messages(&torrent(length, [9; 32], &layer[..64])),
+            [format!(
+                "{path}: layer for a has 64 bytes, expected 3 hashes of 32 bytes"
+            )]
+        );
+
+        // Far more pieces than any layer holds, without overflowing.
+        assert_eq!(
+            messages(&torrent(i64::MAX, good, &layer)),
+            [format!(
+                "piece layers.{}: layer for a has 96 bytes, expected {} hashes of 32 bytes",
+                hex::encode(&good),
+                1u64 << 48
+            )]
+        );
+
+        let mut missing = torrent(length, good, &layer);
+        missing
+            .set_path("piece layers", Bencode::Dictionary(Default::default()))
+            .unwrap();
+        assert_eq!(messages(&missing), ["piece layers: no layer for a"]);
+
+        // A file of one piece needs no layer; one for no file is reported.
+        let mut stray = torrent(BLOCK_SIZE, good, &layer);
+        assert_eq!(messages(&stray), Vec::<String>::new());
+        if let Ok(Some(Bencode::Dictionary(layers))) = stray.get_path_mut("piece layers") {
+            layers.insert(alloc::vec![7; 32], Bencode::from(""));
+        }
+        assert_eq!(
+            messages(&stray),
+            [format!(
+                "piece layers: layer for unknown pieces root {}",
+                hex::encode(&[7; 32])
+            )]
+        );
+    }
+}
@@ -0,0 +1,569 @@
+//! Downloading a torrent's info dictionary from peers (BEP 9), which is how
+//! a magnet link becomes a torrent. After the [handshake](crate::peer) both
+//! sides send an extended handshake (BEP 10) naming the extensions they
+//! support; the info dictionary is then requested in 16 KiB pieces with
+//! `ut_metadata` messages and checked against the infohash once complete.
+//!
+//! The messages and [`Assembler`] work on bytes, for clients with their own
+//! networking; [`fetch`], with the `net` feature, drives them over TCP.
+
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    builder::DictBuilder,
+    convert::derive::{dictionary, field, optional_field},
+    decode, decode_bencoded_value, encode,
+    sha1::sha1,
+    Error,
+};
+
+/// Size of every piece of the info dictionary but the last.
+pub const PIECE_LEN: usize = 16 * 1024;
+
+/// Largest info dictionary accepted, as peers choose the size they announce.
+pub const MAX_SIZE: usize = 64 * 1024 * 1024;
+
+/// Peer wire message ID of extension messages.
+pub const EXTENDED: u8 = 20;
+
+/// The extended message ID this side assigns to `ut_metadata`, which peers
+/// send their `ut_metadata` messages with.
+pub const UT_METADATA: u8 = 1;
+
+/// Messages [`fetch`] accepts that don't help get the metadata, such as
+/// keep-alives and `have`s, before giving up on the peer.
+#[cfg(feature = "net")]
+pub const MAX_IGNORED: usize = 10_000;
+
+/// A peer wire message: its length, ID and payload.
+pub fn message(id: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(5 + payload.len());
+    out.extend_from_slice(&(payload.len() as u32 + 1).to_be_bytes());
+    out.push(id);
+    out.extend_from_slice(payload);
+    out
+}
+
+/// The extended handshake announcing `ut_metadata` as [`UT_METADATA`],
+/// ready to send.
+pub fn extended_handshake() -> Vec<u8> {
+    let handshake = DictBuilder::new()
+        .insert(
+            "m",
+            DictBuilder::new().insert("ut_metadata", UT_METADATA as i64),
+        )
+        .build();
+    extended(0, &encode(&handshake))
+}
+
+fn extended(id: u8, payload: &[u8]) -> Vec<u8> {
+    message(EXTENDED, &[&[id], payload].concat())
+}
+
+/// What a peer's extended handshake says about `ut_metadata`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct ExtendedHandshake {
+    /// The ID the peer wants its `ut_metadata` messages sent with, or
+    /// `None` if it doesn't support the extension.
+    pub ut_metadata: Option<u8>,
+    /// Size of the info dictionary, if the peer has it.
+    pub metadata_size: Option<usize>,
+}
+
+impl ExtendedHandshake {
+    /// Reads the payload of an extended handshake, after the extended
+    /// message ID of 0.
+    pub fn parse(payload: &[u8]) -> Result<Self, Error> {
+        let (value, _) = decode_bencoded_value(payload)?;
+        let d = dictionary(&value)?;
+        let ut_metadata = match d.get(b"m".as_slice()) {
+            Some(m) => {
+                optional_field::<u8>(dictionary(m).map_err(|e| e.at_key("m"))?, "ut_metadata")
+                    .map_err(|error| error.at_key("m"))?
+            }
+            None => None,
+        };
+        Ok(Self {
+            // An ID of 0 means the extension is turned off
+            ut_metadata: ut_metadata.filter(|&id| id != 0),
+            metadata_size: optional_field(d, "metadata_size")?,
+        })
+    }
+}
+
+/// A `ut_metadata` message, borrowing the piece data it carries.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Message<'a> {
+    Request {
+        piece: usize,
+    },
+    Data {
+        piece: usize,
+        total_size: usize,
+        data: &'a [u8],
+    },
+    Reject {
+        piece: usize,
+    },
+}
+
+impl<'a> Message<'a> {
+    /// Reads the payload of a `ut_metadata` message, after its extended
+    /// message ID. Piece data follows the bencoded dictionary.
+    pub fn parse(payload: &'a [u8]) -> Result<Self, Error> {
+        let (value, data) = decode_bencoded_value(payload)?;
+        let d = dictionary(&value)?;
+        let piece = field(d, "piece")?;
+        match field::<i64>(d, "msg_type")? {
+            0 => Ok(Message::Request { piece }),
+            1 => Ok(Message::Data {
+                piece,
+                total_size: field(d, "total_size")?,
+                data,
+            }),
+            2 => Ok(Message::Reject { piece }),
+            _ => Err(Error::invalid_type("ut_metadata message type").at_key("msg_type")),
+        }
+    }
+
+    /// The message ready to send to a peer that assigned `ut_metadata` the
+    /// ID `id`.
+    pub fn to_bytes(&self, id: u8) -> Vec<u8> {
+        let (msg_type, piece) = match *self {
+            Message::Request { piece } => (0, piece),
+            Message::Data { piece, .. } => (1, piece),
+            Message::Reject { piece } => (2, piece),
+        };
+        let mut header = DictBuilder::new()
+            .insert("msg_type", msg_type)
+            .insert("piece", piece as i64);
+        let mut data: &[u8] = &[];
+        if let Message::Data {
+            total_size,
+            data: piece_data,
+            ..
+        } = *self
+        {
+            header = header.insert("total_size", total_size as i64);
+            data = piece_data;
+        }
+        extended(id, &[encode(&header.build()).as_slice(), data].concat())
+    }
+}
+
+/// Collects the pieces of an info dictionary of a known size, in any order.
+#[derive(Debug, Clone)]
+pub struct Assembler {
+    size: usize,
+    pieces: Vec<Option<Vec<u8>>>,
+}
+
+impl Assembler {
+    /// Fails for an empty or implausibly large `size`, such as one from a
+    /// misbehaving peer.
+    pub fn new(size: usize) -> Result<Self, Error> {
+        if size == 0 || size > MAX_SIZE {
+            return Err(Error::SizeLimitExceeded);
+        }
+        Ok(Self {
+            size,
+            pieces: vec![None; size.div_ceil(PIECE_LEN)],
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn piece_count(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Indices of the pieces still to be received, in increasing order.
+    pub fn missing(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.pieces.len()).filter(|&index| self.pieces[index].is_none())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing().next().is_none()
+    }
+
+    /// Stores piece `index`, which must be [`PIECE_LEN`] bytes, or the rest
+    /// of the dictionary for the last one. A piece received twice replaces
+    /// the first copy.
+    pub fn add(&mut self, index: usize, data: &[u8]) -> Result<(), Error> {
+        if index >= self.pieces.len() {
+            return Err(Error::invalid_type("piece of the info dictionary"));
+        }
+        let expected = PIECE_LEN.min(self.size - index * PIECE_LEN);
+        if data.len() != expected {
+            return Err(Error::invalid_type(
+                "info dictionary piece of the full length",
+            ));
+        }
+        self.pieces[index] = Some(data.to_vec());
+        Ok(())
+    }
+
+    /// The complete info dictionary, if its SHA-1 is `infohash` and it
+    /// decodes to a dictionary.
+    pub fn finish(self, infohash: &[u8; 20]) -> Result<Vec<u8>, Error> {
+        if !self.is_complete() {
+            return Err(Error::UnexpectedEof { needed: None });
+        }
+        let info: Vec<u8> = self.pieces.into_iter().flatten().flatten().collect();
+        if sha1(&info) != *infohash {
+            return Err(Error::invalid_type("info dictionary matching the infohash"));
+        }
+        dictionary(&decode(&info)?)?;
+        Ok(info)
+    }
+}
+
+/// Connects to `peer` and downloads the info dictionary of the torrent with
+/// `infohash`, failing if the peer doesn't have it whole, the whole exchange
+/// takes longer than `timeout` or the peer sends more than [`MAX_IGNORED`]
+/// messages that don't help. Decoding errors and a dictionary that doesn't
+/// match come back as `InvalidData` wrapping the [`Error`].
+#[cfg(feature = "net")]
+pub fn fetch(
+    peer: std::net::SocketAddr,
+    infohash: &[u8; 20],
+    peer_id: &[u8; 20],
+    timeout: std::time::Duration,
+) -> std::io::Result<Vec<u8>> {
+    use std::{
+        io::{self, Read, Write},
+        net::TcpStream,
+        time::Instant,
+    };
+
+    use crate::peer::{self, Handshake};
+
+    /// The time left before `deadline`, or `TimedOut` once it's passed.
+    fn remaining(deadline: Instant) -> io::Result<std::time::Duration> {
+        deadline
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "the peer took too long"))
+    }
+
+    /// `read_exact`, but failing at `deadline` however slowly the bytes
+    /// trickle in.
+    fn read_by(stream: &mut TcpStream, mut buf: &mut [u8], deadline: Instant) -> io::Result<()> {
+        while !buf.is_empty() {
+            stream.set_read_timeout(Some(remaining(deadline)?))?;
+            match stream.read(buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => buf = &mut buf[read..],
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    let deadline = Instant::now() + timeout;
+    let invalid = |error: Error| io::Error::new(io::ErrorKind::InvalidData, error);
+    let mut stream = TcpStream::connect_timeout(&peer, timeout)?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    stream.write_all(
+        &Handshake::new(*infohash, *peer_id)
+            .with_extensions()
+            .to_bytes(),
+    )?;
+    let mut handshake = [0; peer::LEN];
+    read_by(&mut stream, &mut handshake, deadline)?;
+    let handshake = Handshake::parse(&handshake).map_err(invalid)?;
+    if handshake.infohash != *infohash {
+        return Err(io::Error::other("the peer answered for another torrent"));
+    }
+    if !handshake.reserved.extensions() {
+        return Err(io::Error::other("the peer doesn't support extensions"));
+    }
+    stream.write_all(&extended_handshake())?;
+
+    let mut assembler: Option<(u8, Assembler)> = None;
+    let mut ignored = 0;
+    loop {
+        if ignored > MAX_IGNORED {
+            return Err(io::Error::other(format!(
+                "the peer sent {MAX_IGNORED} messages without the metadata"
+            )));
+        }
+        let mut len = [0; 4];
+        read_by(&mut stream, &mut len, deadline)?;
+        let len = u32::from_be_bytes(len) as usize;
+        // Piece data and the largest bitfields fit comfortably
+        if len > PIECE_LEN + (1 << 20) {
+            return Err(invalid(Error::SizeLimitExceeded));
+        }
+        let mut body = vec![0; len];
+        read_by(&mut stream, &mut body, deadline)?;
+        // Keep-alives and everything outside the extension protocol are
+        // ignored
+        let Some((&EXTENDED, [extended_id, payload @ ..])) = body.split_first() else {
+            ignored += 1;
+            continue;
+        };
+
+        match (*extended_id, &mut assembler) {
+            (0, None) => {
+                let extensions = ExtendedHandshake::parse(payload).map_err(invalid)?;
+                let (Some(id), Some(size)) = (extensions.ut_metadata, extensions.metadata_size)
+                else {
+                    return Err(io::Error::other("the peer doesn't offer the metadata"));
+                };
+                let pieces = Assembler::new(size).map_err(invalid)?;
+                for piece in pieces.missing() {
+                    stream.write_all(&Message::Request { piece }.to_bytes(id))?;
+                }
+                assembler = Some((id, pieces));
+            }
+            (UT_METADATA, Some((id, pieces))) => match Message::parse(payload).map_err(invalid)? {
+                Message::Data {
+                    piece,
+                    total_size,
+                    data,
+                } => {
+                    if total_size != pieces.size() {
+                        return Err(io::Error::other("the peer changed the metadata size"));
+                    }
+                    pieces.add(piece, data).map_err(invalid)?;
+                    if pieces.is_complete() {
+                        let (_, pieces) = assembler.take().unwrap();
+                        return pieces.finish(infohash).map_err(invalid);
+                    }
+                }
+                Message::Reject { piece } => {
+                    return Err(io::Error::other(format!(
+                        "the peer rejected the request for piece {piece}"
+                    )))
+                }
+                // This side has nothing to share
+                Message::Request { piece } => {
+                    stream.write_all(&Message::Reject { piece }.to_bytes(*id))?
+                }
+            },
+            _ => ignored += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An info dictionary of `PIECE_LEN * 2 + 100` bytes, in three pieces.
+    fn info() -> Vec<u8> {
+        let info = |pieces: usize| {
+            encode(
+                &DictBuilder::new()
+                    .insert("length", 1_000_000i64)
+                    .insert("name", "a")
+                    .insert("piece length", 262144i64)
+                    .insert("pieces", vec![0xab; pieces])
+                    .build(),
+            )
+        };
+        let len = PIECE_LEN * 2 + 100;
+        let info = info(len - (info(len).len() - len));
+        assert_eq!(info.len(), len);
+        info
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            extended_handshake(),
+            b"\x00\x00\x00\x1a\x14\x00d1:md11:ut_metadatai1eee"
+        );
+
+        let request = Message::Request { piece: 2 }.to_bytes(3);
+        assert_eq!(&request[..6], b"\x00\x00\x00\x1b\x14\x03");
+        assert_eq!(&request[6..], b"d8:msg_typei0e5:piecei2ee");
+        assert_eq!(
+            Message::parse(&request[6..]),
+            Ok(Message::Request { piece: 2 })
+        );
+
+        let data = Message::Data {
+            piece: 0,
+            total_size: 5,
+            data: b"d1:ae",
+        };
+        let bytes = data.to_bytes(UT_METADATA);
+        assert_eq!(
+            &bytes[6..],
+            b"d8:msg_typei1e5:piecei0e10:total_sizei5eed1:ae"
+        );
+        assert_eq!(Message::parse(&bytes[6..]), Ok(data));
+        assert!(Message::parse(b"d8:msg_typei7e5:piecei0ee").is_err());
+        assert!(Message::parse(b"d8:msg_typei1e5:piecei0ee").is_err());
+    }
+
+    #[test]
+    fn peer_extensions() {
+        let handshake = b"d1:md6:ut_pexi1e11:ut_metadatai3ee13:metadata_sizei31235e1:v4:testee";
+        assert_eq!(
+            ExtendedHandshake::parse(handshake),
+            Ok(ExtendedHandshake {
+                ut_metadata: Some(3),
+                metadata_size: Some(31235),
+            })
+        );
+        assert_eq!(
+            ExtendedHandshake::parse(b"d1:md11:ut_metadatai0eee"),
+            Ok(ExtendedHandshake {
+                ut_metadata: None,
+                metadata_size: None,
+            })
+        );
+        assert!(ExtendedHandshake::parse(b"d1:mi1ee").is_err());
+    }
+
+    #[test]
+    fn assembles() {
+        let info = info();
+        let infohash = sha1(&info);
+        let mut assembler = Assembler::new(info.len()).unwrap();
+        assert_eq!(assembler.piece_count(), 3);
+
+        let pieces: Vec<_> = info.chunks(PIECE_LEN).collect();
+        assembler.add(2, pieces[2]).unwrap();
+        assembler.add(0, pieces[0]).unwrap();
+        assert_eq!(assembler.missing().collect::<Vec<_>>(), [1]);
+        assert!(assembler.add(1, &pieces[1][1..]).is_err());
+        assert!(assembler.add(3, pieces[2]).is_err());
+        assert!(assembler.clone().finish(&infohash).is_err());
+
+        assembler.add(1, pieces[1]).unwrap();
+        assert!(assembler.clone().finish(&[0; 20]).is_err());
+        assert_eq!(assembler.finish(&infohash).unwrap(), info);
+
+        assert!(Assembler::new(0).is_err());
+        assert!(Assembler::new(MAX_SIZE + 1).is_err());
+    }
+
+    /// Plays a peer that has `info` on one connection, sending some
+    /// messages outside the extension protocol first.
+    #[cfg(feature = "net")]
+    fn serve(listener: std::net::TcpListener, infohash: [u8; 20], info: Vec<u8>) {
+        use std::io::{Read, Write};
+
+        use crate::peer::{self, Handshake};
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut handshake = [0; peer::LEN];
+        stream.read_exact(&mut handshake).unwrap();
+        assert!(Handshake::parse(&handshake).unwrap().reserved.extensions());
+        let reply = Handshake::new(infohash, [7; 20]).with_extensions();
+        stream.write_all(&reply.to_bytes()).unwrap();
+        stream.write_all(&message(5, &[0xff])).unwrap();
+        stream.write_all(&[0; 4]).unwrap();
+        let extensions = format!("d1:md11:ut_metadatai3ee13:metadata_sizei{}ee", info.len());
+        stream
+            .write_all(&extended(0, extensions.as_bytes()))
+            .unwrap();
+
+        let mut served = 0;
+        while served < info.len().div_ceil(PIECE_LEN) {
+            let mut len = [0; 4];
+            stream.read_exact(&mut len).unwrap();
+            let mut body = vec![0; u32::from_be_bytes(len) as usize];
+            stream.read_exact(&mut body).unwrap();
+            if body[..2] != [EXTENDED, 3] {
+                continue;
+            }
+            let Ok(Message::Request { piece }) = Message::parse(&body[2..]) else {
+                panic!("unexpected message {body:?}");
+            };
+            let data = info.chunks(PIECE_LEN).nth(piece).unwrap();
+            let reply = Message::Data {
+                piece,
+                total_size: info.len(),
+                data,
+            };
+            stream.write_all(&reply.to_bytes(UT_METADATA)).unwrap();
+            served += 1;
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn fetches() {
+        let info = info();
+        let infohash = sha1(&info);
+        let timeout = std::time::Duration::from_secs(5);
+
+        // A peer serving a dictionary that doesn't hash to the infohash it
+        // answered for is caught once all of it is in
+        for (claimed, fetched) in [(infohash, true), ([9; 20], false)] {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let served = info.clone();
+            let peer = std::thread::spawn(move || serve(listener, claimed, served));
+
+            let result = fetch(addr, &claimed, &[1; 20], timeout);
+            peer.join().unwrap();
+            match fetched {
+                true => assert_eq!(result.unwrap(), info),
+                false => assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData),
+            }
+        }
+    }
+
+    /// Plays a peer that answers the handshake and then sends `message`
+    /// forever, `pause` apart, without ever offering the metadata.
+    #[cfg(feature = "net")]
+    fn stall(listener: std::net::TcpListener, message: Vec<u8>, pause: std::time::Duration) {
+        use std::io::{Read, Write};
+
+        use crate::peer::{self, Handshake};
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut handshake = [0; peer::LEN];
+        stream.read_exact(&mut handshake).unwrap();
+        let infohash = Handshake::parse(&handshake).unwrap().infohash;
+        let reply = Handshake::new(infohash, [7; 20]).with_extensions();
+        stream.write_all(&reply.to_bytes()).unwrap();
+        // Until the other side hangs up
+        while stream.write_all(&message).is_ok() {
+            std::thread::sleep(pause);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn gives_up_on_stalling_peers() {
+        use std::time::{Duration, Instant};
+
+        // Keep-alives each well within the timeout don't extend it
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let pause = Duration::from_millis(20);
+        let peer = std::thread::spawn(move || stall(listener, vec![0; 4], pause));
+        let start = Instant::now();
+        let error = fetch(addr, &[3; 20], &[1; 20], Duration::from_millis(300)).unwrap_err();
+        assert!(
+            start.elapsed() < Duration::from_secs(3),
+            "{:?}",
+            start.elapsed()
+        );
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+        peer.join().unwrap();
+
+        // Nor does a flood of messages outside the extension protocol
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let have = message(4, &[0; 4]);
+        let peer = std::thread::spawn(move || stall(listener, have, Duration::ZERO));
+        let error = fetch(addr, &[3; 20], &[1; 20], Duration::from_secs(30)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the peer sent 10000 messages without the metadata"
+        );
+        peer.join().unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! Shrinking an input that makes some program fail, for `minimize`. A
+//! document that decodes is reduced value by value, keeping each step that
+//! still fails: emptying lists and dictionaries, hoisting a child into its
+//! parent's place, dropping runs of elements or bytes and zeroing integers. Inputs that don't decode,
+//! or only fail in their exact encoding, are cut down bytewise instead.
+
+use std::{io, mem};
+
+use bencode_decoder::{decode, encode, Bencode};
+
+/// Runs of children or bytes removed at once, as `(start, size)`, halving
+/// down to single ones.
+fn chunks(len: usize) -> impl Iterator<Item = (usize, usize)> {
+    let sizes = std::iter::successors((len > 0).then_some(len), |&size| {
+        (size > 1).then_some(size / 2)
+    });
+    sizes.flat_map(move |size| (0..len).step_by(size).map(move |start| (start, size)))
+}
+
+/// The smallest input found for which `fails` still holds, checking the
+/// original first. `fails` is typically a test run, so each candidate costs
+/// one call.
+pub fn minimize(
+    input: &[u8],
+    fails: &mut dyn FnMut(&[u8]) -> io::Result<bool>,
+) -> io::Result<Vec<u8>> {
+    if !fails(input)? {
+        return Err(io::Error::other(
+            "the command doesn't fail on the original input",
+        ));
+    }
+    match decode(input) {
+        Ok(value) if fails(&encode(&value))? => structural(value, fails),
+        _ => bytewise(input.to_vec(), fails),
+    }
+}
+
+fn structural(
+    mut value: Bencode,
+    fails: &mut dyn FnMut(&[u8]) -> io::Result<bool>,
+) -> io::Result<Vec<u8>> {
+    // Nodes are numbered in pre-order; a node is revisited after each
+    // reduction, as a smaller version may reduce further.
+    let mut index = 0;
+    while let Some(node) = nth(&mut value, index) {
+        let mut reduced = false;
+        for candidate in candidates(node) {
+            let node = nth(&mut value, index).expect("the node is still there");
+            let original = mem::replace(node, candidate);
+            if fails(&encode(&value))? {
+                reduced = true;
+                break;
+            }
+            *nth(&mut value, index).expect("the node is still there") = original;
+        }
+        if !reduced {
+            index += 1;
+        }
+    }
+    Ok(encode(&value))
+}
+
+/// The `index`th value in pre-order, the root being 0.
+fn nth(value: &mut Bencode, index: usize) -> Option<&mut Bencode> {
+    fn walk<'a>(value: &'a mut Bencode, index: &mut usize) -> Option<&'a mut Bencode> {
+        if *index == 0 {
+            return Some(value);
+        }
+        *index -= 1;
+        match value {
+            Bencode::List(l) => l.iter_mut().find_map(|item| walk(item, index)),
+            Bencode::Dictionary(d) => d.values_mut().find_map(|item| walk(item, index)),
+            _ => None,
+        }
+    }
+    walk(value, &mut { index })
+}
+
+/// Smaller replacements for a value, roughly largest reduction first.
+fn candidates(value: &Bencode) -> Vec<Bencode> {
+    let mut candidates = Vec::new();
+    match value {
+        Bencode::String(s) if !s.is_empty() => {
+            candidates.push(Bencode::String(Vec::new()));
+            for (start, size) in chunks(s.len()).filter(|&(_, size)| size < s.len()) {
+                let mut rest = s.clone();
+                rest.drain(start..(start + size).min(s.len()));
+                candidates.push(Bencode::String(rest));
+            }
+        }
+        Bencode::Integer(i) if *i != 0 => candidates.push(Bencode::Integer(0)),
+        Bencode::List(l) if !l.is_empty() => {
+            candidates.push(Bencode::List(Vec::new()));
+            candidates.extend(l.iter().cloned());
+            for (start, size) in chunks(l.len()).filter(|&(_, size)| size < l.len()) {
+                let mut rest = l.clone();
+                rest.drain(start..(start + size).min(l.len()));
+                candidates.push(Bencode::List(rest));
+            }
+        }
+        Bencode::Dictionary(d) if !d.is_empty() => {
+            candidates.push(Bencode::Dictionary(Default::default()));
+            candidates.extend(d.values().cloned());
+            let keys: Vec<_> = d.keys().collect();
+            for (start, size) in chunks(keys.len()).filter(|&(_, size)| size < keys.len()) {
+                let mut rest = d.clone();
+                for key in &keys[start..(start + size).min(keys.len())] {
+                    rest.remove(*key);
+                }
+                candidates.push(Bencode::Dictionary(rest));
+            }
+        }
+        _ => {}
+    }
+    candidates
+}
+
+fn bytewise(
+    mut input: Vec<u8>,
+    fails: &mut dyn FnMut(&[u8]) -> io::Result<bool>,
+) -> io::Result<Vec<u8>> {
+    let mut size = input.len() / 2;
+    while size > 0 {
+        let mut start = 0;
+        let mut reduced = false;
+        while start < input.len() {
+            let mut candidate = input.clone();
+            candidate.drain(start..(start + size).min(input.len()));
+            if fails(&candidate)? {
+                input = candidate;
+                reduced = true;
+            } else {
+                start += size;
+            }
+        }
+        if !reduced {
+            size /= 2;
+        }
+    }
+    Ok(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contains(needle: &'static [u8]) -> impl FnMut(&[u8]) -> io::Result<bool> {
+        move |input| Ok(input.windows(needle.len()).any(|window| window == needle))
+    }
+
+    #[test]
+    fn reduces_structure() {
+        let input = b"d8:announce3:url4:infod5:filesld6:lengthi1e4:pathl1:aeed6:lengthi2e\
+                      4:pathl5:xbadyeee4:name1:tee";
+        let mut runs = 0;
+        let mut fails = contains(b"bad");
+        let minimized = minimize(input, &mut |input| {
+            runs += 1;
+            fails(input)
+        })
+        .unwrap();
+        assert_eq!(minimized, b"3:bad");
+        assert!(runs < 60, "{runs}");
+
+        // Failures that need the structure keep it
+        let mut nested = |input: &[u8]| {
+            let pair = |item: &Bencode| matches!(item, Bencode::Dictionary(d) if d.len() >= 2);
+            Ok(matches!(decode(input), Ok(Bencode::List(l)) if l.iter().any(pair)))
+        };
+        assert_eq!(
+            minimize(b"l1:xd1:ai5e1:bi6e1:cl1:yeee", &mut nested).unwrap(),
+            b"ld1:bi0e1:cleee"
+        );
+    }
+
+    #[test]
+    fn reduces_bytes() {
+        let mut fails = contains(b"i01e");
+        assert_eq!(minimize(b"d1:ai01e1:b1:ce", &mut fails).unwrap(), b"i01e");
+        assert!(minimize(b"i1e", &mut fails).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! Robustness smoke testing, for `mutate`: a valid document is broken in
+//! small random ways and each result is decoded, looking for panics and for
+//! values that couldn't have come from the input. The crate's parsers were
+//! written separately, so they're also checked against each other.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use bencode_decoder::{
+    cow::CowBencode, decode, encode, pull::PullParser, span::parse_spanned, Bencode,
+};
+
+/// What's wrong with how `input` decodes, if anything.
+pub fn check(input: &[u8]) -> Option<String> {
+    match panic::catch_unwind(AssertUnwindSafe(|| anomaly(input))) {
+        Ok(anomaly) => anomaly,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            Some(format!("panicked: {message}"))
+        }
+    }
+}
+
+fn anomaly(input: &[u8]) -> Option<String> {
+    let decoded = decode(input);
+    if let Err(error) = &decoded {
+        if error.offset().is_some_and(|offset| offset > input.len()) {
+            return Some(format!("error past the end of the input: {error}"));
+        }
+    }
+
+    if parse_spanned(input).map(|node| node.to_bencode()) != decoded {
+        return Some("the spanned parser disagrees".into());
+    }
+    if CowBencode::decode(input).map(Bencode::from) != decoded {
+        return Some("borrowed decoding disagrees".into());
+    }
+    let mut parser = PullParser::new(input);
+    let pulled = loop {
+        match parser.next_event() {
+            Ok(Some(_)) => continue,
+            Ok(None) => break true,
+            Err(_) => break false,
+        }
+    };
+    if pulled != decoded.is_ok() {
+        return Some("the pull parser disagrees".into());
+    }
+
+    let value = decoded.ok()?;
+    let encoded = encode(&value);
+    // Re-encoding only sorts keys and drops duplicates, so it can't grow
+    if encoded.len() > input.len() {
+        return Some(format!(
+            "decoded to a value encoding to {} bytes, from {}",
+            encoded.len(),
+            input.len()
+        ));
+    }
+    if decode(&encoded).as_ref() != Ok(&value) {
+        return Some("re-encoded value decodes differently".into());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bencode_decoder::generate::{mutate, Rng};
+
+    #[test]
+    fn no_anomalies() {
+        let document =
+            b"d8:announce3:url4:infod6:lengthi5e4:name1:a6:pieces20:aaaaabbbbbcccccddddde\
+                         8:url-listl0:ee";
+        let mut rng = Rng::new(1);
+        assert_eq!(check(document), None);
+        for _ in 0..2000 {
+            let input = mutate(&mut rng, document);
+            assert_eq!(check(&input), None, "{}", input.escape_ascii());
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! DHT node IDs tied to the node's external IP address (BEP 42), so one
+//! host can't choose IDs near a target. The first 21 bits of an ID come from
+//! a CRC32-C of the masked address, and its last byte picks 3 of the bits
+//! mixed in.
+
+use core::net::{IpAddr, SocketAddr};
+
+use crate::{Bencode, Error};
+
+const V4_MASK: [u8; 4] = [0x03, 0x0f, 0x3f, 0xff];
+const V6_MASK: [u8; 8] = [0x01, 0x03, 0x07, 0x0f, 0x1f, 0x3f, 0x7f, 0xff];
+
+/// A node ID for `ip`, filled out with `random`. The last byte is kept to
+/// choose the prefix, and three bits of the third byte survive.
+pub fn generate(ip: IpAddr, random: [u8; 20]) -> [u8; 20] {
+    let mut id = random;
+    let prefix = prefix(ip, random[19]);
+    id[0] = (prefix >> 24) as u8;
+    id[1] = (prefix >> 16) as u8;
+    id[2] = ((prefix >> 8) as u8 & 0xf8) | (random[2] & 0x07);
+    id
+}
+
+/// Whether `id` is one [`generate`] could give for `ip`. Local addresses
+/// are exempt, as BEP 42 has nodes accept any ID from them.
+pub fn is_valid(id: &[u8; 20], ip: IpAddr) -> bool {
+    if is_exempt(ip) {
+        return true;
+    }
+    let prefix = prefix(ip, id[19]).to_be_bytes();
+    id[0] == prefix[0] && id[1] == prefix[1] && id[2] & 0xf8 == prefix[2] & 0xf8
+}
+
+/// The external address a KRPC response reports back in `ip`, as a compact
+/// address and port.
+pub fn external_addr(message: &Bencode) -> Result<Option<SocketAddr>, Error> {
+    let Some(ip) = message.get_path("ip")? else {
+        return Ok(None);
+    };
+    let addr = match ip {
+        Bencode::String(bytes) => match bytes.len() {
+            6 => {
+                let ip: [u8; 4] = bytes[..4].try_into().unwrap();
+                Some(SocketAddr::new(
+                    ip.into(),
+                    u16::from_be_bytes([bytes[4], bytes[5]]),
+                ))
+            }
+            18 => {
+                let ip: [u8; 16] = bytes[..16].try_into().unwrap();
+                Some(SocketAddr::new(
+                    ip.into(),
+                    u16::from_be_bytes([bytes[16], bytes[17]]),
+                ))
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    addr.map(Some)
+        .ok_or_else(|| Error::invalid_type("compact address").at_key("ip"))
+}
+
+fn prefix(ip: IpAddr, r: u8) -> u32 {
+    let r = r & 0x07;
+    match ip {
+        IpAddr::V4(ip) => crc32c(&masked(ip.octets(), V4_MASK, r)),
+        IpAddr::V6(ip) => {
+            let octets: [u8; 8] = ip.octets()[..8].try_into().unwrap();
+            crc32c(&masked(octets, V6_MASK, r))
+        }
+    }
+}
+
+fn masked<const N: usize>(mut octets: [u8; N], mask: [u8; N], r: u8) -> [u8; N] {
+    for (octet, mask) in octets.iter_mut().zip(mask) {
+        *octet &= mask;
+    }
+    octets[0] |= r << 5;
+    octets
+}
+
+fn is_exempt(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_loopback() || ip.is_link_local(),
+        IpAddr::V6(ip) => ip.is_loopback(),
+    }
+}
+
+/// CRC32-C (Castagnoli), bit by bit; the inputs are a few bytes.
+fn crc32c(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0x82f6_3b78 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+    }
+
+    #[test]
+    fn spec_examples() {
+        // The table from BEP 42: the address, the last byte and the first
+        // three bytes of the ID.
+        for (ip, r, start) in [
+            ([124, 31, 75, 21], 0x01, [0x5f, 0xbf, 0xbf]),
+            ([21, 75, 31, 124], 0x56, [0x5a, 0x3c, 0xe9]),
+            ([65, 23, 51, 170], 0x16, [0xa5, 0xd4, 0x32]),
+            ([84, 124, 73, 14], 0x41, [0x1b, 0x03, 0x21]),
+            ([43, 213, 53, 83], 0x5a, [0xe5, 0x6f, 0x6c]),
+        ] {
+            let ip = IpAddr::from(ip);
+            let mut random = [0xaa; 20];
+            random[2] = start[2];
+            random[19] = r;
+            let id = generate(ip, random);
+            assert_eq!(id[..3], start, "{ip}");
+            assert_eq!(id[3..], random[3..]);
+            assert!(is_valid(&id, ip));
+
+            let mut other = id;
+            other[0] ^= 0x80;
+            assert!(!is_valid(&other, ip));
+            other = id;
+            other[19] ^= 0x01;
+            assert!(!is_valid(&other, ip));
+        }
+    }
+
+    #[test]
+    fn addresses() {
+        let ip = IpAddr::from(Ipv6Addr::new(0x2001, 0xdb8, 1, 2, 3, 4, 5, 6));
+        let id = generate(ip, [7; 20]);
+        assert!(is_valid(&id, ip));
+        assert!(!is_valid(&[0; 20], ip));
+        assert!(is_valid(&[0; 20], Ipv4Addr::new(192, 168, 1, 1).into()));
+        assert!(is_valid(&[0; 20], Ipv4Addr::LOCALHOST.into()));
+
+        let message = crate::decode(b"d2:ip6:\x7f\x00\x00\x01\x1a\xe11:y1:re").unwrap();
+        assert_eq!(
+            external_addr(&message),
+            Ok(Some("127.0.0.1:6881".parse().unwrap()))
+        );
+        assert_eq!(
+            external_addr(&crate::decode(b"d1:y1:re").unwrap()),
+            Ok(None)
+        );
+        let error = external_addr(&crate::decode(b"d2:ip3:abce").unwrap()).unwrap_err();
+        assert_eq!(error.to_string(), "expected compact address at path `ip`");
+    }
+}
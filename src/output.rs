@@ -0,0 +1,65 @@
+//! Writing command results to stdout or, with `--output-file`, to a file.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::cli::Matches;
+
+/// Writes `contents` to the `--output-file` if one was given, or to stdout.
+pub fn emit(matches: &Matches, contents: &[u8]) -> io::Result<()> {
+    match matches.value("output-file") {
+        Some(path) => write_atomic(path.as_ref(), contents),
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(contents)?;
+            stdout.flush()
+        }
+    }
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so
+/// readers never see a partial file and a failed write leaves `path` alone.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "output path has no file name")
+    })?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic_write_replaces_file() {
+        let dir = std::env::temp_dir().join(format!("output_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.bin");
+
+        fs::write(&path, b"old").unwrap();
+        write_atomic(&path, b"\x00new\xff").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"\x00new\xff");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        assert!(write_atomic(&dir.join("missing/out.bin"), b"x").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,74 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::Error;
+
+#[derive(PartialEq, Debug)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Splits a path like `.info.files[0].length` into its segments. The leading
+/// dot is optional and `.` on its own refers to the root value.
+pub fn parse(path: &str) -> Result<Vec<Segment>, Error> {
+    let invalid = || Error::InvalidPath(path.to_string());
+    let mut rest = path.strip_prefix('.').unwrap_or(path);
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        if let Some(index) = rest.strip_prefix('[') {
+            let (index, after) = index.split_once(']').ok_or_else(invalid)?;
+            segments.push(Segment::Index(index.parse().map_err(|_| invalid())?));
+            if !after.is_empty() && !after.starts_with(['.', '[']) {
+                return Err(invalid());
+            }
+            rest = after;
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            segments.push(Segment::Key(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+
+        if let Some(after) = rest.strip_prefix('.') {
+            if after.is_empty() {
+                return Err(invalid());
+            }
+            rest = after;
+        }
+    }
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_path() {
+        assert_eq!(parse("."), Ok(vec![]));
+        assert_eq!(
+            parse(".info.files[2].piece length"),
+            Ok(vec![
+                Segment::Key("info".to_string()),
+                Segment::Key("files".to_string()),
+                Segment::Index(2),
+                Segment::Key("piece length".to_string()),
+            ])
+        );
+        assert_eq!(
+            parse("[0][1]"),
+            Ok(vec![Segment::Index(0), Segment::Index(1)])
+        );
+        assert!(parse(".info..name").is_err());
+        assert!(parse(".files[x]").is_err());
+        assert!(parse(".info.").is_err());
+        assert!(parse("[0]name").is_err());
+    }
+}
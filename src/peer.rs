@@ -0,0 +1,154 @@
+//! The handshake opening every peer wire connection (BEP 3): the protocol
+//! string, eight reserved bytes whose bits announce extensions, the
+//! infohash of the torrent and the sender's peer ID. The extension bit
+//! (BEP 10) is what lets bencoded extension messages such as `ut_metadata`
+//! follow.
+
+use crate::Error;
+
+/// The protocol string, sent after its length byte.
+pub const PROTOCOL: &[u8; 19] = b"BitTorrent protocol";
+
+/// Length of a handshake on the wire.
+pub const LEN: usize = 1 + PROTOCOL.len() + 8 + 20 + 20;
+
+/// The reserved bytes, with bits numbered as in a [`Bitfield`]: most
+/// significant bit of the first byte first.
+///
+/// [`Bitfield`]: crate::bitfield::Bitfield
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Reserved(pub [u8; 8]);
+
+impl Reserved {
+    /// The extension protocol of BEP 10, `0x10` in the sixth byte.
+    pub const EXTENSIONS: usize = 43;
+    /// The fast extension of BEP 6.
+    pub const FAST: usize = 61;
+    /// DHT support and the `port` message of BEP 5.
+    pub const DHT: usize = 63;
+
+    /// Panics if `bit` isn't below 64.
+    pub fn get(&self, bit: usize) -> bool {
+        self.0[bit / 8] & (0x80 >> (bit % 8)) != 0
+    }
+
+    /// Panics if `bit` isn't below 64.
+    pub fn set(&mut self, bit: usize, value: bool) {
+        let mask = 0x80 >> (bit % 8);
+        match value {
+            true => self.0[bit / 8] |= mask,
+            false => self.0[bit / 8] &= !mask,
+        }
+    }
+
+    /// Whether the peer speaks the extension protocol, and so accepts an
+    /// extended handshake.
+    pub fn extensions(&self) -> bool {
+        self.get(Self::EXTENSIONS)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Handshake {
+    pub reserved: Reserved,
+    pub infohash: [u8; 20],
+    pub peer_id: [u8; 20],
+}
+
+impl Handshake {
+    /// A handshake with no reserved bits set.
+    pub fn new(infohash: [u8; 20], peer_id: [u8; 20]) -> Self {
+        Self {
+            reserved: Reserved::default(),
+            infohash,
+            peer_id,
+        }
+    }
+
+    /// Sets the extension bit, for connections that will send extension
+    /// messages.
+    pub fn with_extensions(mut self) -> Self {
+        self.reserved.set(Reserved::EXTENSIONS, true);
+        self
+    }
+
+    pub fn to_bytes(&self) -> [u8; LEN] {
+        let mut out = [0; LEN];
+        out[0] = PROTOCOL.len() as u8;
+        out[1..20].copy_from_slice(PROTOCOL);
+        out[20..28].copy_from_slice(&self.reserved.0);
+        out[28..48].copy_from_slice(&self.infohash);
+        out[48..].copy_from_slice(&self.peer_id);
+        out
+    }
+
+    /// Reads the handshake at the start of `bytes`, ignoring whatever
+    /// follows it, since peers often send their first messages right after.
+    /// Input too short to hold a handshake is an
+    /// [`Error::UnexpectedEof`] saying how much more to read.
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < LEN {
+            return Err(Error::UnexpectedEof {
+                needed: Some(LEN - bytes.len()),
+            });
+        }
+        if bytes[0] as usize != PROTOCOL.len() || &bytes[1..20] != PROTOCOL {
+            return Err(Error::invalid_type("BitTorrent protocol handshake"));
+        }
+        Ok(Self {
+            reserved: Reserved(bytes[20..28].try_into().unwrap()),
+            infohash: bytes[28..48].try_into().unwrap(),
+            peer_id: bytes[48..68].try_into().unwrap(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let handshake = Handshake::new([0xaa; 20], *b"-BD0100-123456789012").with_extensions();
+        let bytes = handshake.to_bytes();
+        assert_eq!(bytes.len(), 68);
+        assert_eq!(&bytes[..20], b"\x13BitTorrent protocol");
+        assert_eq!(bytes[20..28], [0, 0, 0, 0, 0, 0x10, 0, 0]);
+        assert_eq!(&bytes[48..], b"-BD0100-123456789012");
+
+        let mut received = bytes.to_vec();
+        received.extend_from_slice(b"\x00\x00\x00\x01\x02");
+        assert_eq!(Handshake::parse(&received), Ok(handshake));
+        assert!(Handshake::parse(&bytes).unwrap().reserved.extensions());
+    }
+
+    #[test]
+    fn reserved_bits() {
+        let mut reserved = Reserved::default();
+        reserved.set(Reserved::DHT, true);
+        reserved.set(Reserved::FAST, true);
+        assert_eq!(reserved.0, [0, 0, 0, 0, 0, 0, 0, 0x05]);
+        assert!(!reserved.extensions());
+        reserved.set(Reserved::DHT, false);
+        assert_eq!(reserved.0[7], 0x04);
+    }
+
+    #[test]
+    fn invalid() {
+        let bytes = Handshake::new([1; 20], [2; 20]).to_bytes();
+        assert_eq!(
+            Handshake::parse(&bytes[..50]),
+            Err(Error::UnexpectedEof { needed: Some(18) })
+        );
+
+        let mut other = bytes;
+        other[1..20].copy_from_slice(b"BitTorrent protocoL");
+        assert!(matches!(
+            Handshake::parse(&other),
+            Err(Error::InvalidType { .. })
+        ));
+        other = bytes;
+        other[0] = 18;
+        assert!(Handshake::parse(&other).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Progress reporting for long hashing runs. On a terminal this is a bar
+//! redrawn in place on stderr; otherwise a plain line is logged every few
+//! seconds, which keeps CI logs readable. Nothing is shown with `--quiet`.
+
+use std::{
+    io::{self, IsTerminal, Write},
+    time::{Duration, Instant},
+};
+
+use crate::log::{self, Level};
+
+const REDRAW_EVERY: Duration = Duration::from_millis(100);
+const LOG_EVERY: Duration = Duration::from_secs(5);
+const BAR_WIDTH: usize = 30;
+
+pub struct Progress {
+    label: &'static str,
+    total: u64,
+    done: u64,
+    start: Instant,
+    last_shown: Instant,
+    terminal: bool,
+    visible: bool,
+}
+
+impl Progress {
+    /// Tracks `total` bytes of work.
+    pub fn new(label: &'static str, total: u64) -> Self {
+        let now = Instant::now();
+        Self {
+            label,
+            total,
+            done: 0,
+            start: now,
+            last_shown: now,
+            terminal: io::stderr().is_terminal(),
+            visible: log::enabled(Level::Warn),
+        }
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.done = (self.done + bytes).min(self.total);
+
+        let every = if self.terminal {
+            REDRAW_EVERY
+        } else {
+            LOG_EVERY
+        };
+        if self.visible && self.last_shown.elapsed() >= every {
+            self.last_shown = Instant::now();
+            self.show();
+        }
+    }
+
+    /// Draws the final state and ends the bar's line.
+    pub fn finish(&mut self) {
+        if self.visible && self.terminal {
+            self.show();
+            eprintln!();
+        }
+    }
+
+    fn show(&self) {
+        let status = status(self.done, self.total, self.start.elapsed());
+        if self.terminal {
+            let bar = bar(self.done, self.total, BAR_WIDTH);
+            eprint!("\r\x1b[2K{} [{bar}] {status}", self.label);
+            let _ = io::stderr().flush();
+        } else {
+            eprintln!("{}: {status}", self.label);
+        }
+    }
+}
+
+fn fraction(done: u64, total: u64) -> f64 {
+    match total {
+        0 => 1.0,
+        _ => done as f64 / total as f64,
+    }
+}
+
+fn bar(done: u64, total: u64, width: usize) -> String {
+    let filled = (fraction(done, total) * width as f64) as usize;
+    let mut bar = "=".repeat(filled);
+    if filled < width {
+        bar.push('>');
+        bar.push_str(&" ".repeat(width - filled - 1));
+    }
+    bar
+}
+
+/// Percentage, amounts, throughput and, once there is a rate to go by, the
+/// estimated time left.
+fn status(done: u64, total: u64, elapsed: Duration) -> String {
+    let rate = done as f64 / elapsed.as_secs_f64().max(0.001);
+    let mut status = format!(
+        "{:>3.0}% {} of {}, {}/s",
+        fraction(done, total) * 100.0,
+        bytes(done as f64),
+        bytes(total as f64),
+        bytes(rate)
+    );
+    if done > 0 && done < total {
+        let eta = ((total - done) as f64 / rate).ceil() as u64;
+        status.push_str(&format!(", ETA {}", duration(eta)));
+    }
+    status
+}
+
+pub fn bytes(mut amount: f64) -> String {
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if amount < 1024.0 {
+            return match unit {
+                "B" => format!("{amount:.0} {unit}"),
+                _ => format!("{amount:.1} {unit}"),
+            };
+        }
+        amount /= 1024.0;
+    }
+    format!("{amount:.1} TiB")
+}
+
+/// A size like `10GB`, `512MiB` or `1500`: units of 1000 with a `B` and of
+/// 1024 with `iB`, and bytes without a unit.
+pub fn parse_bytes(size: &str) -> Option<u64> {
+    let digits = size
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(digits);
+    let number: u64 = number.parse().ok()?;
+    let (prefix, base) = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => return Some(number),
+        unit if unit.ends_with("ib") => (unit.strip_suffix("ib")?.to_string(), 1024u64),
+        unit => (unit.strip_suffix('b').unwrap_or(unit).to_string(), 1000),
+    };
+    let power = ["k", "m", "g", "t"].iter().position(|p| *p == prefix)? as u32 + 1;
+    number.checked_mul(base.pow(power))
+}
+
+fn duration(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3599 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendering() {
+        assert_eq!(bar(0, 100, 10), ">         ");
+        assert_eq!(bar(55, 100, 10), "=====>    ");
+        assert_eq!(bar(100, 100, 10), "==========");
+        assert_eq!(bar(0, 0, 4), "====");
+
+        assert_eq!(
+            status(1 << 20, 4 << 20, Duration::from_secs(2)),
+            " 25% 1.0 MiB of 4.0 MiB, 512.0 KiB/s, ETA 6s"
+        );
+        assert_eq!(
+            status(300, 300, Duration::from_secs(1)),
+            "100% 300 B of 300 B, 300 B/s"
+        );
+        assert_eq!(duration(3725), "1h02m");
+        assert_eq!(duration(61), "1m01s");
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse_bytes("1500"), Some(1500));
+        assert_eq!(parse_bytes("10GB"), Some(10_000_000_000));
+        assert_eq!(parse_bytes("512MiB"), Some(512 << 20));
+        assert_eq!(parse_bytes("2k"), Some(2000));
+        assert_eq!(parse_bytes("1tib"), Some(1 << 40));
+        assert_eq!(parse_bytes("GB"), None);
+        assert_eq!(parse_bytes("10XB"), None);
+        assert_eq!(parse_bytes("100000000TB"), None);
+    }
+}
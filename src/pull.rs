@@ -0,0 +1,237 @@
+//! Event-based pull parser, for reading documents without building a tree.
+
+use alloc::vec::Vec;
+
+use crate::{
+    lexer::{scan_token, TokenKind},
+    Error,
+};
+
+/// A single parsing event. Strings and keys borrow from the input.
+#[derive(PartialEq, Debug)]
+pub enum Event<'a> {
+    StartList,
+    EndList,
+    StartDict,
+    EndDict,
+    Key(&'a [u8]),
+    Int(i64),
+    String(&'a [u8]),
+}
+
+enum Frame {
+    List,
+    Dict { key_next: bool },
+}
+
+/// Yields the events of one document in order, checking its structure as it
+/// goes. Iteration stops after the first error.
+pub struct PullParser<'a> {
+    input: &'a [u8],
+    offset: usize,
+    stack: Vec<Frame>,
+    started: bool,
+    done: bool,
+}
+
+impl<'a> PullParser<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            offset: 0,
+            stack: Vec::new(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Byte offset of the next unread token.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Number of lists and dictionaries currently open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<Event<'a>>, Error> {
+        if self.done {
+            return Ok(None);
+        }
+
+        match self.read_event() {
+            Ok(None) => {
+                self.done = true;
+                Ok(None)
+            }
+            Err(error) => {
+                self.done = true;
+                Err(error)
+            }
+            event => event,
+        }
+    }
+
+    /// Consumes the next value whole, including everything nested inside it,
+    /// e.g. to pass over the value of an uninteresting key.
+    pub fn skip_value(&mut self) -> Result<(), Error> {
+        let mut depth = 0usize;
+
+        loop {
+            match self
+                .next_event()?
+                .ok_or(Error::UnexpectedEof { needed: None })?
+            {
+                Event::StartList | Event::StartDict => depth += 1,
+                Event::EndList | Event::EndDict => depth = depth.saturating_sub(1),
+                Event::Key(_) => continue,
+                _ => {}
+            }
+
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event<'a>>, Error> {
+        if self.started && self.stack.is_empty() {
+            return match self.offset < self.input.len() {
+                true => Err(Error::TrailingData),
+                false => Ok(None),
+            };
+        }
+        self.started = true;
+
+        let rest = &self.input[self.offset..];
+        let expecting_key = matches!(self.stack.last(), Some(Frame::Dict { key_next: true }));
+        if rest.is_empty()
+            && matches!(
+                self.stack.last(),
+                Some(Frame::List | Frame::Dict { key_next: true })
+            )
+        {
+            // Nothing is half done: all that's missing is each container's e
+            return Err(Error::needs(self.stack.len()));
+        }
+        let (token, after) = scan_token(rest).map_err(|error| {
+            // Each open container still needs its e, and a key its value
+            let pending = self.stack.len() + usize::from(expecting_key);
+            error.offset_by(self.offset).needing_more(pending)
+        })?;
+
+        let event = match token {
+            TokenKind::End => {
+                let event = match self.stack.pop() {
+                    Some(Frame::List) => Event::EndList,
+                    Some(Frame::Dict { key_next: true }) => Event::EndDict,
+                    _ => return Err(Error::UnexpectedByte(b'e')),
+                };
+                self.offset += 1;
+                self.value_done();
+                return Ok(Some(event));
+            }
+            TokenKind::String(string) if expecting_key => Event::Key(string),
+            _ if expecting_key => {
+                return Err(Error::NonStringKey {
+                    offset: self.offset,
+                })
+            }
+            TokenKind::String(string) => Event::String(string),
+            TokenKind::Integer(number) => Event::Int(number),
+            TokenKind::ListStart => Event::StartList,
+            TokenKind::DictStart => Event::StartDict,
+        };
+        self.offset += rest.len() - after.len();
+
+        match event {
+            Event::Key(_) => {
+                if let Some(Frame::Dict { key_next }) = self.stack.last_mut() {
+                    *key_next = false;
+                }
+            }
+            Event::StartList => self.stack.push(Frame::List),
+            Event::StartDict => self.stack.push(Frame::Dict { key_next: true }),
+            _ => self.value_done(),
+        }
+
+        Ok(Some(event))
+    }
+
+    fn value_done(&mut self) {
+        if let Some(Frame::Dict { key_next }) = self.stack.last_mut() {
+            *key_next = true;
+        }
+    }
+}
+
+impl<'a> Iterator for PullParser<'a> {
+    type Item = Result<Event<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events() {
+        let events: Result<Vec<_>, _> = PullParser::new(b"d3:fooli1e3:bare3:nomdee").collect();
+
+        assert_eq!(
+            events.unwrap(),
+            vec![
+                Event::StartDict,
+                Event::Key(b"foo"),
+                Event::StartList,
+                Event::Int(1),
+                Event::String(b"bar"),
+                Event::EndList,
+                Event::Key(b"nom"),
+                Event::StartDict,
+                Event::EndDict,
+                Event::EndDict,
+            ]
+        );
+    }
+
+    #[test]
+    fn find_field_skipping_the_rest() {
+        let mut parser = PullParser::new(b"d8:announce3:url4:infod5:filesli1ei2ee4:name3:fooee");
+        let mut name = None;
+
+        while let Some(event) = parser.next_event().unwrap() {
+            match event {
+                Event::Key(b"info") | Event::StartDict => continue,
+                Event::Key(b"name") => match parser.next_event().unwrap() {
+                    Some(Event::String(s)) => name = Some(s),
+                    _ => panic!("name should be a string"),
+                },
+                Event::Key(_) => parser.skip_value().unwrap(),
+                _ => {}
+            }
+        }
+
+        assert_eq!(name, Some(&b"foo"[..]));
+    }
+
+    #[test]
+    fn structure_errors() {
+        let last = |input| PullParser::new(input).find_map(Result::err);
+
+        assert_eq!(
+            last(&b"di1ei2ee"[..]),
+            Some(Error::NonStringKey { offset: 1 })
+        );
+        assert_eq!(last(b"d3:fooe"), Some(Error::UnexpectedByte(b'e')));
+        assert_eq!(last(b"l1:a"), Some(Error::needs(1)));
+        assert_eq!(last(b"d1:a"), Some(Error::needs(2)));
+        assert_eq!(last(b"ld1:"), Some(Error::needs(4)));
+        assert_eq!(last(b"i1ei2e"), Some(Error::TrailingData));
+        assert_eq!(last(b"e"), Some(Error::UnexpectedByte(b'e')));
+    }
+}
@@ -0,0 +1,665 @@
+//! Queries that select any number of values. They extend the paths of
+//! [`Bencode::get_path`] with `*`, any key or element (also written `[*]`),
+//! and `**`, the value itself and everything nested in it at any depth:
+//!
+//! ```text
+//! .info.files[*].path     the path of every file
+//! .**.length              every `length`, wherever it is
+//! .info.files[-1]         the last file
+//! .info.files[0:10]       the first ten files
+//! ```
+//!
+//! Indices and slices work as in Python: negative numbers count from the
+//! end, `[start:end:step]` may leave out any part and out of range bounds
+//! are clamped.
+//!
+//! A filter `[?(@path op literal)]` keeps the elements (or dictionary
+//! values) for which the comparison holds, with `@path` relative to each
+//! one. The operators are `==`, `!=`, `<`, `<=`, `>` and `>=` and literals
+//! are integers or double-quoted strings; integers compare numerically and
+//! strings bytewise, and a missing value or one of the other type never
+//! matches. `[?(@path)]` alone keeps those where the path exists.
+//!
+//! ```text
+//! .info.files[?(@.length > 1000000000)].path
+//! ```
+//!
+//! Transforms after `|` are applied to the selection in turn: `length`
+//! gives the byte length of a string or the size of a list or dictionary,
+//! `hex` and `base64` render strings, and `sum` adds up all the selected
+//! integers into one value, found at the query's own path.
+//!
+//! ```text
+//! .info.files[*].length | sum
+//! .info.pieces | length
+//! ```
+
+use alloc::{
+    borrow::Cow,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use core::cmp::Ordering;
+
+use crate::{hex, Bencode, Error};
+
+#[derive(PartialEq, Debug)]
+enum Step {
+    Key(String),
+    /// Negative indices count from the end.
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: i64,
+    },
+    Wildcard,
+    Recursive,
+    Filter(Filter),
+}
+
+#[derive(PartialEq, Debug)]
+struct Filter {
+    path: String,
+    comparison: Option<(Op, Bencode)>,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Longer operators first, so that `<=` isn't read as `<`.
+const OPS: &[(&str, Op)] = &[
+    ("==", Op::Eq),
+    ("!=", Op::Ne),
+    ("<=", Op::Le),
+    (">=", Op::Ge),
+    ("<", Op::Lt),
+    (">", Op::Gt),
+];
+
+impl Filter {
+    fn parse(source: &str) -> Option<Self> {
+        let source = source.trim().strip_prefix('@')?;
+        let Some((at, op, op_len)) = OPS
+            .iter()
+            .filter_map(|&(token, op)| Some((source.find(token)?, op, token.len())))
+            .min_by_key(|&(at, _, _)| at)
+        else {
+            return Some(Self {
+                path: source.trim().into(),
+                comparison: None,
+            });
+        };
+
+        let literal = source[at + op_len..].trim();
+        let literal = match literal.strip_prefix('"') {
+            Some(quoted) => Bencode::from(quoted.strip_suffix('"')?),
+            None => Bencode::Integer(literal.parse().ok()?),
+        };
+        Some(Self {
+            path: source[..at].trim().into(),
+            comparison: Some((op, literal)),
+        })
+    }
+
+    fn matches(&self, value: &Bencode) -> bool {
+        let Ok(Some(found)) = value.get_path(&self.path) else {
+            return false;
+        };
+        let Some((op, literal)) = &self.comparison else {
+            return true;
+        };
+
+        let ordering = match (found, literal) {
+            (Bencode::Integer(a), Bencode::Integer(b)) => a.cmp(b),
+            (Bencode::String(a), Bencode::String(b)) => a.cmp(b),
+            _ => return false,
+        };
+        match op {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Transform {
+    Length,
+    Hex,
+    Base64,
+    Sum,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Query {
+    /// The part before any transforms, as written.
+    path: String,
+    steps: Vec<Step>,
+    transforms: Vec<Transform>,
+}
+
+/// A selected value and the path it was found at, e.g. `.info.files[3]`.
+/// Values are borrowed from the document unless a transform made them.
+#[derive(PartialEq, Debug)]
+pub struct Match<'a> {
+    pub path: String,
+    pub value: Cow<'a, Bencode>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidPath(query.to_string());
+        // Filters may contain `|` in string literals, so split after them.
+        let filters_end = query.rfind(")]").map_or(0, |end| end + 2);
+        let (path, transforms) = match query[filters_end..].find('|') {
+            Some(bar) => query.split_at(filters_end + bar),
+            None => (query, ""),
+        };
+        let transforms = transforms
+            .split('|')
+            .skip(1)
+            .map(|name| match name.trim() {
+                "length" => Ok(Transform::Length),
+                "hex" => Ok(Transform::Hex),
+                "base64" => Ok(Transform::Base64),
+                "sum" => Ok(Transform::Sum),
+                _ => Err(invalid()),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let path = path.trim();
+        let mut rest = path.strip_prefix('.').unwrap_or(path);
+        let mut steps = Vec::new();
+
+        while !rest.is_empty() {
+            if let Some(filter) = rest.strip_prefix("[?(") {
+                let (filter, after) = filter.split_once(")]").ok_or_else(invalid)?;
+                steps.push(Step::Filter(Filter::parse(filter).ok_or_else(invalid)?));
+                if !after.is_empty() && !after.starts_with(['.', '[']) {
+                    return Err(invalid());
+                }
+                rest = after;
+            } else if let Some(inner) = rest.strip_prefix('[') {
+                let (inner, after) = inner.split_once(']').ok_or_else(invalid)?;
+                steps.push(match inner {
+                    "*" => Step::Wildcard,
+                    _ if inner.contains(':') => parse_slice(inner).ok_or_else(invalid)?,
+                    _ => Step::Index(inner.parse().map_err(|_| invalid())?),
+                });
+                if !after.is_empty() && !after.starts_with(['.', '[']) {
+                    return Err(invalid());
+                }
+                rest = after;
+            } else {
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                steps.push(match &rest[..end] {
+                    "" => return Err(invalid()),
+                    "*" => Step::Wildcard,
+                    "**" => Step::Recursive,
+                    key => Step::Key(key.to_string()),
+                });
+                rest = &rest[end..];
+            }
+
+            if let Some(after) = rest.strip_prefix('.') {
+                if after.is_empty() {
+                    return Err(invalid());
+                }
+                rest = after;
+            }
+        }
+
+        Ok(Self {
+            path: path.into(),
+            steps,
+            transforms,
+        })
+    }
+
+    /// Whether the query selects at most one value: it has only keys and
+    /// indices, or ends in `sum`.
+    pub fn is_single(&self) -> bool {
+        self.transforms.contains(&Transform::Sum)
+            || self
+                .steps
+                .iter()
+                .all(|step| matches!(step, Step::Key(_) | Step::Index(_)))
+    }
+
+    /// Every value the query selects from `root`, in document order, after
+    /// the transforms. A transform given a value of the wrong type is an
+    /// error.
+    pub fn select<'a>(&self, root: &'a Bencode) -> Result<Vec<Match<'a>>, Error> {
+        let mut matches: Vec<Match<'a>> = self
+            .select_path(root)
+            .into_iter()
+            .map(|(path, value)| Match {
+                path: if path.is_empty() { ".".into() } else { path },
+                value: Cow::Borrowed(value),
+            })
+            .collect();
+
+        for &transform in &self.transforms {
+            matches = match transform {
+                Transform::Sum => {
+                    let mut sum = 0i64;
+                    for found in &matches {
+                        match *found.value {
+                            Bencode::Integer(i) => sum = sum.saturating_add(i),
+                            _ => return Err(type_error("integer", &found.path)),
+                        }
+                    }
+                    alloc::vec![Match {
+                        path: if self.path.is_empty() {
+                            ".".into()
+                        } else {
+                            self.path.clone()
+                        },
+                        value: Cow::Owned(Bencode::Integer(sum)),
+                    }]
+                }
+                _ => matches
+                    .into_iter()
+                    .map(|found| {
+                        let value = apply(transform, &found.value)
+                            .ok_or_else(|| type_error(transform.expects(), &found.path))?;
+                        Ok(Match {
+                            path: found.path,
+                            value: Cow::Owned(value),
+                        })
+                    })
+                    .collect::<Result<_, Error>>()?,
+            };
+        }
+
+        Ok(matches)
+    }
+
+    fn select_path<'a>(&self, root: &'a Bencode) -> Vec<(String, &'a Bencode)> {
+        let mut matches = alloc::vec![(String::new(), root)];
+
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for (path, value) in matches {
+                match (step, value) {
+                    (Step::Key(key), Bencode::Dictionary(d)) => {
+                        if let Some(child) = d.get(key.as_bytes()) {
+                            next.push((format!("{path}.{key}"), child));
+                        }
+                    }
+                    (Step::Index(i), Bencode::List(l)) => {
+                        let i = match *i < 0 {
+                            true => l.len().checked_sub(i.unsigned_abs() as usize),
+                            false => Some(*i as usize),
+                        };
+                        if let Some((i, child)) = i.and_then(|i| Some((i, l.get(i)?))) {
+                            next.push((format!("{path}[{i}]"), child));
+                        }
+                    }
+                    (&Step::Slice { start, end, step }, Bencode::List(l)) => {
+                        for i in slice_indices(l.len(), start, end, step) {
+                            next.push((format!("{path}[{i}]"), &l[i]));
+                        }
+                    }
+                    (Step::Wildcard, _) => children(&path, value, &mut next),
+                    (Step::Recursive, _) => descendants(path, value, &mut next),
+                    (Step::Filter(filter), _) => {
+                        let mut candidates = Vec::new();
+                        children(&path, value, &mut candidates);
+                        next.extend(
+                            candidates
+                                .into_iter()
+                                .filter(|(_, child)| filter.matches(child)),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            matches = next;
+        }
+
+        matches
+    }
+}
+
+/// Parses `start:end` or `start:end:step`, any part of which may be empty.
+fn parse_slice(inner: &str) -> Option<Step> {
+    let mut parts = inner.split(':');
+    let mut bound = || -> Option<Option<i64>> {
+        match parts.next()?.trim() {
+            "" => Some(None),
+            number => number.parse().ok().map(Some),
+        }
+    };
+    let (start, end) = (bound()?, bound()?);
+    let step = bound().unwrap_or(None).unwrap_or(1);
+    if parts.next().is_some() || step == 0 {
+        return None;
+    }
+    Some(Step::Slice { start, end, step })
+}
+
+/// The indices a slice selects from a list of `len`, in order.
+fn slice_indices(len: usize, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    let len = len as i64;
+    // Resolves a bound against the list, clamped to `min..=max`.
+    let resolve = |bound: i64, min: i64, max: i64| match bound < 0 {
+        true => (bound + len).clamp(min, max),
+        false => bound.clamp(min, max),
+    };
+
+    let mut indices = Vec::new();
+    if step > 0 {
+        let mut i = start.map_or(0, |start| resolve(start, 0, len));
+        let end = end.map_or(len, |end| resolve(end, 0, len));
+        while i < end {
+            indices.push(i as usize);
+            // A step past `i64::MAX` is past the end too
+            let Some(next) = i.checked_add(step) else {
+                break;
+            };
+            i = next;
+        }
+    } else {
+        let mut i = start.map_or(len - 1, |start| resolve(start, -1, len - 1));
+        let end = end.map_or(-1, |end| resolve(end, -1, len - 1));
+        while i > end {
+            indices.push(i as usize);
+            i += step;
+        }
+    }
+    indices
+}
+
+impl Transform {
+    fn expects(self) -> &'static str {
+        match self {
+            Transform::Length => "string, list or dictionary",
+            Transform::Hex | Transform::Base64 => "string",
+            Transform::Sum => "integer",
+        }
+    }
+}
+
+fn apply(transform: Transform, value: &Bencode) -> Option<Bencode> {
+    let len = |len: usize| Bencode::Integer(len as i64);
+    Some(match (transform, value) {
+        (Transform::Length, Bencode::String(s)) => len(s.len()),
+        (Transform::Length, Bencode::List(l)) => len(l.len()),
+        (Transform::Length, Bencode::Dictionary(d)) => len(d.len()),
+        (Transform::Hex, Bencode::String(s)) => hex::encode(s).into(),
+        (Transform::Base64, Bencode::String(s)) => base64(s).into(),
+        _ => return None,
+    })
+}
+
+/// An [`Error::InvalidType`] at a match's path.
+fn type_error(expected: &'static str, path: &str) -> Error {
+    Error::InvalidType {
+        path: path.trim_start_matches('.').into(),
+        expected,
+    }
+}
+
+/// Standard base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            out.push(match i <= chunk.len() {
+                true => ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char,
+                false => '=',
+            });
+        }
+    }
+    out
+}
+
+fn children<'a>(path: &str, value: &'a Bencode, out: &mut Vec<(String, &'a Bencode)>) {
+    match value {
+        Bencode::List(l) => {
+            for (i, child) in l.iter().enumerate() {
+                out.push((format!("{path}[{i}]"), child));
+            }
+        }
+        Bencode::Dictionary(d) => {
+            for (key, child) in d {
+                out.push((format!("{path}.{}", String::from_utf8_lossy(key)), child));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn descendants<'a>(path: String, value: &'a Bencode, out: &mut Vec<(String, &'a Bencode)>) {
+    let mut children_of = Vec::new();
+    children(&path, value, &mut children_of);
+    out.push((path, value));
+    for (path, child) in children_of {
+        descendants(path, child, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    const TORRENT: &[u8] =
+        b"d4:infod5:filesld6:lengthi1e4:pathl1:aeed6:lengthi2e4:pathl1:b1:ceee4:name1:xee";
+
+    fn select(query: &str) -> Vec<(String, String)> {
+        let value = decode(TORRENT).unwrap();
+        Query::parse(query)
+            .unwrap()
+            .select(&value)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.path, m.value.to_string()))
+            .collect()
+    }
+
+    fn pair(path: &str, value: &str) -> (String, String) {
+        (path.into(), value.into())
+    }
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(
+            select(".info.files[*].path"),
+            [
+                pair(".info.files[0].path", r#"["a"]"#),
+                pair(".info.files[1].path", r#"["b", "c"]"#)
+            ]
+        );
+        assert_eq!(select(".info.*.name"), []);
+        assert_eq!(
+            select("info.*"),
+            [
+                pair(".info.files", select(".info.files")[0].1.as_str()),
+                pair(".info.name", r#""x""#)
+            ]
+        );
+        assert_eq!(
+            select(".info.files[1].path[0]"),
+            [pair(".info.files[1].path[0]", r#""b""#)]
+        );
+        assert_eq!(select(".nope[*]"), []);
+    }
+
+    #[test]
+    fn recursive() {
+        assert_eq!(
+            select(".**.length"),
+            [
+                pair(".info.files[0].length", "1"),
+                pair(".info.files[1].length", "2")
+            ]
+        );
+        assert_eq!(select("**").len(), 13);
+        assert_eq!(select(".")[0].0, ".");
+    }
+
+    #[test]
+    fn slices() {
+        assert_eq!(
+            select(".info.files[-1].length"),
+            [pair(".info.files[1].length", "2")]
+        );
+        assert_eq!(select(".info.files[-3]"), []);
+        assert_eq!(
+            select(".info.files[0:1].length"),
+            [pair(".info.files[0].length", "1")]
+        );
+        assert_eq!(select(".info.files[:]").len(), 2);
+        assert_eq!(select(".info.files[5:]"), []);
+        assert_eq!(
+            select(".info.files[1].path[::-1]"),
+            [
+                pair(".info.files[1].path[1]", r#""c""#),
+                pair(".info.files[1].path[0]", r#""b""#)
+            ]
+        );
+
+        assert_eq!(slice_indices(5, None, None, 2), [0, 2, 4]);
+        assert_eq!(slice_indices(5, Some(-2), None, 1), [3, 4]);
+        assert_eq!(slice_indices(5, Some(1), Some(-1), 1), [1, 2, 3]);
+        assert_eq!(slice_indices(5, Some(-10), Some(10), 1), [0, 1, 2, 3, 4]);
+        assert_eq!(slice_indices(5, Some(3), Some(0), -1), [3, 2, 1]);
+        assert_eq!(slice_indices(5, Some(10), None, -2), [4, 2, 0]);
+        assert_eq!(slice_indices(0, None, None, -1), []);
+        assert_eq!(slice_indices(3, Some(1), None, i64::MAX), [1]);
+        assert_eq!(slice_indices(3, None, None, i64::MIN), [2]);
+        assert_eq!(
+            select(".info.files[1::9223372036854775807].length"),
+            [pair(".info.files[1].length", "2")]
+        );
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(
+            select(".info.files[?(@.length > 1)].path"),
+            [pair(".info.files[1].path", r#"["b", "c"]"#)]
+        );
+        assert_eq!(
+            select(".info.files[?(@.path[0] == \"a\")].length"),
+            [pair(".info.files[0].length", "1")]
+        );
+        assert_eq!(select(".info.files[?(@.length <= 2)]").len(), 2);
+        assert_eq!(select(".info.files[?(@.length != 1)]").len(), 1);
+        assert_eq!(select(".info.files[?(@.length >= \"1\")]"), []);
+        assert_eq!(select(".info[?(@[0].length)]").len(), 1);
+        assert_eq!(select(".info.files[?(@.nope)]"), []);
+        assert_eq!(select("[?(@ == \"x\")]"), []);
+        assert_eq!(
+            select(".info[?(@ == \"x\")]"),
+            [pair(".info.name", r#""x""#)]
+        );
+    }
+
+    #[test]
+    fn transforms() {
+        assert_eq!(
+            select(".info.files[*].length | sum"),
+            [pair(".info.files[*].length", "3")]
+        );
+        assert_eq!(
+            select(".info.files[*].path|length"),
+            [
+                pair(".info.files[0].path", "1"),
+                pair(".info.files[1].path", "2")
+            ]
+        );
+        assert_eq!(select(".info.name | hex"), [pair(".info.name", r#""78""#)]);
+        assert_eq!(select(".nope | sum"), [pair(".nope", "0")]);
+        assert_eq!(select(".info.files[?(@.path[0] == \"a|b\")] | length"), []);
+        assert_eq!(select(".**.path | length | sum")[0].1, "3");
+
+        let value = decode(TORRENT).unwrap();
+        assert_eq!(
+            Query::parse(".info.files[*].path | sum")
+                .unwrap()
+                .select(&value),
+            Err(Error::invalid_type("integer")
+                .at_key("path")
+                .at_index(0)
+                .at_key("files")
+                .at_key("info"))
+        );
+        assert_eq!(
+            Query::parse(". | hex").unwrap().select(&value),
+            Err(Error::invalid_type("string"))
+        );
+
+        for (input, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+        ] {
+            assert_eq!(base64(input.as_bytes()), encoded);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        for query in [
+            ".info..x",
+            "[x]",
+            ".a.",
+            "[0]b",
+            "[*",
+            "[?(@.a > x)]",
+            "[?(.a)]",
+            "[?(@.a == 1]",
+            ".a | nope",
+            ".a |",
+            "[1:2:0]",
+            "[1:2:3:4]",
+            "[a:]",
+        ] {
+            assert_eq!(
+                Query::parse(query),
+                Err(Error::InvalidPath(query.into())),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn single() {
+        for (query, single) in [
+            (".info.name", true),
+            (".info.files[-1].length", true),
+            (".", true),
+            (".info.files[*].length | sum", true),
+            (".info.files[*].length", false),
+            (".info.files[0:1]", false),
+            (".**", false),
+            (".info.files[?(@.length)]", false),
+        ] {
+            assert_eq!(Query::parse(query).unwrap().is_single(), single, "{query}");
+        }
+    }
+}
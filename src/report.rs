@@ -0,0 +1,359 @@
+//! Summaries of a torrent for people who don't use the command line, for
+//! `report`: its metadata, files, trackers and pieces gathered once and
+//! rendered as a self-contained HTML page, or as Markdown for issues and
+//! wikis.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    fmt::Write,
+};
+
+use bencode_decoder::{torrent, Bencode};
+
+use crate::progress;
+
+pub struct Report {
+    pub name: String,
+    pub infohash: String,
+    pub created: Option<String>,
+    pub created_by: Option<String>,
+    pub comment: Option<String>,
+    pub private: bool,
+    pub trackers: Vec<String>,
+    /// Paths joined with `/`, and lengths.
+    pub files: Vec<(String, i64)>,
+    pub piece_length: Option<i64>,
+    pub pieces: usize,
+    /// Pieces whose hash is the same as an earlier piece's, as with runs of
+    /// zeros.
+    pub repeated_pieces: usize,
+}
+
+impl Report {
+    /// Gathers the report for the torrent in `input`, decoded as `value`.
+    pub fn new(input: &[u8], value: &Bencode) -> Result<Self, Box<dyn Error>> {
+        let text = |path| match value.get_path(path) {
+            Ok(Some(Bencode::String(s))) => Some(String::from_utf8_lossy(s).into_owned()),
+            _ => None,
+        };
+        let pieces = torrent::pieces(value).unwrap_or_default();
+        let mut seen = HashSet::new();
+        let repeated_pieces = pieces.iter().filter(|hash| !seen.insert(*hash)).count();
+
+        Ok(Self {
+            name: text("info.name").unwrap_or_default(),
+            infohash: torrent::infohash(input)?
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect(),
+            created: torrent::creation_date(value)
+                .ok()
+                .flatten()
+                .map(|date| date.to_string()),
+            created_by: text("created by"),
+            comment: text("comment"),
+            private: matches!(
+                value.get_path("info.private"),
+                Ok(Some(Bencode::Integer(1)))
+            ),
+            trackers: torrent::trackers(value)
+                .iter()
+                .map(|tracker| String::from_utf8_lossy(tracker).into_owned())
+                .collect(),
+            files: torrent::files(value)?
+                .iter()
+                .map(|file| (torrent::sanitize_path(&file.path).join("/"), file.length))
+                .collect(),
+            piece_length: match value.get_path("info.piece length") {
+                Ok(Some(Bencode::Integer(length))) => Some(*length),
+                _ => None,
+            },
+            pieces: pieces.len(),
+            repeated_pieces,
+        })
+    }
+
+    pub fn total_size(&self) -> i64 {
+        self.files.iter().map(|(_, length)| length).sum()
+    }
+
+    /// The size of the last piece, which is usually short.
+    fn last_piece(&self) -> Option<i64> {
+        let piece_length = self.piece_length.filter(|&length| length > 0)?;
+        match self.total_size() % piece_length {
+            0 if self.pieces > 0 => Some(piece_length),
+            0 => None,
+            rest => Some(rest),
+        }
+    }
+
+    /// Labels and values of the summary table, including the piece
+    /// statistics.
+    fn summary(&self) -> Vec<(&'static str, String)> {
+        let mut rows = vec![("Infohash", self.infohash.clone())];
+        rows.extend(self.created.clone().map(|date| ("Created", date)));
+        rows.extend(self.created_by.clone().map(|by| ("Created by", by)));
+        rows.extend(self.comment.clone().map(|comment| ("Comment", comment)));
+        rows.push(("Private", if self.private { "yes" } else { "no" }.into()));
+        rows.push(("Files", self.files.len().to_string()));
+        rows.push(("Total size", size(self.total_size())));
+        if let Some(length) = self.piece_length {
+            rows.push(("Piece length", size(length)));
+        }
+        rows.push(("Pieces", self.pieces.to_string()));
+        if let Some(last) = self.last_piece() {
+            rows.push(("Last piece", size(last)));
+        }
+        if self.repeated_pieces > 0 {
+            rows.push(("Repeated piece hashes", self.repeated_pieces.to_string()));
+        }
+        rows
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let name = escape_html(&self.name);
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{name}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+             <h1>{name}</h1>\n<table>\n"
+        );
+        for (label, value) in self.summary() {
+            let _ = writeln!(
+                html,
+                "<tr><th>{label}</th><td>{}</td></tr>",
+                escape_html(&value)
+            );
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>Trackers</h2>\n");
+        match self.trackers.is_empty() {
+            true => html.push_str("<p>None; the torrent relies on DHT or peer exchange.</p>\n"),
+            false => {
+                html.push_str("<ul>\n");
+                for tracker in &self.trackers {
+                    let _ = writeln!(html, "<li><code>{}</code></li>", escape_html(tracker));
+                }
+                html.push_str("</ul>\n");
+            }
+        }
+
+        html.push_str("<h2>Files</h2>\n");
+        let mut tree = Tree::default();
+        for (path, length) in &self.files {
+            tree.insert(&path.split('/').collect::<Vec<_>>(), *length);
+        }
+        tree.write_html(&mut html);
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+impl Report {
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# {}\n\n| | |\n|---|---|\n", escape_markdown(&self.name));
+        for (label, value) in self.summary() {
+            let _ = writeln!(markdown, "| {label} | {} |", escape_markdown(&value));
+        }
+
+        markdown.push_str("\n## Trackers\n\n");
+        match self.trackers.is_empty() {
+            true => markdown.push_str("None; the torrent relies on DHT or peer exchange.\n"),
+            false => {
+                markdown.push_str("| Tracker |\n|---|\n");
+                for tracker in &self.trackers {
+                    let _ = writeln!(markdown, "| {} |", escape_markdown(tracker));
+                }
+            }
+        }
+
+        markdown.push_str("\n## Files\n\n| Path | Size |\n|---|---:|\n");
+        for (path, length) in &self.files {
+            let _ = writeln!(
+                markdown,
+                "| {} | {} |",
+                escape_markdown(path),
+                size(*length)
+            );
+        }
+        markdown
+    }
+}
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:60em;margin:2em auto;padding:0 1em}\
+table{border-collapse:collapse}th,td{text-align:left;padding:.2em 1em .2em 0;\
+vertical-align:top}td{word-break:break-all}ul{list-style:none;padding-left:1.2em}\
+.size{color:#666;margin-left:.5em}";
+
+/// The torrent's files as directories, for the file tree.
+#[derive(Default)]
+struct Tree {
+    directories: BTreeMap<String, Tree>,
+    /// In the torrent's order.
+    files: Vec<(String, i64)>,
+}
+
+impl Tree {
+    fn insert(&mut self, path: &[&str], length: i64) {
+        match path {
+            [] => {}
+            [name] => self.files.push((name.to_string(), length)),
+            [directory, rest @ ..] => self
+                .directories
+                .entry(directory.to_string())
+                .or_default()
+                .insert(rest, length),
+        }
+    }
+
+    fn size(&self) -> i64 {
+        let files: i64 = self.files.iter().map(|(_, length)| length).sum();
+        files + self.directories.values().map(Tree::size).sum::<i64>()
+    }
+
+    fn write_html(&self, html: &mut String) {
+        html.push_str("<ul>\n");
+        for (name, directory) in &self.directories {
+            let _ = writeln!(
+                html,
+                "<li>{}/<span class=\"size\">{}</span>",
+                escape_html(name),
+                size(directory.size())
+            );
+            directory.write_html(html);
+            html.push_str("</li>\n");
+        }
+        for (name, length) in &self.files {
+            let _ = writeln!(
+                html,
+                "<li>{}<span class=\"size\">{}</span></li>",
+                escape_html(name),
+                size(*length)
+            );
+        }
+        html.push_str("</ul>\n");
+    }
+}
+
+fn size(bytes: i64) -> String {
+    match bytes < 1024 {
+        true => format!("{bytes} B"),
+        false => format!("{} ({bytes} bytes)", progress::bytes(bytes as f64)),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Makes `text` safe inside a table cell: pipes and characters Markdown
+/// would format are escaped, and newlines become spaces.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '|' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use bencode_decoder::{
+        builder::{DictBuilder, ListBuilder},
+        encode,
+    };
+
+    use super::*;
+
+    fn example() -> Report {
+        let file = |path: &[&str], length| {
+            DictBuilder::new().insert("length", length).insert(
+                "path",
+                path.iter()
+                    .fold(ListBuilder::new(), |list, component| list.push(*component)),
+            )
+        };
+        let torrent = DictBuilder::new()
+            .insert("announce", "http://tracker/announce?a=1&b=2")
+            .insert("comment", "<b>hi</b>")
+            .insert(
+                "info",
+                DictBuilder::new()
+                    .insert(
+                        "files",
+                        ListBuilder::new()
+                            .push(file(&["docs", "a.txt"], 1500))
+                            .push(file(&["b.bin"], 600))
+                            .push(file(&["docs", "img", "c.png"], 20)),
+                    )
+                    .insert("name", "t")
+                    .insert("piece length", 1024)
+                    .insert("pieces", [[1u8; 20], [2; 20], [1; 20]].concat()),
+            )
+            .build();
+        Report::new(&encode(&torrent), &torrent).unwrap()
+    }
+
+    #[test]
+    fn pieces() {
+        let report = example();
+        assert_eq!(report.total_size(), 2120);
+        assert_eq!(report.pieces, 3);
+        assert_eq!(report.last_piece(), Some(72));
+        assert_eq!(report.repeated_pieces, 1);
+    }
+
+    #[test]
+    fn markdown() {
+        let markdown = example().to_markdown();
+        assert!(markdown.starts_with("# t\n\n| | |\n|---|---|\n| Infohash | "));
+        assert!(markdown.contains("| Comment | \\<b\\>hi\\</b\\> |\n"));
+        assert!(markdown
+            .contains("## Trackers\n\n| Tracker |\n|---|\n| http://tracker/announce?a=1&b=2 |\n"));
+        assert!(markdown.ends_with(
+            "## Files\n\n| Path | Size |\n|---|---:|\n\
+             | t/docs/a.txt | 1.5 KiB (1500 bytes) |\n\
+             | t/b.bin | 600 B |\n\
+             | t/docs/img/c.png | 20 B |\n"
+        ));
+        assert_eq!(escape_markdown("a|b\nc_d"), "a\\|b c\\_d");
+    }
+
+    #[test]
+    fn html() {
+        let html = example().to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h1>t</h1>"));
+        assert!(html.contains("<tr><th>Comment</th><td>&lt;b&gt;hi&lt;/b&gt;</td></tr>"));
+        assert!(html.contains("<li><code>http://tracker/announce?a=1&amp;b=2</code></li>"));
+        assert!(html.contains("<tr><th>Repeated piece hashes</th><td>1</td></tr>"));
+        assert!(html.contains(
+            "<li>docs/<span class=\"size\">1.5 KiB (1520 bytes)</span>\n<ul>\n\
+             <li>img/<span class=\"size\">20 B</span>\n<ul>\n\
+             <li>c.png<span class=\"size\">20 B</span></li>\n</ul>\n</li>\n\
+             <li>a.txt<span class=\"size\">1.5 KiB (1500 bytes)</span></li>\n</ul>\n</li>\n\
+             <li>b.bin<span class=\"size\">600 B</span></li>\n"
+        ));
+    }
+}
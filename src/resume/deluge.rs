@@ -0,0 +1,80 @@
+//! Deluge's `torrents.fastresume`, a dictionary from torrent id (the hex
+//! infohash) to that torrent's libtorrent resume data.
+//!
+//! Deluge keeps the rest of its session (`torrents.state`) as a Python pickle
+//! and labels in the JSON `label.conf`, so neither can be read here.
+
+use alloc::{string::String, vec::Vec};
+
+use super::libtorrent::FastResume;
+use crate::{convert::derive::dictionary, decode, Bencode, Error, FromBencode};
+
+#[derive(PartialEq, Debug)]
+pub struct DelugeResume {
+    pub id: String,
+    pub resume: FastResume,
+}
+
+/// Decodes `torrents.fastresume`, in id order.
+pub fn parse(input: &[u8]) -> Result<Vec<DelugeResume>, Error> {
+    let value = decode(input)?;
+
+    dictionary(&value)?
+        .iter()
+        .map(|(id, entry)| {
+            let id = String::from_utf8_lossy(id).into_owned();
+            let resume = match entry {
+                // Deluge bencodes each entry on its own before writing the
+                // file, so values are usually nested documents
+                Bencode::String(nested) => {
+                    decode(nested).and_then(|nested| FastResume::from_bencode(&nested))
+                }
+                entry => FastResume::from_bencode(entry),
+            };
+            Ok(DelugeResume {
+                resume: resume.map_err(|error| error.at_key(&id))?,
+                id,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::{DictBuilder, ListBuilder},
+        encode,
+    };
+    use alloc::string::ToString;
+
+    #[test]
+    fn nested_and_inline_entries() {
+        let resume = DictBuilder::new()
+            .insert("file_priority", ListBuilder::new().push(0).push(4))
+            .insert("paused", 1)
+            .insert("save_path", "/var/lib/deluge")
+            .build();
+        let input = DictBuilder::new()
+            .insert("aa", encode(&resume))
+            .insert("bb", resume)
+            .build();
+
+        let entries = parse(&encode(&input)).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, "aa");
+        assert_eq!(entries[0].resume, entries[1].resume);
+        assert_eq!(entries[1].resume.file_priority, [0, 4]);
+        assert!(entries[1].resume.paused);
+    }
+
+    #[test]
+    fn entry_errors_name_the_torrent() {
+        assert_eq!(
+            parse(b"d2:aa2:dee").unwrap_err().to_string(),
+            "missing key `save_path` at path `aa`"
+        );
+        assert_eq!(parse(b"d2:aa1:xe"), Err(Error::UnexpectedByte(b'x')));
+    }
+}
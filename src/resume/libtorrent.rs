@@ -0,0 +1,134 @@
+//! libtorrent `.fastresume` files, as written by `write_resume_data()` and
+//! used by qBittorrent, Deluge and others.
+
+use alloc::{string::String, vec::Vec};
+
+use super::timestamp;
+use crate::{
+    bitfield::Bitfield,
+    convert::derive::{dictionary, field, optional_bytes, optional_field},
+    decode,
+    timestamp::Timestamp,
+    Bencode, Error, FromBencode,
+};
+
+const FILE_FORMAT: &str = "libtorrent resume file";
+
+#[derive(PartialEq, Debug)]
+pub struct FastResume {
+    pub info_hash: Option<[u8; 20]>,
+    pub name: Option<String>,
+    pub save_path: String,
+    /// Pieces that have been downloaded and checked.
+    pub pieces: Bitfield,
+    /// Download priority of each file, from 0 (skip) to 7.
+    pub file_priority: Vec<u8>,
+    /// Tracker URLs grouped into tiers, as in `announce-list`.
+    pub trackers: Vec<Vec<String>>,
+    pub total_uploaded: i64,
+    pub total_downloaded: i64,
+    pub added_time: Option<Timestamp>,
+    pub completed_time: Option<Timestamp>,
+    pub paused: bool,
+}
+
+impl FromBencode for FastResume {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        let d = dictionary(value)?;
+
+        if let Some(format) = optional_field::<String>(d, "file-format")? {
+            if format != FILE_FORMAT {
+                return Err(Error::invalid_type(FILE_FORMAT).at_key("file-format"));
+            }
+        }
+
+        let pieces: Option<Vec<u8>> = optional_bytes(d, "pieces")?;
+
+        Ok(Self {
+            info_hash: optional_bytes(d, "info-hash")?,
+            name: optional_field(d, "name")?,
+            save_path: field(d, "save_path")?,
+            pieces: Bitfield::from_piece_bytes(&pieces.unwrap_or_default()),
+            file_priority: optional_field(d, "file_priority")?.unwrap_or_default(),
+            trackers: optional_field(d, "trackers")?.unwrap_or_default(),
+            total_uploaded: optional_field(d, "total_uploaded")?.unwrap_or(0),
+            total_downloaded: optional_field(d, "total_downloaded")?.unwrap_or(0),
+            added_time: timestamp(d, "added_time")?,
+            completed_time: timestamp(d, "completed_time")?,
+            paused: optional_field::<i64>(d, "paused")?.is_some_and(|paused| paused != 0),
+        })
+    }
+}
+
+/// Decodes a `.fastresume` file.
+pub fn parse(input: &[u8]) -> Result<FastResume, Error> {
+    FastResume::from_bencode(&decode(input)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::{DictBuilder, ListBuilder},
+        encode,
+    };
+    use alloc::{string::ToString, vec};
+
+    #[test]
+    fn parse_resume_data() {
+        let input = DictBuilder::new()
+            .insert("added_time", 1700000000)
+            .insert("file-format", FILE_FORMAT)
+            .insert("file_priority", ListBuilder::new().push(1).push(0).push(7))
+            .insert("info-hash", [b'a'; 20].to_vec())
+            .insert("paused", 1)
+            .insert("pieces", vec![1u8, 0, 3, 1])
+            .insert("save_path", "/data/foo")
+            .insert("total_uploaded", 42)
+            .insert(
+                "trackers",
+                ListBuilder::new()
+                    .push(ListBuilder::new().push("udp://a"))
+                    .push(ListBuilder::new().push("http://b")),
+            )
+            .build();
+        let input = encode(&input);
+        let resume = parse(&input).unwrap();
+
+        assert_eq!(resume.info_hash, Some([b'a'; 20]));
+        assert_eq!(resume.save_path, "/data/foo");
+        assert_eq!(resume.pieces.ones().collect::<Vec<_>>(), [0, 2, 3]);
+        assert_eq!(resume.pieces.len(), 4);
+        assert_eq!(resume.file_priority, [1, 0, 7]);
+        assert_eq!(resume.trackers, [["udp://a"], ["http://b"]]);
+        assert_eq!(resume.total_uploaded, 42);
+        assert_eq!(resume.total_downloaded, 0);
+        assert_eq!(resume.added_time, Timestamp::from_unix(1700000000));
+        assert_eq!(resume.completed_time, None);
+        assert!(resume.paused);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse(b"de").unwrap_err().to_string(),
+            "missing key `save_path`"
+        );
+        assert_eq!(
+            parse(b"d11:file-format4:nope9:save_path0:e").unwrap_err(),
+            Error::invalid_type(FILE_FORMAT).at_key("file-format")
+        );
+        assert_eq!(
+            parse(b"d10:added_timei-1e9:save_path0:e")
+                .unwrap_err()
+                .to_string(),
+            "expected Unix timestamp in seconds at path `added_time`"
+        );
+        assert_eq!(
+            parse(b"d13:file_priorityli300ee9:save_path0:e")
+                .unwrap_err()
+                .to_string(),
+            "expected integer fitting u8 at path `file_priority[0]`"
+        );
+    }
+}
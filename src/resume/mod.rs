@@ -0,0 +1,17 @@
+//! Typed views of the resume data BitTorrent clients store alongside their
+//! torrents.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use crate::{convert::derive::optional_field, timestamp::Timestamp, Bencode, Error};
+
+pub mod deluge;
+pub mod libtorrent;
+pub mod qbittorrent;
+pub mod rtorrent;
+pub mod transmission;
+
+/// Reads a timestamp field where, as clients write it, 0 means unset.
+fn timestamp(d: &BTreeMap<Vec<u8>, Bencode>, key: &str) -> Result<Option<Timestamp>, Error> {
+    Ok(optional_field::<Timestamp>(d, key)?.filter(|time| time.unix() != 0))
+}
@@ -0,0 +1,135 @@
+//! qBittorrent's `BT_backup` directory, where each torrent is stored as a
+//! `<infohash>.torrent` file next to a `<infohash>.fastresume` file carrying
+//! libtorrent resume data plus `qBt-*` keys.
+
+use alloc::{string::String, vec::Vec};
+
+use super::libtorrent::FastResume;
+use crate::{
+    convert::derive::{dictionary, optional_field},
+    decode, Bencode, Error, FromBencode,
+};
+
+#[derive(PartialEq, Debug)]
+pub struct QbtResume {
+    pub resume: FastResume,
+    /// Name set in qBittorrent, overriding the metainfo name.
+    pub name: Option<String>,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl FromBencode for QbtResume {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        let d = dictionary(value)?;
+
+        Ok(Self {
+            resume: FastResume::from_bencode(value)?,
+            name: optional_field::<String>(d, "qBt-name")?.filter(|name| !name.is_empty()),
+            category: optional_field::<String>(d, "qBt-category")?
+                .filter(|category| !category.is_empty()),
+            tags: optional_field(d, "qBt-tags")?.unwrap_or_default(),
+        })
+    }
+}
+
+/// Decodes a qBittorrent `.fastresume` file.
+pub fn parse(input: &[u8]) -> Result<QbtResume, Error> {
+    QbtResume::from_bencode(&decode(input)?)
+}
+
+/// The files stored for one torrent, either of which may be missing.
+#[cfg(feature = "std")]
+#[derive(PartialEq, Debug)]
+pub struct BackupEntry {
+    /// The shared file stem, normally the hex infohash.
+    pub hash: String,
+    pub torrent: Option<Vec<u8>>,
+    pub fastresume: Option<Vec<u8>>,
+}
+
+/// Reads a `BT_backup` directory, pairing `.torrent` and `.fastresume` files
+/// by stem. Other files are ignored. Entries are sorted by hash.
+#[cfg(feature = "std")]
+pub fn read_backup_dir(dir: &std::path::Path) -> std::io::Result<Vec<BackupEntry>> {
+    use std::{collections::BTreeMap, fs};
+
+    let mut entries: BTreeMap<String, BackupEntry> = BTreeMap::new();
+
+    for file in fs::read_dir(dir)? {
+        let path = file?.path();
+        let (Some(stem), Some(extension)) = (path.file_stem(), path.extension()) else {
+            continue;
+        };
+        let is_torrent = match extension.to_str() {
+            Some("torrent") => true,
+            Some("fastresume") => false,
+            _ => continue,
+        };
+
+        let hash = stem.to_string_lossy().into_owned();
+        let entry = entries.entry(hash.clone()).or_insert_with(|| BackupEntry {
+            hash,
+            torrent: None,
+            fastresume: None,
+        });
+        let contents = Some(fs::read(&path)?);
+        match is_torrent {
+            true => entry.torrent = contents,
+            false => entry.fastresume = contents,
+        }
+    }
+
+    Ok(entries.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::{DictBuilder, ListBuilder},
+        encode,
+    };
+
+    fn fastresume() -> Vec<u8> {
+        encode(
+            &DictBuilder::new()
+                .insert("qBt-category", "linux")
+                .insert("qBt-name", "")
+                .insert("qBt-tags", ListBuilder::new().push("iso").push("x86"))
+                .insert("save_path", "/downloads")
+                .build(),
+        )
+    }
+
+    #[test]
+    fn parse_qbittorrent_keys() {
+        let resume = parse(&fastresume()).unwrap();
+
+        assert_eq!(resume.category.as_deref(), Some("linux"));
+        assert_eq!(resume.name, None);
+        assert_eq!(resume.tags, ["iso", "x86"]);
+        assert_eq!(resume.resume.save_path, "/downloads");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn pair_backup_files() {
+        let dir = std::env::temp_dir().join(format!("bt_backup_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("aa.torrent"), b"de").unwrap();
+        std::fs::write(dir.join("aa.fastresume"), fastresume()).unwrap();
+        std::fs::write(dir.join("bb.fastresume"), b"de").unwrap();
+        std::fs::write(dir.join("queue"), b"aa\n").unwrap();
+
+        let entries = read_backup_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].hash, "aa");
+        assert_eq!(entries[0].torrent.as_deref(), Some(&b"de"[..]));
+        assert_eq!(entries[0].fastresume, Some(fastresume()));
+        assert_eq!(entries[1].hash, "bb");
+        assert_eq!(entries[1].torrent, None);
+    }
+}
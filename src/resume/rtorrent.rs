@@ -0,0 +1,263 @@
+//! rTorrent's session directory, where each torrent is stored as
+//! `<HASH>.torrent` next to `<HASH>.torrent.rtorrent` (rTorrent's own state)
+//! and `<HASH>.torrent.libtorrent_resume` (libtorrent-rakshasa resume data).
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use super::timestamp;
+use crate::{
+    bitfield::Bitfield,
+    convert::derive::{dictionary, field, optional_field},
+    decode,
+    timestamp::Timestamp,
+    Bencode, Error, FromBencode,
+};
+
+/// The `.torrent.rtorrent` file.
+#[derive(PartialEq, Debug)]
+pub struct RtorrentState {
+    pub directory: String,
+    pub started: bool,
+    pub complete: bool,
+    pub chunks_done: i64,
+    pub chunks_wanted: i64,
+    /// 0 (off) to 3 (high).
+    pub priority: i64,
+    pub total_uploaded: i64,
+    pub total_downloaded: i64,
+    pub views: Vec<String>,
+    /// Values of `custom1` to `custom5` under those names, plus the entries
+    /// of the `custom` dictionary. Empty values are left out.
+    pub custom: BTreeMap<String, String>,
+    pub started_at: Option<Timestamp>,
+    pub finished_at: Option<Timestamp>,
+}
+
+impl FromBencode for RtorrentState {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        let d = dictionary(value)?;
+        let flag = |key| Ok::<_, Error>(optional_field::<i64>(d, key)?.is_some_and(|n| n != 0));
+
+        let mut custom: BTreeMap<String, String> = optional_field(d, "custom")?.unwrap_or_default();
+        for key in ["custom1", "custom2", "custom3", "custom4", "custom5"] {
+            if let Some(value) = optional_field::<String>(d, key)? {
+                custom.insert(key.into(), value);
+            }
+        }
+        custom.retain(|_, value| !value.is_empty());
+
+        Ok(Self {
+            directory: field(d, "directory")?,
+            started: flag("state")?,
+            complete: flag("complete")?,
+            chunks_done: optional_field(d, "chunks_done")?.unwrap_or(0),
+            chunks_wanted: optional_field(d, "chunks_wanted")?.unwrap_or(0),
+            priority: optional_field(d, "priority")?.unwrap_or(2),
+            total_uploaded: optional_field(d, "total_uploaded")?.unwrap_or(0),
+            total_downloaded: optional_field(d, "total_downloaded")?.unwrap_or(0),
+            views: optional_field(d, "views")?.unwrap_or_default(),
+            custom,
+            started_at: timestamp(d, "timestamp.started")?,
+            finished_at: timestamp(d, "timestamp.finished")?,
+        })
+    }
+}
+
+/// Which chunks have been downloaded.
+#[derive(PartialEq, Debug)]
+pub enum Chunks {
+    /// One bit per chunk, most significant bit first.
+    Bitfield(Vec<u8>),
+    /// rTorrent writes the chunk count instead of a bitfield once every
+    /// chunk is done.
+    AllDone(i64),
+}
+
+impl Chunks {
+    /// The chunks of a torrent with `chunk_count` chunks.
+    pub fn bitfield(&self, chunk_count: usize) -> Result<Bitfield, Error> {
+        match self {
+            Chunks::Bitfield(bits) => Bitfield::from_bytes(bits, chunk_count),
+            Chunks::AllDone(_) => Ok(Bitfield::full(chunk_count)),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct ResumeFile {
+    /// 0 (off), 1 (normal) or 2 (high).
+    pub priority: i64,
+    pub completed: i64,
+    pub mtime: i64,
+}
+
+impl FromBencode for ResumeFile {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        let d = dictionary(value)?;
+        Ok(Self {
+            priority: optional_field(d, "priority")?.unwrap_or(1),
+            completed: optional_field(d, "completed")?.unwrap_or(0),
+            mtime: optional_field(d, "mtime")?.unwrap_or(0),
+        })
+    }
+}
+
+/// The `.torrent.libtorrent_resume` file.
+#[derive(PartialEq, Debug)]
+pub struct LibtorrentResume {
+    pub chunks: Option<Chunks>,
+    pub files: Vec<ResumeFile>,
+    /// Whether each tracker URL is enabled.
+    pub trackers: BTreeMap<String, bool>,
+}
+
+impl FromBencode for LibtorrentResume {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        let d = dictionary(value)?;
+
+        let chunks = match d.get(&b"bitfield"[..]) {
+            None => None,
+            Some(Bencode::String(bits)) => Some(Chunks::Bitfield(bits.clone())),
+            Some(Bencode::Integer(count)) => Some(Chunks::AllDone(*count)),
+            Some(_) => {
+                return Err(Error::invalid_type("string or integer").at_key("bitfield"));
+            }
+        };
+
+        let mut trackers = BTreeMap::new();
+        if let Some(Bencode::Dictionary(entries)) = d.get(&b"trackers"[..]) {
+            for (url, tracker) in entries {
+                let url = String::from_utf8_lossy(url).into_owned();
+                let enabled = match tracker {
+                    Bencode::Dictionary(tracker) => {
+                        optional_field::<i64>(tracker, "enabled")
+                            .map_err(|error| error.at_key(&url).at_key("trackers"))?
+                            != Some(0)
+                    }
+                    _ => true,
+                };
+                trackers.insert(url, enabled);
+            }
+        }
+
+        Ok(Self {
+            chunks,
+            files: optional_field(d, "files")?.unwrap_or_default(),
+            trackers,
+        })
+    }
+}
+
+pub fn parse_state(input: &[u8]) -> Result<RtorrentState, Error> {
+    RtorrentState::from_bencode(&decode(input)?)
+}
+
+pub fn parse_resume(input: &[u8]) -> Result<LibtorrentResume, Error> {
+    LibtorrentResume::from_bencode(&decode(input)?)
+}
+
+/// The files stored for one torrent, any of which may be missing.
+#[cfg(feature = "std")]
+#[derive(PartialEq, Debug)]
+pub struct SessionEntry {
+    pub hash: String,
+    pub torrent: Option<Vec<u8>>,
+    pub state: Option<Vec<u8>>,
+    pub resume: Option<Vec<u8>>,
+}
+
+/// Reads a session directory, grouping files by hash. Other files are
+/// ignored. Entries are sorted by hash.
+#[cfg(feature = "std")]
+pub fn read_session_dir(dir: &std::path::Path) -> std::io::Result<Vec<SessionEntry>> {
+    use std::fs;
+
+    let mut entries: BTreeMap<String, SessionEntry> = BTreeMap::new();
+
+    for file in fs::read_dir(dir)? {
+        let path = file?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Some((hash, suffix)) = file_name.split_once('.') else {
+            continue;
+        };
+        if !matches!(
+            suffix,
+            "torrent" | "torrent.rtorrent" | "torrent.libtorrent_resume"
+        ) {
+            continue;
+        }
+
+        let entry = entries.entry(hash.into()).or_insert_with(|| SessionEntry {
+            hash: hash.into(),
+            torrent: None,
+            state: None,
+            resume: None,
+        });
+        let contents = Some(fs::read(&path)?);
+        match suffix {
+            "torrent" => entry.torrent = contents,
+            "torrent.rtorrent" => entry.state = contents,
+            _ => entry.resume = contents,
+        }
+    }
+
+    Ok(entries.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builder::DictBuilder, encode};
+
+    #[test]
+    fn state() {
+        let input = DictBuilder::new()
+            .insert("chunks_done", 10)
+            .insert("custom", DictBuilder::new().insert("addtime", "1700000000"))
+            .insert("custom1", "linux")
+            .insert("custom2", "")
+            .insert("directory", "/srv/dl")
+            .insert("state", 1)
+            .insert("timestamp.finished", 0)
+            .build();
+        let state = parse_state(&encode(&input)).unwrap();
+
+        assert_eq!(state.directory, "/srv/dl");
+        assert!(state.started);
+        assert!(!state.complete);
+        assert_eq!(state.chunks_done, 10);
+        assert_eq!(state.priority, 2);
+        assert_eq!(state.finished_at, None);
+        assert_eq!(
+            state.custom.into_iter().collect::<Vec<_>>(),
+            [
+                ("addtime".into(), "1700000000".into()),
+                ("custom1".into(), "linux".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn resume_bitfield_quirks() {
+        let resume = parse_resume(b"d8:bitfieldi42e5:filesld9:completedi3e8:priorityi0eee8:trackersd5:udp:ad7:enabledi0ee6:http:bdeee").unwrap();
+        assert_eq!(resume.chunks, Some(Chunks::AllDone(42)));
+        assert_eq!(resume.files[0].completed, 3);
+        assert_eq!(resume.files[0].priority, 0);
+        assert!(!resume.trackers["udp:a"]);
+        assert!(resume.trackers["http:b"]);
+
+        let resume = parse_resume(b"d8:bitfield2:\xf0\x01e").unwrap();
+        assert_eq!(resume.chunks, Some(Chunks::Bitfield(vec![0xf0, 0x01])));
+        let chunks = resume.chunks.unwrap();
+        assert_eq!(chunks.bitfield(16).unwrap().count_ones(), 5);
+        assert!(chunks.bitfield(12).is_err());
+        assert!(Chunks::AllDone(3).bitfield(3).unwrap().is_complete());
+
+        assert_eq!(
+            parse_resume(b"d8:bitfieldlee"),
+            Err(Error::invalid_type("string or integer").at_key("bitfield"))
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Transmission's `.resume` files, one per torrent in the `resume`
+//! directory of its config dir.
+
+use alloc::{string::String, vec::Vec};
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use super::timestamp;
+use crate::{
+    bitfield::Bitfield,
+    convert::derive::{dictionary, field, optional_field},
+    decode,
+    timestamp::Timestamp,
+    Bencode, Error, FromBencode,
+};
+
+/// Whether each piece or block is present.
+#[derive(PartialEq, Debug)]
+pub enum Have {
+    All,
+    None,
+    /// One bit per piece or block, most significant bit first.
+    Bitfield(Vec<u8>),
+}
+
+impl FromBencode for Have {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        match value {
+            Bencode::String(s) if s == b"all" => Ok(Have::All),
+            Bencode::String(s) if s == b"none" => Ok(Have::None),
+            Bencode::String(bits) => Ok(Have::Bitfield(bits.clone())),
+            _ => Err(Error::invalid_type("bitfield, \"all\" or \"none\"")),
+        }
+    }
+}
+
+impl Have {
+    /// The pieces or blocks of a torrent with `len` of them.
+    pub fn bitfield(&self, len: usize) -> Result<Bitfield, Error> {
+        match self {
+            Have::All => Ok(Bitfield::full(len)),
+            Have::None => Ok(Bitfield::new(len)),
+            Have::Bitfield(bits) => Bitfield::from_bytes(bits, len),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Progress {
+    /// Written by Transmission 4; older versions only write `blocks`, or
+    /// `have = "all"` for finished torrents.
+    pub pieces: Option<Have>,
+    pub blocks: Option<Have>,
+}
+
+impl FromBencode for Progress {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        let d = dictionary(value)?;
+        let have_all = optional_field::<String>(d, "have")?.is_some_and(|have| have == "all");
+
+        Ok(Self {
+            pieces: optional_field(d, "pieces")?.or(have_all.then_some(Have::All)),
+            blocks: optional_field(d, "blocks")?,
+        })
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum IdleMode {
+    /// Use the session-wide idle limit.
+    Global,
+    /// Use this torrent's `idle_limit`.
+    Single,
+    Unlimited,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct TransmissionResume {
+    pub destination: String,
+    pub incomplete_dir: Option<String>,
+    pub name: Option<String>,
+    pub progress: Option<Progress>,
+    /// Peers from the compact `peers` list. The `peers2` records of newer
+    /// versions are raw in-memory structs and aren't decoded.
+    pub peers: Vec<SocketAddrV4>,
+    pub idle_mode: IdleMode,
+    /// Minutes without activity before seeding stops.
+    pub idle_limit: i64,
+    pub uploaded: i64,
+    pub downloaded: i64,
+    pub corrupt: i64,
+    pub added_date: Option<Timestamp>,
+    pub done_date: Option<Timestamp>,
+    pub paused: bool,
+}
+
+impl FromBencode for TransmissionResume {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        let d = dictionary(value)?;
+
+        let peers = match d.get(&b"peers"[..]) {
+            None => Vec::new(),
+            Some(Bencode::String(compact)) if compact.len() % 6 == 0 => compact
+                .chunks(6)
+                .map(|peer| {
+                    let ip = Ipv4Addr::new(peer[0], peer[1], peer[2], peer[3]);
+                    SocketAddrV4::new(ip, u16::from_be_bytes([peer[4], peer[5]]))
+                })
+                .collect(),
+            Some(_) => return Err(Error::invalid_type("compact peer list").at_key("peers")),
+        };
+
+        let (idle_mode, idle_limit) = match d.get(&b"idle-limit"[..]) {
+            None => (IdleMode::Global, 0),
+            Some(limit) => {
+                let limit = dictionary(limit).map_err(|error| error.at_key("idle-limit"))?;
+                let at = |error: Error| error.at_key("idle-limit");
+                let mode = match optional_field::<i64>(limit, "idle-mode").map_err(at)? {
+                    None | Some(0) => IdleMode::Global,
+                    Some(1) => IdleMode::Single,
+                    Some(2) => IdleMode::Unlimited,
+                    Some(_) => {
+                        return Err(at(
+                            Error::invalid_type("idle mode 0, 1 or 2").at_key("idle-mode")
+                        ));
+                    }
+                };
+                let minutes = optional_field(limit, "idle-limit").map_err(at)?;
+                (mode, minutes.unwrap_or(0))
+            }
+        };
+
+        Ok(Self {
+            destination: field(d, "destination")?,
+            incomplete_dir: optional_field(d, "incomplete-dir")?,
+            name: optional_field(d, "name")?,
+            progress: optional_field(d, "progress")?,
+            peers,
+            idle_mode,
+            idle_limit,
+            uploaded: optional_field(d, "uploaded")?.unwrap_or(0),
+            downloaded: optional_field(d, "downloaded")?.unwrap_or(0),
+            corrupt: optional_field(d, "corrupt")?.unwrap_or(0),
+            added_date: timestamp(d, "added-date")?,
+            done_date: timestamp(d, "done-date")?,
+            paused: optional_field::<i64>(d, "paused")?.is_some_and(|paused| paused != 0),
+        })
+    }
+}
+
+pub fn parse(input: &[u8]) -> Result<TransmissionResume, Error> {
+    TransmissionResume::from_bencode(&decode(input)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builder::DictBuilder, encode};
+    use alloc::{string::ToString, vec};
+
+    #[test]
+    fn parse_resume() {
+        let input = DictBuilder::new()
+            .insert("destination", "/var/lib/transmission/Downloads")
+            .insert(
+                "idle-limit",
+                DictBuilder::new()
+                    .insert("idle-limit", 30)
+                    .insert("idle-mode", 1),
+            )
+            .insert("paused", 0)
+            .insert("peers", vec![10u8, 0, 0, 1, 0x1a, 0xe1])
+            .insert(
+                "progress",
+                DictBuilder::new()
+                    .insert("blocks", "all")
+                    .insert("have", "all"),
+            )
+            .insert("uploaded", 1024)
+            .build();
+        let resume = parse(&encode(&input)).unwrap();
+
+        assert_eq!(resume.destination, "/var/lib/transmission/Downloads");
+        assert_eq!(resume.idle_mode, IdleMode::Single);
+        assert_eq!(resume.idle_limit, 30);
+        assert!(!resume.paused);
+        assert_eq!(resume.peers, ["10.0.0.1:6881".parse().unwrap()]);
+        assert_eq!(
+            resume.progress,
+            Some(Progress {
+                pieces: Some(Have::All),
+                blocks: Some(Have::All)
+            })
+        );
+        assert_eq!(resume.uploaded, 1024);
+        assert_eq!(resume.added_date, None);
+    }
+
+    #[test]
+    fn progress_bitfields() {
+        let progress = crate::decode(b"d6:blocks4:none6:pieces1:\xa0e").unwrap();
+        assert_eq!(
+            Progress::from_bencode(&progress),
+            Ok(Progress {
+                pieces: Some(Have::Bitfield(vec![0xa0])),
+                blocks: Some(Have::None)
+            })
+        );
+    }
+
+    #[test]
+    fn have_bitfield() {
+        assert_eq!(Have::None.bitfield(3).unwrap().count_ones(), 0);
+        assert_eq!(
+            Have::Bitfield(vec![0xa0])
+                .bitfield(3)
+                .unwrap()
+                .completion(3),
+            200.0 / 3.0
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse(b"d11:destination0:10:idle-limitd9:idle-modei7eee")
+                .unwrap_err()
+                .to_string(),
+            "expected idle mode 0, 1 or 2 at path `idle-limit.idle-mode`"
+        );
+        assert_eq!(
+            parse(b"d11:destination0:5:peers5:abcdee"),
+            Err(Error::invalid_type("compact peer list").at_key("peers"))
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Writing changed files back in place, for `edit` and `normalize`. With
+//! `--output-file` the result goes there instead and the input is left
+//! alone. With `--dry-run` nothing is written and the summary says what
+//! would change.
+
+use std::{
+    env,
+    error::Error,
+    fs, io,
+    path::Path,
+    process::{self, Command},
+};
+
+use bencode_decoder::{
+    encode,
+    json::{from_json, to_json_pretty},
+    torrent, Bencode,
+};
+
+use crate::{cli::Matches, output};
+
+/// What happened, or would happen, to one file.
+pub struct Change {
+    pub after: Vec<u8>,
+    /// Lines describing the individual changes.
+    pub details: Vec<String>,
+}
+
+/// Writes `change` over `file`, or to the `--output-file`, unless it's a dry
+/// run or nothing changed, and returns the summary. The output file is
+/// written even when nothing changed, so it always holds the result.
+pub fn apply(
+    matches: &Matches,
+    file: &Path,
+    original: &[u8],
+    change: Change,
+) -> io::Result<String> {
+    let dry_run = matches.flag("dry-run");
+    let sizes = format!("{} -> {} bytes", original.len(), change.after.len());
+
+    let mut summary = match (matches.value("output-file"), dry_run) {
+        (Some(target), true) => format!("would write {} to {target}: {sizes}", file.display()),
+        (Some(target), false) => {
+            output::write_atomic(target.as_ref(), &change.after)?;
+            format!("wrote {} to {target}: {sizes}", file.display())
+        }
+        (None, _) if original == change.after => format!("{}: unchanged", file.display()),
+        (None, true) => format!("would rewrite {}: {sizes}", file.display()),
+        (None, false) => {
+            output::write_atomic(file, &change.after)?;
+            format!("rewrote {}: {sizes}", file.display())
+        }
+    };
+    for detail in &change.details {
+        summary.push_str("\n  ");
+        summary.push_str(detail);
+    }
+    Ok(summary)
+}
+
+/// A note for the summary when re-encoding `value` changes the bytes of the
+/// `info` dictionary in `original`, and so the infohash.
+pub fn infohash_note(original: &[u8], value: &Bencode) -> Option<String> {
+    let raw = torrent::raw_info(original).ok()?;
+    let info = value.get_path("info").ok()??;
+    (raw != encode(info)).then(|| "info dictionary re-encoded, the infohash changes".into())
+}
+
+/// Opens `value` as pretty JSON in `$VISUAL` or `$EDITOR` and reads back the
+/// result. Fails before opening the editor if the document wouldn't survive
+/// the conversion. If the edited JSON is invalid the file is kept, so the
+/// edits aren't lost.
+pub fn edit_as_json(value: &Bencode) -> Result<Bencode, Box<dyn Error>> {
+    let json = to_json_pretty(value);
+    if from_json(&json).as_ref() != Ok(value) {
+        return Err(
+            "document can't be edited as JSON: it has binary dictionary keys or \
+                    dictionaries that look like {\"hex\": ...} strings"
+                .into(),
+        );
+    }
+
+    let path = env::temp_dir().join(format!("bencode-edit-{}.json", process::id()));
+    fs::write(&path, json + "\n")?;
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    // Through the shell, so that editors given with arguments work.
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(&path)
+        .status()?;
+    if !status.success() {
+        fs::remove_file(&path)?;
+        return Err(format!("{editor} exited with {status}, nothing changed").into());
+    }
+
+    let edited = fs::read_to_string(&path)?;
+    match from_json(&edited) {
+        Ok(value) => {
+            fs::remove_file(&path)?;
+            Ok(value)
+        }
+        Err(error) => Err(format!("{error}; the edited JSON is kept in {}", path.display()).into()),
+    }
+}
+
+/// An encoded value for display, shortened if long.
+pub fn excerpt(raw: &[u8]) -> String {
+    const LIMIT: usize = 40;
+    let text = String::from_utf8_lossy(&raw[..raw.len().min(LIMIT)])
+        .escape_debug()
+        .to_string();
+    match raw.len() > LIMIT {
+        true => format!("{text}..."),
+        false => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_run_leaves_file() {
+        let file = std::env::temp_dir().join(format!("rewrite-test-{}", std::process::id()));
+        std::fs::write(&file, b"d1:bi1e1:ai2ee").unwrap();
+        let change = || Change {
+            after: b"d1:ai2e1:bi1ee".to_vec(),
+            details: vec!["sorted keys".into()],
+        };
+
+        let command = crate::cli::find("normalize").unwrap();
+        let dry_run = crate::cli::parse(command, &["--dry-run".into(), "x".into()]).unwrap();
+        let summary = apply(&dry_run, &file, b"d1:bi1e1:ai2ee", change()).unwrap();
+        assert_eq!(
+            summary,
+            format!(
+                "would rewrite {}: 14 -> 14 bytes\n  sorted keys",
+                file.display()
+            )
+        );
+        assert_eq!(std::fs::read(&file).unwrap(), b"d1:bi1e1:ai2ee");
+
+        let matches = crate::cli::parse(command, &["x".into()]).unwrap();
+        apply(&matches, &file, b"d1:bi1e1:ai2ee", change()).unwrap();
+        assert_eq!(std::fs::read(&file).unwrap(), b"d1:ai2e1:bi1ee");
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(excerpt(b"4:a\nbc"), "4:a\\nbc");
+        assert_eq!(excerpt(&[b'x'; 50]), format!("{}...", "x".repeat(40)));
+    }
+
+    #[test]
+    fn output_file_leaves_input() {
+        let dir = std::env::temp_dir().join(format!("rewrite-output-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (file, out) = (dir.join("in.torrent"), dir.join("out.torrent"));
+        std::fs::write(&file, b"d1:ai1ee").unwrap();
+        let change = |after: &[u8]| Change {
+            after: after.to_vec(),
+            details: Vec::new(),
+        };
+
+        let command = crate::cli::find("edit").unwrap();
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let out_arg = out.to_str().unwrap();
+        let dry_run = crate::cli::parse(command, &args(&["-n", "-o", out_arg, "x"])).unwrap();
+        apply(&dry_run, &file, b"d1:ai1ee", change(b"d1:ai2ee")).unwrap();
+        assert!(!out.exists());
+
+        let matches = crate::cli::parse(command, &args(&["x", "--output-file", out_arg])).unwrap();
+        let summary = apply(&matches, &file, b"d1:ai1ee", change(b"d1:ai2ee")).unwrap();
+        assert_eq!(
+            summary,
+            format!("wrote {} to {out_arg}: 8 -> 8 bytes", file.display())
+        );
+        assert_eq!(std::fs::read(&out).unwrap(), b"d1:ai2ee");
+        assert_eq!(std::fs::read(&file).unwrap(), b"d1:ai1ee");
+
+        // Written even when unchanged, so the output always holds the result
+        std::fs::remove_file(&out).unwrap();
+        apply(&matches, &file, b"d1:ai1ee", change(b"d1:ai1ee")).unwrap();
+        assert_eq!(std::fs::read(&out).unwrap(), b"d1:ai1ee");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
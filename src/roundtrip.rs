@@ -0,0 +1,183 @@
+//! Checking whether a document survives decoding and re-encoding byte for
+//! byte. Only dictionaries can differ: the decoder rejects every other
+//! non-canonical form, but accepts keys out of order and repeated keys,
+//! which re-encoding sorts and drops. Run this before hashing a torrent to
+//! learn whether its infohash depends on keeping the original bytes.
+
+use alloc::{collections::BTreeSet, format, string::String, vec::Vec};
+use core::fmt::Display;
+
+use crate::{
+    encode,
+    span::{parse_spanned, Node, NodeKind},
+    Error,
+};
+
+/// The outcome of [`check_roundtrip`].
+#[derive(PartialEq, Debug)]
+pub struct Roundtrip {
+    /// The document re-encoded canonically.
+    pub encoded: Vec<u8>,
+    /// The first offset where `encoded` and the input differ, or `None` if
+    /// they're the same.
+    pub first_difference: Option<usize>,
+    /// Every place re-encoding changes, in input order.
+    pub divergences: Vec<Divergence>,
+}
+
+impl Roundtrip {
+    /// Whether re-encoding gives back the input unchanged.
+    pub fn is_exact(&self) -> bool {
+        self.first_difference.is_none()
+    }
+}
+
+/// A dictionary key re-encoding moves or drops.
+#[derive(PartialEq, Debug)]
+pub struct Divergence {
+    /// Path of the dictionary, like `info.files[0]`; empty for the root.
+    pub path: String,
+    pub key: Vec<u8>,
+    /// Offset of the key in the input.
+    pub offset: usize,
+    pub kind: DivergenceKind,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum DivergenceKind {
+    /// The key sorts before `previous`, a key earlier in the dictionary.
+    Unsorted { previous: Vec<u8> },
+    /// The key appeared before; re-encoding keeps only the last value.
+    Duplicate,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let path = match self.path.is_empty() {
+            true => ".",
+            false => &self.path,
+        };
+        write!(
+            f,
+            "{path}: key `{}` at byte {} ",
+            self.key.escape_ascii(),
+            self.offset
+        )?;
+        match &self.kind {
+            DivergenceKind::Unsorted { previous } => {
+                write!(f, "sorts before `{}`", previous.escape_ascii())
+            }
+            DivergenceKind::Duplicate => f.write_str("is repeated"),
+        }
+    }
+}
+
+/// Decodes `input`, encodes it again and compares the two.
+pub fn check_roundtrip(input: &[u8]) -> Result<Roundtrip, Error> {
+    let root = parse_spanned(input)?;
+    let encoded = encode(&root.to_bencode());
+    let first_difference = match input.iter().zip(&encoded).position(|(a, b)| a != b) {
+        Some(offset) => Some(offset),
+        None => (input.len() != encoded.len()).then_some(input.len().min(encoded.len())),
+    };
+
+    let mut divergences = Vec::new();
+    if first_difference.is_some() {
+        walk(&root, String::new(), &mut divergences);
+    }
+    Ok(Roundtrip {
+        encoded,
+        first_difference,
+        divergences,
+    })
+}
+
+fn walk(node: &Node<'_>, path: String, divergences: &mut Vec<Divergence>) {
+    match node.kind() {
+        NodeKind::String(_) | NodeKind::Integer(_) => {}
+        NodeKind::List(list) => {
+            for (index, item) in list.iter().enumerate() {
+                walk(item, format!("{path}[{index}]"), divergences);
+            }
+        }
+        NodeKind::Dictionary(entries) => {
+            let mut seen = BTreeSet::new();
+            let mut greatest: &[u8] = &[];
+            for entry in entries {
+                let kind = if seen.contains(entry.key) {
+                    Some(DivergenceKind::Duplicate)
+                } else if !seen.is_empty() && entry.key < greatest {
+                    Some(DivergenceKind::Unsorted {
+                        previous: greatest.to_vec(),
+                    })
+                } else {
+                    None
+                };
+                if let Some(kind) = kind {
+                    divergences.push(Divergence {
+                        path: path.clone(),
+                        key: entry.key.to_vec(),
+                        offset: entry.key_span.start,
+                        kind,
+                    });
+                }
+                seen.insert(entry.key);
+                greatest = greatest.max(entry.key);
+
+                let key = String::from_utf8_lossy(entry.key);
+                let child = match path.is_empty() {
+                    true => key.into_owned(),
+                    false => format!("{path}.{key}"),
+                };
+                walk(&entry.value, child, divergences);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_input() {
+        let input = b"d4:infod6:lengthi5e4:name1:ae1:xli1eee";
+        let roundtrip = check_roundtrip(input).unwrap();
+        assert!(roundtrip.is_exact());
+        assert_eq!(roundtrip.encoded, input);
+        assert_eq!(roundtrip.divergences, []);
+        assert_eq!(
+            check_roundtrip(b"i01e").unwrap_err().code(),
+            "invalid_integer"
+        );
+    }
+
+    #[test]
+    fn divergences() {
+        let input = b"d4:infod4:name1:a6:lengthi5ee1:xld1:ci0e1:bi0e1:ci1eeee";
+        let roundtrip = check_roundtrip(input).unwrap();
+        assert_eq!(
+            roundtrip.encoded,
+            b"d4:infod6:lengthi5e4:name1:ae1:xld1:bi0e1:ci1eeee"
+        );
+        assert_eq!(roundtrip.first_difference, Some(8));
+        let messages: Vec<_> = roundtrip
+            .divergences
+            .iter()
+            .map(|divergence| divergence.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "info: key `length` at byte 17 sorts before `name`",
+                "x[0]: key `b` at byte 40 sorts before `c`",
+                "x[0]: key `c` at byte 46 is repeated",
+            ]
+        );
+
+        // The repeated key is dropped, with its first value
+        let roundtrip = check_roundtrip(b"d1:ai1e1:ai1ee").unwrap();
+        assert_eq!(roundtrip.first_difference, Some(7));
+        assert_eq!(roundtrip.divergences[0].kind, DivergenceKind::Duplicate);
+    }
+}
@@ -0,0 +1,189 @@
+//! Inferring the shape of a collection of documents: which keys appear and
+//! how often, and what types and sizes their values take.
+
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+use core::fmt::{self, Display, Formatter};
+
+use crate::Bencode;
+
+/// How many values of one type were seen, and the range they covered.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Stats {
+    pub count: usize,
+    pub min: i64,
+    pub max: i64,
+}
+
+impl Stats {
+    fn record(stats: &mut Option<Stats>, n: i64) {
+        match stats {
+            Some(stats) => {
+                stats.count += 1;
+                stats.min = stats.min.min(n);
+                stats.max = stats.max.max(n);
+            }
+            None => {
+                *stats = Some(Stats {
+                    count: 1,
+                    min: n,
+                    max: n,
+                })
+            }
+        }
+    }
+}
+
+/// Observations of every value found at one position across documents.
+///
+/// String, list and dictionary stats range over lengths (number of bytes,
+/// elements or keys); integer stats range over the values themselves.
+#[derive(PartialEq, Debug, Default)]
+pub struct Shape {
+    pub seen: usize,
+    pub strings: Option<Stats>,
+    pub integers: Option<Stats>,
+    pub lists: Option<Stats>,
+    /// Shape of the elements of every list seen.
+    pub items: Option<Box<Shape>>,
+    pub dictionaries: Option<Stats>,
+    /// Shape of each key's values; `seen` over `dictionaries.count` is how
+    /// often the key is present.
+    pub keys: BTreeMap<Vec<u8>, Shape>,
+}
+
+impl Shape {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, value: &Bencode) {
+        self.seen += 1;
+
+        match value {
+            Bencode::String(s) => Stats::record(&mut self.strings, s.len() as i64),
+            Bencode::Integer(i) => Stats::record(&mut self.integers, *i),
+            Bencode::List(l) => {
+                Stats::record(&mut self.lists, l.len() as i64);
+                let items = self.items.get_or_insert_with(Default::default);
+                for value in l {
+                    items.observe(value);
+                }
+            }
+            Bencode::Dictionary(d) => {
+                Stats::record(&mut self.dictionaries, d.len() as i64);
+                for (key, value) in d {
+                    self.keys.entry(key.clone()).or_default().observe(value);
+                }
+            }
+        }
+    }
+
+    fn write(
+        &self,
+        f: &mut Formatter<'_>,
+        label: &str,
+        depth: usize,
+        of: Option<usize>,
+    ) -> fmt::Result {
+        write!(f, "{:indent$}{label}  ", "", indent = depth * 2)?;
+        match of {
+            Some(of) => write!(f, "{}/{of}", self.seen)?,
+            None => write!(f, "{}", self.seen)?,
+        }
+
+        let kinds = [
+            ("string", self.strings, "len "),
+            ("integer", self.integers, ""),
+            ("list", self.lists, "len "),
+            ("dictionary", self.dictionaries, "keys "),
+        ];
+        let mut separator = "  ";
+        for (name, stats, unit) in kinds {
+            if let Some(Stats { count, min, max }) = stats {
+                write!(f, "{separator}{name}[{count}] {unit}{min}..{max}")?;
+                separator = " | ";
+            }
+        }
+        writeln!(f)?;
+
+        if let Some(items) = &self.items {
+            items.write(f, "[]", depth + 1, None)?;
+        }
+        let dictionaries = self.dictionaries.map_or(0, |stats| stats.count);
+        for (key, shape) in &self.keys {
+            let key = String::from_utf8_lossy(key);
+            shape.write(f, &key, depth + 1, Some(dictionaries))?;
+        }
+        Ok(())
+    }
+}
+
+/// An indented tree, one line per position: its label, how many values were
+/// seen there (out of how many enclosing dictionaries, for keys), and the
+/// types observed with their counts and ranges.
+impl Display for Shape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write(f, ".", 0, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+    use alloc::string::ToString;
+
+    fn infer(documents: &[&[u8]]) -> Shape {
+        let mut shape = Shape::new();
+        for document in documents {
+            shape.observe(&decode(document).unwrap());
+        }
+        shape
+    }
+
+    #[test]
+    fn counts_and_ranges() {
+        let shape = infer(&[
+            b"d4:infod6:lengthi5e4:name3:fooee",
+            b"d8:announce3:url4:infod5:filesli1ei2ee4:name5:hello6:lengthi-3eee",
+        ]);
+
+        assert_eq!(shape.dictionaries.unwrap().count, 2);
+        assert_eq!(shape.keys[&b"announce"[..]].seen, 1);
+
+        let info = &shape.keys[&b"info"[..]];
+        assert_eq!(
+            info.dictionaries,
+            Some(Stats {
+                count: 2,
+                min: 2,
+                max: 3
+            })
+        );
+        assert_eq!(
+            info.keys[&b"name"[..]].strings,
+            Some(Stats {
+                count: 2,
+                min: 3,
+                max: 5
+            })
+        );
+        assert_eq!(info.keys[&b"length"[..]].integers.unwrap().min, -3);
+        assert_eq!(info.keys[&b"files"[..]].items.as_ref().unwrap().seen, 2);
+    }
+
+    #[test]
+    fn display() {
+        let shape = infer(&[b"d1:ai1e1:bl1:xee", b"d1:a2:hie"]);
+
+        assert_eq!(
+            shape.to_string(),
+            "\
+.  2  dictionary[2] keys 1..2
+  a  2/2  string[1] len 2..2 | integer[1] 1..1
+  b  1/2  list[1] len 1..1
+    []  1  string[1] len 1..1
+"
+        );
+    }
+}
@@ -0,0 +1,632 @@
+//! Structural schemas for bencode documents, with built-in schemas for
+//! torrent metainfo (BEP 3), tracker responses and KRPC messages (BEP 5),
+//! and [`Shape`] for inferring what a collection of documents looks like.
+
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Display;
+
+use crate::Bencode;
+
+mod infer;
+
+pub use infer::{Shape, Stats};
+
+#[derive(Debug)]
+pub enum Schema<'a> {
+    Any,
+    Integer {
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    String(StringRule<'a>),
+    List {
+        items: &'a Schema<'a>,
+        min_len: usize,
+    },
+    Dictionary {
+        fields: &'a [Field<'a>],
+        /// Keys of which exactly one must be present.
+        one_of: &'a [&'a str],
+    },
+    /// Matches if any of the alternatives does.
+    OneOf(&'a [Schema<'a>]),
+}
+
+#[derive(Debug)]
+pub struct StringRule<'a> {
+    pub min_len: usize,
+    pub max_len: Option<usize>,
+    /// Length must be a multiple of this, e.g. 20 for concatenated SHA-1s.
+    pub multiple_of: Option<usize>,
+    pub utf8: bool,
+    /// Allowed values, or empty to allow any.
+    pub values: &'a [&'a str],
+}
+
+impl StringRule<'_> {
+    pub const ANY: StringRule<'static> = StringRule {
+        min_len: 0,
+        max_len: None,
+        multiple_of: None,
+        utf8: false,
+        values: &[],
+    };
+}
+
+#[derive(Debug)]
+pub struct Field<'a> {
+    pub key: &'a str,
+    pub schema: Schema<'a>,
+    pub required: bool,
+}
+
+/// A place where a value doesn't match its schema.
+#[derive(PartialEq, Debug)]
+pub struct Violation {
+    /// Path of the value, like `info.files[0].length`; empty for the root.
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, ".: {}", self.message),
+            false => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Checks `value` against `schema`, returning every violation found.
+pub fn validate_schema(value: &Bencode, schema: &Schema) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    check(value, schema, "", &mut violations);
+    violations
+}
+
+fn kind(value: &Bencode) -> &'static str {
+    match value {
+        Bencode::String(_) => "string",
+        Bencode::Integer(_) => "integer",
+        Bencode::List(_) => "list",
+        Bencode::Dictionary(_) => "dictionary",
+    }
+}
+
+fn expects(schema: &Schema) -> &'static str {
+    match schema {
+        Schema::Any | Schema::OneOf(_) => "any",
+        Schema::Integer { .. } => "integer",
+        Schema::String(_) => "string",
+        Schema::List { .. } => "list",
+        Schema::Dictionary { .. } => "dictionary",
+    }
+}
+
+fn push(out: &mut Vec<Violation>, path: &str, message: String) {
+    out.push(Violation {
+        path: path.into(),
+        message,
+    });
+}
+
+fn check(value: &Bencode, schema: &Schema, path: &str, out: &mut Vec<Violation>) {
+    match (schema, value) {
+        (Schema::Any, _) => {}
+        (Schema::Integer { min, max }, Bencode::Integer(i)) => {
+            if min.is_some_and(|min| *i < min) || max.is_some_and(|max| *i > max) {
+                let min = min.map_or("..".into(), |min| format!("{min}.."));
+                let max = max.map_or(String::new(), |max| format!("{max}"));
+                push(out, path, format!("{i} is outside {min}{max}"));
+            }
+        }
+        (Schema::String(rule), Bencode::String(s)) => {
+            if s.len() < rule.min_len || rule.max_len.is_some_and(|max| s.len() > max) {
+                push(out, path, format!("length {} is out of range", s.len()));
+            }
+            if let Some(n) = rule.multiple_of.filter(|n| s.len() % n != 0) {
+                push(
+                    out,
+                    path,
+                    format!("length {} is not a multiple of {n}", s.len()),
+                );
+            }
+            match core::str::from_utf8(s) {
+                Err(_) if rule.utf8 => push(out, path, "not valid UTF-8".into()),
+                Ok(s) if !rule.values.is_empty() && !rule.values.contains(&s) => push(
+                    out,
+                    path,
+                    format!("{s:?} is not one of {}", rule.values.join(", ")),
+                ),
+                Err(_) if !rule.values.is_empty() => {
+                    push(out, path, format!("not one of {}", rule.values.join(", ")))
+                }
+                _ => {}
+            }
+        }
+        (Schema::List { items, min_len }, Bencode::List(l)) => {
+            if l.len() < *min_len {
+                push(out, path, format!("expected at least {min_len} elements"));
+            }
+            for (i, value) in l.iter().enumerate() {
+                check(value, items, &format!("{path}[{i}]"), out);
+            }
+        }
+        (Schema::Dictionary { fields, one_of }, Bencode::Dictionary(d)) => {
+            let present = one_of
+                .iter()
+                .filter(|key| d.contains_key(key.as_bytes()))
+                .count();
+            if !one_of.is_empty() && present != 1 {
+                push(
+                    out,
+                    path,
+                    format!("expected exactly one of {}", one_of.join(", ")),
+                );
+            }
+
+            for field in fields.iter() {
+                let field_path = match path.is_empty() {
+                    true => field.key.into(),
+                    false => format!("{path}.{}", field.key),
+                };
+                match d.get(field.key.as_bytes()) {
+                    Some(value) => check(value, &field.schema, &field_path, out),
+                    None if field.required => {
+                        push(out, path, format!("missing required key `{}`", field.key))
+                    }
+                    None => {}
+                }
+            }
+        }
+        (Schema::OneOf(alternatives), value) => {
+            let matching: Vec<_> = alternatives
+                .iter()
+                .filter(|alternative| expects(alternative) == kind(value))
+                .collect();
+
+            match matching.as_slice() {
+                [] => {
+                    let kinds: Vec<_> = alternatives.iter().map(expects).collect();
+                    push(
+                        out,
+                        path,
+                        format!("expected {}, found {}", kinds.join(" or "), kind(value)),
+                    );
+                }
+                [alternative] => check(value, alternative, path, out),
+                [first, ..] => {
+                    let mut attempt = Vec::new();
+                    for alternative in &matching {
+                        attempt.clear();
+                        check(value, alternative, path, &mut attempt);
+                        if attempt.is_empty() {
+                            return;
+                        }
+                    }
+                    check(value, first, path, out);
+                }
+            }
+        }
+        (schema, value) => push(
+            out,
+            path,
+            format!("expected {}, found {}", expects(schema), kind(value)),
+        ),
+    }
+}
+
+const TEXT: Schema = Schema::String(StringRule {
+    utf8: true,
+    ..StringRule::ANY
+});
+
+const NON_NEGATIVE: Schema = Schema::Integer {
+    min: Some(0),
+    max: None,
+};
+
+const FILE: Schema = Schema::Dictionary {
+    fields: &[
+        Field {
+            key: "length",
+            schema: NON_NEGATIVE,
+            required: true,
+        },
+        Field {
+            key: "path",
+            schema: Schema::List {
+                items: &TEXT,
+                min_len: 1,
+            },
+            required: true,
+        },
+    ],
+    one_of: &[],
+};
+
+/// The info dictionary of a v1 torrent, or of a hybrid one, which has the
+/// v2 keys as well.
+const V1_INFO: Schema = Schema::Dictionary {
+    fields: &[
+        Field {
+            key: "name",
+            schema: Schema::String(StringRule {
+                min_len: 1,
+                utf8: true,
+                ..StringRule::ANY
+            }),
+            required: true,
+        },
+        Field {
+            key: "piece length",
+            schema: Schema::Integer {
+                min: Some(1),
+                max: None,
+            },
+            required: true,
+        },
+        Field {
+            key: "pieces",
+            schema: Schema::String(StringRule {
+                multiple_of: Some(20),
+                ..StringRule::ANY
+            }),
+            required: true,
+        },
+        Field {
+            key: "length",
+            schema: NON_NEGATIVE,
+            required: false,
+        },
+        Field {
+            key: "files",
+            schema: Schema::List {
+                items: &FILE,
+                min_len: 1,
+            },
+            required: false,
+        },
+        Field {
+            key: "private",
+            schema: Schema::Integer {
+                min: Some(0),
+                max: Some(1),
+            },
+            required: false,
+        },
+    ],
+    one_of: &["length", "files"],
+};
+
+/// The info dictionary of a v2-only torrent (BEP 52), which lists files in
+/// `file tree` rather than `length` or `files` and has no `pieces`.
+const V2_INFO: Schema = Schema::Dictionary {
+    fields: &[
+        Field {
+            key: "name",
+            schema: Schema::String(StringRule {
+                min_len: 1,
+                utf8: true,
+                ..StringRule::ANY
+            }),
+            required: true,
+        },
+        Field {
+            key: "piece length",
+            schema: Schema::Integer {
+                min: Some(16384),
+                max: None,
+            },
+            required: true,
+        },
+        Field {
+            key: "meta version",
+            schema: Schema::Integer {
+                min: Some(2),
+                max: Some(2),
+            },
+            required: true,
+        },
+        Field {
+            key: "file tree",
+            schema: ANY_DICTIONARY,
+            required: true,
+        },
+        Field {
+            key: "private",
+            schema: Schema::Integer {
+                min: Some(0),
+                max: Some(1),
+            },
+            required: false,
+        },
+    ],
+    one_of: &[],
+};
+
+const ANY_DICTIONARY: Schema = Schema::Dictionary {
+    fields: &[],
+    one_of: &[],
+};
+
+/// BEP 3 single- or multi-file `.torrent` metainfo, or its v2 (BEP 52) or
+/// hybrid form.
+pub const TORRENT: Schema = Schema::Dictionary {
+    fields: &[
+        Field {
+            key: "announce",
+            schema: TEXT,
+            required: false,
+        },
+        Field {
+            key: "announce-list",
+            schema: Schema::List {
+                items: &Schema::List {
+                    items: &TEXT,
+                    min_len: 1,
+                },
+                min_len: 0,
+            },
+            required: false,
+        },
+        Field {
+            key: "creation date",
+            schema: NON_NEGATIVE,
+            required: false,
+        },
+        Field {
+            key: "comment",
+            schema: TEXT,
+            required: false,
+        },
+        Field {
+            key: "created by",
+            schema: TEXT,
+            required: false,
+        },
+        Field {
+            key: "piece layers",
+            schema: ANY_DICTIONARY,
+            required: false,
+        },
+        Field {
+            key: "info",
+            schema: Schema::OneOf(&[V1_INFO, V2_INFO]),
+            required: true,
+        },
+    ],
+    one_of: &[],
+};
+
+const PEER: Schema = Schema::Dictionary {
+    fields: &[
+        Field {
+            key: "peer id",
+            schema: Schema::String(StringRule {
+                min_len: 20,
+                max_len: Some(20),
+                ..StringRule::ANY
+            }),
+            required: false,
+        },
+        Field {
+            key: "ip",
+            schema: TEXT,
+            required: true,
+        },
+        Field {
+            key: "port",
+            schema: Schema::Integer {
+                min: Some(0),
+                max: Some(65535),
+            },
+            required: true,
+        },
+    ],
+    one_of: &[],
+};
+
+/// HTTP tracker announce response, either a failure or a peer list in the
+/// dictionary or compact (BEP 23) form.
+pub const TRACKER_RESPONSE: Schema = Schema::Dictionary {
+    fields: &[
+        Field {
+            key: "failure reason",
+            schema: TEXT,
+            required: false,
+        },
+        Field {
+            key: "warning message",
+            schema: TEXT,
+            required: false,
+        },
+        Field {
+            key: "interval",
+            schema: NON_NEGATIVE,
+            required: false,
+        },
+        Field {
+            key: "min interval",
+            schema: NON_NEGATIVE,
+            required: false,
+        },
+        Field {
+            key: "complete",
+            schema: NON_NEGATIVE,
+            required: false,
+        },
+        Field {
+            key: "incomplete",
+            schema: NON_NEGATIVE,
+            required: false,
+        },
+        Field {
+            key: "peers",
+            schema: Schema::OneOf(&[
+                Schema::String(StringRule {
+                    multiple_of: Some(6),
+                    ..StringRule::ANY
+                }),
+                Schema::List {
+                    items: &PEER,
+                    min_len: 0,
+                },
+            ]),
+            required: false,
+        },
+        Field {
+            key: "peers6",
+            schema: Schema::String(StringRule {
+                multiple_of: Some(18),
+                ..StringRule::ANY
+            }),
+            required: false,
+        },
+    ],
+    one_of: &["failure reason", "peers"],
+};
+
+/// DHT KRPC query, response or error message.
+pub const KRPC: Schema = Schema::Dictionary {
+    fields: &[
+        Field {
+            key: "t",
+            schema: Schema::String(StringRule::ANY),
+            required: true,
+        },
+        Field {
+            key: "y",
+            schema: Schema::String(StringRule {
+                values: &["q", "r", "e"],
+                ..StringRule::ANY
+            }),
+            required: true,
+        },
+        Field {
+            key: "q",
+            schema: TEXT,
+            required: false,
+        },
+        Field {
+            key: "a",
+            schema: Schema::Dictionary {
+                fields: &[Field {
+                    key: "id",
+                    schema: Schema::String(StringRule {
+                        min_len: 20,
+                        max_len: Some(20),
+                        ..StringRule::ANY
+                    }),
+                    required: true,
+                }],
+                one_of: &[],
+            },
+            required: false,
+        },
+        Field {
+            key: "r",
+            schema: Schema::Dictionary {
+                fields: &[],
+                one_of: &[],
+            },
+            required: false,
+        },
+        Field {
+            key: "e",
+            schema: Schema::List {
+                items: &Schema::Any,
+                min_len: 2,
+            },
+            required: false,
+        },
+    ],
+    one_of: &["a", "r", "e"],
+};
+
+/// Names accepted by [`builtin`].
+pub const BUILTIN_NAMES: &[&str] = &["torrent", "tracker-response", "krpc"];
+
+pub fn builtin(name: &str) -> Option<&'static Schema<'static>> {
+    match name {
+        "torrent" => Some(&TORRENT),
+        "tracker-response" => Some(&TRACKER_RESPONSE),
+        "krpc" => Some(&KRPC),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    fn messages(input: &[u8], schema: &Schema) -> Vec<String> {
+        validate_schema(&decode(input).unwrap(), schema)
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn torrent() {
+        let valid = b"d8:announce3:url4:infod6:lengthi5e4:name1:a12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+        assert_eq!(messages(valid, &TORRENT), Vec::<String>::new());
+
+        let invalid = b"d4:infod5:filesld6:lengthi-1e4:pathleee6:lengthi1e4:name0:6:pieces3:abcee";
+        assert_eq!(
+            messages(invalid, &TORRENT),
+            [
+                "info: expected exactly one of length, files",
+                "info.name: length 0 is out of range",
+                "info: missing required key `piece length`",
+                "info.pieces: length 3 is not a multiple of 20",
+                "info.files[0].length: -1 is outside 0..",
+                "info.files[0].path: expected at least 1 elements",
+            ]
+        );
+    }
+
+    #[test]
+    fn v2_torrent() {
+        let v2 = b"d4:infod9:file treed1:ad0:d6:lengthi5e11:pieces root32:\
+                   aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaeee12:meta versioni2e4:name1:t\
+                   12:piece lengthi16384eee";
+        assert_eq!(messages(v2, &TORRENT), Vec::<String>::new());
+
+        // Invalid either way; the v1 violations are the ones reported
+        let v3 = b"d4:infod9:file treede12:meta versioni3e4:name1:t12:piece lengthi16384eee";
+        assert_eq!(
+            messages(v3, &TORRENT),
+            [
+                "info: expected exactly one of length, files",
+                "info: missing required key `pieces`",
+            ]
+        );
+        assert!(!messages(b"d4:infod12:meta versioni2e4:name1:tee", &TORRENT).is_empty());
+    }
+
+    #[test]
+    fn tracker_response() {
+        assert!(messages(b"d8:intervali60e5:peers6:abcdefe", &TRACKER_RESPONSE).is_empty());
+        assert!(messages(b"d5:peersld2:ip3:::14:porti6881eeee", &TRACKER_RESPONSE).is_empty());
+        assert_eq!(
+            messages(b"d5:peersi1ee", &TRACKER_RESPONSE),
+            ["peers: expected string or list, found integer"]
+        );
+        assert_eq!(
+            messages(b"d5:peersld2:ip1:x4:porti70000eeee", &TRACKER_RESPONSE),
+            ["peers[0].port: 70000 is outside 0..65535"]
+        );
+    }
+
+    #[test]
+    fn krpc() {
+        let ping = b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe";
+        assert!(messages(ping, &KRPC).is_empty());
+        assert_eq!(
+            messages(b"d1:rde1:t2:aa1:y1:xe", &KRPC),
+            [r#"y: "x" is not one of q, r, e"#]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Known-answer checks of the build, for `selftest`: decoding and encoding,
+//! the conformance table, the hash functions and the torrent helpers, each
+//! against results worked out elsewhere. Packagers and users on unusual
+//! platforms can run it to catch a miscompiled or misported build.
+
+use bencode_decoder::{
+    conformance::CASES,
+    decode, decode_with, encode,
+    json::{from_json, to_json},
+    merkle, sha1, sha256, torrent, DecodeOptions,
+};
+
+const TORRENT: &[u8] = include_bytes!("../fixtures/multi-file.torrent");
+
+type Check = fn() -> Result<(), String>;
+
+/// Every check by name, with what went wrong for those that failed.
+pub fn run() -> Vec<(&'static str, Result<(), String>)> {
+    let checks: [(&'static str, Check); 7] = [
+        ("round trip", round_trip),
+        ("conformance", conformance),
+        ("sha1", sha1),
+        ("sha256", sha256),
+        ("merkle root", merkle_root),
+        ("infohash", infohash),
+        ("json", json),
+    ];
+    checks
+        .into_iter()
+        .map(|(name, check)| (name, check()))
+        .collect()
+}
+
+fn expect<T: PartialEq + std::fmt::Debug>(
+    what: &str,
+    actual: T,
+    expected: T,
+) -> Result<(), String> {
+    match actual == expected {
+        true => Ok(()),
+        false => Err(format!("{what}: got {actual:?}, expected {expected:?}")),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn round_trip() -> Result<(), String> {
+    for input in [
+        &b"i-9223372036854775808e"[..],
+        b"d4:infod6:lengthi5e4:name1:ae4:listl0:i0eleee",
+        b"3:\x00\xff\n",
+        TORRENT,
+    ] {
+        let value = decode(input).map_err(|error| error.to_string())?;
+        expect("encoding", encode(&value), input.to_vec())?;
+    }
+    Ok(())
+}
+
+fn conformance() -> Result<(), String> {
+    let strict = DecodeOptions {
+        sorted_keys: true,
+        ..Default::default()
+    };
+    for case in CASES {
+        let accepted = decode_with(case.input, &strict).is_ok();
+        if !case.verdict.allows(accepted) {
+            return Err(format!(
+                "{}: {}",
+                case.name,
+                if accepted { "accepted" } else { "rejected" }
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn sha1() -> Result<(), String> {
+    expect(
+        "sha1(\"abc\")",
+        hex(&sha1::sha1(b"abc")),
+        "a9993e364706816aba3e25717850c26c9cd0d89d".into(),
+    )?;
+    // Longer than a block, so the padding spills into a second one
+    expect(
+        "sha1 of 64 bytes",
+        hex(&sha1::sha1(&[b'a'; 64])),
+        "0098ba824b5c16427bd7a1122a5a442a25ec644d".into(),
+    )
+}
+
+fn sha256() -> Result<(), String> {
+    expect(
+        "sha256(\"abc\")",
+        hex(&sha256::sha256(b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".into(),
+    )
+}
+
+fn merkle_root() -> Result<(), String> {
+    // Three leaves are padded to four, the last paired with the padding
+    let (a, b, c, pad) = ([1u8; 32], [2; 32], [3; 32], [9; 32]);
+    let parent = |left: [u8; 32], right: [u8; 32]| sha256::sha256(&[left, right].concat());
+    expect(
+        "root of three leaves",
+        merkle::root(&[a, b, c], pad),
+        parent(parent(a, b), parent(c, pad)),
+    )
+}
+
+fn infohash() -> Result<(), String> {
+    let hash = torrent::infohash(TORRENT).map_err(|error| error.to_string())?;
+    expect(
+        "infohash",
+        hex(&hash),
+        "cfee3c0cde82791a190068dfbee9bd24d09cf0d0".into(),
+    )?;
+    let sha = sha1::sha1(torrent::raw_info(TORRENT).map_err(|error| error.to_string())?);
+    expect("infohash matches sha1 of info", sha, hash)
+}
+
+fn json() -> Result<(), String> {
+    let value = decode(TORRENT).map_err(|error| error.to_string())?;
+    let back = from_json(&to_json(&value)).map_err(|error| error.to_string())?;
+    expect("json round trip", back, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes() {
+        for (name, result) in run() {
+            assert_eq!(result, Ok(()), "{name}");
+        }
+    }
+}
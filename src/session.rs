@@ -0,0 +1,163 @@
+//! Per-torrent reports on client session directories, for `inspect-session`.
+
+use std::{error::Error, fmt::Display, path::Path};
+
+use bencode_decoder::{
+    resume::{qbittorrent, rtorrent},
+    Bencode,
+};
+
+pub const CLIENTS: &[&str] = &["qbittorrent", "rtorrent"];
+
+/// One report per torrent in `dir`, in hash order.
+pub fn inspect(client: &str, dir: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(match client {
+        "qbittorrent" => qbittorrent::read_backup_dir(dir)?
+            .iter()
+            .map(qbittorrent_report)
+            .collect(),
+        "rtorrent" => rtorrent::read_session_dir(dir)?
+            .iter()
+            .map(rtorrent_report)
+            .collect(),
+        _ => {
+            return Err(format!(
+                "unknown client {client:?}, expected one of: {}",
+                CLIENTS.join(", ")
+            )
+            .into())
+        }
+    })
+}
+
+struct Report(String);
+
+impl Report {
+    fn new(hash: &str) -> Self {
+        Self(format!("{hash}\n"))
+    }
+
+    fn line(&mut self, label: &str, value: impl Display) {
+        self.0.push_str(&format!("  {label:<14}{value}\n"));
+    }
+
+    /// Unwraps a parsed session file, noting it if missing or invalid.
+    fn file<T, E: Display>(&mut self, label: &str, file: Option<Result<T, E>>) -> Option<T> {
+        match file {
+            None => self.line(label, "missing"),
+            Some(Err(error)) => self.line(label, format!("invalid ({error})")),
+            Some(Ok(file)) => return Some(file),
+        }
+        None
+    }
+}
+
+fn torrent_name(torrent: &Bencode) -> Option<String> {
+    match torrent.get_path("info.name") {
+        Ok(Some(Bencode::String(name))) => Some(String::from_utf8_lossy(name).into_owned()),
+        _ => None,
+    }
+}
+
+fn list(items: &[String]) -> String {
+    match items.is_empty() {
+        true => "-".into(),
+        false => items.join(", "),
+    }
+}
+
+fn qbittorrent_report(entry: &qbittorrent::BackupEntry) -> String {
+    let mut report = Report::new(&entry.hash);
+
+    let torrent = entry.torrent.as_deref().map(bencode_decoder::decode);
+    let torrent = report.file("torrent", torrent);
+    let resume = entry.fastresume.as_deref().map(qbittorrent::parse);
+    let resume = report.file("fastresume", resume);
+
+    let name = resume
+        .as_ref()
+        .and_then(|qbt| qbt.name.clone().or_else(|| qbt.resume.name.clone()))
+        .or_else(|| torrent.as_ref().and_then(torrent_name));
+    if let Some(name) = name {
+        report.line("name", name);
+    }
+
+    if let Some(qbt) = resume {
+        let resume = &qbt.resume;
+        report.line("category", qbt.category.as_deref().unwrap_or("-"));
+        report.line("tags", list(&qbt.tags));
+        report.line("save path", &resume.save_path);
+        report.line(
+            "progress",
+            format!(
+                "{}/{} pieces",
+                resume.pieces.count_ones(),
+                resume.pieces.len()
+            ),
+        );
+        report.line("state", if resume.paused { "paused" } else { "active" });
+        if let Some(added) = resume.added_time {
+            report.line("added", added);
+        }
+        if let Some(completed) = resume.completed_time {
+            report.line("completed", completed);
+        }
+        report.line("uploaded", resume.total_uploaded);
+        report.line("downloaded", resume.total_downloaded);
+        report.line("trackers", resume.trackers.iter().flatten().count());
+    }
+
+    report.0
+}
+
+fn rtorrent_report(entry: &rtorrent::SessionEntry) -> String {
+    let mut report = Report::new(&entry.hash);
+
+    let torrent = entry.torrent.as_deref().map(bencode_decoder::decode);
+    let torrent = report.file("torrent", torrent);
+    let state = entry.state.as_deref().map(rtorrent::parse_state);
+    let state = report.file("rtorrent", state);
+    let resume = entry.resume.as_deref().map(rtorrent::parse_resume);
+    let resume = report.file("resume", resume);
+
+    if let Some(name) = torrent.as_ref().and_then(torrent_name) {
+        report.line("name", name);
+    }
+
+    if let Some(state) = state {
+        report.line("directory", &state.directory);
+        report.line("state", if state.started { "started" } else { "stopped" });
+        report.line(
+            "progress",
+            format!(
+                "{}/{} chunks{}",
+                state.chunks_done,
+                state.chunks_wanted + state.chunks_done,
+                if state.complete { ", complete" } else { "" }
+            ),
+        );
+        if let Some(started) = state.started_at {
+            report.line("started", started);
+        }
+        if let Some(finished) = state.finished_at {
+            report.line("finished", finished);
+        }
+        report.line("priority", state.priority);
+        report.line("uploaded", state.total_uploaded);
+        report.line("downloaded", state.total_downloaded);
+        report.line("views", list(&state.views));
+        for (key, value) in &state.custom {
+            report.line(key, value);
+        }
+    }
+
+    if let Some(resume) = resume {
+        let enabled = resume.trackers.values().filter(|&&enabled| enabled).count();
+        report.line(
+            "trackers",
+            format!("{} ({enabled} enabled)", resume.trackers.len()),
+        );
+    }
+
+    report.0
+}
@@ -0,0 +1,221 @@
+//! SHA-1, as needed for infohashes and piece hashes. On x86-64 CPUs with
+//! the SHA extensions the compression runs on them, which is several times
+//! faster; elsewhere, and without `std` to detect them, it's portable code.
+
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let bit_len = (data.len() as u64).wrapping_mul(8);
+    let mut tail = [0u8; 128];
+    let remainder = data.len() % 64;
+    tail[..remainder].copy_from_slice(&data[data.len() - remainder..]);
+    tail[remainder] = 0x80;
+    let tail_len = if remainder < 56 { 64 } else { 128 };
+    tail[tail_len - 8..tail_len].copy_from_slice(&bit_len.to_be_bytes());
+
+    compress_blocks(&mut h, &data[..data.len() - remainder]);
+    compress_blocks(&mut h, &tail[..tail_len]);
+
+    let mut digest = [0u8; 20];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Whether hashing, with SHA-1 and SHA-256 alike, uses the CPU's SHA
+/// extensions.
+pub fn is_accelerated() -> bool {
+    #[cfg(all(feature = "std", target_arch = "x86_64"))]
+    {
+        std::is_x86_feature_detected!("sha")
+            && std::is_x86_feature_detected!("sse4.1")
+            && std::is_x86_feature_detected!("ssse3")
+    }
+    #[cfg(not(all(feature = "std", target_arch = "x86_64")))]
+    {
+        false
+    }
+}
+
+/// Runs the compression over `blocks`, a whole number of 64-byte blocks.
+fn compress_blocks(h: &mut [u32; 5], blocks: &[u8]) {
+    #[cfg(all(feature = "std", target_arch = "x86_64"))]
+    if is_accelerated() {
+        // SAFETY: the CPU supports the features `x86::compress` is built with.
+        unsafe { x86::compress(h, blocks) };
+        return;
+    }
+
+    for block in blocks.chunks_exact(64) {
+        compress(h, block);
+    }
+}
+
+fn compress(h: &mut [u32; 5], block: &[u8]) {
+    let mut w = [0u32; 80];
+    for (i, word) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *h;
+    for (i, &word) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5A827999),
+            20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+            _ => (b ^ c ^ d, 0xCA62C1D6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+        *state = state.wrapping_add(value);
+    }
+}
+
+#[cfg(all(feature = "std", target_arch = "x86_64"))]
+mod x86 {
+    use core::arch::x86_64::*;
+
+    /// Four rounds on `abcd`, with `e` folded into the next words.
+    macro_rules! rounds4 {
+        ($abcd:ident, $e:ident, $w:expr, $f:literal) => {
+            _mm_sha1rnds4_epu32($abcd, _mm_sha1nexte_epu32($e, $w), $f)
+        };
+    }
+
+    /// The next four message words from the previous sixteen.
+    macro_rules! schedule {
+        ($w0:expr, $w1:expr, $w2:expr, $w3:expr) => {
+            _mm_sha1msg2_epu32(_mm_xor_si128(_mm_sha1msg1_epu32($w0, $w1), $w2), $w3)
+        };
+    }
+
+    macro_rules! schedule_rounds4 {
+        ($h0:ident, $h1:ident, $w0:ident, $w1:ident, $w2:ident, $w3:ident, $w4:ident, $f:literal) => {
+            $w4 = schedule!($w0, $w1, $w2, $w3);
+            $h1 = rounds4!($h0, $h1, $w4, $f);
+        };
+    }
+
+    #[target_feature(enable = "sha,sse2,ssse3,sse4.1")]
+    pub unsafe fn compress(h: &mut [u32; 5], blocks: &[u8]) {
+        // Reverses the bytes of the vector, making the words big-endian and
+        // putting the first one in the highest lane, as the instructions
+        // expect.
+        let mask = _mm_set_epi64x(0x0001_0203_0405_0607, 0x0809_0a0b_0c0d_0e0f);
+        let mut abcd = _mm_set_epi32(h[0] as i32, h[1] as i32, h[2] as i32, h[3] as i32);
+        let mut e = _mm_set_epi32(h[4] as i32, 0, 0, 0);
+
+        for block in blocks.chunks_exact(64) {
+            let load = |i: usize| {
+                // SAFETY: the block has 64 bytes and the loads are unaligned.
+                _mm_shuffle_epi8(
+                    unsafe { _mm_loadu_si128(block.as_ptr().add(16 * i).cast()) },
+                    mask,
+                )
+            };
+            let mut w0 = load(0);
+            let mut w1 = load(1);
+            let mut w2 = load(2);
+            let mut w3 = load(3);
+            let mut w4;
+
+            let mut h0 = abcd;
+            let mut h1 = _mm_add_epi32(e, w0);
+
+            // Rounds 0 to 19
+            h1 = _mm_sha1rnds4_epu32(h0, h1, 0);
+            h0 = rounds4!(h1, h0, w1, 0);
+            h1 = rounds4!(h0, h1, w2, 0);
+            h0 = rounds4!(h1, h0, w3, 0);
+            schedule_rounds4!(h0, h1, w0, w1, w2, w3, w4, 0);
+
+            // Rounds 20 to 39
+            schedule_rounds4!(h1, h0, w1, w2, w3, w4, w0, 1);
+            schedule_rounds4!(h0, h1, w2, w3, w4, w0, w1, 1);
+            schedule_rounds4!(h1, h0, w3, w4, w0, w1, w2, 1);
+            schedule_rounds4!(h0, h1, w4, w0, w1, w2, w3, 1);
+            schedule_rounds4!(h1, h0, w0, w1, w2, w3, w4, 1);
+
+            // Rounds 40 to 59
+            schedule_rounds4!(h0, h1, w1, w2, w3, w4, w0, 2);
+            schedule_rounds4!(h1, h0, w2, w3, w4, w0, w1, 2);
+            schedule_rounds4!(h0, h1, w3, w4, w0, w1, w2, 2);
+            schedule_rounds4!(h1, h0, w4, w0, w1, w2, w3, 2);
+            schedule_rounds4!(h0, h1, w0, w1, w2, w3, w4, 2);
+
+            // Rounds 60 to 79
+            schedule_rounds4!(h1, h0, w1, w2, w3, w4, w0, 3);
+            schedule_rounds4!(h0, h1, w2, w3, w4, w0, w1, 3);
+            schedule_rounds4!(h1, h0, w3, w4, w0, w1, w2, 3);
+            schedule_rounds4!(h0, h1, w4, w0, w1, w2, w3, 3);
+            schedule_rounds4!(h1, h0, w0, w1, w2, w3, w4, 3);
+
+            abcd = _mm_add_epi32(abcd, h0);
+            e = _mm_sha1nexte_epu32(h1, e);
+        }
+
+        h[0] = _mm_extract_epi32(abcd, 3) as u32;
+        h[1] = _mm_extract_epi32(abcd, 2) as u32;
+        h[2] = _mm_extract_epi32(abcd, 1) as u32;
+        h[3] = _mm_extract_epi32(abcd, 0) as u32;
+        h[4] = _mm_extract_epi32(e, 3) as u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+
+    #[test]
+    fn sha1_vectors() {
+        assert_eq!(
+            hex::encode(&sha1(b"")),
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+        );
+        assert_eq!(
+            hex::encode(&sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hex::encode(&sha1(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    /// The accelerated path, where the CPU has it, against the portable one
+    /// across block and padding boundaries.
+    #[test]
+    fn matches_portable() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 + i / 13) as u8).collect();
+        for len in [0, 1, 55, 56, 63, 64, 65, 119, 128, 999, 1000] {
+            let data = &data[..len];
+            let mut expected = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+            let mut actual = expected;
+            let blocks = &data[..len - len % 64];
+            for block in blocks.chunks_exact(64) {
+                compress(&mut expected, block);
+            }
+            compress_blocks(&mut actual, blocks);
+            assert_eq!(actual, expected, "{len} bytes");
+        }
+    }
+}
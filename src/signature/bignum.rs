@@ -0,0 +1,255 @@
+//! Just enough unsigned big-integer arithmetic for RSA: modular
+//! exponentiation with Montgomery multiplication, for odd moduli.
+
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
+
+/// An unsigned integer in little-endian 32-bit limbs, without high zero
+/// limbs.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Uint(Vec<u32>);
+
+impl Uint {
+    pub fn from_be_bytes(bytes: &[u8]) -> Self {
+        let mut limbs: Vec<u32> = bytes
+            .rchunks(4)
+            .map(|chunk| chunk.iter().fold(0, |limb, &b| (limb << 8) | u32::from(b)))
+            .collect();
+        trim(&mut limbs);
+        Self(limbs)
+    }
+
+    /// The value as exactly `len` big-endian bytes, or `None` if it doesn't
+    /// fit.
+    pub fn to_be_bytes(&self, len: usize) -> Option<Vec<u8>> {
+        if self.bits() > len * 8 {
+            return None;
+        }
+        let mut bytes = vec![0; len];
+        for (i, byte) in bytes.iter_mut().rev().enumerate() {
+            *byte = self
+                .0
+                .get(i / 4)
+                .map_or(0, |limb| (limb >> (8 * (i % 4))) as u8);
+        }
+        Some(bytes)
+    }
+
+    pub fn bits(&self) -> usize {
+        self.0
+            .last()
+            .map_or(0, |top| self.0.len() * 32 - top.leading_zeros() as usize)
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.0
+            .get(i / 32)
+            .is_some_and(|limb| limb >> (i % 32) & 1 == 1)
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.bit(0)
+    }
+}
+
+impl PartialOrd for Uint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Uint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare(&self.0, &other.0)
+    }
+}
+
+fn trim(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+/// Compares limbs of possibly different lengths.
+fn compare(a: &[u32], b: &[u32]) -> Ordering {
+    let len = a.len().max(b.len());
+    (0..len)
+        .rev()
+        .map(|i| {
+            let a = a.get(i).copied().unwrap_or(0);
+            let b = b.get(i).copied().unwrap_or(0);
+            a.cmp(&b)
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// `a -= b` modulo 2^(32 × limbs of `a`); `b` may be shorter.
+fn subtract(a: &mut [u32], b: &[u32]) {
+    let mut borrow = 0i64;
+    for (i, limb) in a.iter_mut().enumerate() {
+        let x = i64::from(*limb) - i64::from(b.get(i).copied().unwrap_or(0)) - borrow;
+        *limb = x as u32;
+        borrow = i64::from(x < 0);
+    }
+}
+
+/// Montgomery arithmetic modulo an odd `n`, with R = 2^(32 × limbs).
+struct Montgomery<'a> {
+    n: &'a [u32],
+    /// -n⁻¹ mod 2^32.
+    n_prime: u32,
+    /// R² mod n, to move values into Montgomery form.
+    r2: Vec<u32>,
+}
+
+impl<'a> Montgomery<'a> {
+    fn new(n: &'a [u32]) -> Self {
+        // Newton's iteration doubles the correct low bits each time.
+        let mut inverse = 1u32;
+        for _ in 0..5 {
+            inverse = inverse.wrapping_mul(2u32.wrapping_sub(n[0].wrapping_mul(inverse)));
+        }
+
+        // R² mod n by doubling 1 modulo n, 2 × 32 × limbs times.
+        let mut r2 = vec![0; n.len()];
+        r2[0] = 1;
+        for _ in 0..64 * n.len() {
+            let carry = r2.iter_mut().fold(0, |carry, limb| {
+                let top = *limb >> 31;
+                *limb = (*limb << 1) | carry;
+                top
+            });
+            if carry == 1 || compare(&r2, n).is_ge() {
+                // With the carry the true value is 2^(32 × limbs) higher,
+                // which the wrapping subtraction accounts for.
+                subtract(&mut r2, n);
+            }
+        }
+
+        Self {
+            n,
+            n_prime: inverse.wrapping_neg(),
+            r2,
+        }
+    }
+
+    /// a × b × R⁻¹ mod n, for a and b below n (coarsely integrated
+    /// operand scanning).
+    fn multiply(&self, a: &[u32], b: &[u32]) -> Vec<u32> {
+        let s = self.n.len();
+        let mut t = vec![0u32; s + 2];
+        for &ai in a {
+            let mut carry = 0u64;
+            for j in 0..s {
+                let x = u64::from(t[j]) + u64::from(ai) * u64::from(b[j]) + carry;
+                t[j] = x as u32;
+                carry = x >> 32;
+            }
+            let x = u64::from(t[s]) + carry;
+            t[s] = x as u32;
+            t[s + 1] = (x >> 32) as u32;
+
+            let m = t[0].wrapping_mul(self.n_prime);
+            let x = u64::from(t[0]) + u64::from(m) * u64::from(self.n[0]);
+            let mut carry = x >> 32;
+            for j in 1..s {
+                let x = u64::from(t[j]) + u64::from(m) * u64::from(self.n[j]) + carry;
+                t[j - 1] = x as u32;
+                carry = x >> 32;
+            }
+            let x = u64::from(t[s]) + carry;
+            t[s - 1] = x as u32;
+            t[s] = t[s + 1] + (x >> 32) as u32;
+            t[s + 1] = 0;
+        }
+
+        if t[s] != 0 || compare(&t[..s], self.n).is_ge() {
+            subtract(&mut t[..s], self.n);
+        }
+        t.truncate(s);
+        t
+    }
+}
+
+/// base^exponent mod modulus, for an odd modulus and base below it.
+pub fn modpow(base: &Uint, exponent: &Uint, modulus: &Uint) -> Uint {
+    debug_assert!(modulus.is_odd() && base < modulus);
+    let s = modulus.0.len();
+    let widen = |value: &Uint| {
+        let mut limbs = value.0.clone();
+        limbs.resize(s, 0);
+        limbs
+    };
+    let mont = Montgomery::new(&modulus.0);
+
+    let base = mont.multiply(&widen(base), &mont.r2);
+    let mut one = vec![0; s];
+    one[0] = 1;
+    let mut result = mont.multiply(&one, &mont.r2);
+    for i in (0..exponent.bits()).rev() {
+        result = mont.multiply(&result, &result);
+        if exponent.bit(i) {
+            result = mont.multiply(&result, &base);
+        }
+    }
+
+    let mut result = mont.multiply(&result, &one);
+    trim(&mut result);
+    Uint(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uint(value: u128) -> Uint {
+        Uint::from_be_bytes(&value.to_be_bytes())
+    }
+
+    #[test]
+    fn bytes() {
+        let value = Uint::from_be_bytes(&[0, 0, 1, 2, 3, 4, 5]);
+        assert_eq!(value.0, [0x0203_0405, 0x01]);
+        assert_eq!(value.bits(), 33);
+        assert_eq!(value.to_be_bytes(6).unwrap(), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(value.to_be_bytes(4), None);
+        assert_eq!(Uint::from_be_bytes(&[0, 0]).bits(), 0);
+    }
+
+    #[test]
+    fn powers() {
+        // Checked against u128 arithmetic, with moduli of one to three limbs.
+        for (base, exponent, modulus) in [
+            (4u128, 13u128, 497u128),
+            (2, 0, 7),
+            (0, 5, 7),
+            (123_456_789, 65_537, 4_294_967_311),
+            (0xdead_beef_cafe, 0x1_0001, 0xffff_ffff_ffff_ffc5),
+            (987_654_321_987, 3, 0x1_0000_0000_0000_0000_0001),
+        ] {
+            let mut expected = 1u128;
+            for _ in 0..exponent {
+                expected = widening_mod(expected, base, modulus);
+            }
+            assert_eq!(
+                modpow(&uint(base), &uint(exponent), &uint(modulus)),
+                uint(expected),
+                "{base}^{exponent} mod {modulus}"
+            );
+        }
+    }
+
+    /// a × b mod m without overflowing u128, by doubling.
+    fn widening_mod(a: u128, b: u128, m: u128) -> u128 {
+        let (mut result, mut a, mut b) = (0u128, a % m, b % m);
+        while b > 0 {
+            if b & 1 == 1 {
+                result = (result + a) % m;
+            }
+            a = (a << 1) % m;
+            b >>= 1;
+        }
+        result
+    }
+}
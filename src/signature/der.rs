@@ -0,0 +1,180 @@
+//! Reading RSA keys from PEM files: the DER structures of PKCS #1, PKCS #8,
+//! SubjectPublicKeyInfo and X.509 certificates, as far as the keys in them.
+
+use alloc::vec::Vec;
+
+use super::bignum::Uint;
+
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const SEQUENCE: u8 = 0x30;
+/// `[0] EXPLICIT`, the version of an X.509 certificate.
+const VERSION: u8 = 0xa0;
+
+/// DER values one after the other.
+pub struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub fn new(der: &'a [u8]) -> Self {
+        Self(der)
+    }
+
+    /// The tag and contents of the next value.
+    fn next(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.0.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+        let (len, rest) = match first {
+            0..=0x7f => (usize::from(first), rest),
+            0x81..=0x84 => {
+                let (len, rest) = rest.split_at_checked(usize::from(first & 0x7f))?;
+                (
+                    len.iter().fold(0, |len, &b| (len << 8) | usize::from(b)),
+                    rest,
+                )
+            }
+            _ => return None,
+        };
+        let (contents, rest) = rest.split_at_checked(len)?;
+        self.0 = rest;
+        Some((tag, contents))
+    }
+
+    fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+        self.next()
+            .filter(|&(found, _)| found == tag)
+            .map(|(_, contents)| contents)
+    }
+
+    /// The contents of the next value, which must be a sequence.
+    pub fn sequence(&mut self) -> Option<Reader<'a>> {
+        self.expect(SEQUENCE).map(Reader)
+    }
+
+    pub fn integer(&mut self) -> Option<Uint> {
+        self.expect(INTEGER).map(Uint::from_be_bytes)
+    }
+
+    pub fn skip(&mut self) -> Option<()> {
+        self.next().map(|_| ())
+    }
+
+    fn octet_string(&mut self) -> Option<&'a [u8]> {
+        self.expect(OCTET_STRING)
+    }
+
+    /// A bit string's contents, which must be whole bytes.
+    fn bit_string(&mut self) -> Option<&'a [u8]> {
+        match self.expect(BIT_STRING)?.split_first()? {
+            (0, bits) => Some(bits),
+            _ => None,
+        }
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+}
+
+/// `n` and `e` from a PKCS #1 `RSAPublicKey`.
+pub fn rsa_public_key(der: &[u8]) -> Option<(Uint, Uint)> {
+    let mut key = Reader::new(der).sequence()?;
+    Some((key.integer()?, key.integer()?))
+}
+
+/// `n` and `e` from a `SubjectPublicKeyInfo`, whatever the algorithm
+/// identifier says; a key that isn't RSA fails to parse as one.
+pub fn subject_public_key_info(der: &[u8]) -> Option<(Uint, Uint)> {
+    let mut info = Reader::new(der).sequence()?;
+    info.skip()?;
+    rsa_public_key(info.bit_string()?)
+}
+
+/// The subject's key from an X.509 certificate.
+pub fn certificate(der: &[u8]) -> Option<(Uint, Uint)> {
+    let mut certificate = Reader::new(der).sequence()?;
+    let mut tbs = certificate.sequence()?;
+    if tbs.peek_tag() == Some(VERSION) {
+        tbs.skip()?;
+    }
+    // Serial number, signature algorithm, issuer, validity and subject.
+    for _ in 0..5 {
+        tbs.skip()?;
+    }
+    let start = tbs.0;
+    tbs.sequence()?;
+    subject_public_key_info(&start[..start.len() - tbs.0.len()])
+}
+
+/// `n`, `e` and `d` from a PKCS #1 `RSAPrivateKey`.
+pub fn rsa_private_key(der: &[u8]) -> Option<(Uint, Uint, Uint)> {
+    let mut key = Reader::new(der).sequence()?;
+    key.integer()?;
+    Some((key.integer()?, key.integer()?, key.integer()?))
+}
+
+/// `n`, `e` and `d` from a PKCS #8 `PrivateKeyInfo` holding an RSA key.
+pub fn private_key_info(der: &[u8]) -> Option<(Uint, Uint, Uint)> {
+    let mut info = Reader::new(der).sequence()?;
+    info.integer()?;
+    info.skip()?;
+    rsa_private_key(info.octet_string()?)
+}
+
+/// The base64 body of the first PEM block labelled `label`, decoded.
+pub fn pem(text: &str, label: &str) -> Option<Vec<u8>> {
+    let begin = alloc::format!("-----BEGIN {label}-----");
+    let end = alloc::format!("-----END {label}-----");
+    let start = text.find(&begin)? + begin.len();
+    let body = &text[start..start + text[start..].find(&end)?];
+    base64_decode(body)
+}
+
+/// Standard base64 with padding, ignoring whitespace.
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => continue,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reading() {
+        assert_eq!(base64_decode("Zm9v\nYmFy").unwrap(), b"foobar");
+        assert_eq!(base64_decode("Zg==").unwrap(), b"f");
+        assert_eq!(base64_decode("Z!"), None);
+        assert_eq!(
+            pem("x\n-----BEGIN A-----\nZm9v\n-----END A-----\n", "A").unwrap(),
+            b"foo"
+        );
+        assert_eq!(pem("-----BEGIN A-----\nZm9v\n", "A"), None);
+
+        // SEQUENCE { INTEGER 5, INTEGER 0x0100 }, the second with a long
+        // form length.
+        let der = [0x30, 0x08, 0x02, 0x01, 0x05, 0x02, 0x81, 0x02, 0x01, 0x00];
+        let (n, e) = rsa_public_key(&der).unwrap();
+        assert_eq!(n, Uint::from_be_bytes(&[5]));
+        assert_eq!(e, Uint::from_be_bytes(&[1, 0]));
+        assert_eq!(rsa_public_key(&der[..9]), None);
+    }
+}
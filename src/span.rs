@@ -0,0 +1,330 @@
+//! Parse tree that remembers where each value sits in the source.
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::{
+    fmt::{Display, Write},
+    ops::Range,
+};
+
+use crate::{
+    display::{Key, Preview},
+    lexer::{scan_integer, scan_string},
+    path::{self, Segment},
+    Bencode, Error, DEFAULT_MAX_DEPTH,
+};
+
+/// Byte range of an encoded value within the source.
+pub type Span = Range<usize>;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Node<'a> {
+    span: Span,
+    raw: &'a [u8],
+    kind: NodeKind<'a>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum NodeKind<'a> {
+    String(&'a [u8]),
+    Integer(i64),
+    List(Vec<Node<'a>>),
+    /// Entries in source order, duplicates included.
+    Dictionary(Vec<Entry<'a>>),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Entry<'a> {
+    pub key: &'a [u8],
+    pub key_span: Span,
+    pub value: Node<'a>,
+}
+
+impl<'a> Node<'a> {
+    /// Byte range of the whole encoded value, markers and prefixes included.
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    /// The exact source bytes of this value, suitable for hashing or copying
+    /// byte-for-byte.
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    pub fn kind(&self) -> &NodeKind<'a> {
+        &self.kind
+    }
+
+    /// Value of the last entry with the given key, as a decoder keeping only
+    /// one entry per key would see it.
+    pub fn get(&self, key: &[u8]) -> Option<&Node<'a>> {
+        match &self.kind {
+            NodeKind::Dictionary(entries) => entries
+                .iter()
+                .rev()
+                .find(|entry| entry.key == key)
+                .map(|entry| &entry.value),
+            _ => None,
+        }
+    }
+
+    pub fn get_path(&self, path: &str) -> Result<Option<&Node<'a>>, Error> {
+        Ok(self.get_segments(&path::parse(path)?))
+    }
+
+    pub(crate) fn get_segments(&self, segments: &[Segment]) -> Option<&Node<'a>> {
+        let mut node = self;
+
+        for segment in segments {
+            node = match (segment, &node.kind) {
+                (Segment::Key(key), NodeKind::Dictionary(_)) => node.get(key.as_bytes())?,
+                (Segment::Index(i), NodeKind::List(l)) => l.get(*i)?,
+                _ => return None,
+            };
+        }
+
+        Some(node)
+    }
+
+    /// Source bytes of the value at `path`, e.g. `.info` for an infohash.
+    pub fn get_raw(&self, path: &str) -> Result<Option<&'a [u8]>, Error> {
+        Ok(self.get_path(path)?.map(Node::raw))
+    }
+
+    /// Like [`Bencode::to_debug_tree`], with where each value sits in the
+    /// source, for pointing at the exact bytes of a damaged file:
+    /// `"pieces": bytes[20480] at 120, 20486 bytes`.
+    pub fn to_debug_tree(&self) -> String {
+        let mut out = String::new();
+        self.debug_tree(None, 0, &mut out);
+        out
+    }
+
+    fn debug_tree(&self, label: Option<&dyn Display>, depth: usize, out: &mut String) {
+        let _ = write!(out, "{:indent$}", "", indent = depth * 2);
+        if let Some(label) = label {
+            let _ = write!(out, "{label}: ");
+        }
+        let _ = match &self.kind {
+            NodeKind::String(s) => write!(out, "bytes[{}]", s.len()),
+            NodeKind::Integer(i) => write!(out, "int {i}"),
+            NodeKind::List(l) => write!(out, "list[{}]", l.len()),
+            NodeKind::Dictionary(entries) => write!(out, "dict[{}]", entries.len()),
+        };
+        let _ = write!(out, " at {}, {} bytes", self.span.start, self.raw.len());
+        if let NodeKind::String(s) = &self.kind {
+            let _ = write!(out, "{}", Preview(s));
+        }
+        out.push('\n');
+
+        match &self.kind {
+            NodeKind::List(l) => {
+                for (index, item) in l.iter().enumerate() {
+                    item.debug_tree(Some(&format_args!("[{index}]")), depth + 1, out);
+                }
+            }
+            NodeKind::Dictionary(entries) => {
+                for entry in entries {
+                    entry
+                        .value
+                        .debug_tree(Some(&Key(entry.key)), depth + 1, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Converts to an owned value, dropping the span information.
+    pub fn to_bencode(&self) -> Bencode {
+        match &self.kind {
+            NodeKind::String(s) => Bencode::String(s.to_vec()),
+            NodeKind::Integer(i) => Bencode::Integer(*i),
+            NodeKind::List(l) => Bencode::List(l.iter().map(Node::to_bencode).collect()),
+            NodeKind::Dictionary(entries) => Bencode::Dictionary(
+                entries
+                    .iter()
+                    .map(|entry| (entry.key.to_vec(), entry.value.to_bencode()))
+                    .collect::<BTreeMap<_, _>>(),
+            ),
+        }
+    }
+}
+
+/// Parses a complete document, recording the span of every node. Nesting
+/// deeper than [`DEFAULT_MAX_DEPTH`] is an [`Error::DepthLimitExceeded`].
+pub fn parse_spanned(source: &[u8]) -> Result<Node<'_>, Error> {
+    parse_spanned_within(source, Some(DEFAULT_MAX_DEPTH))
+}
+
+/// Like [`parse_spanned`], allowing nesting as deep as `max_depth`, as in
+/// [`DecodeOptions`](crate::DecodeOptions); `None` lifts the limit.
+pub fn parse_spanned_within(source: &[u8], max_depth: Option<usize>) -> Result<Node<'_>, Error> {
+    let (node, end) = parse_node(source, 0, max_depth.unwrap_or(usize::MAX))?;
+    if end != source.len() {
+        return Err(Error::TrailingData);
+    }
+    Ok(node)
+}
+
+/// `depth` is how many more levels of nesting may be opened.
+fn parse_node(source: &[u8], start: usize, depth: usize) -> Result<(Node<'_>, usize), Error> {
+    let rest = &source[start..];
+
+    let (kind, end) = match rest.first() {
+        None => return Err(Error::needs(1)),
+        Some(b'0'..=b'9') => {
+            let (string, after) = scan_string(rest).map_err(|error| error.offset_by(start))?;
+            (NodeKind::String(string), source.len() - after.len())
+        }
+        Some(b'i') => {
+            let (number, after) = scan_integer(rest).map_err(|error| error.offset_by(start))?;
+            (NodeKind::Integer(number), source.len() - after.len())
+        }
+        Some(b'l' | b'd') if depth == 0 => return Err(Error::DepthLimitExceeded),
+        Some(b'l') => {
+            let mut offset = start + 1;
+            let mut list = Vec::new();
+
+            while !at_end(source, offset)? {
+                let (node, end) =
+                    parse_node(source, offset, depth - 1).map_err(|error| error.needing_more(1))?;
+                list.push(node);
+                offset = end;
+            }
+
+            (NodeKind::List(list), offset + 1)
+        }
+        Some(b'd') => {
+            let mut offset = start + 1;
+            let mut entries = Vec::new();
+
+            while !at_end(source, offset)? {
+                // Still to come are at least a value and the e
+                let (key, value_start) =
+                    parse_node(source, offset, depth - 1).map_err(|error| error.needing_more(2))?;
+                let NodeKind::String(key_bytes) = key.kind else {
+                    return Err(Error::NonStringKey { offset });
+                };
+                let (value, end) = parse_node(source, value_start, depth - 1)
+                    .map_err(|error| error.needing_more(1))?;
+                entries.push(Entry {
+                    key: key_bytes,
+                    key_span: key.span,
+                    value,
+                });
+                offset = end;
+            }
+
+            (NodeKind::Dictionary(entries), offset + 1)
+        }
+        Some(&b) => return Err(Error::UnexpectedByte(b)),
+    };
+
+    Ok((
+        Node {
+            span: start..end,
+            raw: &source[start..end],
+            kind,
+        },
+        end,
+    ))
+}
+
+/// Whether the `e` closing a list or dictionary is at `offset`.
+fn at_end(source: &[u8], offset: usize) -> Result<bool, Error> {
+    match source.get(offset) {
+        None => Err(Error::needs(1)),
+        Some(&byte) => Ok(byte == b'e'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn spans() {
+        let source = b"d3:fooli1e2:abe4:infod4:name1:xee";
+        let root = parse_spanned(source).unwrap();
+
+        assert_eq!(root.span(), 0..source.len());
+        assert_eq!(root.get_path(".foo").unwrap().unwrap().span(), 6..15);
+        assert_eq!(root.get_path(".foo[1]").unwrap().unwrap().span(), 10..14);
+        assert_eq!(
+            &source[root.get_path(".info").unwrap().unwrap().span()],
+            b"d4:name1:xe"
+        );
+
+        let NodeKind::Dictionary(entries) = root.kind() else {
+            panic!("root should be a dictionary");
+        };
+        assert_eq!(entries[1].key, b"info");
+        assert_eq!(entries[1].key_span, 15..21);
+
+        assert_eq!(root.to_bencode(), decode(source).unwrap());
+    }
+
+    #[test]
+    fn raw_subtrees() {
+        let source = b"d4:infod6:lengthi1e4:name1:xe5:otherli1e1:yee";
+        let root = parse_spanned(source).unwrap();
+
+        assert_eq!(root.raw(), source);
+        assert_eq!(
+            root.get_raw(".info"),
+            Ok(Some(&b"d6:lengthi1e4:name1:xe"[..]))
+        );
+        assert_eq!(root.get_raw(".other[1]"), Ok(Some(&b"1:y"[..])));
+        assert_eq!(root.get_raw(".missing"), Ok(None));
+    }
+
+    #[test]
+    fn keeps_duplicate_keys() {
+        let root = parse_spanned(b"d1:ai1e1:ai2ee").unwrap();
+
+        let NodeKind::Dictionary(entries) = root.kind() else {
+            panic!("root should be a dictionary");
+        };
+        assert_eq!(entries.len(), 2);
+        assert_eq!(root.get(b"a").unwrap().kind(), &NodeKind::Integer(2));
+    }
+
+    #[test]
+    fn debug_tree() {
+        let root = parse_spanned(b"d1:ai1e1:ai2e4:infod4:name3:foo6:pieces2:\x00\xffee").unwrap();
+        assert_eq!(
+            root.to_debug_tree(),
+            "\
+dict[3] at 0, 45 bytes
+  \"a\": int 1 at 4, 3 bytes
+  \"a\": int 2 at 10, 3 bytes
+  \"info\": dict[2] at 19, 25 bytes
+    \"name\": bytes[3] at 26, 5 bytes \"foo\"
+    \"pieces\": bytes[2] at 39, 4 bytes
+"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_spanned(b"l1:a"), Err(Error::needs(1)));
+        assert_eq!(
+            parse_spanned(b"di1ei2ee"),
+            Err(Error::NonStringKey { offset: 1 })
+        );
+        assert_eq!(parse_spanned(b"i1e "), Err(Error::TrailingData));
+
+        let nested = |depth: usize| [vec![b'l'; depth], vec![b'e'; depth]].concat();
+        assert!(parse_spanned(&nested(DEFAULT_MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse_spanned(&nested(200_000)),
+            Err(Error::DepthLimitExceeded)
+        );
+        assert_eq!(
+            parse_spanned_within(b"ld1:alee", Some(2)),
+            Err(Error::DepthLimitExceeded)
+        );
+        assert!(parse_spanned_within(&nested(DEFAULT_MAX_DEPTH + 1), None).is_ok());
+    }
+}
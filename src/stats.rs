@@ -0,0 +1,141 @@
+//! Counts of what a file is made of, for `stats`, and with
+//! `--size-breakdown` which paths its bytes go to, measured on the spans of
+//! the source so they add up to what's on disk.
+
+use bencode_decoder::span::{Node, NodeKind};
+
+/// Smallest share of the file a path needs to be listed in the breakdown,
+/// so a list of thousands of small files doesn't push everything else out.
+const MIN_SHARE: f64 = 0.01;
+
+#[derive(PartialEq, Debug, Default)]
+pub struct Stats {
+    pub strings: usize,
+    pub integers: usize,
+    pub lists: usize,
+    pub dictionaries: usize,
+    /// Bytes of string payload, keys included, without length prefixes.
+    pub string_bytes: usize,
+    /// Deepest nesting of lists and dictionaries; a flat list has depth 1.
+    pub max_depth: usize,
+    pub total: usize,
+}
+
+impl Stats {
+    pub fn new(root: &Node) -> Self {
+        let mut stats = Stats {
+            total: root.span().len(),
+            ..Default::default()
+        };
+        stats.count(root, 0);
+        stats
+    }
+
+    fn count(&mut self, node: &Node, depth: usize) {
+        match node.kind() {
+            NodeKind::String(s) => {
+                self.strings += 1;
+                self.string_bytes += s.len();
+            }
+            NodeKind::Integer(_) => self.integers += 1,
+            NodeKind::List(items) => {
+                self.lists += 1;
+                self.max_depth = self.max_depth.max(depth + 1);
+                for item in items {
+                    self.count(item, depth + 1);
+                }
+            }
+            NodeKind::Dictionary(entries) => {
+                self.dictionaries += 1;
+                self.max_depth = self.max_depth.max(depth + 1);
+                for entry in entries {
+                    self.string_bytes += entry.key.len();
+                    self.count(&entry.value, depth + 1);
+                }
+            }
+        }
+    }
+}
+
+/// Paths below the root taking at least [`MIN_SHARE`] of its encoded size,
+/// largest first, with their sizes in bytes. A list or dictionary counts
+/// its contents too, so `.info` is listed above `.info.pieces`.
+pub fn size_breakdown(root: &Node) -> Vec<(String, usize)> {
+    let min = (root.span().len() as f64 * MIN_SHARE).ceil() as usize;
+    let mut paths = Vec::new();
+    collect(root, &mut String::new(), min, &mut paths);
+    // Stable, so paths of the same size stay in document order
+    paths.sort_by(|(_, a), (_, b)| b.cmp(a));
+    paths
+}
+
+fn collect(node: &Node, path: &mut String, min: usize, paths: &mut Vec<(String, usize)>) {
+    let children: Vec<(String, &Node)> = match node.kind() {
+        NodeKind::List(items) => items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (format!("[{index}]"), item))
+            .collect(),
+        NodeKind::Dictionary(entries) => entries
+            .iter()
+            .map(|entry| {
+                let key = String::from_utf8_lossy(entry.key);
+                (format!(".{key}"), &entry.value)
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    for (segment, child) in children {
+        // Smaller values can't hold anything big enough either
+        if child.span().len() < min {
+            continue;
+        }
+        let len = path.len();
+        path.push_str(&segment);
+        paths.push((path.clone(), child.span().len()));
+        collect(child, path, min, paths);
+        path.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bencode_decoder::span::parse_spanned;
+
+    #[test]
+    fn counts() {
+        let root = parse_spanned(b"d1:ali1ei2ee1:bd1:c3:xyzee").unwrap();
+        assert_eq!(
+            Stats::new(&root),
+            Stats {
+                strings: 1,
+                integers: 2,
+                lists: 1,
+                dictionaries: 2,
+                string_bytes: 6,
+                max_depth: 2,
+                total: 26,
+            }
+        );
+    }
+
+    #[test]
+    fn breakdown() {
+        let mut input = b"d7:comment10:0123456789".to_vec();
+        input.extend_from_slice(b"4:infod4:name1:x6:pieces200:");
+        input.extend_from_slice(&[0; 200]);
+        input.extend_from_slice(b"ee");
+        let root = parse_spanned(&input).unwrap();
+        assert_eq!(
+            size_breakdown(&root),
+            [
+                (".info".to_string(), 223),
+                (".info.pieces".to_string(), 204),
+                (".comment".to_string(), 13),
+                (".info.name".to_string(), 3),
+            ]
+        );
+    }
+}
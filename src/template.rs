@@ -0,0 +1,108 @@
+//! The placeholder language of `info --format`. `{name}` is replaced by a
+//! field, `{.info.name}` by the value at a path, `{{` and `}}` are literal
+//! braces and `\t`, `\n` and `\\` are escapes, so that tab-separated
+//! formats can be written without shell quoting tricks.
+
+#[derive(PartialEq, Debug)]
+enum Piece {
+    Text(String),
+    Field(String),
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Template(Vec<Piece>);
+
+impl Template {
+    /// Parses `source`, accepting `fields` and paths as placeholders.
+    pub fn parse(source: &str, fields: &[&str]) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some('\\') => text.push('\\'),
+                    Some(other) => {
+                        text.push('\\');
+                        text.push(other);
+                    }
+                    None => text.push('\\'),
+                },
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err("unmatched `}`, write `}}` for a literal brace".into()),
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or("unterminated `{` placeholder")?;
+                    let name = rest[..end].trim();
+                    if !name.starts_with('.') && !fields.contains(&name) {
+                        return Err(format!(
+                            "unknown field `{name}`, expected a path like `.info.name` or one of: {}",
+                            fields.join(", ")
+                        ));
+                    }
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Field(name.into()));
+                    chars = rest[end + 1..].chars();
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+
+        Ok(Self(pieces))
+    }
+
+    /// Fills in each placeholder with `field`; missing values are empty.
+    pub fn render(&self, field: impl Fn(&str) -> Option<String>) -> String {
+        let mut out = String::new();
+        for piece in &self.0 {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Field(name) => out.push_str(&field(name).unwrap_or_default()),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_render() {
+        let template =
+            Template::parse(r"{name}\t{ size }\n{{{.info.name}}}", &["name", "size"]).unwrap();
+        let rendered = template.render(|field| match field {
+            "name" => Some("a".into()),
+            ".info.name" => Some("b".into()),
+            _ => None,
+        });
+        assert_eq!(rendered, "a\t\n{b}");
+
+        assert_eq!(
+            Template::parse(r"C:\x", &[]).unwrap().render(|_| None),
+            r"C:\x"
+        );
+        assert_eq!(
+            Template::parse("{nope}", &["name"]).unwrap_err(),
+            "unknown field `nope`, expected a path like `.info.name` or one of: name"
+        );
+        assert!(Template::parse("{name", &["name"]).is_err());
+        assert!(Template::parse("}", &[]).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! Unix timestamps, as stored in `creation date` and client resume data.
+
+use core::fmt::{self, Display, Formatter};
+
+use crate::{Bencode, Error, FromBencode, ToBencode};
+
+/// Seconds since the Unix epoch, between 1970 and the end of year 9999.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Hash)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    /// 9999-12-31T23:59:59Z
+    const MAX: i64 = 253_402_300_799;
+
+    /// Returns `None` for values outside the supported range, like negative
+    /// times or millisecond timestamps written by some clients.
+    pub fn from_unix(seconds: i64) -> Option<Self> {
+        (0..=Self::MAX).contains(&seconds).then_some(Self(seconds))
+    }
+
+    pub fn unix(self) -> i64 {
+        self.0
+    }
+
+    #[cfg(feature = "std")]
+    pub fn to_system_time(self) -> std::time::SystemTime {
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(self.0 as u64)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Timestamp> for std::time::SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.to_system_time()
+    }
+}
+
+/// Formats as UTC, like `2023-11-14 22:13:20 UTC`.
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (days, seconds) = (self.0 / 86_400, self.0 % 86_400);
+
+        // Days to civil date, from Howard Hinnant's `civil_from_days`
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+impl FromBencode for Timestamp {
+    fn from_bencode(value: &Bencode) -> Result<Self, Error> {
+        match value {
+            Bencode::Integer(seconds) => Timestamp::from_unix(*seconds)
+                .ok_or_else(|| Error::invalid_type("Unix timestamp in seconds")),
+            _ => Err(Error::invalid_type("integer")),
+        }
+    }
+}
+
+impl ToBencode for Timestamp {
+    fn to_bencode(&self) -> Bencode {
+        Bencode::Integer(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn display() {
+        let format = |seconds| Timestamp::from_unix(seconds).unwrap().to_string();
+
+        assert_eq!(format(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format(1_700_000_000), "2023-11-14 22:13:20 UTC");
+        assert_eq!(format(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format(Timestamp::MAX), "9999-12-31 23:59:59 UTC");
+    }
+
+    #[test]
+    fn validation() {
+        assert_eq!(Timestamp::from_unix(-1), None);
+        assert_eq!(Timestamp::from_unix(1_700_000_000_000), None);
+        assert_eq!(
+            Timestamp::from_bencode(&Bencode::Integer(-5)),
+            Err(Error::invalid_type("Unix timestamp in seconds"))
+        );
+        assert_eq!(
+            Timestamp::from_bencode(&Bencode::Integer(60)).map(Timestamp::unix),
+            Ok(60)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn system_time() {
+        let time = std::time::SystemTime::from(Timestamp::from_unix(86_400).unwrap());
+        assert_eq!(
+            time.duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            86_400
+        );
+    }
+}
@@ -0,0 +1,637 @@
+//! Helpers for `.torrent` metainfo files.
+
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+
+use crate::{
+    convert::derive::{bytes, dictionary, field, optional_bytes, optional_field},
+    decode, decode_bencoded_value, hex,
+    sha1::sha1,
+    timestamp::Timestamp,
+    Bencode, Error,
+};
+
+/// Returns the exact bytes of the top-level `info` dictionary, as they appear
+/// in the file. Hashing these rather than a re-encoding keeps the infohash
+/// correct for torrents that aren't canonically encoded.
+pub fn raw_info(torrent: &[u8]) -> Result<&[u8], Error> {
+    let Some(mut rest) = torrent.strip_prefix(b"d") else {
+        return Err(Error::invalid_type("dictionary"));
+    };
+
+    loop {
+        if rest.starts_with(b"e") || rest.is_empty() {
+            return Err(Error::missing_key("info"));
+        }
+
+        let (key, value) = decode_bencoded_value(rest)?;
+        let (_, after) = decode_bencoded_value(value)?;
+        if key == Bencode::String(b"info".to_vec()) {
+            if !value.starts_with(b"d") {
+                return Err(Error::invalid_type("dictionary").at_key("info"));
+            }
+            return Ok(&value[..value.len() - after.len()]);
+        }
+
+        rest = after;
+    }
+}
+
+/// SHA-1 of the raw `info` dictionary.
+pub fn infohash(torrent: &[u8]) -> Result<[u8; 20], Error> {
+    Ok(sha1(raw_info(torrent)?))
+}
+
+/// The forms of an infohash that trackers and tools expect.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum HashEncoding {
+    /// 40 lowercase hex digits.
+    Hex,
+    /// 32 uppercase RFC 4648 base32 characters, as in older magnet links.
+    Base32,
+    /// The 20 bytes themselves.
+    Raw,
+}
+
+/// Encodes an infohash from [`infohash`] as `encoding`.
+pub fn encode_infohash(hash: &[u8; 20], encoding: HashEncoding) -> Vec<u8> {
+    match encoding {
+        HashEncoding::Hex => hex::encode(hash).into_bytes(),
+        HashEncoding::Base32 => base32(hash).into_bytes(),
+        HashEncoding::Raw => hash.to_vec(),
+    }
+}
+
+/// Reads an infohash written as 40 hex digits or 32 base32 characters, of
+/// either case, as magnet links carry it.
+pub fn decode_infohash(text: &str) -> Option<[u8; 20]> {
+    let bytes = match text.len() {
+        40 => hex::decode(text.as_bytes())?,
+        32 => unbase32(text.as_bytes())?,
+        _ => return None,
+    };
+    bytes.try_into().ok()
+}
+
+/// Builds a `magnet:` URI with the infohash, display name and trackers.
+pub fn magnet_link(torrent: &[u8]) -> Result<String, Error> {
+    let infohash = infohash(torrent)?;
+    let value = decode(torrent)?;
+
+    let mut link = format!("magnet:?xt=urn:btih:{}", hex::encode(&infohash));
+
+    if let Some(Bencode::String(name)) = value.get_path(".info.name")? {
+        link.push_str("&dn=");
+        link.push_str(&percent_encode(name));
+    }
+
+    for tracker in trackers(&value) {
+        link.push_str("&tr=");
+        link.push_str(&percent_encode(tracker));
+    }
+
+    Ok(link)
+}
+
+/// The `creation date` of a decoded torrent, if it has one. Dates that
+/// aren't plausible Unix timestamps are errors.
+pub fn creation_date(torrent: &Bencode) -> Result<Option<Timestamp>, Error> {
+    optional_field(dictionary(torrent)?, "creation date")
+}
+
+/// Collects `announce` and every `announce-list` tracker of a decoded
+/// torrent, without repeats.
+pub fn trackers(value: &Bencode) -> Vec<&[u8]> {
+    let mut trackers: Vec<&[u8]> = Vec::new();
+
+    if let Ok(Some(Bencode::String(announce))) = value.get_path(".announce") {
+        trackers.push(announce);
+    }
+
+    if let Ok(Some(Bencode::List(tiers))) = value.get_path(".announce-list") {
+        for tier in tiers {
+            let Bencode::List(tier) = tier else { continue };
+            for tracker in tier {
+                if let Bencode::String(tracker) = tracker {
+                    if !trackers.contains(&tracker.as_slice()) {
+                        trackers.push(tracker);
+                    }
+                }
+            }
+        }
+    }
+
+    trackers
+}
+
+/// One file of a torrent. `path` starts with the torrent's `name` and holds
+/// the components exactly as stored, which may be hostile: check them with
+/// [`is_path_safe`] or clean them with [`sanitize_path`] before touching the
+/// filesystem.
+#[derive(PartialEq, Debug)]
+pub struct File {
+    pub path: Vec<Vec<u8>>,
+    pub length: i64,
+    /// The root of the file's merkle tree in a v2 or hybrid torrent (BEP
+    /// 52). Empty files have none.
+    pub pieces_root: Option<[u8; 32]>,
+}
+
+/// Lists the files of a decoded single- or multi-file torrent. A v1 file
+/// list is preferred, since its order (with any padding files) is the one
+/// the v1 pieces span; v2 `file tree` entries fill in the pieces roots.
+pub fn files(torrent: &Bencode) -> Result<Vec<File>, Error> {
+    let info = dictionary(torrent)?
+        .get(&b"info"[..])
+        .ok_or_else(|| Error::missing_key("info"))?;
+    let at_info = |error: Error| error.at_key("info");
+    let d = dictionary(info).map_err(at_info)?;
+    let name: Vec<u8> = bytes(d, "name").map_err(at_info)?;
+
+    let mut tree = Vec::new();
+    if let Some(root) = d.get(&b"file tree"[..]) {
+        let at_tree = |error: Error| at_info(error.at_key("file tree"));
+        let root = dictionary(root).map_err(at_tree)?;
+        // A single-file torrent's tree holds just the file, under `name`.
+        let single = root.len() == 1
+            && matches!(root.get(&name), Some(Bencode::Dictionary(entry))
+                if entry.contains_key(&b""[..]));
+        let prefix = match single {
+            true => Vec::new(),
+            false => alloc::vec![name.clone()],
+        };
+        file_tree(root, prefix, &mut tree).map_err(at_tree)?;
+    }
+
+    let mut files = match (d.get(&b"files"[..]), d.get(&b"length"[..])) {
+        (None, None) if d.contains_key(&b"file tree"[..]) => return Ok(tree),
+        (None, _) => alloc::vec![File {
+            path: alloc::vec![name],
+            length: field(d, "length").map_err(at_info)?,
+            pieces_root: None,
+        }],
+        (Some(Bencode::List(entries)), _) => v1_files(entries, &name).map_err(at_info)?,
+        (Some(_), _) => return Err(at_info(Error::invalid_type("list").at_key("files"))),
+    };
+    for file in &mut files {
+        file.pieces_root = tree
+            .iter()
+            .find(|entry| entry.path == file.path)
+            .and_then(|entry| entry.pieces_root);
+    }
+    Ok(files)
+}
+
+fn v1_files(entries: &[Bencode], name: &[u8]) -> Result<Vec<File>, Error> {
+    let mut files = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let at = |error: Error| error.at_index(i).at_key("files");
+        let entry = dictionary(entry).map_err(at)?;
+
+        let mut path = alloc::vec![name.to_vec()];
+        for (j, component) in field::<Vec<Bencode>>(entry, "path")
+            .map_err(at)?
+            .into_iter()
+            .enumerate()
+        {
+            match component {
+                Bencode::String(component) => path.push(component),
+                _ => return Err(at(Error::invalid_type("string").at_index(j).at_key("path"))),
+            }
+        }
+        files.push(File {
+            path,
+            length: field(entry, "length").map_err(at)?,
+            pieces_root: None,
+        });
+    }
+    Ok(files)
+}
+
+/// Walks a v2 `file tree`, where a file is a dictionary under the empty key
+/// and every other key is a path component.
+fn file_tree(
+    tree: &BTreeMap<Vec<u8>, Bencode>,
+    path: Vec<Vec<u8>>,
+    files: &mut Vec<File>,
+) -> Result<(), Error> {
+    for (component, node) in tree {
+        let at = |error: Error| error.at_key(&lossy(component));
+        let node = dictionary(node).map_err(at)?;
+        let mut path = path.clone();
+
+        if component.is_empty() {
+            files.push(File {
+                path,
+                length: field(node, "length").map_err(at)?,
+                pieces_root: optional_bytes(node, "pieces root").map_err(at)?,
+            });
+        } else {
+            path.push(component.clone());
+            file_tree(node, path, files).map_err(at)?;
+        }
+    }
+    Ok(())
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// The SHA-1 hash of each piece, from the `pieces` string of a decoded
+/// torrent, which must be a whole number of 20-byte hashes.
+pub fn pieces(torrent: &Bencode) -> Result<Vec<[u8; 20]>, Error> {
+    let pieces = match torrent.get_path("info.pieces")? {
+        Some(Bencode::String(pieces)) => pieces,
+        Some(_) => {
+            return Err(Error::invalid_type("string")
+                .at_key("pieces")
+                .at_key("info"))
+        }
+        None => return Err(Error::missing_key("pieces").at_key("info")),
+    };
+    let (hashes, rest) = pieces.as_chunks::<20>();
+    if !rest.is_empty() {
+        return Err(Error::invalid_type("a multiple of 20 bytes")
+            .at_key("pieces")
+            .at_key("info"));
+    }
+    Ok(hashes.to_vec())
+}
+
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Windows device names, which refer to the device whatever the extension.
+fn is_reserved(component: &[u8]) -> bool {
+    let stem = component.split(|&b| b == b'.').next().unwrap_or_default();
+    RESERVED_NAMES
+        .iter()
+        .any(|name| stem.eq_ignore_ascii_case(name.as_bytes()))
+}
+
+fn is_component_safe(component: &[u8]) -> bool {
+    !matches!(component, b"" | b"." | b"..")
+        && !component
+            .iter()
+            .any(|&b| b < 0x20 || b"/\\:*?\"<>|".contains(&b))
+        && !component.ends_with(b".")
+        && !component.ends_with(b" ")
+        && !is_reserved(component)
+}
+
+/// Whether a path from a torrent can be joined onto a download directory
+/// as-is on any platform: no empty, `.` or `..` components, no separators,
+/// drive letters or other characters Windows rejects inside a component, and
+/// no reserved device names.
+pub fn is_path_safe<C: AsRef<[u8]>>(path: &[C]) -> bool {
+    !path.is_empty()
+        && path
+            .iter()
+            .all(|component| is_component_safe(component.as_ref()))
+}
+
+/// Rewrites a path from a torrent so that it passes [`is_path_safe`]:
+/// empty, `.` and `..` components are dropped, forbidden characters become
+/// `_`, trailing dots and spaces are trimmed and reserved names get a `_`
+/// suffix. Invalid UTF-8 is replaced.
+pub fn sanitize_path<C: AsRef<[u8]>>(path: &[C]) -> Vec<String> {
+    let mut sanitized = Vec::new();
+
+    for component in path {
+        let component = component.as_ref();
+        if matches!(component, b"" | b"." | b"..") {
+            continue;
+        }
+
+        let mut clean: String = String::from_utf8_lossy(component)
+            .chars()
+            .map(|c| match c {
+                '\0'..='\x1f' | '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c => c,
+            })
+            .collect();
+        clean.truncate(clean.trim_end_matches(['.', ' ']).len());
+        if clean.is_empty() {
+            clean.push('_');
+        } else if is_reserved(clean.as_bytes()) {
+            match clean.find('.') {
+                Some(dot) => clean.insert(dot, '_'),
+                None => clean.push('_'),
+            }
+        }
+        sanitized.push(clean);
+    }
+
+    if sanitized.is_empty() {
+        sanitized.push("_".into());
+    }
+    sanitized
+}
+
+/// RFC 4648 base32. Infohashes are a multiple of 5 bytes long and so need
+/// no padding; other lengths get none either.
+fn base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for &b in bytes {
+        buffer = (buffer << 8) | u16::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[usize::from(buffer >> bits) & 31] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(ALPHABET[usize::from(buffer << (5 - bits)) & 31] as char);
+    }
+    encoded
+}
+
+/// Reverses [`base32`], for characters of either case. Bits left over at
+/// the end, as the padding of a partial byte, are dropped.
+fn unbase32(text: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(text.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for &c in text {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'A'..=b'Z' => c - b'A',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | u16::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+fn percent_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    for &b in bytes {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::DictBuilder;
+
+    const TORRENT: &[u8] = b"d8:announce14:http://a/track13:announce-listll14:http://a/trackel9:udp://b:1ee4:infod6:lengthi5e4:name5:a b.c12:piece lengthi16384e6:pieces0:ee";
+
+    #[test]
+    fn raw_info_slice() {
+        assert_eq!(
+            raw_info(TORRENT),
+            Ok(&b"d6:lengthi5e4:name5:a b.c12:piece lengthi16384e6:pieces0:e"[..])
+        );
+        assert_eq!(raw_info(b"d4:name1:ae"), Err(Error::missing_key("info")));
+        assert_eq!(
+            raw_info(b"d4:infoi1ee"),
+            Err(Error::invalid_type("dictionary").at_key("info"))
+        );
+    }
+
+    #[test]
+    fn infohash_encodings() {
+        let hash = infohash(TORRENT).unwrap();
+        let hex = encode_infohash(&hash, HashEncoding::Hex);
+        assert_eq!(hex.len(), 40);
+        assert_eq!(
+            &magnet_link(TORRENT).unwrap().as_bytes()[20..60],
+            hex.as_slice()
+        );
+        assert_eq!(encode_infohash(&hash, HashEncoding::Raw), hash);
+
+        assert_eq!(
+            encode_infohash(&[0xff; 20], HashEncoding::Base32),
+            b"77777777777777777777777777777777"
+        );
+        // RFC 4648 test vectors, without the padding.
+        for (input, encoded) in [
+            ("", ""),
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foobar", "MZXW6YTBOI"),
+        ] {
+            assert_eq!(base32(input.as_bytes()), encoded);
+            assert_eq!(unbase32(encoded.as_bytes()).unwrap(), input.as_bytes());
+        }
+    }
+
+    #[test]
+    fn infohash_decoding() {
+        let hash = infohash(TORRENT).unwrap();
+        for encoding in [HashEncoding::Hex, HashEncoding::Base32] {
+            let encoded = String::from_utf8(encode_infohash(&hash, encoding)).unwrap();
+            assert_eq!(decode_infohash(&encoded), Some(hash));
+            assert_eq!(decode_infohash(&encoded.to_ascii_lowercase()), Some(hash));
+            assert_eq!(decode_infohash(&encoded.to_ascii_uppercase()), Some(hash));
+        }
+        assert_eq!(
+            decode_infohash("77777777777777777777777777777777"),
+            Some([0xff; 20])
+        );
+        assert_eq!(decode_infohash("7777777777777777777777777777777"), None);
+        assert_eq!(decode_infohash("1777777777777777777777777777777A"), None);
+        assert_eq!(decode_infohash(&"g".repeat(40)), None);
+    }
+
+    #[test]
+    fn file_lists() {
+        assert_eq!(
+            files(&decode(TORRENT).unwrap()),
+            Ok(alloc::vec![File {
+                path: alloc::vec![b"a b.c".to_vec()],
+                length: 5,
+                pieces_root: None,
+            }])
+        );
+
+        let multi = decode(
+            b"d4:infod5:filesld6:lengthi1e4:pathl1:a1:beed6:lengthi2e4:pathl2:..eee4:name3:diree",
+        )
+        .unwrap();
+        let files = files(&multi).unwrap();
+        assert_eq!(files[0].path, [&b"dir"[..], b"a", b"b"]);
+        assert_eq!(files[1].length, 2);
+        assert!(is_path_safe(&files[0].path));
+        assert!(!is_path_safe(&files[1].path));
+
+        let bad = decode(b"d4:infod5:filesld6:lengthi1e4:pathli1eeee4:name1:aee").unwrap();
+        assert_eq!(
+            super::files(&bad).unwrap_err().to_string(),
+            "expected string at path `info.files[0].path[0]`"
+        );
+    }
+
+    #[test]
+    fn v2_file_tree() {
+        let root = |byte: u8| [byte; 32];
+        let leaf = |length: i64, root: Option<[u8; 32]>| {
+            let mut file = DictBuilder::new().insert("length", length);
+            if let Some(root) = root {
+                file = file.insert("pieces root", root.to_vec());
+            }
+            DictBuilder::new().insert("", file).build()
+        };
+
+        let v2 = DictBuilder::new()
+            .insert(
+                "info",
+                DictBuilder::new().insert("name", "dir").insert(
+                    "file tree",
+                    DictBuilder::new()
+                        .insert("b", leaf(0, None))
+                        .insert("a", DictBuilder::new().insert("x", leaf(3, Some(root(1))))),
+                ),
+            )
+            .build();
+        assert_eq!(
+            files(&v2),
+            Ok(alloc::vec![
+                File {
+                    path: alloc::vec![b"dir".to_vec(), b"a".to_vec(), b"x".to_vec()],
+                    length: 3,
+                    pieces_root: Some(root(1)),
+                },
+                File {
+                    path: alloc::vec![b"dir".to_vec(), b"b".to_vec()],
+                    length: 0,
+                    pieces_root: None,
+                },
+            ])
+        );
+
+        let hybrid = DictBuilder::new()
+            .insert(
+                "info",
+                DictBuilder::new()
+                    .insert("name", "a")
+                    .insert("length", 3)
+                    .insert(
+                        "file tree",
+                        DictBuilder::new().insert("a", leaf(3, Some(root(2)))),
+                    ),
+            )
+            .build();
+        assert_eq!(files(&hybrid).unwrap()[0].pieces_root, Some(root(2)));
+
+        let bad = DictBuilder::new()
+            .insert(
+                "info",
+                DictBuilder::new().insert("name", "a").insert(
+                    "file tree",
+                    DictBuilder::new().insert(
+                        "a",
+                        DictBuilder::new().insert(
+                            "",
+                            DictBuilder::new()
+                                .insert("length", 1)
+                                .insert("pieces root", "short"),
+                        ),
+                    ),
+                ),
+            )
+            .build();
+        assert_eq!(
+            files(&bad).unwrap_err().to_string(),
+            "expected byte string of the expected length at path `info.file tree.a..pieces root`"
+        );
+    }
+
+    #[test]
+    fn piece_hashes() {
+        let torrent = |pieces: &[u8]| {
+            let mut raw = b"d4:infod6:pieces".to_vec();
+            raw.extend(format!("{}:", pieces.len()).as_bytes());
+            raw.extend(pieces);
+            decode(&[raw, b"ee".to_vec()].concat()).unwrap()
+        };
+        let hashes = [[1; 20], [2; 20]];
+        assert_eq!(pieces(&torrent(&hashes.concat())), Ok(hashes.to_vec()));
+        assert_eq!(pieces(&torrent(b"")), Ok(alloc::vec![]));
+        assert_eq!(
+            pieces(&torrent(&[0; 21])).unwrap_err().to_string(),
+            "expected a multiple of 20 bytes at path `info.pieces`"
+        );
+        assert_eq!(
+            pieces(&decode(b"d4:infodee").unwrap()),
+            Err(Error::missing_key("pieces").at_key("info"))
+        );
+    }
+
+    #[test]
+    fn path_safety() {
+        assert!(is_path_safe(&["dir", "file.txt"]));
+        assert!(is_path_safe(&["CONSOLE.txt"]));
+
+        for unsafe_path in [
+            &[][..],
+            &["a", ".."],
+            &["/etc", "passwd"],
+            &["a/../../b"],
+            &["C:", "Windows"],
+            &["..\\evil"],
+            &["nul.txt"],
+            &["Com1"],
+            &["trailing."],
+            &["a\nb"],
+        ] {
+            assert!(!is_path_safe(unsafe_path), "{unsafe_path:?}");
+        }
+    }
+
+    #[test]
+    fn sanitizing() {
+        assert_eq!(sanitize_path(&["", ".", "..", "a", "..", "b"]), ["a", "b"]);
+        assert_eq!(sanitize_path(&["/etc/passwd"]), ["_etc_passwd"]);
+        assert_eq!(sanitize_path(&["C:", "x. "]), ["C_", "x"]);
+        assert_eq!(
+            sanitize_path(&["aux.tar.gz", "LPT1"]),
+            ["aux_.tar.gz", "LPT1_"]
+        );
+        assert_eq!(sanitize_path(&[".."]), ["_"]);
+        assert_eq!(sanitize_path(&[&b"\xff"[..]]), ["\u{fffd}"]);
+
+        for path in [&["..", "x"][..], &["con"], &["a:b", "..."]] {
+            assert!(is_path_safe(&sanitize_path(path)), "{path:?}");
+        }
+    }
+
+    #[test]
+    fn creation_dates() {
+        let date = |input| creation_date(&decode(input).unwrap());
+
+        assert_eq!(date(TORRENT), Ok(None));
+        assert_eq!(
+            date(b"d13:creation datei1700000000ee"),
+            Ok(Timestamp::from_unix(1700000000))
+        );
+        assert_eq!(
+            date(b"d13:creation datei1700000000000ee"),
+            Err(Error::invalid_type("Unix timestamp in seconds").at_key("creation date"))
+        );
+    }
+
+    #[test]
+    fn magnet() {
+        let infohash = hex::encode(&infohash(TORRENT).unwrap());
+
+        assert_eq!(
+            magnet_link(TORRENT).unwrap(),
+            format!("magnet:?xt=urn:btih:{infohash}&dn=a%20b.c&tr=http%3A%2F%2Fa%2Ftrack&tr=udp%3A%2F%2Fb%3A1")
+        );
+    }
+}
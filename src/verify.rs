@@ -0,0 +1,161 @@
+//! Checking downloaded data against a torrent's piece hashes, for `verify`.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use bencode_decoder::{sha1::sha1, torrent, Bencode};
+
+use crate::progress::Progress;
+
+pub struct Report {
+    pub pieces: usize,
+    /// Indices of pieces whose data doesn't match.
+    pub bad: Vec<usize>,
+    /// Files that couldn't be opened; their bytes count as zeros.
+    pub missing: Vec<PathBuf>,
+}
+
+/// Hashes the files of `torrent`, found under `dir`, piece by piece.
+pub fn verify(torrent: &Bencode, dir: &Path) -> Result<Report, Box<dyn Error>> {
+    let piece_length = match torrent.get_path("info.piece length")? {
+        Some(Bencode::Integer(length)) if *length > 0 => *length as usize,
+        _ => return Err("torrent has no valid `piece length`".into()),
+    };
+    let hashes = torrent::pieces(torrent)?;
+
+    let mut files = Vec::new();
+    for file in torrent::files(torrent)? {
+        if !torrent::is_path_safe(&file.path) {
+            let path: Vec<_> = file
+                .path
+                .iter()
+                .map(|c| String::from_utf8_lossy(c))
+                .collect();
+            return Err(format!("unsafe path in torrent: {}", path.join("/")).into());
+        }
+        let path = file.path.iter().fold(dir.to_path_buf(), |path, component| {
+            path.join(String::from_utf8_lossy(component).as_ref())
+        });
+        files.push((path, file.length.max(0) as u64));
+    }
+    let total: u64 = files.iter().map(|(_, length)| length).sum();
+
+    let mut data = Data {
+        files: files.into_iter(),
+        current: None,
+        missing: Vec::new(),
+    };
+    let mut progress = Progress::new("verify", total);
+    let mut bad = Vec::new();
+    let mut piece = vec![0; piece_length];
+
+    for (index, hash) in hashes.iter().enumerate() {
+        let read = data.fill(&mut piece)?;
+        progress.advance(read as u64);
+        if read == 0 || sha1(&piece[..read]) != *hash {
+            bad.push(index);
+        }
+    }
+    progress.finish();
+
+    Ok(Report {
+        pieces: hashes.len(),
+        bad,
+        missing: data.missing,
+    })
+}
+
+/// The torrent's files read back to back, as the pieces span them.
+struct Data {
+    files: std::vec::IntoIter<(PathBuf, u64)>,
+    current: Option<(Box<dyn Read>, u64)>,
+    missing: Vec<PathBuf>,
+}
+
+impl Data {
+    /// Fills as much of `piece` as the remaining files allow.
+    fn fill(&mut self, piece: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+
+        while filled < piece.len() {
+            let Some((reader, left)) = &mut self.current else {
+                let Some((path, length)) = self.files.next() else {
+                    break;
+                };
+                let reader: Box<dyn Read> = match File::open(&path) {
+                    Ok(file) => Box::new(file.take(length).chain(io::repeat(0))),
+                    Err(_) => {
+                        self.missing.push(path);
+                        Box::new(io::repeat(0))
+                    }
+                };
+                self.current = Some((reader, length));
+                continue;
+            };
+
+            let want = (piece.len() - filled).min(*left as usize);
+            reader.read_exact(&mut piece[filled..filled + want])?;
+            filled += want;
+            *left -= want as u64;
+            if *left == 0 {
+                self.current = None;
+            }
+        }
+
+        Ok(filled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use bencode_decoder::builder::{DictBuilder, ListBuilder};
+
+    use super::*;
+
+    #[test]
+    fn pieces_across_files() {
+        let dir = std::env::temp_dir().join(format!("verify-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("t")).unwrap();
+        fs::write(dir.join("t/a"), b"abcde").unwrap();
+        fs::write(dir.join("t/b"), b"fgh").unwrap();
+
+        let pieces: Vec<u8> = [&b"abcd"[..], b"efgh", b"ijk"]
+            .iter()
+            .flat_map(|piece| sha1(piece))
+            .collect();
+        let file = |name: &str, length| {
+            DictBuilder::new()
+                .insert("length", length)
+                .insert("path", ListBuilder::new().push(name))
+        };
+        let torrent = DictBuilder::new()
+            .insert(
+                "info",
+                DictBuilder::new()
+                    .insert(
+                        "files",
+                        ListBuilder::new()
+                            .push(file("a", 5))
+                            .push(file("b", 3))
+                            .push(file("c", 3)),
+                    )
+                    .insert("name", "t")
+                    .insert("piece length", 4)
+                    .insert("pieces", pieces),
+            )
+            .build();
+
+        let report = verify(&torrent, &dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.pieces, 3);
+        assert_eq!(report.bad, [2]);
+        assert_eq!(report.missing, [dir.join("t/c")]);
+    }
+}
@@ -0,0 +1,980 @@
+//! A Zstandard (RFC 8878) decoder for reading zstd-compressed input, such as
+//! session backups, after the reference's educational decoder. Frames that
+//! need a dictionary aren't supported; checksums are verified.
+
+use std::io;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("zstd: {message}"))
+}
+
+fn too_large(limit: usize) -> io::Error {
+    invalid(&format!("decompressed data is over the {limit}-byte limit"))
+}
+
+fn truncated() -> io::Error {
+    invalid("unexpected end of data")
+}
+
+fn take(input: &[u8], start: usize, len: usize) -> io::Result<&[u8]> {
+    input.get(start..start + len).ok_or_else(truncated)
+}
+
+fn little_endian(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| value << 8 | u64::from(byte))
+}
+
+pub const MAGIC: &[u8] = b"\x28\xb5\x2f\xfd";
+
+const SKIPPABLE_MAGIC: u32 = 0x184d_2a50;
+
+const MAX_BLOCK_SIZE: usize = 128 * 1024;
+
+/// Reads the table descriptions at the start of FSE-coded data, least
+/// significant bit first.
+struct Bits<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl Bits<'_> {
+    fn take(&mut self, n: u32) -> io::Result<u32> {
+        let mut value = 0;
+        for i in 0..n {
+            let byte = *self.input.get(self.position / 8).ok_or_else(truncated)?;
+            value |= u32::from(byte >> (self.position % 8) & 1) << i;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn bytes_used(&self) -> usize {
+        self.position.div_ceil(8)
+    }
+}
+
+/// Reads coded data backwards from its last bit, as FSE and Huffman coders
+/// write it: the highest set bit of the last byte marks where it ends. Bits
+/// before the start read as zeros; decoders check where reading stopped.
+struct BackBits<'a> {
+    input: &'a [u8],
+    /// Bits left to read, negative once reads went past the start.
+    left: i64,
+}
+
+impl<'a> BackBits<'a> {
+    fn new(input: &'a [u8]) -> io::Result<Self> {
+        let last = input
+            .last()
+            .filter(|&&byte| byte != 0)
+            .ok_or_else(|| invalid("missing end of stream marker"))?;
+        let marker = 7 - i64::from(last.leading_zeros());
+        Ok(Self {
+            input,
+            left: (input.len() as i64 - 1) * 8 + marker,
+        })
+    }
+
+    /// Up to 56 bits, the first read one the most significant.
+    fn take(&mut self, n: u32) -> u64 {
+        let end = self.left;
+        self.left -= i64::from(n);
+        if n == 0 || end <= 0 {
+            return 0;
+        }
+        let start = self.left.max(0) as usize;
+        let padding = (start as i64 - self.left) as u32;
+        let width = end as usize - start;
+
+        let mut word = [0; 8];
+        let available = &self.input[start / 8..self.input.len().min(start / 8 + 8)];
+        word[..available.len()].copy_from_slice(available);
+        let value = (u64::from_le_bytes(word) >> (start % 8)) & ((1 << width) - 1);
+        value << padding
+    }
+}
+
+/// Decoding table of a finite state entropy code: for each state, its
+/// symbol, and the bits to read and the baseline to add for the next state.
+#[derive(Clone)]
+struct Fse {
+    log: u32,
+    states: Vec<(u8, u8, u16)>,
+}
+
+impl Fse {
+    fn rle(symbol: u8) -> Self {
+        Self {
+            log: 0,
+            states: vec![(symbol, 0, 0)],
+        }
+    }
+
+    /// Reads a table description, returning the table and the bytes it took.
+    fn read(input: &[u8], max_log: u32, max_symbol: usize) -> io::Result<(Self, usize)> {
+        let mut bits = Bits { input, position: 0 };
+        let log = bits.take(4)? + 5;
+        if log > max_log {
+            return Err(invalid("FSE accuracy too high"));
+        }
+
+        let mut counts = Vec::new();
+        let mut remaining = (1i32 << log) + 1;
+        let mut threshold = 1i32 << log;
+        let mut width = log + 1;
+        while remaining > 1 {
+            if counts.len() > max_symbol {
+                return Err(invalid("too many FSE symbols"));
+            }
+            let max = 2 * threshold - 1 - remaining;
+            let mut count = bits.take(width - 1)? as i32;
+            if count >= max {
+                count += (bits.take(1)? as i32) << (width - 1);
+                if count >= threshold {
+                    count -= max;
+                }
+            }
+            // Counts are stored plus one, -1 meaning "less than 1"
+            let probability = count - 1;
+            remaining -= probability.abs();
+            counts.push(probability as i16);
+            if probability == 0 {
+                loop {
+                    let zeros = bits.take(2)?;
+                    counts.extend(std::iter::repeat_n(0, zeros as usize));
+                    if zeros != 3 {
+                        break;
+                    }
+                }
+            }
+            while remaining < threshold {
+                width -= 1;
+                threshold >>= 1;
+            }
+        }
+        if remaining != 1 || counts.len() > max_symbol + 1 {
+            return Err(invalid("corrupt FSE table description"));
+        }
+        Ok((Self::new(&counts, log)?, bits.bytes_used()))
+    }
+
+    fn new(counts: &[i16], log: u32) -> io::Result<Self> {
+        let size = 1usize << log;
+        let mut symbols = vec![0u8; size];
+        let mut next = vec![0u16; counts.len()];
+
+        // Symbols of less than 1 take a state each, from the top
+        let mut high = size;
+        for (symbol, &count) in counts.iter().enumerate() {
+            if count == -1 {
+                high = high
+                    .checked_sub(1)
+                    .ok_or_else(|| invalid("corrupt FSE table"))?;
+                symbols[high] = symbol as u8;
+                next[symbol] = 1;
+            }
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in counts.iter().enumerate() {
+            if count <= 0 {
+                continue;
+            }
+            next[symbol] = count as u16;
+            for _ in 0..count {
+                symbols[position] = symbol as u8;
+                position = (position + step) & (size - 1);
+                while position >= high {
+                    position = (position + step) & (size - 1);
+                }
+            }
+        }
+        if position != 0 {
+            return Err(invalid("corrupt FSE table"));
+        }
+
+        let states = symbols
+            .iter()
+            .map(|&symbol| {
+                let state = next[symbol as usize];
+                next[symbol as usize] += 1;
+                let bits = log - (15 - state.leading_zeros());
+                let baseline = ((u32::from(state) << bits) - size as u32) as u16;
+                (symbol, bits as u8, baseline)
+            })
+            .collect();
+        Ok(Self { log, states })
+    }
+}
+
+struct FseState<'a> {
+    table: &'a Fse,
+    state: usize,
+}
+
+impl<'a> FseState<'a> {
+    fn new(table: &'a Fse, bits: &mut BackBits) -> Self {
+        let state = bits.take(table.log) as usize;
+        Self { table, state }
+    }
+
+    fn symbol(&self) -> u8 {
+        self.table.states[self.state].0
+    }
+
+    fn update(&mut self, bits: &mut BackBits) {
+        let (_, width, baseline) = self.table.states[self.state];
+        self.state = usize::from(baseline) + bits.take(u32::from(width)) as usize;
+    }
+}
+
+/// Decoding table of a Huffman code, indexed by the next `max_bits` bits:
+/// the symbol and how many of those bits its code takes.
+struct Huffman {
+    max_bits: u32,
+    entries: Vec<(u8, u8)>,
+}
+
+impl Huffman {
+    /// Reads a tree description, returning the table and the bytes it took.
+    fn read(input: &[u8]) -> io::Result<(Self, usize)> {
+        let header = *input.first().ok_or_else(truncated)? as usize;
+        let mut weights = Vec::new();
+        let used = if header >= 128 {
+            // Four bits per weight
+            let count = header - 127;
+            let packed = take(input, 1, count.div_ceil(2))?;
+            for i in 0..count {
+                let byte = packed[i / 2];
+                weights.push(if i % 2 == 0 { byte >> 4 } else { byte & 0xf });
+            }
+            1 + packed.len()
+        } else {
+            let compressed = take(input, 1, header)?;
+            let (table, used) = Fse::read(compressed, 6, 255)?;
+            let mut bits = BackBits::new(&compressed[used..])?;
+            let mut states = [
+                FseState::new(&table, &mut bits),
+                FseState::new(&table, &mut bits),
+            ];
+            // The two states take turns, until the stream runs out
+            'decode: loop {
+                for i in [0, 1] {
+                    weights.push(states[i].symbol());
+                    states[i].update(&mut bits);
+                    if bits.left < 0 {
+                        weights.push(states[1 - i].symbol());
+                        break 'decode;
+                    }
+                }
+                if weights.len() > 255 {
+                    return Err(invalid("too many Huffman weights"));
+                }
+            }
+            1 + header
+        };
+        Ok((Self::new(weights)?, used))
+    }
+
+    fn new(mut weights: Vec<u8>) -> io::Result<Self> {
+        if weights.len() > 255 || weights.iter().any(|&weight| weight > 11) {
+            return Err(invalid("corrupt Huffman weights"));
+        }
+        let sum: u32 = weights
+            .iter()
+            .filter(|&&weight| weight > 0)
+            .map(|&weight| 1 << (weight - 1))
+            .sum();
+        if sum == 0 {
+            return Err(invalid("corrupt Huffman weights"));
+        }
+        // The last weight is implied, completing a power of two
+        let max_bits = 32 - sum.leading_zeros();
+        let rest = (1 << max_bits) - sum;
+        if !rest.is_power_of_two() || max_bits > 11 {
+            return Err(invalid("corrupt Huffman weights"));
+        }
+        weights.push(rest.trailing_zeros() as u8 + 1);
+
+        let mut entries = Vec::with_capacity(1 << max_bits);
+        for weight in 1..=max_bits as u8 {
+            for (symbol, _) in weights.iter().enumerate().filter(|&(_, &w)| w == weight) {
+                let bits = max_bits as u8 + 1 - weight;
+                entries.extend(std::iter::repeat_n((symbol as u8, bits), 1 << (weight - 1)));
+            }
+        }
+        Ok(Self { max_bits, entries })
+    }
+
+    /// Decodes `count` symbols from one stream, which they must use exactly.
+    fn decode(&self, stream: &[u8], count: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        let mut bits = BackBits::new(stream)?;
+        let mask = (1 << self.max_bits) - 1;
+        let mut state = bits.take(self.max_bits) as usize;
+        for _ in 0..count {
+            let (symbol, width) = self.entries[state];
+            out.push(symbol);
+            state = ((state << width) | bits.take(u32::from(width)) as usize) & mask;
+        }
+        if bits.left != -i64::from(self.max_bits) {
+            return Err(invalid("corrupt literals"));
+        }
+        Ok(())
+    }
+}
+
+/// What carries over between the blocks of a frame.
+struct Frame {
+    huffman: Option<Huffman>,
+    literal_lengths: Option<Fse>,
+    offsets: Option<Fse>,
+    match_lengths: Option<Fse>,
+    repeats: [usize; 3],
+}
+
+fn literals(block: &[u8], huffman: &mut Option<Huffman>) -> io::Result<(Vec<u8>, usize)> {
+    let header = take(block, 0, 1)?[0];
+    let format = header >> 2 & 3;
+    match header & 3 {
+        kind @ (0 | 1) => {
+            let (size, used) = match format {
+                0 | 2 => (usize::from(header >> 3), 1),
+                1 => (little_endian(take(block, 0, 2)?) as usize >> 4, 2),
+                _ => (little_endian(take(block, 0, 3)?) as usize >> 4, 3),
+            };
+            if kind == 0 {
+                Ok((take(block, used, size)?.to_vec(), used + size))
+            } else {
+                Ok((vec![take(block, used, 1)?[0]; size], used + 1))
+            }
+        }
+        kind => {
+            let (used, bits) = match format {
+                0 | 1 => (3, 10),
+                2 => (4, 14),
+                _ => (5, 18),
+            };
+            let sizes = little_endian(take(block, 0, used)?) >> 4;
+            let mask = (1 << bits) - 1;
+            let (size, compressed) = ((sizes & mask) as usize, (sizes >> bits & mask) as usize);
+            if size > MAX_BLOCK_SIZE {
+                return Err(invalid("literals larger than a block"));
+            }
+            let mut data = take(block, used, compressed)?;
+            if kind == 2 {
+                let (table, table_size) = Huffman::read(data)?;
+                *huffman = Some(table);
+                data = &data[table_size..];
+            }
+            let table = huffman
+                .as_ref()
+                .ok_or_else(|| invalid("literals reuse a missing Huffman table"))?;
+
+            let mut literals = Vec::with_capacity(size);
+            if format == 0 {
+                table.decode(data, size, &mut literals)?;
+            } else {
+                let jumps = take(data, 0, 6)?;
+                let mut lengths = [0; 4];
+                for i in 0..3 {
+                    lengths[i] = usize::from(u16::from_le_bytes([jumps[2 * i], jumps[2 * i + 1]]));
+                }
+                lengths[3] = (data.len() - 6)
+                    .checked_sub(lengths[..3].iter().sum())
+                    .ok_or_else(truncated)?;
+                let segment = size.div_ceil(4);
+                let last = size
+                    .checked_sub(3 * segment)
+                    .ok_or_else(|| invalid("corrupt literals"))?;
+                let mut start = 6;
+                for (i, length) in lengths.into_iter().enumerate() {
+                    let count = if i == 3 { last } else { segment };
+                    table.decode(take(data, start, length)?, count, &mut literals)?;
+                    start += length;
+                }
+            }
+            Ok((literals, used + compressed))
+        }
+    }
+}
+
+const LITERAL_LENGTHS: [(u32, u8); 36] = [
+    (0, 0),
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 0),
+    (12, 0),
+    (13, 0),
+    (14, 0),
+    (15, 0),
+    (16, 1),
+    (18, 1),
+    (20, 1),
+    (22, 1),
+    (24, 2),
+    (28, 2),
+    (32, 3),
+    (40, 3),
+    (48, 4),
+    (64, 6),
+    (128, 7),
+    (256, 8),
+    (512, 9),
+    (1024, 10),
+    (2048, 11),
+    (4096, 12),
+    (8192, 13),
+    (16384, 14),
+    (32768, 15),
+    (65536, 16),
+];
+
+const MATCH_LENGTHS: [(u32, u8); 53] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 0),
+    (12, 0),
+    (13, 0),
+    (14, 0),
+    (15, 0),
+    (16, 0),
+    (17, 0),
+    (18, 0),
+    (19, 0),
+    (20, 0),
+    (21, 0),
+    (22, 0),
+    (23, 0),
+    (24, 0),
+    (25, 0),
+    (26, 0),
+    (27, 0),
+    (28, 0),
+    (29, 0),
+    (30, 0),
+    (31, 0),
+    (32, 0),
+    (33, 0),
+    (34, 0),
+    (35, 1),
+    (37, 1),
+    (39, 1),
+    (41, 1),
+    (43, 2),
+    (47, 2),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 5),
+    (131, 7),
+    (259, 8),
+    (515, 9),
+    (1027, 10),
+    (2051, 11),
+    (4099, 12),
+    (8195, 13),
+    (16387, 14),
+    (32771, 15),
+    (65539, 16),
+];
+
+const LITERAL_LENGTH_COUNTS: [i16; 36] = [
+    4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1,
+    -1, -1, -1, -1,
+];
+
+const MATCH_LENGTH_COUNTS: [i16; 53] = [
+    1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+];
+
+const OFFSET_COUNTS: [i16; 29] = [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+];
+
+/// Sets up `table` as the block's mode says, from the predefined `counts`, a
+/// single symbol, a description or the previous block's table, returning
+/// the bytes it took.
+fn sequence_table(
+    mode: u8,
+    input: &[u8],
+    table: &mut Option<Fse>,
+    (counts, log): (&[i16], u32),
+    (max_log, max_symbol): (u32, usize),
+) -> io::Result<usize> {
+    match mode {
+        0 => {
+            *table = Some(Fse::new(counts, log)?);
+            Ok(0)
+        }
+        1 => {
+            let symbol = take(input, 0, 1)?[0];
+            if usize::from(symbol) > max_symbol {
+                return Err(invalid("corrupt sequences"));
+            }
+            *table = Some(Fse::rle(symbol));
+            Ok(1)
+        }
+        2 => {
+            let (fse, used) = Fse::read(input, max_log, max_symbol)?;
+            *table = Some(fse);
+            Ok(used)
+        }
+        _ => match table {
+            Some(_) => Ok(0),
+            None => Err(invalid("sequences reuse a missing table")),
+        },
+    }
+}
+
+struct Sequence {
+    literals: usize,
+    offset: u64,
+    matched: usize,
+}
+
+fn sequences(input: &[u8], frame: &mut Frame) -> io::Result<Vec<Sequence>> {
+    let first = take(input, 0, 1)?[0];
+    let (count, mut position) = match first {
+        0 => return Ok(Vec::new()),
+        1..=127 => (usize::from(first), 1),
+        128..=254 => (
+            (usize::from(first - 128) << 8) + usize::from(take(input, 1, 1)?[0]),
+            2,
+        ),
+        255 => (little_endian(take(input, 1, 2)?) as usize + 0x7f00, 3),
+    };
+    let modes = take(input, position, 1)?[0];
+    position += 1;
+    if modes & 3 != 0 {
+        return Err(invalid("reserved bits set"));
+    }
+    position += sequence_table(
+        modes >> 6,
+        &input[position..],
+        &mut frame.literal_lengths,
+        (&LITERAL_LENGTH_COUNTS, 6),
+        (9, 35),
+    )?;
+    position += sequence_table(
+        modes >> 4 & 3,
+        &input[position..],
+        &mut frame.offsets,
+        (&OFFSET_COUNTS, 5),
+        (8, 31),
+    )?;
+    position += sequence_table(
+        modes >> 2 & 3,
+        &input[position..],
+        &mut frame.match_lengths,
+        (&MATCH_LENGTH_COUNTS, 6),
+        (9, 52),
+    )?;
+
+    let mut bits = BackBits::new(&input[position..])?;
+    let mut literal_lengths = FseState::new(frame.literal_lengths.as_ref().unwrap(), &mut bits);
+    let mut offsets = FseState::new(frame.offsets.as_ref().unwrap(), &mut bits);
+    let mut match_lengths = FseState::new(frame.match_lengths.as_ref().unwrap(), &mut bits);
+
+    let mut sequences = Vec::with_capacity(count);
+    for i in 0..count {
+        let offset_code = u32::from(offsets.symbol());
+        let offset = (1u64 << offset_code) + bits.take(offset_code);
+        let (base, extra) = MATCH_LENGTHS[usize::from(match_lengths.symbol())];
+        let matched = base as usize + bits.take(u32::from(extra)) as usize;
+        let (base, extra) = LITERAL_LENGTHS[usize::from(literal_lengths.symbol())];
+        let literals = base as usize + bits.take(u32::from(extra)) as usize;
+        sequences.push(Sequence {
+            literals,
+            offset,
+            matched,
+        });
+
+        if i + 1 < count {
+            literal_lengths.update(&mut bits);
+            match_lengths.update(&mut bits);
+            offsets.update(&mut bits);
+        }
+    }
+    if bits.left != 0 {
+        return Err(invalid("corrupt sequences"));
+    }
+    Ok(sequences)
+}
+
+/// The distance back of a match, keeping the three most recent ones, which
+/// offset values 1 to 3 refer to.
+fn offset(sequence: &Sequence, repeats: &mut [usize; 3]) -> usize {
+    if sequence.offset > 3 {
+        let offset = (sequence.offset - 3) as usize;
+        *repeats = [offset, repeats[0], repeats[1]];
+        return offset;
+    }
+    // Without literals, the first repeat would be pointless; the rest shift
+    let index = sequence.offset as usize - 1 + usize::from(sequence.literals == 0);
+    if index == 0 {
+        return repeats[0];
+    }
+    let offset = match index {
+        3 => repeats[0].wrapping_sub(1),
+        _ => repeats[index],
+    };
+    if index > 1 {
+        repeats[2] = repeats[1];
+    }
+    repeats[1] = repeats[0];
+    repeats[0] = offset;
+    offset
+}
+
+fn compressed_block(
+    block: &[u8],
+    frame: &mut Frame,
+    out: &mut Vec<u8>,
+    frame_start: usize,
+    limit: usize,
+) -> io::Result<()> {
+    let (literals, used) = literals(block, &mut frame.huffman)?;
+    let sequences = sequences(&block[used..], frame)?;
+
+    let mut next = 0;
+    for sequence in &sequences {
+        let copied = &literals
+            .get(next..next + sequence.literals)
+            .ok_or_else(|| invalid("sequences use more literals than there are"))?;
+        if out.len() + copied.len() + sequence.matched > limit {
+            return Err(too_large(limit));
+        }
+        out.extend_from_slice(copied);
+        next += sequence.literals;
+
+        let offset = offset(sequence, &mut frame.repeats);
+        if offset == 0 || offset > out.len() - frame_start {
+            return Err(invalid("match distance too far back"));
+        }
+        let start = out.len() - offset;
+        if offset >= sequence.matched {
+            out.extend_from_within(start..start + sequence.matched);
+        } else {
+            for i in 0..sequence.matched {
+                out.push(out[start + i]);
+            }
+        }
+    }
+    if out.len() + literals.len() - next > limit {
+        return Err(too_large(limit));
+    }
+    out.extend_from_slice(&literals[next..]);
+    Ok(())
+}
+
+/// Decodes the frame after its magic number into `out`, returning the input
+/// left after it.
+fn frame<'a>(input: &'a [u8], out: &mut Vec<u8>, limit: usize) -> io::Result<&'a [u8]> {
+    let descriptor = take(input, 0, 1)?[0];
+    if descriptor & 0x08 != 0 {
+        return Err(invalid("reserved bit set"));
+    }
+    let single_segment = descriptor & 0x20 != 0;
+    let checksum = descriptor & 0x04 != 0;
+    // The window size only matters to decoders that don't keep all output
+    let mut position = 1 + usize::from(!single_segment);
+
+    let dictionary_size = [0, 1, 2, 4][usize::from(descriptor & 3)];
+    if little_endian(take(input, position, dictionary_size)?) != 0 {
+        return Err(invalid("frames that need a dictionary aren't supported"));
+    }
+    position += dictionary_size;
+    let content_size = match descriptor >> 6 {
+        0 => usize::from(single_segment),
+        1 => 2,
+        2 => 4,
+        _ => 8,
+    };
+    let declared = (content_size > 0).then(|| -> io::Result<u64> {
+        let size = little_endian(take(input, position, content_size)?);
+        Ok(if content_size == 2 { size + 256 } else { size })
+    });
+    let declared = declared.transpose()?;
+    position += content_size;
+
+    let start = out.len();
+    let mut frame = Frame {
+        huffman: None,
+        literal_lengths: None,
+        offsets: None,
+        match_lengths: None,
+        repeats: [1, 4, 8],
+    };
+    loop {
+        let header = little_endian(take(input, position, 3)?) as usize;
+        position += 3;
+        let size = header >> 3;
+        if size > MAX_BLOCK_SIZE {
+            return Err(invalid("block too large"));
+        }
+        match header >> 1 & 3 {
+            0 => {
+                let block = take(input, position, size)?;
+                if out.len() + size > limit {
+                    return Err(too_large(limit));
+                }
+                out.extend_from_slice(block);
+                position += size;
+            }
+            1 => {
+                let byte = take(input, position, 1)?[0];
+                if out.len() + size > limit {
+                    return Err(too_large(limit));
+                }
+                out.resize(out.len() + size, byte);
+                position += 1;
+            }
+            2 => {
+                let block = take(input, position, size)?;
+                compressed_block(block, &mut frame, out, start, limit)?;
+                position += size;
+            }
+            _ => return Err(invalid("reserved block type")),
+        }
+        if header & 1 == 1 {
+            break;
+        }
+    }
+
+    if declared.is_some_and(|size| size != (out.len() - start) as u64) {
+        return Err(invalid("content size mismatch"));
+    }
+    if checksum {
+        let expected = little_endian(take(input, position, 4)?);
+        if xxh64(&out[start..]) & 0xffff_ffff != expected {
+            return Err(invalid("checksum mismatch"));
+        }
+        position += 4;
+    }
+    Ok(&input[position..])
+}
+
+/// Decompresses zstd input, frame by frame, skipping skippable frames. More
+/// than `limit` bytes of output is an error, against files built to expand
+/// enormously.
+pub fn decompress(mut input: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    while !input.is_empty() {
+        let magic = little_endian(take(input, 0, 4)?) as u32;
+        if magic & !0xf == SKIPPABLE_MAGIC {
+            let size = little_endian(take(input, 4, 4)?) as usize;
+            take(input, 8, size)?;
+            input = &input[8 + size..];
+        } else if input.starts_with(MAGIC) {
+            input = frame(&input[4..], &mut out, limit)?;
+        } else {
+            return Err(invalid("not a zstd frame"));
+        }
+    }
+    Ok(out)
+}
+
+/// XXH64 with a seed of 0, whose low 32 bits are a frame's checksum.
+fn xxh64(data: &[u8]) -> u64 {
+    const PRIME_1: u64 = 0x9e37_79b1_85eb_ca87;
+    const PRIME_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+    const PRIME_3: u64 = 0x1656_67b1_9e37_79f9;
+    const PRIME_4: u64 = 0x85eb_ca77_c2b2_ae63;
+    const PRIME_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+    let round = |acc: u64, lane: u64| {
+        acc.wrapping_add(lane.wrapping_mul(PRIME_2))
+            .rotate_left(31)
+            .wrapping_mul(PRIME_1)
+    };
+    let lane = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().unwrap());
+
+    let mut stripes = data.chunks_exact(32);
+    let mut hash = if data.len() >= 32 {
+        let mut acc = [
+            PRIME_1.wrapping_add(PRIME_2),
+            PRIME_2,
+            0,
+            PRIME_1.wrapping_neg(),
+        ];
+        for stripe in &mut stripes {
+            for (i, acc) in acc.iter_mut().enumerate() {
+                *acc = round(*acc, lane(&stripe[i * 8..i * 8 + 8]));
+            }
+        }
+        let mut hash = acc[0]
+            .rotate_left(1)
+            .wrapping_add(acc[1].rotate_left(7))
+            .wrapping_add(acc[2].rotate_left(12))
+            .wrapping_add(acc[3].rotate_left(18));
+        for acc in acc {
+            hash = (hash ^ round(0, acc))
+                .wrapping_mul(PRIME_1)
+                .wrapping_add(PRIME_4);
+        }
+        hash
+    } else {
+        PRIME_5
+    };
+    hash = hash.wrapping_add(data.len() as u64);
+
+    let mut rest = stripes.remainder();
+    while rest.len() >= 8 {
+        hash ^= round(0, lane(&rest[..8]));
+        hash = hash
+            .rotate_left(27)
+            .wrapping_mul(PRIME_1)
+            .wrapping_add(PRIME_4);
+        rest = &rest[8..];
+    }
+    if rest.len() >= 4 {
+        let word = u32::from_le_bytes(rest[..4].try_into().unwrap());
+        hash ^= u64::from(word).wrapping_mul(PRIME_1);
+        hash = hash
+            .rotate_left(23)
+            .wrapping_mul(PRIME_2)
+            .wrapping_add(PRIME_3);
+        rest = &rest[4..];
+    }
+    for &byte in rest {
+        hash ^= u64::from(byte).wrapping_mul(PRIME_5);
+        hash = hash.rotate_left(11).wrapping_mul(PRIME_1);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME_3);
+    hash ^ hash >> 32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decompress_all(input: &[u8]) -> io::Result<Vec<u8>> {
+        decompress(input, usize::MAX)
+    }
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// What `compressed` below decompresses to, 1505 bytes.
+    fn keys() -> Vec<u8> {
+        const WORDS: [&str; 9] = [
+            "announce",
+            "length",
+            "name",
+            "path",
+            "piece length",
+            "pieces",
+            "files",
+            "info",
+            "comment",
+        ];
+        let entries = (0..100u64).flat_map(|i| {
+            let word = WORDS[i as usize * 7 % 9];
+            let value = i * i * 7919 % 10u64.pow(i as u32 % 9 + 1);
+            format!("{}:{word}i{value}e", word.len()).into_bytes()
+        });
+        [&b"d"[..], &entries.collect::<Vec<u8>>(), b"e"].concat()
+    }
+
+    fn compressed() -> Vec<u8> {
+        unhex(concat!(
+            "28b52ffd64e1048d0d0036a04716804bdac07f5f296208a10902d529b237b73cc444563451003b003b00adac",
+            "abfa3b2fd7bc31a42002c9e1d29acc02508b732c095994034238ac181e5f84911404222138b078f0a89094c3",
+            "71a548450c8c80e270393c64510405511c575e24414890c3659022481a8924091c3908bfd72bdb9af1e08fae",
+            "d6863883f795adefb1e8f26c3eb74e678ac7bcd7a6b232acfb76f5ccb53eef4875249ab1eb2d93c847484386",
+            "d8c49a4a660dfd676e45837866ecf595f6d50cd1d4ab1b7beab73b7fc63996afb9b3abbd5a99ce1b166dd3e1",
+            "a9ee5b789b313cbe563d2c2bdab2adb212f9dc6b03a5fc706c7358cc8f193dd63c19da49842df7ead7fc7584",
+            "745768aa5efbacdff43c3344d7e7eb7f9f399ac8f7bdc7660d6bb73b8665dbb53bde035ea811e020835062f6",
+            "da38502549e23621040902a41c422975650c9033b443c4c47f0894e44057617b56828945d6c38e25ce7e4e1c",
+            "a8303e205bf71ba18907207e6520af9ef0c53fb4235a7990b40ea9d707c35700809720cd418209490fd03103",
+            "93ca7e01ef8629e89d506072a0b306ea4f8878311c1184e5b388f17aaf00c118c001f0f09d676d0960e2aa5e",
+            "00a30a85f54535",
+        ))
+    }
+
+    #[test]
+    fn frames() {
+        // Huffman-coded literals in four streams, and FSE-coded sequences
+        assert_eq!(decompress_all(&compressed()).unwrap(), keys());
+
+        let raw = unhex("28b52ffd240c61000064343a7370616d6934326565eed2e9b2");
+        assert_eq!(decompress_all(&raw).unwrap(), b"d4:spami42ee");
+
+        // A skippable frame, then a single RLE block
+        let mut both = unhex("502a4d1803000000ffffff28b52ffd20052b000061");
+        assert_eq!(decompress_all(&both).unwrap(), b"aaaaa");
+        both.extend(&raw);
+        assert_eq!(decompress_all(&both).unwrap(), b"aaaaad4:spami42ee");
+    }
+
+    #[test]
+    fn corrupt_input() {
+        let mut raw = unhex("28b52ffd240c61000064343a7370616d6934326565eed2e9b2");
+        let last = raw.len() - 1;
+        raw[last] ^= 1;
+        assert!(decompress_all(&raw)
+            .unwrap_err()
+            .to_string()
+            .contains("checksum mismatch"));
+
+        let compressed = compressed();
+        for len in [3, 5, 20, compressed.len() - 5] {
+            assert!(decompress_all(&compressed[..len]).is_err());
+        }
+        let mut flipped = compressed.clone();
+        flipped[100] ^= 0x10;
+        assert!(decompress_all(&flipped).is_err());
+
+        let dictionary = unhex("28b52ffd210701");
+        assert!(decompress_all(&dictionary)
+            .unwrap_err()
+            .to_string()
+            .contains("dictionary"));
+    }
+
+    #[test]
+    fn limit() {
+        // 100 KB of zeros in 22 bytes
+        let zeros = unhex("28b52ffd04684d0000080001009c86391002db234ef3");
+        assert_eq!(decompress_all(&zeros).unwrap(), vec![0; 100_000]);
+        assert_eq!(decompress(&zeros, 100_000).unwrap().len(), 100_000);
+        let error = decompress(&zeros, 1000).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "zstd: decompressed data is over the 1000-byte limit"
+        );
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(xxh64(b""), 0xef46_db37_51d8_e999);
+        assert_eq!(xxh64(b"abc"), 0x44bc_2cf5_ad77_0999);
+    }
+}